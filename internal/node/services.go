@@ -114,6 +114,7 @@ func newAuthorityClaimer(c config.NodeConfig, workDir string) services.CommandSe
 	s.Env = append(s.Env, fmt.Sprintf("TX_DEFAULT_CONFIRMATIONS=%v",
 		c.BlockchainFinalityOffset))
 	s.Env = append(s.Env, fmt.Sprintf("REDIS_ENDPOINT=%v", getRedisEndpoint(c)))
+	s.Env = append(s.Env, fmt.Sprintf("POSTGRES_ENDPOINT=%v", c.PostgresEndpoint.Value))
 	s.Env = append(s.Env, fmt.Sprintf("HISTORY_ADDRESS=%v", c.ContractsHistoryAddress))
 	s.Env = append(s.Env, fmt.Sprintf("AUTHORITY_ADDRESS=%v", c.ContractsAuthorityAddress))
 	s.Env = append(s.Env, fmt.Sprintf("INPUT_BOX_ADDRESS=%v", c.ContractsInputBoxAddress))
@@ -222,6 +223,12 @@ func newIndexer(c config.NodeConfig, workdir string) services.CommandService {
 	s.Env = append(s.Env, fmt.Sprintf("DAPP_CONTRACT_ADDRESS=%v",
 		c.ContractsApplicationAddress))
 	s.Env = append(s.Env, fmt.Sprintf("REDIS_ENDPOINT=%v", getRedisEndpoint(c)))
+	s.Env = append(s.Env, fmt.Sprintf("PROVIDER_HTTP_ENDPOINT=%v",
+		c.BlockchainHttpEndpoint.Value))
+	s.Env = append(s.Env, fmt.Sprintf("DAPP_DEPLOYMENT_BLOCK_NUMBER=%v",
+		c.ContractsApplicationDeploymentBlockNumber))
+	s.Env = append(s.Env, fmt.Sprintf("VOUCHER_EXECUTION_CONFIRMATIONS=%v",
+		c.BlockchainFinalityOffset))
 	s.Env = append(s.Env, fmt.Sprintf("INDEXER_HEALTHCHECK_PORT=%v",
 		getPort(c, portOffsetIndexer)))
 	s.Env = append(s.Env, os.Environ()...)
@@ -326,6 +333,8 @@ func newSupervisorService(c config.NodeConfig, workDir string) services.Supervis
 	}
 
 	// enable claimer if reader mode and sunodo validator mode are disabled
+	// the claimer records the claims in the tables created by the migrations of
+	// the indexer, so it must be added after the indexer
 	if !c.FeatureDisableClaimer && !c.ExperimentalSunodoValidatorEnabled {
 		s = append(s, newAuthorityClaimer(c, workDir))
 	}
@@ -2,11 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
 use rollups_events::{
-    Broker, BrokerConfig, BrokerError, DAppMetadata, RollupsClaim,
-    RollupsClaimsStream, RollupsInput, RollupsInputsStream, RollupsOutput,
-    RollupsOutputsStream, INITIAL_ID,
+    Broker, BrokerConfig, BrokerError, DAppMetadata, Event, RetentionConfig,
+    RollupsClaim, RollupsClaimsStream, RollupsInput, RollupsInputsStream,
+    RollupsOutput, RollupsOutputsStream, StreamTrimmer,
+    ADVANCE_RUNNER_CONSUMER, DISPATCHER_CONSUMER, INDEXER_CONSUMER, INITIAL_ID,
 };
 use snafu::{ResultExt, Snafu};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
 
 #[derive(Debug, Snafu)]
 pub enum BrokerFacadeError {
@@ -35,6 +38,29 @@ pub enum BrokerFacadeError {
 
 pub type Result<T> = std::result::Result<T, BrokerFacadeError>;
 
+/// Number of claims read at a time when looking for the claims of the dapp
+const CLAIMS_PAGE_SIZE: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsumedInput {
+    /// Input that follows the last consumed one
+    Next(RollupsInput),
+
+    /// Input whose parent is not the last consumed one because of a reorg
+    Reorg(Event<RollupsInput>),
+}
+
+/// Chain of input events that leads to an event after a reorg
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    /// Whether the branch starts right after the restore point; otherwise, it
+    /// starts at the beginning of the stream
+    pub from_restore_point: bool,
+
+    /// Events of the branch in order, ending with the reorg event
+    pub events: Vec<Event<RollupsInput>>,
+}
+
 pub struct BrokerFacade {
     client: Broker,
     inputs_stream: RollupsInputsStream,
@@ -42,6 +68,7 @@ pub struct BrokerFacade {
     claims_stream: RollupsClaimsStream,
     reader_mode: bool,
     last_id: String,
+    retention: RetentionConfig,
 }
 
 impl BrokerFacade {
@@ -52,6 +79,7 @@ impl BrokerFacade {
         reader_mode: bool,
     ) -> Result<Self> {
         tracing::trace!(?config, "connecting to broker");
+        let retention = config.retention.clone();
         let client = Broker::new(config).await.context(BrokerInternalSnafu)?;
         let inputs_stream = RollupsInputsStream::new(&dapp_metadata);
         let outputs_stream = RollupsOutputsStream::new(&dapp_metadata);
@@ -63,12 +91,56 @@ impl BrokerFacade {
             claims_stream,
             reader_mode,
             last_id: INITIAL_ID.to_owned(),
+            retention,
         })
     }
 
+    /// Create the task that trims the inputs and outputs streams
+    /// The inputs are consumed by the advance-runner and the indexer, and the
+    /// dispatcher reads back the inputs of the current epoch on a reorg. The
+    /// outputs are only consumed by the indexer.
+    pub fn stream_trimmer(&self) -> StreamTrimmer {
+        let mut trimmer =
+            StreamTrimmer::new(self.client.clone(), self.retention.clone());
+        trimmer.add_stream(
+            &self.inputs_stream,
+            &[
+                ADVANCE_RUNNER_CONSUMER,
+                DISPATCHER_CONSUMER,
+                INDEXER_CONSUMER,
+            ],
+        );
+        trimmer.add_stream(&self.outputs_stream, &[INDEXER_CONSUMER]);
+        trimmer
+    }
+
+    /// Record that the inputs up to the given event are no longer needed to
+    /// restore the machine
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn acknowledge_input(&mut self, id: &str) -> Result<()> {
+        tracing::trace!(id, "acknowledging input event");
+        self.client
+            .acknowledge(&self.inputs_stream, ADVANCE_RUNNER_CONSUMER, id)
+            .await
+            .context(BrokerInternalSnafu)
+    }
+
+    /// Resume consuming inputs right after the given event id
+    pub fn resume_from(&mut self, last_id: String) {
+        tracing::trace!(last_id, "resuming from event");
+        self.last_id = last_id;
+    }
+
+    /// Id of the last consumed input event
+    pub fn last_id(&self) -> &str {
+        &self.last_id
+    }
+
     /// Consume rollups input event
+    /// If the event doesn't follow the last consumed one, return it as a reorg
+    /// without moving the last id forward.
     #[tracing::instrument(level = "trace", skip_all)]
-    pub async fn consume_input(&mut self) -> Result<RollupsInput> {
+    pub async fn consume_input(&mut self) -> Result<ConsumedInput> {
         tracing::trace!(self.last_id, "consuming rollups input event");
         let event = self
             .client
@@ -76,17 +148,62 @@ impl BrokerFacade {
             .await
             .context(BrokerInternalSnafu)?;
         if event.payload.parent_id != self.last_id {
-            Err(BrokerFacadeError::ParentIdMismatchError {
-                expected: self.last_id.to_owned(),
-                got: event.payload.parent_id,
-            })
+            tracing::warn!(
+                expected = self.last_id,
+                got = event.payload.parent_id,
+                "parent id doesn't match; found reorg"
+            );
+            Ok(ConsumedInput::Reorg(event))
         } else {
             self.last_id = event.id;
-            Ok(event.payload)
+            Ok(ConsumedInput::Next(event.payload))
+        }
+    }
+
+    /// Follow the parent ids from the reorg event back to the restore point or
+    /// to the beginning of the stream, whichever comes first
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn find_branch(
+        &mut self,
+        restore_id: &str,
+        event: Event<RollupsInput>,
+    ) -> Result<Branch> {
+        tracing::trace!(restore_id, event.id, "finding reorg branch");
+        let mut parent_id = event.payload.parent_id.clone();
+        let mut events = VecDeque::from([event]);
+        while parent_id != restore_id && parent_id != INITIAL_ID {
+            let parent = self
+                .client
+                .get_event(&self.inputs_stream, &parent_id)
+                .await
+                .context(BrokerInternalSnafu)?;
+            let parent = match parent {
+                Some(parent) => parent,
+                None => {
+                    return Err(BrokerFacadeError::ParentIdMismatchError {
+                        expected: self.last_id.to_owned(),
+                        got: parent_id,
+                    })
+                }
+            };
+            parent_id = parent.payload.parent_id.clone();
+            events.push_front(parent);
         }
+
+        let branch = Branch {
+            from_restore_point: parent_id == restore_id,
+            events: events.into(),
+        };
+        tracing::trace!(
+            branch.from_restore_point,
+            len = branch.events.len(),
+            "found reorg branch"
+        );
+        Ok(branch)
     }
 
-    /// Produce the rollups claim if it isn't in the stream yet
+    /// Produce the rollups claim if it isn't in the stream yet; a claim for the
+    /// same epoch with another hash comes from a reorg, so it is produced too
     #[tracing::instrument(level = "trace", skip_all)]
     pub async fn produce_rollups_claim(
         &mut self,
@@ -101,22 +218,7 @@ impl BrokerFacade {
             "producing rollups claim"
         );
 
-        let result = self
-            .client
-            .peek_latest(&self.claims_stream)
-            .await
-            .context(BrokerInternalSnafu)?;
-
-        let claim_produced = match result {
-            Some(event) => {
-                tracing::trace!(?event, "got last claim produced");
-                rollups_claim.epoch_index <= event.payload.epoch_index
-            }
-            None => {
-                tracing::trace!("no claims in the stream");
-                false
-            }
-        };
+        let claim_produced = self.claim_produced(&rollups_claim).await?;
 
         if !claim_produced {
             self.client
@@ -128,6 +230,54 @@ impl BrokerFacade {
         Ok(())
     }
 
+    /// Whether the claim is in the stream with the same epoch hash.
+    /// The claims stream is shared by the dapps of the chain, so it skips the
+    /// claims of the other dapps. Going back from the latest claim, it skips
+    /// the later epochs of the dapp until it gets to the epoch of the claim.
+    /// If the stream starts before that, the epoch is claimed only if it has
+    /// later epochs, because the claims of the epoch were trimmed.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn claim_produced(
+        &mut self,
+        rollups_claim: &RollupsClaim,
+    ) -> Result<bool> {
+        let mut later_epoch_claimed = false;
+        let mut before_id: Option<String> = None;
+        loop {
+            let events = self
+                .client
+                .peek_previous(
+                    &self.claims_stream,
+                    before_id.as_deref(),
+                    CLAIMS_PAGE_SIZE,
+                )
+                .await
+                .context(BrokerInternalSnafu)?;
+            let Some(last) = events.last() else {
+                tracing::trace!(later_epoch_claimed, "got to the stream start");
+                return Ok(later_epoch_claimed);
+            };
+            before_id = Some(last.id.clone());
+            for event in events.into_iter().filter(|event| {
+                event.payload.dapp_address == rollups_claim.dapp_address
+            }) {
+                match event.payload.epoch_index.cmp(&rollups_claim.epoch_index)
+                {
+                    Ordering::Greater => later_epoch_claimed = true,
+                    Ordering::Equal => {
+                        tracing::trace!(?event, "got claim of the epoch");
+                        return Ok(event.payload.epoch_hash
+                            == rollups_claim.epoch_hash);
+                    }
+                    Ordering::Less => {
+                        tracing::trace!(?event, "got claim of previous epoch");
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+    }
+
     /// Produce outputs to the rollups-outputs stream
     #[tracing::instrument(level = "trace", skip_all)]
     pub async fn produce_outputs(
@@ -136,12 +286,10 @@ impl BrokerFacade {
     ) -> Result<()> {
         tracing::trace!(?outputs, "producing rollups outputs");
 
-        for output in outputs {
-            self.client
-                .produce(&self.outputs_stream, output)
-                .await
-                .context(BrokerInternalSnafu)?;
-        }
+        self.client
+            .produce_many(&self.outputs_stream, outputs)
+            .await
+            .context(BrokerInternalSnafu)?;
 
         Ok(())
     }
@@ -156,16 +304,15 @@ mod tests {
         RollupsAdvanceStateInput, RollupsData, ADDRESS_SIZE, HASH_SIZE,
     };
     use test_fixtures::BrokerFixture;
-    use testcontainers::clients::Cli;
 
-    struct TestState<'d> {
-        fixture: BrokerFixture<'d>,
+    struct TestState {
+        fixture: BrokerFixture<'static>,
         facade: BrokerFacade,
     }
 
-    impl TestState<'_> {
-        async fn setup(docker: &Cli) -> TestState<'_> {
-            let fixture = BrokerFixture::setup(docker).await;
+    impl TestState {
+        async fn setup() -> TestState {
+            let fixture = BrokerFixture::setup_memory().await;
             let backoff = ExponentialBackoff::default();
             let dapp_metadata = DAppMetadata {
                 chain_id: fixture.chain_id(),
@@ -174,6 +321,9 @@ mod tests {
             let config = BrokerConfig {
                 redis_endpoint: fixture.redis_endpoint().to_owned(),
                 consume_timeout: 10,
+                retention: Default::default(),
+                payload_encoding: Default::default(),
+                consumer_group: None,
                 backoff,
             };
             let facade = BrokerFacade::new(config, dapp_metadata, false)
@@ -185,8 +335,7 @@ mod tests {
 
     #[test_log::test(tokio::test)]
     async fn test_it_consumes_inputs() {
-        let docker = Cli::default();
-        let mut state = TestState::setup(&docker).await;
+        let mut state = TestState::setup().await;
         let inputs = vec![
             RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
                 metadata: InputMetadata {
@@ -197,7 +346,7 @@ mod tests {
                 payload: Payload::new(vec![0, 0]),
                 tx_hash: Hash::default(),
             }),
-            RollupsData::FinishEpoch {},
+            RollupsData::FinishEpoch { boundary: None },
             RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
                 metadata: InputMetadata {
                     epoch_index: 1,
@@ -214,43 +363,80 @@ mod tests {
         }
         assert_eq!(
             state.facade.consume_input().await.unwrap(),
-            RollupsInput {
+            ConsumedInput::Next(RollupsInput {
                 parent_id: INITIAL_ID.to_owned(),
                 epoch_index: 0,
                 inputs_sent_count: 1,
                 data: inputs[0].clone(),
-            },
+            }),
         );
         assert_eq!(
             state.facade.consume_input().await.unwrap(),
-            RollupsInput {
+            ConsumedInput::Next(RollupsInput {
                 parent_id: ids[0].clone(),
                 epoch_index: 0,
                 inputs_sent_count: 1,
                 data: inputs[1].clone(),
-            },
+            }),
         );
         assert_eq!(
             state.facade.consume_input().await.unwrap(),
-            RollupsInput {
+            ConsumedInput::Next(RollupsInput {
                 parent_id: ids[1].clone(),
                 epoch_index: 1,
                 inputs_sent_count: 2,
                 data: inputs[2].clone(),
-            },
+            }),
         );
     }
 
+    #[test_log::test(tokio::test)]
+    async fn test_it_finds_reorg_branch() {
+        let mut state = TestState::setup().await;
+        let data = RollupsData::FinishEpoch { boundary: None };
+        let id0 = state.fixture.produce_input_event(data.clone()).await;
+        let id1 = state.fixture.produce_input_event(data.clone()).await;
+        let id2 = state.fixture.produce_input_event(data.clone()).await;
+        let input = RollupsInput {
+            parent_id: id1.clone(),
+            epoch_index: 2,
+            inputs_sent_count: 0,
+            data: data.clone(),
+        };
+        let id3 = state.fixture.produce_raw_input_event(input.clone()).await;
+        for _ in 0..3 {
+            state.facade.consume_input().await.unwrap();
+        }
+        let event = match state.facade.consume_input().await.unwrap() {
+            ConsumedInput::Reorg(event) => event,
+            ConsumedInput::Next(input) => panic!("expected reorg: {:?}", input),
+        };
+        assert_eq!(event.id, id3);
+        assert_eq!(event.payload, input);
+
+        let branch =
+            state.facade.find_branch(&id0, event.clone()).await.unwrap();
+        assert!(branch.from_restore_point);
+        let ids: Vec<_> = branch.events.iter().map(|e| e.id.clone()).collect();
+        assert_eq!(ids, vec![id1.clone(), id3.clone()]);
+
+        // The restore point is not in the branch
+        let branch = state.facade.find_branch(&id2, event).await.unwrap();
+        assert!(!branch.from_restore_point);
+        let ids: Vec<_> = branch.events.iter().map(|e| e.id.clone()).collect();
+        assert_eq!(ids, vec![id0, id1, id3]);
+    }
+
     #[test_log::test(tokio::test)]
     async fn test_it_does_not_produce_claim_when_it_was_already_produced() {
-        let docker = Cli::default();
-        let mut state = TestState::setup(&docker).await;
+        let mut state = TestState::setup().await;
         let rollups_claim = RollupsClaim {
             dapp_address: Address::new([0xa0; ADDRESS_SIZE]),
             epoch_index: 0,
             epoch_hash: Hash::new([0xb0; HASH_SIZE]),
             first_index: 0,
             last_index: 6,
+            ..Default::default()
         };
         state
             .fixture
@@ -267,16 +453,93 @@ mod tests {
         );
     }
 
+    #[test_log::test(tokio::test)]
+    async fn test_it_produces_claim_when_other_dapp_claimed_the_epoch() {
+        let mut state = TestState::setup().await;
+        let rollups_claim = |dapp: u8, epoch_index: u64| RollupsClaim {
+            dapp_address: Address::new([dapp; ADDRESS_SIZE]),
+            epoch_index,
+            epoch_hash: Hash::new([dapp; HASH_SIZE]),
+            ..Default::default()
+        };
+        // The claims of the other dapp are after the claim of this dapp and
+        // they don't fit in a single page
+        let mut claims = vec![rollups_claim(0xa0, 0)];
+        for epoch_index in 0..=CLAIMS_PAGE_SIZE as u64 {
+            claims.push(rollups_claim(0xa1, epoch_index));
+        }
+        for claim in claims.iter() {
+            state
+                .facade
+                .produce_rollups_claim(claim.clone())
+                .await
+                .unwrap();
+        }
+        // The epoch 0 of this dapp was already claimed, but not the epoch 1
+        state
+            .facade
+            .produce_rollups_claim(rollups_claim(0xa0, 0))
+            .await
+            .unwrap();
+        state
+            .facade
+            .produce_rollups_claim(rollups_claim(0xa0, 1))
+            .await
+            .unwrap();
+        claims.push(rollups_claim(0xa0, 1));
+        assert_eq!(state.fixture.consume_all_claims().await, claims);
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_it_produces_claims_of_epochs_replaced_by_reorg() {
+        let mut state = TestState::setup().await;
+        let rollups_claim = |epoch_index: u64, hash: u8| RollupsClaim {
+            dapp_address: Address::new([0xa0; ADDRESS_SIZE]),
+            epoch_index,
+            epoch_hash: Hash::new([hash; HASH_SIZE]),
+            ..Default::default()
+        };
+        let mut claims = vec![rollups_claim(0, 0xb0), rollups_claim(1, 0xb1)];
+        for claim in claims.iter() {
+            state
+                .facade
+                .produce_rollups_claim(claim.clone())
+                .await
+                .unwrap();
+        }
+        // The reorg dropped the epoch 1 and the runner replays the epoch 0
+        // with the same claim, then the epoch 1 with another one
+        for claim in [rollups_claim(0, 0xb0), rollups_claim(1, 0xc1)] {
+            state
+                .facade
+                .produce_rollups_claim(claim.clone())
+                .await
+                .unwrap();
+        }
+        claims.push(rollups_claim(1, 0xc1));
+        // The reorg dropped both epochs
+        for claim in [rollups_claim(0, 0xc0), rollups_claim(1, 0xd1)] {
+            state
+                .facade
+                .produce_rollups_claim(claim.clone())
+                .await
+                .unwrap();
+        }
+        claims.push(rollups_claim(0, 0xc0));
+        claims.push(rollups_claim(1, 0xd1));
+        assert_eq!(state.fixture.consume_all_claims().await, claims);
+    }
+
     #[test_log::test(tokio::test)]
     async fn test_it_produces_claims() {
-        let docker = Cli::default();
-        let mut state = TestState::setup(&docker).await;
+        let mut state = TestState::setup().await;
         let rollups_claim0 = RollupsClaim {
             dapp_address: Address::new([0xa0; ADDRESS_SIZE]),
             epoch_index: 0,
             epoch_hash: Hash::new([0xb0; HASH_SIZE]),
             first_index: 0,
             last_index: 0,
+            ..Default::default()
         };
         let rollups_claim1 = RollupsClaim {
             dapp_address: Address::new([0xa1; ADDRESS_SIZE]),
@@ -284,6 +547,7 @@ mod tests {
             epoch_hash: Hash::new([0xb1; HASH_SIZE]),
             first_index: 1,
             last_index: 1,
+            ..Default::default()
         };
         state
             .facade
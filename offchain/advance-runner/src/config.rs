@@ -6,6 +6,8 @@ use std::time::Duration;
 
 use crate::server_manager::ServerManagerCLIConfig;
 pub use crate::server_manager::ServerManagerConfig;
+use crate::snapshot::SnapshotCLIConfig;
+pub use crate::snapshot::SnapshotConfig;
 use log::{LogConfig, LogEnvCliConfig};
 pub use rollups_events::{
     BrokerCLIConfig, BrokerConfig, DAppMetadata, DAppMetadataCLIConfig,
@@ -14,6 +16,7 @@ pub use rollups_events::{
 #[derive(Debug, Clone)]
 pub struct AdvanceRunnerConfig {
     pub server_manager_config: ServerManagerConfig,
+    pub snapshot_config: SnapshotConfig,
     pub broker_config: BrokerConfig,
     pub dapp_metadata: DAppMetadata,
     pub log_config: LogConfig,
@@ -30,6 +33,7 @@ impl AdvanceRunnerConfig {
             cli_config.dapp_metadata_cli_config.into();
         let server_manager_config =
             ServerManagerConfig::parse_from_cli(cli_config.sm_cli_config);
+        let snapshot_config = cli_config.snapshot_cli_config.into();
 
         let log_config = LogConfig::initialize(cli_config.log_cli_config);
 
@@ -42,6 +46,7 @@ impl AdvanceRunnerConfig {
 
         Self {
             server_manager_config,
+            snapshot_config,
             broker_config,
             dapp_metadata,
             log_config,
@@ -59,6 +64,9 @@ struct CLIConfig {
     #[command(flatten)]
     sm_cli_config: ServerManagerCLIConfig,
 
+    #[command(flatten)]
+    snapshot_cli_config: SnapshotCLIConfig,
+
     #[command(flatten)]
     broker_cli_config: BrokerCLIConfig,
 
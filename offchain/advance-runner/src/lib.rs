@@ -7,6 +7,7 @@ use config::AdvanceRunnerConfig;
 use runner::Runner;
 use server_manager::ServerManagerFacade;
 use snafu::ResultExt;
+use snapshot::SnapshotManager;
 
 pub use broker::BrokerFacadeError;
 pub use error::AdvanceRunnerError;
@@ -17,6 +18,7 @@ pub mod config;
 mod error;
 pub mod runner;
 mod server_manager;
+mod snapshot;
 
 #[tracing::instrument(level = "trace", skip_all)]
 pub async fn run(
@@ -60,7 +62,11 @@ async fn start_advance_runner(
     .context(error::BrokerSnafu)?;
     tracing::trace!("connected the broker");
 
-    Runner::start(server_manager, broker)
+    tokio::spawn(broker.stream_trimmer().start());
+
+    let snapshot_manager = SnapshotManager::new(config.snapshot_config);
+
+    Runner::start(server_manager, broker, snapshot_manager)
         .await
         .context(error::RunnerSnafu)
 }
@@ -1,14 +1,32 @@
 // (c) Cartesi and individual authors (see AUTHORS)
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
-use rollups_events::{InputMetadata, RollupsData};
+use rollups_events::{
+    Event, InputMetadata, RollupsData, RollupsInput, INITIAL_ID,
+};
 use snafu::{ResultExt, Snafu};
 
-use crate::broker::{BrokerFacade, BrokerFacadeError};
+use crate::broker::{BrokerFacade, BrokerFacadeError, ConsumedInput};
 use crate::server_manager::{ServerManagerError, ServerManagerFacade};
+use crate::snapshot::{Snapshot, SnapshotError, SnapshotManager};
 
 #[derive(Debug, Snafu)]
 pub enum RunnerError {
+    #[snafu(display("failed to start server-manager session"))]
+    StartSessionError { source: ServerManagerError },
+
+    #[snafu(display("failed to restart server-manager session"))]
+    RestartSessionError { source: ServerManagerError },
+
+    #[snafu(display("failed to get latest snapshot"))]
+    GetLatestSnapshotError { source: SnapshotError },
+
+    #[snafu(display("failed to get snapshot storage directory"))]
+    GetStorageDirectoryError { source: SnapshotError },
+
+    #[snafu(display("failed to set latest snapshot"))]
+    SetLatestSnapshotError { source: SnapshotError },
+
     #[snafu(display("failed to send advance-state input to server-manager"))]
     AdvanceError { source: ServerManagerError },
 
@@ -32,6 +50,9 @@ pub enum RunnerError {
 
     #[snafu(display("failed to produce outputs in broker"))]
     ProduceOutputsError { source: BrokerFacadeError },
+
+    #[snafu(display("failed to acknowledge snapshot input in broker"))]
+    AcknowledgeInputError { source: BrokerFacadeError },
 }
 
 type Result<T> = std::result::Result<T, RunnerError>;
@@ -39,6 +60,10 @@ type Result<T> = std::result::Result<T, RunnerError>;
 pub struct Runner {
     server_manager: ServerManagerFacade,
     broker: BrokerFacade,
+    snapshot_manager: SnapshotManager,
+
+    /// Snapshot the current session was restored from, if any
+    snapshot: Option<Snapshot>,
 }
 
 impl Runner {
@@ -46,40 +71,110 @@ impl Runner {
     pub async fn start(
         server_manager: ServerManagerFacade,
         broker: BrokerFacade,
+        snapshot_manager: SnapshotManager,
     ) -> Result<()> {
+        let snapshot = snapshot_manager
+            .get_latest()
+            .context(GetLatestSnapshotSnafu)?;
+        tracing::info!(?snapshot, "starting from snapshot");
+
         let mut runner = Self {
             server_manager,
             broker,
+            snapshot_manager,
+            snapshot,
         };
 
+        runner
+            .server_manager
+            .start_session(runner.snapshot.as_ref())
+            .await
+            .context(StartSessionSnafu)?;
+        if let Some(snapshot) = &runner.snapshot {
+            runner.broker.resume_from(snapshot.event_id.clone());
+        }
+
         tracing::info!("starting runner main loop");
         loop {
-            let event = runner
+            let consumed = runner
                 .broker
                 .consume_input()
                 .await
                 .context(ConsumeInputSnafu)?;
-            tracing::info!(?event, "consumed input event");
-
-            match event.data {
-                RollupsData::AdvanceStateInput(input) => {
-                    runner
-                        .handle_advance(
-                            event.epoch_index,
-                            event.inputs_sent_count,
-                            input.metadata,
-                            input.payload.into_inner(),
-                        )
-                        .await?;
+
+            match consumed {
+                ConsumedInput::Next(event) => {
+                    tracing::info!(?event, "consumed input event");
+                    runner.handle_input(event).await?;
                 }
-                RollupsData::FinishEpoch {} => {
-                    runner.handle_finish(event.epoch_index).await?;
+                ConsumedInput::Reorg(event) => {
+                    tracing::warn!(?event, "consumed input event after reorg");
+                    runner.handle_reorg(event).await?;
                 }
             }
             tracing::info!("waiting for the next input event");
         }
     }
 
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn handle_input(&mut self, event: RollupsInput) -> Result<()> {
+        match event.data {
+            RollupsData::AdvanceStateInput(input) => {
+                self.handle_advance(
+                    event.epoch_index,
+                    event.inputs_sent_count,
+                    input.metadata,
+                    input.payload.into_inner(),
+                )
+                .await
+            }
+            RollupsData::FinishEpoch { .. } => {
+                self.handle_finish(event.epoch_index, event.inputs_sent_count)
+                    .await
+            }
+        }
+    }
+
+    /// Roll the machine back to the last snapshot before the reorg and replay
+    /// the inputs of the new branch. If the reorg goes past the snapshot,
+    /// replay every input from the machine template.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn handle_reorg(&mut self, event: Event<RollupsInput>) -> Result<()> {
+        let restore_id = match &self.snapshot {
+            Some(snapshot) => snapshot.event_id.clone(),
+            None => INITIAL_ID.to_owned(),
+        };
+        let branch = self
+            .broker
+            .find_branch(&restore_id, event)
+            .await
+            .context(ConsumeInputSnafu)?;
+        if !branch.from_restore_point {
+            tracing::warn!(
+                "reorg goes past the snapshot; replaying from start"
+            );
+            self.snapshot = None;
+        }
+
+        tracing::info!(
+            snapshot = ?self.snapshot,
+            inputs = branch.events.len(),
+            "rolling back server-manager session"
+        );
+        self.server_manager
+            .restart_session(self.snapshot.as_ref())
+            .await
+            .context(RestartSessionSnafu)?;
+
+        for event in branch.events {
+            tracing::info!(?event, "replaying input event");
+            self.broker.resume_from(event.id);
+            self.handle_input(event.payload).await?;
+        }
+
+        Ok(())
+    }
+
     #[tracing::instrument(level = "trace", skip_all)]
     async fn handle_advance(
         &mut self,
@@ -113,10 +208,24 @@ impl Runner {
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
-    async fn handle_finish(&mut self, epoch_index: u64) -> Result<()> {
+    async fn handle_finish(
+        &mut self,
+        epoch_index: u64,
+        inputs_sent_count: u64,
+    ) -> Result<()> {
         tracing::trace!("handling finish");
 
-        let result = self.server_manager.finish_epoch(epoch_index).await;
+        // The snapshot starts a session at the next epoch
+        let next_epoch_index = epoch_index + 1;
+        let storage_directory = self
+            .snapshot_manager
+            .get_storage_directory(next_epoch_index, inputs_sent_count)
+            .context(GetStorageDirectorySnafu)?;
+
+        let result = self
+            .server_manager
+            .finish_epoch(epoch_index, storage_directory.as_deref())
+            .await;
         tracing::trace!("finished epoch in server-manager");
 
         match result {
@@ -141,6 +250,29 @@ impl Runner {
                 }
             }
         }
+
+        // Only mark the snapshot as valid after producing the outputs
+        if let Some(path) = storage_directory {
+            let snapshot = Snapshot {
+                path,
+                epoch: next_epoch_index,
+                processed_input_count: inputs_sent_count,
+                event_id: self.broker.last_id().to_owned(),
+            };
+            self.snapshot_manager
+                .set_latest(&snapshot)
+                .context(SetLatestSnapshotSnafu)?;
+            tracing::info!(?snapshot, "stored snapshot");
+
+            // The machine is restored from the snapshot, so the previous
+            // inputs are no longer needed
+            self.broker
+                .acknowledge_input(&snapshot.event_id)
+                .await
+                .context(AcknowledgeInputSnafu)?;
+            self.snapshot = Some(snapshot);
+        }
+
         Ok(())
     }
 }
@@ -7,6 +7,7 @@ use rollups_events::{
     RollupsClaim, RollupsNotice, RollupsOutput, RollupsReport, RollupsVoucher,
 };
 use snafu::{OptionExt, ResultExt};
+use std::path::Path;
 use tonic::{transport::Channel, Request};
 use uuid::Uuid;
 
@@ -29,6 +30,7 @@ use super::error::{
     ConnectionSnafu, EmptyEpochSnafu, InvalidProcessedInputSnafu,
     ServerManagerError,
 };
+use crate::snapshot::Snapshot;
 
 /// Call the grpc method passing an unique request-id and with retry
 macro_rules! grpc_call {
@@ -115,59 +117,100 @@ impl ServerManagerFacade {
         };
 
         // If session exists, delete it before creating new one
-        let response = grpc_call!(sm_facade, get_status, Void {})?;
-        if response.session_id.contains(&sm_facade.config.session_id) {
-            tracing::warn!("deleting previous server-manager session");
-            let session_status = grpc_call!(
-                sm_facade,
-                get_session_status,
-                GetSessionStatusRequest {
-                    session_id: sm_facade.config.session_id.clone(),
-                }
-            )?;
-            let active_epoch_index = session_status.active_epoch_index;
-            let processed_input_count_within_epoch = sm_facade
-                .wait_for_pending_inputs(active_epoch_index)
-                .await?
-                .len()
-                as u64;
-            grpc_call!(
-                sm_facade,
-                finish_epoch,
-                FinishEpochRequest {
-                    session_id: sm_facade.config.session_id.clone(),
-                    active_epoch_index,
-                    processed_input_count_within_epoch,
-                    storage_directory: "".to_string(),
-                }
-            )?;
-            grpc_call!(
-                sm_facade,
-                end_session,
-                EndSessionRequest {
-                    session_id: sm_facade.config.session_id.clone(),
-                }
-            )?;
-        }
+        sm_facade.delete_session().await?;
+
+        Ok(sm_facade)
+    }
+
+    /// Delete the current session and start it again from the given snapshot
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn restart_session(
+        &mut self,
+        snapshot: Option<&Snapshot>,
+    ) -> Result<()> {
+        self.delete_session().await?;
+        self.start_session(snapshot).await
+    }
 
-        tracing::trace!("starting server-manager session");
+    /// Start the server-manager session from the given snapshot
+    /// If there is no snapshot, start from the machine template at epoch 0.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn start_session(
+        &mut self,
+        snapshot: Option<&Snapshot>,
+    ) -> Result<()> {
+        let (machine_directory, active_epoch_index, processed_input_count) =
+            match snapshot {
+                Some(snapshot) => (
+                    snapshot.path.to_string_lossy().to_string(),
+                    snapshot.epoch,
+                    snapshot.processed_input_count,
+                ),
+                None => (self.config.machine_snapshot_path.clone(), 0, 0),
+            };
 
-        grpc_call!(sm_facade, start_session, {
+        tracing::trace!(
+            machine_directory,
+            active_epoch_index,
+            processed_input_count,
+            "starting server-manager session"
+        );
+
+        grpc_call!(self, start_session, {
             StartSessionRequest {
-                session_id: sm_facade.config.session_id.clone(),
-                machine_directory: sm_facade
-                    .config
-                    .machine_snapshot_path
-                    .clone(),
-                runtime: Some(sm_facade.config.runtime_config.clone()),
-                active_epoch_index: 0,
-                processed_input_count: 0,
-                server_cycles: Some(sm_facade.config.cycles_config.clone()),
-                server_deadline: Some(sm_facade.config.deadline_config.clone()),
+                session_id: self.config.session_id.clone(),
+                machine_directory: machine_directory.clone(),
+                runtime: Some(self.config.runtime_config.clone()),
+                active_epoch_index,
+                processed_input_count,
+                server_cycles: Some(self.config.cycles_config.clone()),
+                server_deadline: Some(self.config.deadline_config.clone()),
             }
         })?;
 
-        Ok(sm_facade)
+        Ok(())
+    }
+
+    /// Delete the session if it exists
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn delete_session(&mut self) -> Result<()> {
+        let response = grpc_call!(self, get_status, Void {})?;
+        if !response.session_id.contains(&self.config.session_id) {
+            return Ok(());
+        }
+
+        tracing::warn!("deleting previous server-manager session");
+        let session_status = grpc_call!(
+            self,
+            get_session_status,
+            GetSessionStatusRequest {
+                session_id: self.config.session_id.clone(),
+            }
+        )?;
+        let active_epoch_index = session_status.active_epoch_index;
+        let processed_input_count_within_epoch = self
+            .wait_for_pending_inputs(active_epoch_index)
+            .await?
+            .len() as u64;
+        grpc_call!(
+            self,
+            finish_epoch,
+            FinishEpochRequest {
+                session_id: self.config.session_id.clone(),
+                active_epoch_index,
+                processed_input_count_within_epoch,
+                storage_directory: "".to_string(),
+            }
+        )?;
+        grpc_call!(
+            self,
+            end_session,
+            EndSessionRequest {
+                session_id: self.config.session_id.clone(),
+            }
+        )?;
+
+        Ok(())
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
@@ -272,13 +315,24 @@ impl ServerManagerFacade {
     }
 
     /// Send a finish-epoch request to the server-manager
+    /// If the storage directory is set, the server-manager stores the machine
+    /// snapshot in it.
     /// Return the epoch claim and the proofs
     #[tracing::instrument(level = "trace", skip_all)]
     pub async fn finish_epoch(
         &mut self,
         epoch_index: u64,
+        storage_directory: Option<&Path>,
     ) -> Result<(RollupsClaim, Vec<RollupsOutput>)> {
-        tracing::trace!(epoch_index, "sending finish epoch");
+        tracing::trace!(
+            epoch_index,
+            ?storage_directory,
+            "sending finish epoch"
+        );
+
+        let storage_directory = storage_directory
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default();
 
         // Wait for pending inputs before sending a finish request
         let processed_inputs =
@@ -290,7 +344,7 @@ impl ServerManagerFacade {
                 session_id: self.config.session_id.to_owned(),
                 active_epoch_index: epoch_index,
                 processed_input_count_within_epoch,
-                storage_directory: "".to_owned(),
+                storage_directory: storage_directory.clone(),
             }
         })?;
 
@@ -326,6 +380,9 @@ impl ServerManagerFacade {
             epoch_hash,
             first_index: first_input.input_index as u128,
             last_index: last_input.input_index as u128,
+            machine_state_hash,
+            vouchers_epoch_root_hash: vouchers_metadata_hash,
+            notices_epoch_root_hash: notices_metadata_hash,
         };
 
         Ok((rollups_claim, proofs))
@@ -0,0 +1,30 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+use clap::Parser;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotConfig {
+    /// Directory where the snapshots are stored; None disables snapshots
+    pub snapshot_dir: Option<PathBuf>,
+}
+
+impl From<SnapshotCLIConfig> for SnapshotConfig {
+    fn from(cli_config: SnapshotCLIConfig) -> Self {
+        Self {
+            snapshot_dir: cli_config.snapshot_dir,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "snapshot_config")]
+pub struct SnapshotCLIConfig {
+    /// Path to the directory where the advance-runner stores a machine
+    /// snapshot at the end of each epoch. The directory must be shared with
+    /// the server-manager. If not set, snapshots are disabled and the
+    /// advance-runner replays every input from the beginning of the stream.
+    #[arg(long, env)]
+    pub snapshot_dir: Option<PathBuf>,
+}
@@ -0,0 +1,296 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Manages the machine snapshots stored by the server-manager at the end of
+//! each epoch.
+//!
+//! Each snapshot is stored in the directory `<epoch>_<processed_input_count>`,
+//! where `epoch` is the active epoch index when the session is restarted from
+//! the snapshot. A snapshot is only valid after the advance-runner writes the
+//! marker file `<epoch>_<processed_input_count>.event-id` next to it. This file
+//! contains the id of the broker event that finished the previous epoch, so the
+//! advance-runner knows where to resume consuming inputs.
+use snafu::{ResultExt, Snafu};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+mod config;
+
+pub use config::{SnapshotCLIConfig, SnapshotConfig};
+
+const EVENT_ID_EXTENSION: &str = "event-id";
+
+#[derive(Debug, Snafu)]
+pub enum SnapshotError {
+    #[snafu(display("failed to read snapshots directory {}", path.display()))]
+    ReadDirError { path: PathBuf, source: io::Error },
+
+    #[snafu(display("failed to read snapshot event id {}", path.display()))]
+    ReadEventIdError { path: PathBuf, source: io::Error },
+
+    #[snafu(display("failed to write snapshot event id {}", path.display()))]
+    WriteEventIdError { path: PathBuf, source: io::Error },
+
+    #[snafu(display("failed to remove snapshot {}", path.display()))]
+    RemoveError { path: PathBuf, source: io::Error },
+}
+
+pub type Result<T> = std::result::Result<T, SnapshotError>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Path to the machine snapshot
+    pub path: PathBuf,
+
+    /// Active epoch index when starting a session from this snapshot
+    pub epoch: u64,
+
+    /// Number of inputs processed by the machine in the snapshot
+    pub processed_input_count: u64,
+
+    /// Id of the last broker input event processed by the machine
+    pub event_id: String,
+}
+
+#[derive(Debug)]
+pub struct SnapshotManager {
+    config: SnapshotConfig,
+}
+
+impl SnapshotManager {
+    pub fn new(config: SnapshotConfig) -> Self {
+        Self { config }
+    }
+
+    /// Get the newest valid snapshot
+    /// Return None if snapshots are disabled or there is no valid snapshot.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn get_latest(&self) -> Result<Option<Snapshot>> {
+        let snapshot_dir = match &self.config.snapshot_dir {
+            Some(snapshot_dir) => snapshot_dir,
+            None => return Ok(None),
+        };
+
+        tracing::trace!(?snapshot_dir, "looking for latest snapshot");
+
+        let mut latest: Option<(u64, u64, PathBuf)> = None;
+        for (epoch, processed_input_count, path) in
+            list_snapshots(snapshot_dir)?
+        {
+            if !path.is_dir() || !event_id_path(&path).is_file() {
+                tracing::trace!(?path, "ignoring incomplete snapshot");
+                continue;
+            }
+            let is_newer = match &latest {
+                Some((latest_epoch, latest_count, _)) => {
+                    (epoch, processed_input_count)
+                        > (*latest_epoch, *latest_count)
+                }
+                None => true,
+            };
+            if is_newer {
+                latest = Some((epoch, processed_input_count, path));
+            }
+        }
+
+        let snapshot = match latest {
+            Some((epoch, processed_input_count, path)) => {
+                let event_id_path = event_id_path(&path);
+                let event_id = fs::read_to_string(&event_id_path)
+                    .context(ReadEventIdSnafu {
+                        path: event_id_path,
+                    })?
+                    .trim()
+                    .to_owned();
+                Some(Snapshot {
+                    path,
+                    epoch,
+                    processed_input_count,
+                    event_id,
+                })
+            }
+            None => None,
+        };
+
+        tracing::trace!(?snapshot, "got latest snapshot");
+        Ok(snapshot)
+    }
+
+    /// Get the directory where the server-manager should store the snapshot
+    /// Return None if snapshots are disabled.
+    /// Leftovers of a previous attempt to store the same snapshot are removed
+    /// because the server-manager requires the directory to not exist.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn get_storage_directory(
+        &self,
+        epoch: u64,
+        processed_input_count: u64,
+    ) -> Result<Option<PathBuf>> {
+        let snapshot_dir = match &self.config.snapshot_dir {
+            Some(snapshot_dir) => snapshot_dir,
+            None => return Ok(None),
+        };
+
+        let path =
+            snapshot_dir.join(format!("{}_{}", epoch, processed_input_count));
+        remove_snapshot(&path)?;
+
+        tracing::trace!(?path, "got storage directory");
+        Ok(Some(path))
+    }
+
+    /// Mark the snapshot as valid and remove the older ones
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn set_latest(&self, snapshot: &Snapshot) -> Result<()> {
+        let snapshot_dir = match &self.config.snapshot_dir {
+            Some(snapshot_dir) => snapshot_dir,
+            None => return Ok(()),
+        };
+
+        tracing::trace!(?snapshot, "setting latest snapshot");
+
+        // Write to a temporary file and rename it so the marker is atomic
+        let event_id_path = event_id_path(&snapshot.path);
+        let tmp_path = event_id_path.with_extension("tmp");
+        fs::write(&tmp_path, &snapshot.event_id)
+            .and_then(|_| fs::rename(&tmp_path, &event_id_path))
+            .context(WriteEventIdSnafu {
+                path: event_id_path,
+            })?;
+
+        for (_, _, path) in list_snapshots(snapshot_dir)? {
+            if path != snapshot.path {
+                tracing::trace!(?path, "removing old snapshot");
+                remove_snapshot(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn event_id_path(snapshot_path: &Path) -> PathBuf {
+    snapshot_path.with_extension(EVENT_ID_EXTENSION)
+}
+
+/// List the snapshot directories as (epoch, processed_input_count, path)
+fn list_snapshots(snapshot_dir: &Path) -> Result<Vec<(u64, u64, PathBuf)>> {
+    let entries = match fs::read_dir(snapshot_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(source) => {
+            return Err(SnapshotError::ReadDirError {
+                path: snapshot_dir.to_owned(),
+                source,
+            })
+        }
+    };
+
+    let mut snapshots = vec![];
+    for entry in entries {
+        let entry = entry.context(ReadDirSnafu {
+            path: snapshot_dir.to_owned(),
+        })?;
+        let path = entry.path();
+        if path.extension().is_some() {
+            continue;
+        }
+        let name = entry.file_name();
+        let parsed = name.to_str().and_then(|name| {
+            let (epoch, count) = name.split_once('_')?;
+            Some((epoch.parse().ok()?, count.parse().ok()?))
+        });
+        if let Some((epoch, processed_input_count)) = parsed {
+            snapshots.push((epoch, processed_input_count, path));
+        }
+    }
+    Ok(snapshots)
+}
+
+/// Remove the snapshot directory and its marker if they exist
+fn remove_snapshot(path: &Path) -> Result<()> {
+    let event_id_path = event_id_path(path);
+    if event_id_path.exists() {
+        fs::remove_file(&event_id_path).context(RemoveSnafu {
+            path: event_id_path,
+        })?;
+    }
+    if path.exists() {
+        fs::remove_dir_all(path).context(RemoveSnafu { path })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, SnapshotManager) {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let manager = SnapshotManager::new(SnapshotConfig {
+            snapshot_dir: Some(dir.path().to_owned()),
+        });
+        (dir, manager)
+    }
+
+    fn store(manager: &SnapshotManager, epoch: u64, count: u64) -> Snapshot {
+        let path = manager
+            .get_storage_directory(epoch, count)
+            .unwrap()
+            .unwrap();
+        fs::create_dir(&path).unwrap();
+        Snapshot {
+            path,
+            epoch,
+            processed_input_count: count,
+            event_id: format!("{}-0", epoch),
+        }
+    }
+
+    #[test]
+    fn test_it_returns_none_when_disabled() {
+        let manager = SnapshotManager::new(SnapshotConfig::default());
+        assert_eq!(manager.get_latest().unwrap(), None);
+        assert_eq!(manager.get_storage_directory(1, 1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_it_returns_none_when_there_are_no_snapshots() {
+        let (_dir, manager) = setup();
+        assert_eq!(manager.get_latest().unwrap(), None);
+    }
+
+    #[test]
+    fn test_it_ignores_snapshots_without_event_id() {
+        let (_dir, manager) = setup();
+        let snapshot = store(&manager, 1, 2);
+        manager.set_latest(&snapshot).unwrap();
+        store(&manager, 2, 4);
+        assert_eq!(manager.get_latest().unwrap(), Some(snapshot));
+    }
+
+    #[test]
+    fn test_it_returns_latest_and_removes_older_snapshots() {
+        let (_dir, manager) = setup();
+        let snapshot1 = store(&manager, 1, 2);
+        manager.set_latest(&snapshot1).unwrap();
+        let snapshot2 = store(&manager, 2, 4);
+        manager.set_latest(&snapshot2).unwrap();
+        assert_eq!(manager.get_latest().unwrap(), Some(snapshot2));
+        assert!(!snapshot1.path.exists());
+        assert!(!event_id_path(&snapshot1.path).exists());
+    }
+
+    #[test]
+    fn test_it_removes_leftovers_from_storage_directory() {
+        let (_dir, manager) = setup();
+        let snapshot = store(&manager, 1, 2);
+        manager.set_latest(&snapshot).unwrap();
+        let path = manager.get_storage_directory(1, 2).unwrap().unwrap();
+        assert_eq!(path, snapshot.path);
+        assert!(!path.exists());
+        assert_eq!(manager.get_latest().unwrap(), None);
+    }
+}
@@ -3,6 +3,7 @@
 
 use advance_runner::config::{
     AdvanceRunnerConfig, BrokerConfig, DAppMetadata, ServerManagerConfig,
+    SnapshotConfig,
 };
 use advance_runner::AdvanceRunnerError;
 use grpc_interfaces::cartesi_machine::{
@@ -73,6 +74,9 @@ impl AdvanceRunnerFixture {
         let broker_config = BrokerConfig {
             redis_endpoint,
             consume_timeout: 100,
+            retention: Default::default(),
+            payload_encoding: Default::default(),
+            consumer_group: None,
             backoff: Default::default(),
         };
 
@@ -80,6 +84,7 @@ impl AdvanceRunnerFixture {
 
         let config = AdvanceRunnerConfig {
             server_manager_config,
+            snapshot_config: SnapshotConfig::default(),
             broker_config,
             dapp_metadata,
             backoff_max_elapsed_duration,
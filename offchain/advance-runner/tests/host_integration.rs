@@ -4,8 +4,8 @@
 use fixtures::AdvanceRunnerFixture;
 use rand::Rng;
 use rollups_events::{
-    Hash, InputMetadata, Payload, RollupsAdvanceStateInput, RollupsClaim,
-    RollupsData, RollupsInput, INITIAL_ID,
+    Hash, InputMetadata, Payload, RollupsAdvanceStateInput, RollupsData,
+    RollupsInput, INITIAL_ID,
 };
 use test_fixtures::{BrokerFixture, EchoDAppFixture, HostServerManagerFixture};
 use testcontainers::clients::Cli;
@@ -173,7 +173,7 @@ async fn advance_runner_generates_claim_after_finishing_epoch() {
                 payload: generate_payload(),
                 tx_hash: Hash::default(),
             });
-        let finish = RollupsData::FinishEpoch {};
+        let finish = RollupsData::FinishEpoch { boundary: None };
         state.broker.produce_input_event(advance).await;
         state.broker.produce_input_event(finish).await;
     }
@@ -194,11 +194,11 @@ async fn advance_runner_finishes_epoch_when_the_previous_epoch_has_inputs() {
     tracing::info!("finishing epochs with no inputs");
     state
         .broker
-        .produce_input_event(RollupsData::FinishEpoch {})
+        .produce_input_event(RollupsData::FinishEpoch { boundary: None })
         .await;
     state
         .broker
-        .produce_input_event(RollupsData::FinishEpoch {})
+        .produce_input_event(RollupsData::FinishEpoch { boundary: None })
         .await;
 
     tracing::info!("waiting until second epoch is finished");
@@ -226,7 +226,7 @@ async fn finish_epoch_and_wait_for_next_input(state: &TestState<'_>) {
             payload: Default::default(),
             tx_hash: Hash::default(),
         }),
-        RollupsData::FinishEpoch {},
+        RollupsData::FinishEpoch { boundary: None },
         RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
             metadata: InputMetadata {
                 input_index: 1,
@@ -249,6 +249,52 @@ async fn finish_epoch_and_wait_for_next_input(state: &TestState<'_>) {
         .await;
 }
 
+/// Rewind the inputs to the beginning of the stream, as if a reorg dropped
+/// the previous epoch, and send the given input, an finish epoch, and another
+/// input. Wait until the advance_runner processes the last input.
+async fn reorg_and_wait_for_next_input(
+    state: &TestState<'_>,
+    payload: Payload,
+) {
+    tracing::info!("producing input after reorg, finish, and another input");
+    let data = RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
+        metadata: InputMetadata {
+            input_index: 0,
+            ..Default::default()
+        },
+        payload,
+        tx_hash: Hash::default(),
+    });
+    let input = RollupsInput {
+        parent_id: INITIAL_ID.to_owned(),
+        epoch_index: 0,
+        inputs_sent_count: 1,
+        data,
+    };
+    state.broker.produce_raw_input_event(input).await;
+    let next_payload = generate_payload();
+    let inputs = vec![
+        RollupsData::FinishEpoch { boundary: None },
+        RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
+            metadata: InputMetadata {
+                input_index: 1,
+                ..Default::default()
+            },
+            payload: next_payload.clone(),
+            tx_hash: Hash::default(),
+        }),
+    ];
+    for input in inputs {
+        state.broker.produce_input_event(input).await;
+    }
+
+    tracing::info!("waiting until the input after the reorg is processed");
+    state
+        .server_manager
+        .assert_epoch_status_payloads(1, &vec![next_payload])
+        .await;
+}
+
 #[test_log::test(tokio::test)]
 async fn advance_runner_sends_inputs_after_finishing_epoch() {
     let docker = Cli::default();
@@ -261,16 +307,16 @@ async fn advance_runner_does_not_generate_duplicate_claim() {
     let docker = Cli::default();
     let state = TestState::setup(&docker).await;
 
-    tracing::info!("producing claim");
-    let claim = RollupsClaim::default();
-    state.broker.produce_rollups_claim(claim.clone()).await;
-
     finish_epoch_and_wait_for_next_input(&state).await;
+    let claims = state.broker.consume_all_claims().await;
+    assert_eq!(claims.len(), 1);
+
+    // Replaying the same input gives the same claim
+    reorg_and_wait_for_next_input(&state, Default::default()).await;
 
     tracing::info!("getting all claims");
     let produced_claims = state.broker.consume_all_claims().await;
-    assert_eq!(produced_claims.len(), 1);
-    assert_eq!(produced_claims[0], claim);
+    assert_eq!(produced_claims, claims);
 }
 
 #[test_log::test(tokio::test)]
@@ -4,8 +4,8 @@
 use fixtures::AdvanceRunnerFixture;
 use rand::Rng;
 use rollups_events::{
-    Hash, InputMetadata, Payload, RollupsAdvanceStateInput, RollupsClaim,
-    RollupsData, RollupsInput, INITIAL_ID,
+    Hash, InputMetadata, Payload, RollupsAdvanceStateInput, RollupsData,
+    RollupsInput, INITIAL_ID,
 };
 use test_fixtures::{
     BrokerFixture, MachineSnapshotsFixture, ServerManagerFixture,
@@ -169,7 +169,7 @@ async fn test_advance_runner_generates_claim_after_finishing_epoch() {
                 payload: generate_payload(),
                 tx_hash: Hash::default(),
             });
-        let finish = RollupsData::FinishEpoch {};
+        let finish = RollupsData::FinishEpoch { boundary: None };
         state.broker.produce_input_event(advance).await;
         state.broker.produce_input_event(finish).await;
     }
@@ -190,11 +190,11 @@ async fn test_advance_runner_finishes_epoch_when_it_has_no_inputs() {
     tracing::info!("finishing epochs with no inputs");
     state
         .broker
-        .produce_input_event(RollupsData::FinishEpoch {})
+        .produce_input_event(RollupsData::FinishEpoch { boundary: None })
         .await;
     state
         .broker
-        .produce_input_event(RollupsData::FinishEpoch {})
+        .produce_input_event(RollupsData::FinishEpoch { boundary: None })
         .await;
 
     tracing::info!("waiting until second epoch is finished");
@@ -222,7 +222,7 @@ async fn finish_epoch_and_wait_for_next_input(state: &TestState<'_>) {
             payload: Default::default(),
             tx_hash: Hash::default(),
         }),
-        RollupsData::FinishEpoch {},
+        RollupsData::FinishEpoch { boundary: None },
         RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
             metadata: InputMetadata {
                 input_index: 1,
@@ -245,6 +245,52 @@ async fn finish_epoch_and_wait_for_next_input(state: &TestState<'_>) {
         .await;
 }
 
+/// Rewind the inputs to the beginning of the stream, as if a reorg dropped
+/// the previous epoch, and send the given input, an finish epoch, and another
+/// input. Wait until the advance_runner processes the last input.
+async fn reorg_and_wait_for_next_input(
+    state: &TestState<'_>,
+    payload: Payload,
+) {
+    tracing::info!("producing input after reorg, finish, and another input");
+    let data = RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
+        metadata: InputMetadata {
+            input_index: 0,
+            ..Default::default()
+        },
+        payload,
+        tx_hash: Hash::default(),
+    });
+    let input = RollupsInput {
+        parent_id: INITIAL_ID.to_owned(),
+        epoch_index: 0,
+        inputs_sent_count: 1,
+        data,
+    };
+    state.broker.produce_raw_input_event(input).await;
+    let next_payload = generate_payload();
+    let inputs = vec![
+        RollupsData::FinishEpoch { boundary: None },
+        RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
+            metadata: InputMetadata {
+                input_index: 1,
+                ..Default::default()
+            },
+            payload: next_payload.clone(),
+            tx_hash: Hash::default(),
+        }),
+    ];
+    for input in inputs {
+        state.broker.produce_input_event(input).await;
+    }
+
+    tracing::info!("waiting until the input after the reorg is processed");
+    state
+        .server_manager
+        .assert_epoch_status_payloads(1, &vec![next_payload])
+        .await;
+}
+
 #[test_log::test(tokio::test)]
 async fn test_advance_runner_sends_inputs_after_finishing_epoch() {
     let docker = Cli::default();
@@ -257,19 +303,36 @@ async fn test_advance_runner_does_not_generate_duplicate_claim() {
     let docker = Cli::default();
     let state = TestState::setup(&docker).await;
 
-    tracing::info!("producing claim");
-    let rollups_claim = RollupsClaim::default();
-    state
-        .broker
-        .produce_rollups_claim(rollups_claim.clone())
-        .await;
+    finish_epoch_and_wait_for_next_input(&state).await;
+    let claims = state.broker.consume_all_claims().await;
+    assert_eq!(claims.len(), 1);
+
+    // Replaying the same input gives the same claim
+    reorg_and_wait_for_next_input(&state, Default::default()).await;
+
+    tracing::info!("getting all claims");
+    let produced_claims = state.broker.consume_all_claims().await;
+    assert_eq!(produced_claims, claims);
+}
+
+#[test_log::test(tokio::test)]
+async fn test_advance_runner_generates_claim_after_reorg_past_finish_epoch() {
+    let docker = Cli::default();
+    let state = TestState::setup(&docker).await;
 
     finish_epoch_and_wait_for_next_input(&state).await;
+    let claims = state.broker.consume_all_claims().await;
+    assert_eq!(claims.len(), 1);
+
+    // Another input in the same epoch gives a claim with another hash
+    reorg_and_wait_for_next_input(&state, generate_payload()).await;
 
     tracing::info!("getting all claims");
     let produced_claims = state.broker.consume_all_claims().await;
-    assert_eq!(produced_claims.len(), 1);
-    assert_eq!(produced_claims[0].epoch_hash, rollups_claim.epoch_hash);
+    assert_eq!(produced_claims.len(), 2);
+    assert_eq!(produced_claims[0], claims[0]);
+    assert_eq!(produced_claims[1].epoch_index, claims[0].epoch_index);
+    assert_ne!(produced_claims[1].epoch_hash, claims[0].epoch_hash);
 }
 
 #[test_log::test(tokio::test)]
@@ -4,20 +4,26 @@
 use async_trait::async_trait;
 use snafu::ResultExt;
 use std::fmt::Debug;
-use tracing::{info, trace};
+use tracing::{info, trace, warn};
+
+use ethers::types::H256;
+use rollups_data::ClaimStatus;
+use rollups_events::RollupsClaim;
 
 use crate::{
     checker::DuplicateChecker, listener::BrokerListener,
-    sender::TransactionSender,
+    recorder::ClaimRecorder, sender::TransactionSender,
 };
 
 /// The `Claimer` starts an event loop that waits for claim messages
 /// from the broker, and then sends the claims to the blockchain. It checks to
 /// see if the claim is duplicated before sending.
 ///
-/// It uses three injected traits, `BrokerListener`, `DuplicateChecker`, and
-/// `TransactionSender`, to, respectivelly, listen for messages, check for
-/// duplicated claims, and send claims to the blockchain.
+/// It uses four injected traits, `BrokerListener`, `DuplicateChecker`,
+/// `TransactionSender`, and `ClaimRecorder`, to, respectivelly, listen for
+/// messages, check for duplicated claims, send claims to the blockchain, and
+/// record the submission status of the claims. The claims are recorded after
+/// they are handled, so failing to record a claim doesn't stop the loop.
 #[async_trait]
 pub trait Claimer: Sized + Debug {
     type Error: snafu::Error + 'static;
@@ -45,41 +51,66 @@ pub enum ClaimerError<
 // DefaultClaimer
 // ------------------------------------------------------------------------------------------------
 
-/// The `DefaultClaimer` must be injected with a
-/// `BrokerListener`, a `DuplicateChecker` and a `TransactionSender`.
+/// The `DefaultClaimer` must be injected with a `BrokerListener`, a
+/// `DuplicateChecker`, a `TransactionSender` and a `ClaimRecorder`.
 #[derive(Debug)]
 pub struct DefaultClaimer<
     B: BrokerListener,
     D: DuplicateChecker,
     T: TransactionSender,
+    R: ClaimRecorder,
 > {
     broker_listener: B,
     duplicate_checker: D,
     transaction_sender: T,
+    claim_recorder: R,
 }
 
-impl<B: BrokerListener, D: DuplicateChecker, T: TransactionSender>
-    DefaultClaimer<B, D, T>
+impl<
+        B: BrokerListener,
+        D: DuplicateChecker,
+        T: TransactionSender,
+        R: ClaimRecorder,
+    > DefaultClaimer<B, D, T, R>
 {
     pub fn new(
         broker_listener: B,
         duplicate_checker: D,
         transaction_sender: T,
+        claim_recorder: R,
     ) -> Self {
         Self {
             broker_listener,
             duplicate_checker,
             transaction_sender,
+            claim_recorder,
+        }
+    }
+
+    /// Record the claim, logging the error if the repository fails
+    async fn record_rollups_claim(
+        &mut self,
+        rollups_claim: RollupsClaim,
+        status: ClaimStatus,
+        transaction_hash: Option<H256>,
+    ) {
+        if let Err(err) = self
+            .claim_recorder
+            .record_rollups_claim(rollups_claim, status, transaction_hash)
+            .await
+        {
+            warn!("Failed to record the claim: {:?}", err);
         }
     }
 }
 
 #[async_trait]
-impl<B, D, T> Claimer for DefaultClaimer<B, D, T>
+impl<B, D, T, R> Claimer for DefaultClaimer<B, D, T, R>
 where
     B: BrokerListener + Send + Sync + 'static,
     D: DuplicateChecker + Send + Sync + 'static,
     T: TransactionSender + Send + 'static,
+    R: ClaimRecorder + Send + Sync + 'static,
 {
     type Error = ClaimerError<B, D, T>;
 
@@ -100,15 +131,29 @@ where
                 .context(DuplicatedClaimSnafu)?;
             if is_duplicated_rollups_claim {
                 trace!("It was a duplicated claim");
+                self.record_rollups_claim(
+                    rollups_claim,
+                    ClaimStatus::Duplicated,
+                    None,
+                )
+                .await;
                 continue;
             }
 
             info!("Sending a new rollups claim");
-            self.transaction_sender = self
+            let (transaction_sender, transaction_hash) = self
                 .transaction_sender
-                .send_rollups_claim_transaction(rollups_claim)
+                .send_rollups_claim_transaction(rollups_claim.clone())
                 .await
-                .context(TransactionSenderSnafu)?
+                .context(TransactionSenderSnafu)?;
+            self.transaction_sender = transaction_sender;
+
+            self.record_rollups_claim(
+                rollups_claim,
+                ClaimStatus::Submitted,
+                Some(transaction_hash),
+            )
+            .await;
         }
     }
 }
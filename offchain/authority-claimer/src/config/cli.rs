@@ -8,6 +8,7 @@ use eth_tx_manager::{
 };
 use log::{LogConfig, LogEnvCliConfig};
 use redacted::Redacted;
+use rollups_data::RepositoryCLIConfig;
 use rollups_events::{BrokerCLIConfig, BrokerConfig};
 use rusoto_core::Region;
 use snafu::ResultExt;
@@ -41,6 +42,9 @@ pub(crate) struct AuthorityClaimerCLI {
     #[command(flatten)]
     pub broker_config: BrokerCLIConfig,
 
+    #[command(flatten)]
+    pub repository_config: RepositoryCLIConfig,
+
     #[command(flatten)]
     pub log_config: LogEnvCliConfig,
 
@@ -77,6 +81,7 @@ impl TryFrom<AuthorityClaimerCLI> for AuthorityClaimerConfig {
             tx_signing_config,
             tx_manager_priority: Priority::Normal,
             broker_config,
+            repository_config: cli_config.repository_config.into(),
             log_config,
             contracts_config,
             genesis_block: cli_config.genesis_block,
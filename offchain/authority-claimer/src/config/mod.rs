@@ -13,6 +13,7 @@ use eth_tx_manager::{config::TxManagerConfig, Priority};
 use http_server::HttpServerConfig;
 use log::LogConfig;
 use redacted::Redacted;
+use rollups_data::RepositoryConfig;
 use rollups_events::BrokerConfig;
 use rusoto_core::Region;
 
@@ -28,6 +29,7 @@ pub struct AuthorityClaimerConfig {
     pub tx_signing_config: TxSigningConfig,
     pub tx_manager_priority: Priority,
     pub broker_config: BrokerConfig,
+    pub repository_config: RepositoryConfig,
     pub log_config: LogConfig,
     pub contracts_config: ContractsConfig,
     pub genesis_block: u64,
@@ -6,6 +6,7 @@ pub mod claimer;
 pub mod config;
 pub mod listener;
 pub mod metrics;
+pub mod recorder;
 pub mod sender;
 pub mod signer;
 
@@ -18,6 +19,7 @@ use crate::{
     claimer::{Claimer, DefaultClaimer},
     listener::DefaultBrokerListener,
     metrics::AuthorityClaimerMetrics,
+    recorder::DefaultClaimRecorder,
     sender::DefaultTransactionSender,
 };
 
@@ -35,6 +37,7 @@ pub async fn run(config: Config) -> Result<(), Box<dyn Error>> {
     let broker_listener =
         DefaultBrokerListener::new(config.broker_config.clone(), chain_id)
             .await?;
+    tokio::spawn(broker_listener.stream_trimmer().start());
 
     // Creating the duplicate checker.
     trace!("Creating the duplicate checker");
@@ -52,11 +55,18 @@ pub async fn run(config: Config) -> Result<(), Box<dyn Error>> {
         DefaultTransactionSender::new(config.clone(), chain_id, metrics)
             .await?;
 
+    // Creating the claim recorder.
+    trace!("Creating the claim recorder");
+    let claim_recorder =
+        DefaultClaimRecorder::new(config.repository_config.clone(), chain_id)
+            .await?;
+
     // Creating the claimer loop.
     let claimer = DefaultClaimer::new(
         broker_listener,
         duplicate_checker,
         transaction_sender,
+        claim_recorder,
     );
     let claimer_handle = claimer.start();
 
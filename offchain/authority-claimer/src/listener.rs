@@ -3,8 +3,9 @@
 
 use async_trait::async_trait;
 use rollups_events::{
-    Broker, BrokerConfig, BrokerError, RollupsClaim, RollupsClaimsStream,
-    INITIAL_ID,
+    Broker, BrokerConfig, BrokerError, ConsumerGroup, Event, RetentionConfig,
+    RollupsClaim, RollupsClaimsStream, StreamTrimmer,
+    AUTHORITY_CLAIMER_CONSUMER, INITIAL_ID,
 };
 use snafu::ResultExt;
 use std::fmt::Debug;
@@ -22,11 +23,19 @@ pub trait BrokerListener: Debug {
 // DefaultBrokerListener
 // ------------------------------------------------------------------------------------------------
 
+/// Name of the consumer group of the claimers
+const CONSUMER_GROUP: &str = "authority-claimer";
+
 #[derive(Debug)]
 pub struct DefaultBrokerListener {
     broker: Broker,
     stream: RollupsClaimsStream,
     last_claim_id: String,
+    /// Consumer group shared by the replicas of the claimer, if enabled
+    group: Option<ConsumerGroup>,
+    /// Claim returned by the last listen, acknowledged by the next one
+    unacknowledged_id: Option<String>,
+    retention: RetentionConfig,
 }
 
 #[derive(Debug, snafu::Snafu)]
@@ -41,15 +50,77 @@ impl DefaultBrokerListener {
         chain_id: u64,
     ) -> Result<Self, BrokerError> {
         tracing::trace!("Connecting to the broker ({:?})", broker_config);
-        let broker = Broker::new(broker_config).await?;
+        let group = broker_config
+            .consumer_group
+            .as_ref()
+            .map(|config| config.group(CONSUMER_GROUP));
+        let retention = broker_config.retention.clone();
+        let mut broker = Broker::new(broker_config).await?;
         let stream = RollupsClaimsStream::new(chain_id);
+        if let Some(group) = &group {
+            tracing::trace!("Joining the consumer group ({:?})", group);
+            broker.create_group(&stream, group).await?;
+        }
         let last_claim_id = INITIAL_ID.to_string();
         Ok(Self {
             broker,
             stream,
             last_claim_id,
+            group,
+            unacknowledged_id: None,
+            retention,
         })
     }
+
+    /// Create the task that trims the claims stream
+    /// The claims are trimmed once the claimer processed them. The indexers
+    /// may miss the trimmed claims, but the claimer records them as well.
+    pub fn stream_trimmer(&self) -> StreamTrimmer {
+        let mut trimmer =
+            StreamTrimmer::new(self.broker.clone(), self.retention.clone());
+        trimmer.add_stream(&self.stream, &[AUTHORITY_CLAIMER_CONSUMER]);
+        trimmer
+    }
+
+    /// Allow the trimmer to remove the claims up to the last one, which the
+    /// claimer finished processing before listening again
+    /// The replicas of a consumer group finish the claims out of order, so
+    /// they don't acknowledge the claims and the stream is not trimmed.
+    async fn acknowledge_last_claim(&mut self) -> Result<(), BrokerError> {
+        if self.last_claim_id == INITIAL_ID {
+            return Ok(());
+        }
+        tracing::trace!("Acknowledging claim with id {}", self.last_claim_id);
+        self.broker
+            .acknowledge(
+                &self.stream,
+                AUTHORITY_CLAIMER_CONSUMER,
+                &self.last_claim_id,
+            )
+            .await
+    }
+
+    /// Acknowledge the previous claim, which the claimer finished processing
+    /// before listening again, and consume the next one as a group consumer
+    async fn consume_group(
+        &mut self,
+        group: &ConsumerGroup,
+    ) -> Result<Event<RollupsClaim>, BrokerError> {
+        if let Some(id) = self.unacknowledged_id.take() {
+            tracing::trace!("Acknowledging claim with id {}", id);
+            self.broker
+                .acknowledge_group(&self.stream, group, &[&id])
+                .await?;
+        }
+        let event = self
+            .broker
+            .consume_group(&self.stream, group, 1)
+            .await?
+            .pop()
+            .ok_or(BrokerError::FailedToConsume)?;
+        self.unacknowledged_id = Some(event.id.clone());
+        Ok(event)
+    }
 }
 
 #[async_trait]
@@ -57,12 +128,17 @@ impl BrokerListener for DefaultBrokerListener {
     type Error = BrokerListenerError;
 
     async fn listen(&mut self) -> Result<RollupsClaim, Self::Error> {
-        tracing::trace!("Waiting for claim with id {}", self.last_claim_id);
-        let event = self
-            .broker
-            .consume_blocking(&self.stream, &self.last_claim_id)
-            .await
-            .context(BrokerSnafu)?;
+        let event = if let Some(group) = self.group.clone() {
+            tracing::trace!("Waiting for claim of group {}", group.name);
+            self.consume_group(&group).await
+        } else {
+            self.acknowledge_last_claim().await.context(BrokerSnafu)?;
+            tracing::trace!("Waiting for claim with id {}", self.last_claim_id);
+            self.broker
+                .consume_blocking(&self.stream, &self.last_claim_id)
+                .await
+        }
+        .context(BrokerSnafu)?;
 
         self.last_claim_id = event.id;
 
@@ -73,7 +149,6 @@ impl BrokerListener for DefaultBrokerListener {
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
-    use testcontainers::clients::Cli;
 
     use test_fixtures::BrokerFixture;
 
@@ -81,8 +156,9 @@ mod tests {
 
     use backoff::ExponentialBackoffBuilder;
     use rollups_events::{
-        BrokerConfig, BrokerEndpoint, BrokerError, RedactedUrl, RollupsClaim,
-        Url,
+        BrokerConfig, BrokerEndpoint, BrokerError, ConsumerGroupConfig,
+        RedactedUrl, RetentionPolicy, RollupsClaim, RollupsClaimsStream, Url,
+        AUTHORITY_CLAIMER_CONSUMER,
     };
     use snafu::Snafu;
 
@@ -98,10 +174,10 @@ mod tests {
     }
 
     pub async fn setup_broker(
-        docker: &Cli,
         should_fail: bool,
-    ) -> Result<(BrokerFixture, DefaultBrokerListener), BrokerError> {
-        let fixture = BrokerFixture::setup(docker).await;
+    ) -> Result<(BrokerFixture<'static>, DefaultBrokerListener), BrokerError>
+    {
+        let fixture = BrokerFixture::setup_memory().await;
 
         let redis_endpoint = if should_fail {
             BrokerEndpoint::Single(RedactedUrl::new(
@@ -114,6 +190,9 @@ mod tests {
         let config = BrokerConfig {
             redis_endpoint,
             consume_timeout: 300000,
+            retention: Default::default(),
+            payload_encoding: Default::default(),
+            consumer_group: None,
             backoff: ExponentialBackoffBuilder::new()
                 .with_initial_interval(Duration::from_millis(1000))
                 .with_max_elapsed_time(Some(Duration::from_millis(3000)))
@@ -153,14 +232,12 @@ mod tests {
 
     #[tokio::test]
     async fn instantiate_new_broker_listener_ok() {
-        let docker = Cli::default();
-        let _ = setup_broker(&docker, false).await;
+        let _ = setup_broker(false).await;
     }
 
     #[tokio::test]
     async fn instantiate_new_broker_listener_error() {
-        let docker = Cli::default();
-        let result = setup_broker(&docker, true).await;
+        let result = setup_broker(true).await;
         assert!(result.is_err(), "setup_broker didn't fail as it should");
         let error = result.err().unwrap().to_string();
         assert_eq!(error, "error connecting to Redis");
@@ -168,9 +245,7 @@ mod tests {
 
     #[tokio::test]
     async fn start_broker_listener_with_one_claim_enqueued() {
-        let docker = Cli::default();
-        let (fixture, mut broker_listener) =
-            setup_broker(&docker, false).await.unwrap();
+        let (fixture, mut broker_listener) = setup_broker(false).await.unwrap();
         let n = 5;
         produce_rollups_claims(&fixture, n, 0).await;
         produce_last_claim(&fixture, n).await;
@@ -180,9 +255,7 @@ mod tests {
 
     #[tokio::test]
     async fn start_broker_listener_with_claims_enqueued() {
-        let docker = Cli::default();
-        let (fixture, mut broker_listener) =
-            setup_broker(&docker, false).await.unwrap();
+        let (fixture, mut broker_listener) = setup_broker(false).await.unwrap();
         produce_last_claim(&fixture, 0).await;
         let claim = broker_listener.listen().await;
         assert!(claim.is_ok());
@@ -190,9 +263,7 @@ mod tests {
 
     #[tokio::test]
     async fn start_broker_listener_listener_with_no_claims_enqueued() {
-        let docker = Cli::default();
-        let (fixture, mut broker_listener) =
-            setup_broker(&docker, false).await.unwrap();
+        let (fixture, mut broker_listener) = setup_broker(false).await.unwrap();
         let n = 7;
 
         let broker_listener_thread = tokio::spawn(async move {
@@ -220,4 +291,66 @@ mod tests {
 
         broker_listener_thread.await.unwrap();
     }
+
+    #[tokio::test]
+    async fn start_broker_listener_acknowledges_processed_claims() {
+        let (fixture, mut broker_listener) = setup_broker(false).await.unwrap();
+        produce_rollups_claims(&fixture, 3, 0).await;
+        broker_listener.listen().await.unwrap();
+        broker_listener.listen().await.unwrap();
+
+        // Only the first claim was processed before listening again
+        let stream = RollupsClaimsStream::new(fixture.chain_id());
+        let count = broker_listener
+            .broker
+            .trim(
+                &stream,
+                &RetentionPolicy::Acknowledged,
+                &[AUTHORITY_CLAIMER_CONSUMER],
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+        broker_listener.listen().await.unwrap();
+        let count = broker_listener
+            .broker
+            .trim(
+                &stream,
+                &RetentionPolicy::Acknowledged,
+                &[AUTHORITY_CLAIMER_CONSUMER],
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn start_broker_listener_with_consumer_group() {
+        let fixture = BrokerFixture::setup_memory().await;
+        let config = BrokerConfig {
+            redis_endpoint: fixture.redis_endpoint().clone(),
+            consume_timeout: 300000,
+            retention: Default::default(),
+            payload_encoding: Default::default(),
+            consumer_group: Some(ConsumerGroupConfig {
+                consumer: "claimer-0".to_owned(),
+                claim_idle: Duration::from_secs(60),
+            }),
+            backoff: Default::default(),
+        };
+        let claims = produce_rollups_claims(&fixture, 2, 0).await;
+        let mut broker_listener =
+            DefaultBrokerListener::new(config.clone(), fixture.chain_id())
+                .await
+                .unwrap();
+        assert_eq!(broker_listener.listen().await.unwrap(), claims[0]);
+        assert_eq!(broker_listener.listen().await.unwrap(), claims[1]);
+
+        // The restarted listener gets back the unacknowledged claim
+        let mut broker_listener =
+            DefaultBrokerListener::new(config, fixture.chain_id())
+                .await
+                .unwrap();
+        assert_eq!(broker_listener.listen().await.unwrap(), claims[1]);
+    }
 }
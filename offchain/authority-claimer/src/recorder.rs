@@ -0,0 +1,159 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+use async_trait::async_trait;
+use ethers::types::H256;
+use rollups_data::{Claim, ClaimStatus, Epoch, Repository, RepositoryConfig};
+use rollups_events::{Address, RollupsClaim};
+use snafu::{ResultExt, Snafu};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use tracing::trace;
+
+/// The `ClaimRecorder` stores the submission status of the claims, so it can
+/// be queried without reading the broker.
+#[async_trait]
+pub trait ClaimRecorder: Debug {
+    type Error: snafu::Error + 'static;
+
+    async fn record_rollups_claim(
+        &mut self,
+        rollups_claim: RollupsClaim,
+        status: ClaimStatus,
+        transaction_hash: Option<H256>,
+    ) -> Result<(), Self::Error>;
+}
+
+// ------------------------------------------------------------------------------------------------
+// DefaultClaimRecorder
+// ------------------------------------------------------------------------------------------------
+
+/// The `DefaultClaimRecorder` stores the claims in the rollups-data
+/// repository, under the application of each dapp.
+///
+/// The recorder doesn't run the migrations: the database schema is created by
+/// the indexer, so the indexer must start before the claims are recorded. The
+/// claimer logs the claims it fails to record and keeps submitting them.
+#[derive(Debug)]
+pub struct DefaultClaimRecorder {
+    repository: Repository,
+    chain_id: u64,
+    applications: HashMap<Address, Repository>,
+}
+
+#[derive(Debug, Snafu)]
+pub enum ClaimRecorderError {
+    #[snafu(display("repository error"))]
+    RepositoryError { source: rollups_data::Error },
+
+    #[snafu(display("failed to join repository task"))]
+    JoinError { source: tokio::task::JoinError },
+}
+
+impl DefaultClaimRecorder {
+    pub async fn new(
+        repository_config: RepositoryConfig,
+        chain_id: u64,
+    ) -> Result<Self, ClaimRecorderError> {
+        let repository =
+            tokio::task::spawn_blocking(|| Repository::new(repository_config))
+                .await
+                .context(JoinSnafu)?
+                .context(RepositorySnafu)?;
+        Ok(Self {
+            repository,
+            chain_id,
+            applications: HashMap::new(),
+        })
+    }
+
+    /// Get the repository of the dapp, if its indexer registered the
+    /// application
+    /// The claims stream has the claims of every dapp in the chain, so the
+    /// recorder only looks up the applications and never registers them.
+    async fn application(
+        &mut self,
+        dapp_address: &Address,
+    ) -> Result<Option<Repository>, ClaimRecorderError> {
+        if let Some(repository) = self.applications.get(dapp_address) {
+            return Ok(Some(repository.clone()));
+        }
+        let repository = self.repository.clone();
+        let chain_id = self.chain_id as i64;
+        let address = dapp_address.inner().to_vec();
+        let application = tokio::task::spawn_blocking(move || {
+            repository.find_application(chain_id, &address)
+        })
+        .await
+        .context(JoinSnafu)?
+        .context(RepositorySnafu)?;
+        Ok(application.map(|application| {
+            let repository = self.repository.for_application(application.id);
+            self.applications
+                .insert(dapp_address.clone(), repository.clone());
+            repository
+        }))
+    }
+}
+
+#[async_trait]
+impl ClaimRecorder for DefaultClaimRecorder {
+    type Error = ClaimRecorderError;
+
+    async fn record_rollups_claim(
+        &mut self,
+        rollups_claim: RollupsClaim,
+        status: ClaimStatus,
+        transaction_hash: Option<H256>,
+    ) -> Result<(), Self::Error> {
+        let Some(repository) =
+            self.application(&rollups_claim.dapp_address).await?
+        else {
+            trace!(
+                "Skipping claim of dapp without application: `{:?}`",
+                rollups_claim.dapp_address
+            );
+            return Ok(());
+        };
+        let (epoch, claim) =
+            convert_claim(rollups_claim, status, transaction_hash);
+        trace!("Recording claim: `{:?}`", claim);
+        tokio::task::spawn_blocking(move || {
+            repository.upsert_claim(epoch, claim)
+        })
+        .await
+        .context(JoinSnafu)?
+        .context(RepositorySnafu)
+    }
+}
+
+fn convert_claim(
+    rollups_claim: RollupsClaim,
+    status: ClaimStatus,
+    transaction_hash: Option<H256>,
+) -> (Epoch, Claim) {
+    let epoch = Epoch {
+        index: rollups_claim.epoch_index as i32,
+        first_input_index: rollups_claim.first_index as i32,
+        last_input_index: rollups_claim.last_index as i32,
+    };
+    let claim = Claim {
+        epoch_index: rollups_claim.epoch_index as i32,
+        epoch_hash: rollups_claim.epoch_hash.into_inner().into(),
+        machine_state_hash: rollups_claim
+            .machine_state_hash
+            .into_inner()
+            .into(),
+        vouchers_epoch_root_hash: rollups_claim
+            .vouchers_epoch_root_hash
+            .into_inner()
+            .into(),
+        notices_epoch_root_hash: rollups_claim
+            .notices_epoch_root_hash
+            .into_inner()
+            .into(),
+        status,
+        transaction_hash: transaction_hash.map(|hash| hash.0.into()),
+    };
+    (epoch, claim)
+}
@@ -20,7 +20,7 @@ use ethers::{
         Http, HttpRateLimitRetryPolicy, MockProvider, Provider, RetryClient,
     },
     signers::Signer,
-    types::{Bytes, NameOrAddress, H160},
+    types::{Bytes, NameOrAddress, H160, H256},
 };
 use rollups_events::{DAppMetadata, RollupsClaim};
 use snafu::{OptionExt, ResultExt, Snafu};
@@ -45,10 +45,11 @@ pub trait TransactionSender: Sized + Debug {
     /// The `send_rollups_claim_transaction` function consumes the
     /// `TransactionSender` object and then returns it to avoid
     /// that processes use the transaction sender concurrently.
+    /// It also returns the hash of the confirmed transaction.
     async fn send_rollups_claim_transaction(
         self,
         rollups_claim: RollupsClaim,
-    ) -> Result<Self, Self::Error>;
+    ) -> Result<(Self, H256), Self::Error>;
 }
 
 // ------------------------------------------------------------------------------------------------
@@ -222,7 +223,7 @@ impl TransactionSender for DefaultTransactionSender {
     async fn send_rollups_claim_transaction(
         self,
         rollups_claim: RollupsClaim,
-    ) -> Result<Self, Self::Error> {
+    ) -> Result<(Self, H256), Self::Error> {
         let dapp_address = rollups_claim.dapp_address.clone();
 
         let transaction = {
@@ -262,6 +263,6 @@ impl TransactionSender for DefaultTransactionSender {
             .inc();
         trace!("Claim transaction confirmed: `{:?}`", receipt);
 
-        Ok(Self { tx_manager, ..self })
+        Ok((Self { tx_manager, ..self }, receipt.transaction_hash))
     }
 }
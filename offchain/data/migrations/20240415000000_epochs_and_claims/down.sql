@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE "claims";
+
+DROP TYPE "ClaimStatus";
+
+DROP TABLE "epochs";
@@ -0,0 +1,29 @@
+-- (c) Cartesi and individual authors (see AUTHORS)
+-- SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+CREATE TABLE "epochs"
+(
+    "index" INT NOT NULL,
+    "first_input_index" INT NOT NULL,
+    "last_input_index" INT NOT NULL,
+    CONSTRAINT "epochs_pkey" PRIMARY KEY ("index")
+);
+
+CREATE TYPE "ClaimStatus" AS ENUM (
+    'Pending',
+    'Submitted',
+    'Duplicated'
+);
+
+CREATE TABLE "claims"
+(
+    "epoch_index" INT NOT NULL,
+    "epoch_hash" BYTEA NOT NULL,
+    "machine_state_hash" BYTEA NOT NULL,
+    "vouchers_epoch_root_hash" BYTEA NOT NULL,
+    "notices_epoch_root_hash" BYTEA NOT NULL,
+    "status" "ClaimStatus" NOT NULL DEFAULT 'Pending',
+    "transaction_hash" BYTEA,
+    CONSTRAINT "claims_pkey" PRIMARY KEY ("epoch_index"),
+    CONSTRAINT "claims_epoch_index_fkey" FOREIGN KEY ("epoch_index") REFERENCES "epochs"("index")
+);
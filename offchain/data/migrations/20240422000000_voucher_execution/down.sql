@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE "vouchers"
+    DROP COLUMN "execution_transaction_hash",
+    DROP COLUMN "executed";
@@ -0,0 +1,6 @@
+-- (c) Cartesi and individual authors (see AUTHORS)
+-- SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+ALTER TABLE "vouchers"
+    ADD COLUMN "executed" BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN "execution_transaction_hash" BYTEA;
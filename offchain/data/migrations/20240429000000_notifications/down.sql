@@ -0,0 +1,15 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER "claims_notify_submitted" ON "claims";
+
+DROP TRIGGER "reports_notify_insert" ON "reports";
+
+DROP TRIGGER "vouchers_notify_insert" ON "vouchers";
+
+DROP TRIGGER "notices_notify_insert" ON "notices";
+
+DROP TRIGGER "inputs_notify_status" ON "inputs";
+
+DROP TRIGGER "inputs_notify_insert" ON "inputs";
+
+DROP FUNCTION "notify_rollups_change";
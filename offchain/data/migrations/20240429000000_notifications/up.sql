@@ -0,0 +1,38 @@
+-- (c) Cartesi and individual authors (see AUTHORS)
+-- SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+-- Notify the listeners of the `rollups_changes` channel about the changed row.
+-- The notification only carries the row keys because its payload is limited.
+CREATE FUNCTION "notify_rollups_change"() RETURNS TRIGGER AS $$
+DECLARE
+    "row" JSONB := to_jsonb(NEW);
+BEGIN
+    PERFORM pg_notify('rollups_changes', json_build_object(
+        'kind', TG_ARGV[0],
+        'index', "row"->'index',
+        'input_index', "row"->'input_index',
+        'epoch_index', "row"->'epoch_index'
+    )::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER "inputs_notify_insert" AFTER INSERT ON "inputs"
+    FOR EACH ROW EXECUTE FUNCTION "notify_rollups_change"('input_added');
+
+CREATE TRIGGER "inputs_notify_status" AFTER UPDATE OF "status" ON "inputs"
+    FOR EACH ROW WHEN (OLD."status" IS DISTINCT FROM NEW."status")
+    EXECUTE FUNCTION "notify_rollups_change"('input_status_changed');
+
+CREATE TRIGGER "notices_notify_insert" AFTER INSERT ON "notices"
+    FOR EACH ROW EXECUTE FUNCTION "notify_rollups_change"('notice_added');
+
+CREATE TRIGGER "vouchers_notify_insert" AFTER INSERT ON "vouchers"
+    FOR EACH ROW EXECUTE FUNCTION "notify_rollups_change"('voucher_added');
+
+CREATE TRIGGER "reports_notify_insert" AFTER INSERT ON "reports"
+    FOR EACH ROW EXECUTE FUNCTION "notify_rollups_change"('report_added');
+
+CREATE TRIGGER "claims_notify_submitted" AFTER INSERT OR UPDATE OF "status" ON "claims"
+    FOR EACH ROW WHEN (NEW."status" <> 'Pending')
+    EXECUTE FUNCTION "notify_rollups_change"('claim_submitted');
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX "vouchers_destination_idx";
+DROP INDEX "inputs_timestamp_idx";
+DROP INDEX "inputs_block_number_idx";
+DROP INDEX "inputs_status_idx";
+DROP INDEX "inputs_msg_sender_idx";
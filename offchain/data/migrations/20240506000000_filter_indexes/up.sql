@@ -0,0 +1,8 @@
+-- (c) Cartesi and individual authors (see AUTHORS)
+-- SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+CREATE INDEX "inputs_msg_sender_idx" ON "inputs"("msg_sender");
+CREATE INDEX "inputs_status_idx" ON "inputs"("status");
+CREATE INDEX "inputs_block_number_idx" ON "inputs"("block_number");
+CREATE INDEX "inputs_timestamp_idx" ON "inputs"("timestamp");
+CREATE INDEX "vouchers_destination_idx" ON "vouchers"("destination");
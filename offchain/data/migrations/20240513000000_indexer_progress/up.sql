@@ -0,0 +1,9 @@
+-- (c) Cartesi and individual authors (see AUTHORS)
+-- SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+CREATE TABLE "indexer_progress"
+(
+    "stream_key" VARCHAR NOT NULL,
+    "last_event_id" VARCHAR NOT NULL,
+    CONSTRAINT "indexer_progress_pkey" PRIMARY KEY ("stream_key")
+);
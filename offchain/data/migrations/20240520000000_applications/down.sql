@@ -0,0 +1,88 @@
+-- This file should undo anything in `up.sql`
+
+CREATE OR REPLACE FUNCTION "notify_rollups_change"() RETURNS TRIGGER AS $$
+DECLARE
+    "row" JSONB := to_jsonb(NEW);
+BEGIN
+    PERFORM pg_notify('rollups_changes', json_build_object(
+        'kind', TG_ARGV[0],
+        'index', "row"->'index',
+        'input_index', "row"->'input_index',
+        'epoch_index', "row"->'epoch_index'
+    )::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP INDEX "vouchers_destination_idx";
+DROP INDEX "inputs_timestamp_idx";
+DROP INDEX "inputs_block_number_idx";
+DROP INDEX "inputs_status_idx";
+DROP INDEX "inputs_msg_sender_idx";
+CREATE INDEX "inputs_msg_sender_idx" ON "inputs"("msg_sender");
+CREATE INDEX "inputs_status_idx" ON "inputs"("status");
+CREATE INDEX "inputs_block_number_idx" ON "inputs"("block_number");
+CREATE INDEX "inputs_timestamp_idx" ON "inputs"("timestamp");
+CREATE INDEX "vouchers_destination_idx" ON "vouchers"("destination");
+
+-- Only the rows of the default application are kept
+DELETE FROM "indexer_progress" WHERE "application_id" <> 0;
+DELETE FROM "claims" WHERE "application_id" <> 0;
+DELETE FROM "epochs" WHERE "application_id" <> 0;
+DELETE FROM "proofs" WHERE "application_id" <> 0;
+DELETE FROM "reports" WHERE "application_id" <> 0;
+DELETE FROM "notices" WHERE "application_id" <> 0;
+DELETE FROM "vouchers" WHERE "application_id" <> 0;
+DELETE FROM "inputs" WHERE "application_id" <> 0;
+
+ALTER TABLE "claims" DROP CONSTRAINT "claims_epoch_index_fkey";
+ALTER TABLE "reports" DROP CONSTRAINT "reports_input_index_fkey";
+ALTER TABLE "notices" DROP CONSTRAINT "notices_input_index_fkey";
+ALTER TABLE "vouchers" DROP CONSTRAINT "vouchers_input_index_fkey";
+
+ALTER TABLE "indexer_progress"
+    DROP CONSTRAINT "indexer_progress_pkey",
+    DROP COLUMN "application_id",
+    ADD CONSTRAINT "indexer_progress_pkey" PRIMARY KEY ("stream_key");
+
+ALTER TABLE "claims"
+    DROP CONSTRAINT "claims_pkey",
+    DROP COLUMN "application_id",
+    ADD CONSTRAINT "claims_pkey" PRIMARY KEY ("epoch_index");
+
+ALTER TABLE "epochs"
+    DROP CONSTRAINT "epochs_pkey",
+    DROP COLUMN "application_id",
+    ADD CONSTRAINT "epochs_pkey" PRIMARY KEY ("index");
+
+ALTER TABLE "proofs"
+    DROP CONSTRAINT "proofs_pkey",
+    DROP COLUMN "application_id",
+    ADD CONSTRAINT "proofs_pkey" PRIMARY KEY ("input_index", "output_index", "output_enum");
+
+ALTER TABLE "reports"
+    DROP CONSTRAINT "reports_pkey",
+    DROP COLUMN "application_id",
+    ADD CONSTRAINT "reports_pkey" PRIMARY KEY ("input_index", "index");
+
+ALTER TABLE "notices"
+    DROP CONSTRAINT "notices_pkey",
+    DROP COLUMN "application_id",
+    ADD CONSTRAINT "notices_pkey" PRIMARY KEY ("input_index", "index");
+
+ALTER TABLE "vouchers"
+    DROP CONSTRAINT "vouchers_pkey",
+    DROP COLUMN "application_id",
+    ADD CONSTRAINT "vouchers_pkey" PRIMARY KEY ("input_index", "index");
+
+ALTER TABLE "inputs"
+    DROP CONSTRAINT "inputs_pkey",
+    DROP COLUMN "application_id",
+    ADD CONSTRAINT "inputs_pkey" PRIMARY KEY ("index");
+
+ALTER TABLE "vouchers" ADD CONSTRAINT "vouchers_input_index_fkey" FOREIGN KEY ("input_index") REFERENCES "inputs"("index");
+ALTER TABLE "notices" ADD CONSTRAINT "notices_input_index_fkey" FOREIGN KEY ("input_index") REFERENCES "inputs"("index");
+ALTER TABLE "reports" ADD CONSTRAINT "reports_input_index_fkey" FOREIGN KEY ("input_index") REFERENCES "inputs"("index");
+ALTER TABLE "claims" ADD CONSTRAINT "claims_epoch_index_fkey" FOREIGN KEY ("epoch_index") REFERENCES "epochs"("index");
+
+DROP TABLE "applications";
@@ -0,0 +1,107 @@
+-- (c) Cartesi and individual authors (see AUTHORS)
+-- SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+CREATE TABLE "applications"
+(
+    "id" SERIAL NOT NULL,
+    "chain_id" BIGINT NOT NULL,
+    "dapp_address" BYTEA NOT NULL,
+    CONSTRAINT "applications_pkey" PRIMARY KEY ("id"),
+    CONSTRAINT "applications_chain_id_dapp_address_key" UNIQUE ("chain_id", "dapp_address")
+);
+
+-- The rows stored before this migration belong to the default application,
+-- which is adopted by the dapp whose indexer is configured to adopt it.
+INSERT INTO "applications" ("id", "chain_id", "dapp_address") VALUES (0, 0, '\x');
+
+-- The foreign keys are replaced by ones that include the application
+ALTER TABLE "claims" DROP CONSTRAINT "claims_epoch_index_fkey";
+ALTER TABLE "reports" DROP CONSTRAINT "reports_input_index_fkey";
+ALTER TABLE "notices" DROP CONSTRAINT "notices_input_index_fkey";
+ALTER TABLE "vouchers" DROP CONSTRAINT "vouchers_input_index_fkey";
+
+ALTER TABLE "inputs"
+    ADD COLUMN "application_id" INT NOT NULL DEFAULT 0,
+    DROP CONSTRAINT "inputs_pkey",
+    ADD CONSTRAINT "inputs_pkey" PRIMARY KEY ("application_id", "index"),
+    ADD CONSTRAINT "inputs_application_id_fkey" FOREIGN KEY ("application_id") REFERENCES "applications"("id");
+
+ALTER TABLE "vouchers"
+    ADD COLUMN "application_id" INT NOT NULL DEFAULT 0,
+    DROP CONSTRAINT "vouchers_pkey",
+    ADD CONSTRAINT "vouchers_pkey" PRIMARY KEY ("application_id", "input_index", "index"),
+    ADD CONSTRAINT "vouchers_input_index_fkey" FOREIGN KEY ("application_id", "input_index") REFERENCES "inputs"("application_id", "index");
+
+ALTER TABLE "notices"
+    ADD COLUMN "application_id" INT NOT NULL DEFAULT 0,
+    DROP CONSTRAINT "notices_pkey",
+    ADD CONSTRAINT "notices_pkey" PRIMARY KEY ("application_id", "input_index", "index"),
+    ADD CONSTRAINT "notices_input_index_fkey" FOREIGN KEY ("application_id", "input_index") REFERENCES "inputs"("application_id", "index");
+
+ALTER TABLE "reports"
+    ADD COLUMN "application_id" INT NOT NULL DEFAULT 0,
+    DROP CONSTRAINT "reports_pkey",
+    ADD CONSTRAINT "reports_pkey" PRIMARY KEY ("application_id", "input_index", "index"),
+    ADD CONSTRAINT "reports_input_index_fkey" FOREIGN KEY ("application_id", "input_index") REFERENCES "inputs"("application_id", "index");
+
+ALTER TABLE "proofs"
+    ADD COLUMN "application_id" INT NOT NULL DEFAULT 0,
+    DROP CONSTRAINT "proofs_pkey",
+    ADD CONSTRAINT "proofs_pkey" PRIMARY KEY ("application_id", "input_index", "output_index", "output_enum"),
+    ADD CONSTRAINT "proofs_application_id_fkey" FOREIGN KEY ("application_id") REFERENCES "applications"("id");
+
+ALTER TABLE "epochs"
+    ADD COLUMN "application_id" INT NOT NULL DEFAULT 0,
+    DROP CONSTRAINT "epochs_pkey",
+    ADD CONSTRAINT "epochs_pkey" PRIMARY KEY ("application_id", "index"),
+    ADD CONSTRAINT "epochs_application_id_fkey" FOREIGN KEY ("application_id") REFERENCES "applications"("id");
+
+ALTER TABLE "claims"
+    ADD COLUMN "application_id" INT NOT NULL DEFAULT 0,
+    DROP CONSTRAINT "claims_pkey",
+    ADD CONSTRAINT "claims_pkey" PRIMARY KEY ("application_id", "epoch_index"),
+    ADD CONSTRAINT "claims_epoch_index_fkey" FOREIGN KEY ("application_id", "epoch_index") REFERENCES "epochs"("application_id", "index");
+
+ALTER TABLE "indexer_progress"
+    ADD COLUMN "application_id" INT NOT NULL DEFAULT 0,
+    DROP CONSTRAINT "indexer_progress_pkey",
+    ADD CONSTRAINT "indexer_progress_pkey" PRIMARY KEY ("application_id", "stream_key"),
+    ADD CONSTRAINT "indexer_progress_application_id_fkey" FOREIGN KEY ("application_id") REFERENCES "applications"("id");
+
+-- The application of the new rows is always set
+ALTER TABLE "inputs" ALTER COLUMN "application_id" DROP DEFAULT;
+ALTER TABLE "vouchers" ALTER COLUMN "application_id" DROP DEFAULT;
+ALTER TABLE "notices" ALTER COLUMN "application_id" DROP DEFAULT;
+ALTER TABLE "reports" ALTER COLUMN "application_id" DROP DEFAULT;
+ALTER TABLE "proofs" ALTER COLUMN "application_id" DROP DEFAULT;
+ALTER TABLE "epochs" ALTER COLUMN "application_id" DROP DEFAULT;
+ALTER TABLE "claims" ALTER COLUMN "application_id" DROP DEFAULT;
+ALTER TABLE "indexer_progress" ALTER COLUMN "application_id" DROP DEFAULT;
+
+-- The filters are applied to the rows of a single application
+DROP INDEX "inputs_msg_sender_idx";
+DROP INDEX "inputs_status_idx";
+DROP INDEX "inputs_block_number_idx";
+DROP INDEX "inputs_timestamp_idx";
+DROP INDEX "vouchers_destination_idx";
+CREATE INDEX "inputs_msg_sender_idx" ON "inputs"("application_id", "msg_sender");
+CREATE INDEX "inputs_status_idx" ON "inputs"("application_id", "status");
+CREATE INDEX "inputs_block_number_idx" ON "inputs"("application_id", "block_number");
+CREATE INDEX "inputs_timestamp_idx" ON "inputs"("application_id", "timestamp");
+CREATE INDEX "vouchers_destination_idx" ON "vouchers"("application_id", "destination");
+
+-- The notifications carry the application of the changed row
+CREATE OR REPLACE FUNCTION "notify_rollups_change"() RETURNS TRIGGER AS $$
+DECLARE
+    "row" JSONB := to_jsonb(NEW);
+BEGIN
+    PERFORM pg_notify('rollups_changes', json_build_object(
+        'kind', TG_ARGV[0],
+        'application_id', "row"->'application_id',
+        'index', "row"->'index',
+        'input_index', "row"->'input_index',
+        'epoch_index', "row"->'epoch_index'
+    )::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
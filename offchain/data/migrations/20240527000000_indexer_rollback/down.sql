@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE "indexer_progress" DROP COLUMN "rollback_index";
@@ -0,0 +1,19 @@
+-- (c) Cartesi and individual authors (see AUTHORS)
+-- SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+-- The executions are kept apart from the vouchers, because a voucher may be
+-- executed before the indexer stores it
+CREATE TABLE "voucher_executions"
+(
+    "input_index" INTEGER NOT NULL,
+    "index" INTEGER NOT NULL,
+    "transaction_hash" BYTEA NOT NULL,
+    "application_id" INTEGER NOT NULL,
+    CONSTRAINT "voucher_executions_pkey" PRIMARY KEY ("application_id", "input_index", "index"),
+    CONSTRAINT "voucher_executions_application_id_fkey" FOREIGN KEY ("application_id") REFERENCES "applications"("id")
+);
+
+INSERT INTO "voucher_executions" ("input_index", "index", "transaction_hash", "application_id")
+SELECT "input_index", "index", "execution_transaction_hash", "application_id"
+FROM "vouchers"
+WHERE "executed" AND "execution_transaction_hash" IS NOT NULL;
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX "reports_payload_prefix_idx";
+DROP INDEX "notices_payload_prefix_idx";
+DROP INDEX "vouchers_payload_prefix_idx";
+
+DROP FUNCTION "payload_prefix";
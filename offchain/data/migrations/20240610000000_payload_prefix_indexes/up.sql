@@ -0,0 +1,11 @@
+-- (c) Cartesi and individual authors (see AUTHORS)
+-- SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+-- The indexes keep only the first bytes of each payload, which is enough to
+-- find the outputs by payload prefix without storing the whole payloads twice
+CREATE FUNCTION "payload_prefix"("payload" BYTEA) RETURNS BYTEA
+    AS 'SELECT substr($1, 1, 32)' LANGUAGE SQL IMMUTABLE STRICT;
+
+CREATE INDEX "vouchers_payload_prefix_idx" ON "vouchers"("application_id", "payload_prefix"("payload"));
+CREATE INDEX "notices_payload_prefix_idx" ON "notices"("application_id", "payload_prefix"("payload"));
+CREATE INDEX "reports_payload_prefix_idx" ON "reports"("application_id", "payload_prefix"("payload"));
@@ -0,0 +1,81 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+use backoff::ExponentialBackoff;
+use diesel::prelude::*;
+use diesel_async::pooled_connection::deadpool::{Object, Pool};
+use diesel_async::pooled_connection::AsyncDieselConnectionManager;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use snafu::ResultExt;
+
+use super::config::RepositoryConfig;
+use super::error::{
+    AsyncDatabaseConnectionSnafu, AsyncDatabasePoolSnafu, Error,
+};
+use super::repository::{
+    impl_paginated_queries, impl_read_queries, DEFAULT_APPLICATION_ID,
+};
+
+/// Repository that runs the queries without blocking the async runtime
+///
+/// It has the same queries as the synchronous repository. The writes are
+/// left to the synchronous repository, since the indexer runs them in
+/// database transactions. Unlike the synchronous repository, the endpoint
+/// can't be loaded from the Pg environment.
+#[derive(Clone)]
+pub struct AsyncRepository {
+    db_pool: Pool<AsyncPgConnection>,
+    backoff: ExponentialBackoff,
+    /// Application whose rows are read by the queries
+    application_id: i32,
+}
+
+impl AsyncRepository {
+    /// Create the database connection pool, with up to
+    /// `connection_pool_size` connections
+    /// The connections are only opened when the queries need them.
+    /// The repository reads the rows of the default application.
+    pub fn new(config: RepositoryConfig) -> Result<Self, Error> {
+        tracing::info!(?config, "creating async db pool for database");
+        let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(
+            config.endpoint(),
+        );
+        let db_pool = Pool::builder(manager)
+            .max_size(config.connection_pool_size as usize)
+            .build()
+            .context(AsyncDatabasePoolSnafu)?;
+        Ok(Self {
+            db_pool,
+            backoff: config.backoff,
+            application_id: DEFAULT_APPLICATION_ID,
+        })
+    }
+
+    /// Id of the application whose rows the repository reads
+    pub fn application_id(&self) -> i32 {
+        self.application_id
+    }
+
+    /// Create a repository that reads the rows of the given application,
+    /// sharing the connection pool with this one
+    pub fn for_application(&self, application_id: i32) -> Self {
+        Self {
+            db_pool: self.db_pool.clone(),
+            backoff: self.backoff.clone(),
+            application_id,
+        }
+    }
+
+    /// Obtain the connection from the connection pool, waiting until the
+    /// database server is available with backoff strategy
+    async fn conn(&self) -> Result<Object<AsyncPgConnection>, Error> {
+        backoff::future::retry(self.backoff.clone(), || async {
+            self.db_pool.get().await.map_err(backoff::Error::transient)
+        })
+        .await
+        .context(AsyncDatabaseConnectionSnafu)
+    }
+}
+
+impl_read_queries!(AsyncRepository, [async], [.await]);
+impl_paginated_queries!(AsyncRepository, [async], [.await]);
@@ -6,7 +6,7 @@ use clap::Parser;
 pub use redacted::{RedactedUrl, Url};
 use std::time::Duration;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RepositoryConfig {
     pub redacted_endpoint: Option<RedactedUrl>,
     pub connection_pool_size: u32,
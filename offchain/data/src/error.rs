@@ -1,6 +1,7 @@
 // (c) Cartesi and individual authors (see AUTHORS)
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 use snafu::Snafu;
 
 #[derive(Debug, Snafu)]
@@ -11,12 +12,27 @@ pub enum Error {
         source: backoff::Error<diesel::r2d2::PoolError>,
     },
 
+    #[snafu(display("failed to create the async database pool"))]
+    AsyncDatabasePoolError {
+        source: diesel_async::pooled_connection::deadpool::BuildError,
+    },
+
+    #[snafu(display("async database pool connection error"))]
+    AsyncDatabaseConnectionError {
+        source: diesel_async::pooled_connection::deadpool::PoolError,
+    },
+
     #[snafu(display("database error"))]
     DatabaseError { source: diesel::result::Error },
 
     #[snafu(display("{} not found", item_type))]
     ItemNotFound { item_type: String },
 
+    #[snafu(display(
+        "default application can only be adopted by the dapp that used it"
+    ))]
+    DefaultApplicationAdoptedError {},
+
     #[snafu(display("failed to decode UTF8 cursor"))]
     DecodeUTF8CursorError { source: std::str::Utf8Error },
 
@@ -37,3 +53,25 @@ pub enum Error {
     #[snafu(display("invalid pagination limit {}", arg))]
     PaginationLimitError { arg: String },
 }
+
+impl From<DieselError> for Error {
+    fn from(source: DieselError) -> Self {
+        Error::DatabaseError { source }
+    }
+}
+
+impl Error {
+    /// Whether the error was caused by a foreign key violation, such as
+    /// inserting an output whose input doesn't exist
+    pub fn is_foreign_key_violation(&self) -> bool {
+        matches!(
+            self,
+            Error::DatabaseError {
+                source: DieselError::DatabaseError(
+                    DatabaseErrorKind::ForeignKeyViolation,
+                    _
+                ),
+            }
+        )
+    }
+}
@@ -1,6 +1,7 @@
 // (c) Cartesi and individual authors (see AUTHORS)
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
+mod async_repository;
 mod config;
 mod error;
 mod migrations;
@@ -9,12 +10,15 @@ mod repository;
 mod schema;
 mod types;
 
+pub use async_repository::AsyncRepository;
 pub use config::{RedactedUrl, RepositoryCLIConfig, RepositoryConfig, Url};
 pub use error::Error;
 pub use migrations::{run_migrations, MigrationError};
 pub use pagination::{Connection, Cursor, Edge, PageInfo};
-pub use repository::Repository;
+pub use repository::{Repository, Transaction, DEFAULT_APPLICATION_ID};
 pub use types::{
-    CompletionStatus, Input, InputQueryFilter, Notice, NoticeQueryFilter,
-    OutputEnum, Proof, Report, ReportQueryFilter, Voucher, VoucherQueryFilter,
+    Application, Claim, ClaimQueryFilter, ClaimStatus, CompletionStatus, Epoch,
+    EpochQueryFilter, IndexerProgress, Input, InputQueryFilter, Notice,
+    NoticeQueryFilter, OutputEnum, Proof, Report, ReportQueryFilter, Voucher,
+    VoucherQueryFilter,
 };
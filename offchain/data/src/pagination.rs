@@ -14,16 +14,21 @@ use super::error::{
 const DEFAULT_PAGINATION_LIMIT: i32 = 1000;
 
 macro_rules! ensure_cursor {
-    ($arg: ident, $total_count: expr) => {{
-        let cursor = Cursor::decode(&$arg)?;
-        snafu::ensure!(
-            cursor.offset >= 0 && cursor.offset < $total_count,
-            PaginationCursorSnafu {
-                arg: stringify!($arg),
+    ($arg: ident, $key_len: expr) => {
+        match $arg {
+            Some($arg) => {
+                let cursor = Cursor::decode(&$arg)?;
+                snafu::ensure!(
+                    cursor.key.len() == $key_len,
+                    PaginationCursorSnafu {
+                        arg: stringify!($arg),
+                    }
+                );
+                Some(cursor)
             }
-        );
-        cursor.offset
-    }};
+            None => None,
+        }
+    };
 }
 
 macro_rules! ensure_limit {
@@ -43,10 +48,15 @@ macro_rules! ensure_limit {
     };
 }
 
+/// Keyset pagination over the primary key of a table
+///
+/// The cursor holds the key of an entry instead of its offset, so each page
+/// is loaded with a range scan over the primary key index and the cursors
+/// remain valid when new entries are inserted.
 #[derive(Debug, PartialEq)]
 pub struct Pagination {
-    total_count: i32,
-    offset: i32,
+    backward: bool,
+    cursor: Option<Cursor>,
     limit: i32,
 }
 
@@ -56,102 +66,103 @@ impl Pagination {
         last: Option<i32>,
         after: Option<String>,
         before: Option<String>,
-        total_count: i32,
+        key_len: usize,
     ) -> Result<Self, Error> {
         let forward = first.is_some() || after.is_some();
         let backward = last.is_some() || before.is_some();
         snafu::ensure!(!forward || !backward, MixedPaginationSnafu);
         if backward {
-            let before_offset = match before {
-                Some(before) => ensure_cursor!(before, total_count),
-                None => total_count,
-            };
-            let limit = ensure_limit!(last);
-            if limit >= before_offset {
-                Ok(Self {
-                    total_count,
-                    offset: 0,
-                    limit: before_offset,
-                })
-            } else {
-                Ok(Self {
-                    total_count,
-                    offset: before_offset - limit,
-                    limit,
-                })
-            }
+            Ok(Self {
+                backward,
+                cursor: ensure_cursor!(before, key_len),
+                limit: ensure_limit!(last),
+            })
         } else {
-            let offset = match after {
-                Some(after) => ensure_cursor!(after, total_count) + 1,
-                None => 0,
-            };
-            let limit = ensure_limit!(first);
-            if offset + limit > total_count {
-                Ok(Self {
-                    total_count,
-                    offset,
-                    limit: total_count - offset,
-                })
-            } else {
-                Ok(Self {
-                    total_count,
-                    offset,
-                    limit,
-                })
-            }
+            Ok(Self {
+                backward,
+                cursor: ensure_cursor!(after, key_len),
+                limit: ensure_limit!(first),
+            })
         }
     }
 
-    pub fn offset(&self) -> i32 {
-        self.offset
+    /// Whether the entries are loaded in descending key order, from the
+    /// cursor backwards
+    pub fn is_backward(&self) -> bool {
+        self.backward
     }
 
-    pub fn limit(&self) -> i32 {
-        self.limit
+    /// Cursor where the page starts, excluding the entry it points to
+    pub fn cursor(&self) -> Option<&Cursor> {
+        self.cursor.as_ref()
     }
 
-    pub fn create_connection<T: Debug>(&self, nodes: Vec<T>) -> Connection<T> {
-        let mut edges = vec![];
-        for (i, node) in nodes.into_iter().enumerate() {
-            let cursor = Cursor {
-                offset: self.offset + i as i32,
-            };
-            edges.push(Edge { node, cursor });
+    /// Number of entries to load from the database; the entry past the limit
+    /// tells whether there are more pages
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit as i64 + 1
+    }
+
+    /// Create the connection from the nodes loaded in pagination order.
+    /// The `has_entries_behind` argument tells whether there are entries
+    /// behind the cursor, in the opposite direction of the pagination.
+    pub fn create_connection<T: Debug>(
+        &self,
+        mut nodes: Vec<T>,
+        cursor: impl Fn(&T) -> Cursor,
+        has_entries_behind: bool,
+        total_count: Option<i32>,
+    ) -> Connection<T> {
+        let has_more_entries = nodes.len() > self.limit as usize;
+        nodes.truncate(self.limit as usize);
+        if self.backward {
+            nodes.reverse();
         }
-        let (start_cursor, has_previous_page) =
-            if let Some(edge) = edges.first() {
-                (Some(edge.cursor), edge.cursor.offset > 0)
-            } else {
-                (None, false)
-            };
-        let (end_cursor, has_next_page) = if let Some(edge) = edges.last() {
-            (Some(edge.cursor), edge.cursor.offset < self.total_count - 1)
+        let edges: Vec<_> = nodes
+            .into_iter()
+            .map(|node| Edge {
+                cursor: cursor(&node),
+                node,
+            })
+            .collect();
+        let (has_next_page, has_previous_page) = if self.backward {
+            (has_entries_behind, has_more_entries)
         } else {
-            (None, false)
+            (has_more_entries, has_entries_behind)
         };
         let page_info = PageInfo {
-            start_cursor,
-            end_cursor,
+            start_cursor: edges.first().map(|edge| edge.cursor.clone()),
+            end_cursor: edges.last().map(|edge| edge.cursor.clone()),
             has_next_page,
             has_previous_page,
         };
         Connection {
-            total_count: self.total_count,
+            total_count,
             edges,
             page_info,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Cursor that points to an entry by its primary key
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Cursor {
-    offset: i32,
+    key: Vec<i32>,
 }
 
 impl Cursor {
+    pub fn new(key: Vec<i32>) -> Self {
+        Self { key }
+    }
+
+    pub fn key(&self) -> &[i32] {
+        &self.key
+    }
+
     /// Encode cursor as base64
     pub fn encode(&self) -> String {
-        base64_engine.encode(self.offset.to_string())
+        let key: Vec<_> = self.key.iter().map(i32::to_string).collect();
+        base64_engine.encode(key.join(":"))
     }
 
     /// Decode cursor from base64 String
@@ -159,17 +170,20 @@ impl Cursor {
         let bytes = base64_engine
             .decode(value)
             .context(DecodeBase64CursorSnafu)?;
-        let offset = std::str::from_utf8(&bytes)
+        let key = std::str::from_utf8(&bytes)
             .context(DecodeUTF8CursorSnafu)?
-            .parse::<i32>()
+            .split(':')
+            .map(str::parse::<i32>)
+            .collect::<Result<_, _>>()
             .context(ParseCursorSnafu)?;
-        Ok(Cursor { offset })
+        Ok(Cursor { key })
     }
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Connection<N: Debug> {
-    pub total_count: i32,
+    /// Number of entries that match the query, if it was requested
+    pub total_count: Option<i32>,
     pub edges: Vec<Edge<N>>,
     pub page_info: PageInfo,
 }
@@ -192,28 +206,44 @@ pub struct PageInfo {
 mod tests {
     use super::*;
 
+    fn cursor(key: &[i32]) -> Cursor {
+        Cursor { key: key.to_vec() }
+    }
+
+    fn edge(node: i32) -> Edge<i32> {
+        Edge {
+            node,
+            cursor: cursor(&[node]),
+        }
+    }
+
+    fn node_cursor(node: &i32) -> Cursor {
+        cursor(&[*node])
+    }
+
     #[test]
     fn it_encodes_cursor() {
-        assert_eq!(Cursor { offset: 0 }.encode(), "MA==");
-        assert_eq!(Cursor { offset: 1 }.encode(), "MQ==");
-        assert_eq!(Cursor { offset: 2 }.encode(), "Mg==");
-        assert_eq!(Cursor { offset: 1000 }.encode(), "MTAwMA==");
-        assert_eq!(Cursor { offset: i32::MAX }.encode(), "MjE0NzQ4MzY0Nw==");
+        assert_eq!(cursor(&[0]).encode(), "MA==");
+        assert_eq!(cursor(&[1]).encode(), "MQ==");
+        assert_eq!(cursor(&[2]).encode(), "Mg==");
+        assert_eq!(cursor(&[1000]).encode(), "MTAwMA==");
+        assert_eq!(cursor(&[i32::MAX]).encode(), "MjE0NzQ4MzY0Nw==");
+        assert_eq!(cursor(&[0, 2]).encode(), "MDoy");
+        assert_eq!(cursor(&[10, 1]).encode(), "MTA6MQ==");
     }
 
     #[test]
     fn it_decodes_cursor() {
-        assert_eq!(Cursor::decode("MA==").unwrap(), Cursor { offset: 0 });
-        assert_eq!(Cursor::decode("MQ==").unwrap(), Cursor { offset: 1 });
-        assert_eq!(Cursor::decode("Mg==").unwrap(), Cursor { offset: 2 });
-        assert_eq!(
-            Cursor::decode("MTAwMA==").unwrap(),
-            Cursor { offset: 1000 }
-        );
+        assert_eq!(Cursor::decode("MA==").unwrap(), cursor(&[0]));
+        assert_eq!(Cursor::decode("MQ==").unwrap(), cursor(&[1]));
+        assert_eq!(Cursor::decode("Mg==").unwrap(), cursor(&[2]));
+        assert_eq!(Cursor::decode("MTAwMA==").unwrap(), cursor(&[1000]));
         assert_eq!(
             Cursor::decode("MjE0NzQ4MzY0Nw==").unwrap(),
-            Cursor { offset: i32::MAX }
+            cursor(&[i32::MAX])
         );
+        assert_eq!(Cursor::decode("MDoy").unwrap(), cursor(&[0, 2]));
+        assert_eq!(Cursor::decode("MTA6MQ==").unwrap(), cursor(&[10, 1]));
     }
 
     #[test]
@@ -238,6 +268,10 @@ mod tests {
             Cursor::decode("aW52YWxpZA==").unwrap_err(),
             Error::ParseCursorError { .. }
         ));
+        assert!(matches!(
+            Cursor::decode("MDo=").unwrap_err(),
+            Error::ParseCursorError { .. }
+        ));
     }
 
     #[test]
@@ -245,163 +279,70 @@ mod tests {
         assert_eq!(
             Pagination::new(None, None, None, None, 1).unwrap(),
             Pagination {
-                total_count: 1,
-                offset: 0,
-                limit: 1
-            }
-        );
-        assert_eq!(
-            Pagination::new(None, None, None, None, 10).unwrap(),
-            Pagination {
-                total_count: 10,
-                offset: 0,
-                limit: 10
-            }
-        );
-        assert_eq!(
-            Pagination::new(
-                None,
-                None,
-                None,
-                None,
-                DEFAULT_PAGINATION_LIMIT * 10
-            )
-            .unwrap(),
-            Pagination {
-                total_count: DEFAULT_PAGINATION_LIMIT * 10,
-                offset: 0,
+                backward: false,
+                cursor: None,
                 limit: DEFAULT_PAGINATION_LIMIT
             }
         );
     }
 
-    #[test]
-    fn it_paginates_with_zero_total_count() {
-        assert_eq!(
-            Pagination::new(Some(10), None, None, None, 0).unwrap(),
-            Pagination {
-                total_count: 0,
-                offset: 0,
-                limit: 0
-            }
-        );
-        assert_eq!(
-            Pagination::new(None, Some(10), None, None, 0).unwrap(),
-            Pagination {
-                total_count: 0,
-                offset: 0,
-                limit: 0
-            }
-        );
-    }
-
     #[test]
     fn it_paginates_forward_with_bounded_limit() {
         assert_eq!(
-            Pagination::new(Some(5), None, None, None, 10).unwrap(),
+            Pagination::new(Some(5), None, None, None, 1).unwrap(),
             Pagination {
-                total_count: 10,
-                offset: 0,
+                backward: false,
+                cursor: None,
                 limit: 5
             }
         );
-        let cursor = Cursor { offset: 2 }.encode();
+        let after = cursor(&[0, 2]).encode();
         assert_eq!(
-            Pagination::new(Some(5), None, Some(cursor), None, 10).unwrap(),
+            Pagination::new(Some(5), None, Some(after), None, 2).unwrap(),
             Pagination {
-                total_count: 10,
-                offset: 3,
+                backward: false,
+                cursor: Some(cursor(&[0, 2])),
                 limit: 5
             }
         );
-        let cursor = Cursor { offset: 4 }.encode();
         assert_eq!(
-            Pagination::new(Some(5), None, Some(cursor), None, 10).unwrap(),
-            Pagination {
-                total_count: 10,
-                offset: 5,
-                limit: 5
-            }
-        );
-    }
-
-    #[test]
-    fn it_paginates_forward_with_out_of_bounds_limit() {
-        let cursor = Cursor { offset: 7 }.encode();
-        assert_eq!(
-            Pagination::new(Some(5), None, Some(cursor), None, 10).unwrap(),
-            Pagination {
-                total_count: 10,
-                offset: 8,
-                limit: 2
-            }
-        );
-        let cursor = Cursor { offset: 9 }.encode();
-        assert_eq!(
-            Pagination::new(Some(5), None, Some(cursor), None, 10).unwrap(),
-            Pagination {
-                total_count: 10,
-                offset: 10,
-                limit: 0
-            }
+            Pagination::new(
+                Some(DEFAULT_PAGINATION_LIMIT * 10),
+                None,
+                None,
+                None,
+                1
+            )
+            .unwrap()
+            .limit,
+            DEFAULT_PAGINATION_LIMIT
         );
     }
 
     #[test]
     fn it_paginates_backward_with_bounded_limit() {
         assert_eq!(
-            Pagination::new(None, Some(5), None, None, 10).unwrap(),
-            Pagination {
-                total_count: 10,
-                offset: 5,
-                limit: 5
-            }
-        );
-        let cursor = Cursor { offset: 7 }.encode();
-        assert_eq!(
-            Pagination::new(None, Some(5), None, Some(cursor), 10).unwrap(),
+            Pagination::new(None, Some(5), None, None, 1).unwrap(),
             Pagination {
-                total_count: 10,
-                offset: 2,
+                backward: true,
+                cursor: None,
                 limit: 5
             }
         );
-        let cursor = Cursor { offset: 5 }.encode();
+        let before = cursor(&[7]).encode();
         assert_eq!(
-            Pagination::new(None, Some(5), None, Some(cursor), 10).unwrap(),
+            Pagination::new(None, Some(5), None, Some(before), 1).unwrap(),
             Pagination {
-                total_count: 10,
-                offset: 0,
+                backward: true,
+                cursor: Some(cursor(&[7])),
                 limit: 5
             }
         );
     }
 
-    #[test]
-    fn it_paginates_backward_with_out_of_bounds_limit() {
-        let cursor = Cursor { offset: 3 }.encode();
-        assert_eq!(
-            Pagination::new(None, Some(5), None, Some(cursor), 10).unwrap(),
-            Pagination {
-                total_count: 10,
-                offset: 0,
-                limit: 3
-            }
-        );
-        let cursor = Cursor { offset: 0 }.encode();
-        assert_eq!(
-            Pagination::new(None, Some(5), None, Some(cursor), 10).unwrap(),
-            Pagination {
-                total_count: 10,
-                offset: 0,
-                limit: 0
-            }
-        );
-    }
-
     #[test]
     fn it_fails_to_paginate_when_mixing_backward_and_forward_args() {
-        let cursor = Cursor { offset: 0 }.encode();
+        let cursor = cursor(&[0]).encode();
         assert!(matches!(
             Pagination::new(Some(1), Some(1), None, None, 1).unwrap_err(),
             Error::MixedPaginationError {}
@@ -432,11 +373,11 @@ mod tests {
     #[test]
     fn it_fails_to_paginate_when_limit_is_negative() {
         assert!(matches!(
-            Pagination::new(Some(-1), None, None, None, 10).unwrap_err(),
+            Pagination::new(Some(-1), None, None, None, 1).unwrap_err(),
             Error::PaginationLimitError { arg } if arg == "first"
         ));
         assert!(matches!(
-            Pagination::new(None, Some(-1), None, None, 10).unwrap_err(),
+            Pagination::new(None, Some(-1), None, None, 1).unwrap_err(),
             Error::PaginationLimitError { arg } if arg == "last"
         ));
     }
@@ -444,27 +385,27 @@ mod tests {
     #[test]
     fn it_fails_to_paginate_with_invalid_cursor() {
         assert!(matches!(
-            Pagination::new(None, None, Some("invalid".to_owned()), None, 10)
+            Pagination::new(None, None, Some("invalid".to_owned()), None, 1)
                 .unwrap_err(),
             Error::DecodeBase64CursorError { .. }
         ));
         assert!(matches!(
-            Pagination::new(None, None, None, Some("invalid".to_owned()), 10)
+            Pagination::new(None, None, None, Some("invalid".to_owned()), 1)
                 .unwrap_err(),
             Error::DecodeBase64CursorError { .. }
         ));
     }
 
     #[test]
-    fn it_fails_to_paginate_with_cursor_out_of_range() {
-        let cursor = Cursor { offset: 10 }.encode();
+    fn it_fails_to_paginate_with_cursor_of_another_key() {
+        let cursor = cursor(&[0]).encode();
         assert!(matches!(
-            Pagination::new(None, None, Some(cursor.clone()), None, 10)
+            Pagination::new(None, None, Some(cursor.clone()), None, 2)
                 .unwrap_err(),
             Error::PaginationCursorError { arg } if arg == "after"
         ));
         assert!(matches!(
-            Pagination::new(None, None, None, Some(cursor.clone()), 10)
+            Pagination::new(None, None, None, Some(cursor.clone()), 2)
                 .unwrap_err(),
             Error::PaginationCursorError { arg } if arg == "before"
         ));
@@ -472,16 +413,13 @@ mod tests {
 
     #[test]
     fn it_creates_connection_without_nodes() {
-        let pagination = Pagination {
-            total_count: 3,
-            offset: 0,
-            limit: 0,
-        };
-        let connection = pagination.create_connection::<String>(vec![]);
+        let pagination = Pagination::new(None, None, None, None, 1).unwrap();
+        let connection =
+            pagination.create_connection(vec![], node_cursor, false, Some(0));
         assert_eq!(
             connection,
             Connection {
-                total_count: 3,
+                total_count: Some(0),
                 edges: vec![],
                 page_info: PageInfo {
                     start_cursor: None,
@@ -495,37 +433,21 @@ mod tests {
 
     #[test]
     fn it_creates_connection_with_all_nodes() {
-        let pagination = Pagination {
-            total_count: 3,
-            offset: 0,
-            limit: 3,
-        };
-        let connection = pagination.create_connection::<String>(vec![
-            "0".to_owned(),
-            "1".to_owned(),
-            "2".to_owned(),
-        ]);
+        let pagination = Pagination::new(Some(3), None, None, None, 1).unwrap();
+        let connection = pagination.create_connection(
+            vec![0, 1, 2],
+            node_cursor,
+            false,
+            None,
+        );
         assert_eq!(
             connection,
             Connection {
-                total_count: 3,
-                edges: vec![
-                    Edge {
-                        node: "0".to_owned(),
-                        cursor: Cursor { offset: 0 },
-                    },
-                    Edge {
-                        node: "1".to_owned(),
-                        cursor: Cursor { offset: 1 },
-                    },
-                    Edge {
-                        node: "2".to_owned(),
-                        cursor: Cursor { offset: 2 },
-                    },
-                ],
+                total_count: None,
+                edges: vec![edge(0), edge(1), edge(2)],
                 page_info: PageInfo {
-                    start_cursor: Some(Cursor { offset: 0 }),
-                    end_cursor: Some(Cursor { offset: 2 }),
+                    start_cursor: Some(cursor(&[0])),
+                    end_cursor: Some(cursor(&[2])),
                     has_next_page: false,
                     has_previous_page: false,
                 }
@@ -535,30 +457,22 @@ mod tests {
 
     #[test]
     fn it_creates_connection_on_first_page() {
-        let pagination = Pagination {
-            total_count: 3,
-            offset: 0,
-            limit: 2,
-        };
-        let connection = pagination
-            .create_connection::<String>(vec!["0".to_owned(), "1".to_owned()]);
+        // The extra node shows there is a next page
+        let pagination = Pagination::new(Some(2), None, None, None, 1).unwrap();
+        let connection = pagination.create_connection(
+            vec![0, 1, 2],
+            node_cursor,
+            false,
+            Some(3),
+        );
         assert_eq!(
             connection,
             Connection {
-                total_count: 3,
-                edges: vec![
-                    Edge {
-                        node: "0".to_owned(),
-                        cursor: Cursor { offset: 0 },
-                    },
-                    Edge {
-                        node: "1".to_owned(),
-                        cursor: Cursor { offset: 1 },
-                    },
-                ],
+                total_count: Some(3),
+                edges: vec![edge(0), edge(1)],
                 page_info: PageInfo {
-                    start_cursor: Some(Cursor { offset: 0 }),
-                    end_cursor: Some(Cursor { offset: 1 }),
+                    start_cursor: Some(cursor(&[0])),
+                    end_cursor: Some(cursor(&[1])),
                     has_next_page: true,
                     has_previous_page: false,
                 }
@@ -568,30 +482,23 @@ mod tests {
 
     #[test]
     fn it_creates_connection_on_last_page() {
-        let pagination = Pagination {
-            total_count: 3,
-            offset: 1,
-            limit: 2,
-        };
-        let connection = pagination
-            .create_connection::<String>(vec!["1".to_owned(), "2".to_owned()]);
+        let after = Some(cursor(&[0]).encode());
+        let pagination =
+            Pagination::new(Some(2), None, after, None, 1).unwrap();
+        let connection = pagination.create_connection(
+            vec![1, 2],
+            node_cursor,
+            true,
+            Some(3),
+        );
         assert_eq!(
             connection,
             Connection {
-                total_count: 3,
-                edges: vec![
-                    Edge {
-                        node: "1".to_owned(),
-                        cursor: Cursor { offset: 1 },
-                    },
-                    Edge {
-                        node: "2".to_owned(),
-                        cursor: Cursor { offset: 2 },
-                    },
-                ],
+                total_count: Some(3),
+                edges: vec![edge(1), edge(2)],
                 page_info: PageInfo {
-                    start_cursor: Some(Cursor { offset: 1 }),
-                    end_cursor: Some(Cursor { offset: 2 }),
+                    start_cursor: Some(cursor(&[1])),
+                    end_cursor: Some(cursor(&[2])),
                     has_next_page: false,
                     has_previous_page: true,
                 }
@@ -600,25 +507,25 @@ mod tests {
     }
 
     #[test]
-    fn it_creates_connection_on_middle_page() {
-        let pagination = Pagination {
-            total_count: 3,
-            offset: 1,
-            limit: 1,
-        };
-        let connection =
-            pagination.create_connection::<String>(vec!["1".to_owned()]);
+    fn it_creates_connection_backwards() {
+        // The nodes are loaded in descending order
+        let before = Some(cursor(&[3]).encode());
+        let pagination =
+            Pagination::new(None, Some(2), None, before, 1).unwrap();
+        let connection = pagination.create_connection(
+            vec![2, 1, 0],
+            node_cursor,
+            true,
+            None,
+        );
         assert_eq!(
             connection,
             Connection {
-                total_count: 3,
-                edges: vec![Edge {
-                    node: "1".to_owned(),
-                    cursor: Cursor { offset: 1 },
-                },],
+                total_count: None,
+                edges: vec![edge(1), edge(2)],
                 page_info: PageInfo {
-                    start_cursor: Some(Cursor { offset: 1 }),
-                    end_cursor: Some(Cursor { offset: 1 }),
+                    start_cursor: Some(cursor(&[1])),
+                    end_cursor: Some(cursor(&[2])),
                     has_next_page: true,
                     has_previous_page: true,
                 }
@@ -4,35 +4,47 @@
 use backoff::ExponentialBackoff;
 use diesel::pg::{Pg, PgConnection};
 use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
-use diesel::{insert_into, prelude::*, update};
+use diesel::result::Error as DieselError;
+use diesel::sql_types::Binary;
+use diesel::{delete, insert_into, prelude::*, update};
 use snafu::ResultExt;
 use std::sync::Arc;
 
 use super::config::RepositoryConfig;
 use super::error::{DatabaseConnectionSnafu, DatabaseSnafu, Error};
-use super::pagination::{Connection, Pagination};
 use super::schema;
 use super::types::{
-    CompletionStatus, Input, InputQueryFilter, Notice, NoticeQueryFilter,
-    OutputEnum, Proof, Report, ReportQueryFilter, Voucher, VoucherQueryFilter,
+    Application, Claim, ClaimQueryFilter, CompletionStatus, Epoch,
+    EpochQueryFilter, IndexerProgress, Input, InputQueryFilter, Notice,
+    NoticeQueryFilter, Proof, Report, ReportQueryFilter, Voucher,
+    VoucherQueryFilter,
 };
 
-pub const POOL_CONNECTION_SIZE: u32 = 3;
+/// Application of the rows stored before the database had an application
+/// dimension, which is adopted by the first application registered
+pub const DEFAULT_APPLICATION_ID: i32 = 0;
+
+/// Max number of rows in each multi-row insert, which keeps the number of
+/// bind parameters below the Postgres limit
+const INSERT_CHUNK_SIZE: usize = 1000;
 
 #[derive(Clone, Debug)]
 pub struct Repository {
     // Connection is not thread safe to share between threads, we use connection pool
     db_pool: Arc<Pool<ConnectionManager<PgConnection>>>,
     backoff: ExponentialBackoff,
+    /// Application whose rows are read and written by the queries
+    application_id: i32,
 }
 
 impl Repository {
     /// Create database connection pool, wait until database server is available with backoff strategy
+    /// The repository reads and writes the rows of the default application.
     pub fn new(config: RepositoryConfig) -> Result<Self, Error> {
         let db_pool = backoff::retry(config.backoff.clone(), || {
             tracing::info!(?config, "trying to create db pool for database");
             Pool::builder()
-                .max_size(POOL_CONNECTION_SIZE)
+                .max_size(config.connection_pool_size)
                 .build(ConnectionManager::<PgConnection>::new(
                     config.endpoint(),
                 ))
@@ -42,9 +54,25 @@ impl Repository {
         Ok(Self {
             db_pool: Arc::new(db_pool),
             backoff: config.backoff,
+            application_id: DEFAULT_APPLICATION_ID,
         })
     }
 
+    /// Id of the application whose rows the repository reads and writes
+    pub fn application_id(&self) -> i32 {
+        self.application_id
+    }
+
+    /// Create a repository that reads and writes the rows of the given
+    /// application, sharing the connection pool with this one
+    pub fn for_application(&self, application_id: i32) -> Self {
+        Self {
+            db_pool: self.db_pool.clone(),
+            backoff: self.backoff.clone(),
+            application_id,
+        }
+    }
+
     /// Obtain the connection from the connection pool
     fn conn(
         &self,
@@ -56,114 +84,269 @@ impl Repository {
     }
 }
 
-/// Basic queries that fetch by primary_key
+/// Queries of the applications, which scope the rows of every other table
 impl Repository {
-    pub fn get_input(&self, index: i32) -> Result<Input, Error> {
-        use schema::inputs::dsl;
+    /// Register the application, unless it was already registered
+    pub fn register_application(
+        &self,
+        chain_id: i64,
+        dapp_address: &[u8],
+    ) -> Result<Application, Error> {
+        use schema::applications::dsl;
         let mut conn = self.conn()?;
-        dsl::inputs
-            .filter(dsl::index.eq(index))
-            .load::<Input>(&mut conn)
-            .context(DatabaseSnafu)?
-            .pop()
-            .ok_or(Error::ItemNotFound {
-                item_type: "input".to_owned(),
+        let application = conn
+            .transaction::<_, DieselError, _>(|conn| {
+                if let Some(application) =
+                    find_application(conn, chain_id, dapp_address)?
+                {
+                    return Ok(application);
+                }
+                insert_into(dsl::applications)
+                    .values((
+                        dsl::chain_id.eq(chain_id),
+                        dsl::dapp_address.eq(dapp_address),
+                    ))
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+                find_application(conn, chain_id, dapp_address)?
+                    .ok_or(DieselError::NotFound)
             })
+            .context(DatabaseSnafu)?;
+        tracing::trace!(
+            "Application {} of chain {} was registered in the db",
+            application.id,
+            chain_id
+        );
+        Ok(application)
     }
 
-    pub fn get_voucher(
+    /// Register the application as the default application, which has the
+    /// rows stored before the migration
+    /// Only the dapp that used the database before the migration may adopt
+    /// the default application, so it fails if another dapp adopted it or if
+    /// the dapp was already registered as another application.
+    pub fn adopt_default_application(
         &self,
-        index: i32,
-        input_index: i32,
-    ) -> Result<Voucher, Error> {
+        chain_id: i64,
+        dapp_address: &[u8],
+    ) -> Result<Application, Error> {
+        use schema::applications::dsl;
         let mut conn = self.conn()?;
-        use schema::vouchers::dsl;
-        dsl::vouchers
-            .filter(dsl::index.eq(index))
-            .filter(dsl::input_index.eq(input_index))
-            .load::<Voucher>(&mut conn)
-            .context(DatabaseSnafu)?
-            .pop()
-            .ok_or(Error::ItemNotFound {
-                item_type: "voucher".to_owned(),
+        let adopted = conn
+            .transaction::<_, DieselError, _>(|conn| {
+                if let Some(application) =
+                    find_application(conn, chain_id, dapp_address)?
+                {
+                    let is_default = application.id == DEFAULT_APPLICATION_ID;
+                    return Ok(is_default.then_some(application));
+                }
+                update(dsl::applications)
+                    .filter(dsl::id.eq(DEFAULT_APPLICATION_ID))
+                    .filter(dsl::dapp_address.eq(Vec::<u8>::new()))
+                    .set((
+                        dsl::chain_id.eq(chain_id),
+                        dsl::dapp_address.eq(dapp_address),
+                    ))
+                    .returning(Application::as_returning())
+                    .get_result(conn)
+                    .optional()
             })
+            .context(DatabaseSnafu)?;
+        let application =
+            adopted.ok_or(Error::DefaultApplicationAdoptedError {})?;
+        tracing::trace!(
+            "Application of chain {} adopted the default application",
+            chain_id
+        );
+        Ok(application)
     }
 
-    pub fn get_notice(
+    /// Get the application deployed at the given address of the chain, if it
+    /// was registered
+    pub fn find_application(
         &self,
-        index: i32,
-        input_index: i32,
-    ) -> Result<Notice, Error> {
-        use schema::notices::dsl;
+        chain_id: i64,
+        dapp_address: &[u8],
+    ) -> Result<Option<Application>, Error> {
         let mut conn = self.conn()?;
-        dsl::notices
-            .filter(dsl::index.eq(index))
-            .filter(dsl::input_index.eq(input_index))
-            .load::<Notice>(&mut conn)
-            .context(DatabaseSnafu)?
-            .pop()
-            .ok_or(Error::ItemNotFound {
-                item_type: "notice".to_owned(),
-            })
+        find_application(&mut conn, chain_id, dapp_address)
+            .context(DatabaseSnafu)
     }
+}
+
+fn find_application(
+    conn: &mut PgConnection,
+    chain_id: i64,
+    dapp_address: &[u8],
+) -> Result<Option<Application>, DieselError> {
+    use schema::applications::dsl;
+    dsl::applications
+        .filter(dsl::chain_id.eq(chain_id))
+        .filter(dsl::dapp_address.eq(dapp_address))
+        .select(Application::as_select())
+        .first(conn)
+        .optional()
+}
 
-    pub fn get_report(
+/// Write operations, each one in its own transaction
+impl Repository {
+    /// Run the write operations in a single database transaction, which is
+    /// rolled back if any of them fails
+    pub fn transaction<T>(
         &self,
-        index: i32,
-        input_index: i32,
-    ) -> Result<Report, Error> {
-        use schema::reports::dsl;
+        f: impl FnOnce(&mut Transaction<'_>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
         let mut conn = self.conn()?;
-        dsl::reports
-            .filter(dsl::index.eq(index))
-            .filter(dsl::input_index.eq(input_index))
-            .load::<Report>(&mut conn)
-            .context(DatabaseSnafu)?
-            .pop()
-            .ok_or(Error::ItemNotFound {
-                item_type: "report".to_owned(),
+        let application_id = self.application_id;
+        conn.transaction(|conn| {
+            f(&mut Transaction {
+                conn,
+                application_id,
             })
+        })
+    }
+
+    pub fn insert_input(&self, input: Input) -> Result<(), Error> {
+        self.transaction(|tx| tx.insert_input(input))
+    }
+
+    pub fn insert_notice(&self, notice: Notice) -> Result<(), Error> {
+        self.transaction(|tx| tx.insert_notice(notice))
+    }
+
+    pub fn insert_voucher(&self, voucher: Voucher) -> Result<(), Error> {
+        self.transaction(|tx| tx.insert_voucher(voucher))
+    }
+
+    pub fn insert_report(&self, report: Report) -> Result<(), Error> {
+        self.transaction(|tx| tx.insert_report(report))
+    }
+
+    pub fn insert_proof(&self, proof: Proof) -> Result<(), Error> {
+        self.transaction(|tx| tx.insert_proof(proof))
+    }
+
+    pub fn insert_claim(
+        &self,
+        epoch: Epoch,
+        claim: Claim,
+    ) -> Result<(), Error> {
+        self.transaction(|tx| tx.insert_claim(epoch, claim))
     }
 
-    pub fn get_proof(
+    pub fn upsert_claim(
+        &self,
+        epoch: Epoch,
+        claim: Claim,
+    ) -> Result<(), Error> {
+        self.transaction(|tx| tx.upsert_claim(epoch, claim))
+    }
+
+    pub fn delete_inputs_from(&self, first_index: i32) -> Result<(), Error> {
+        self.transaction(|tx| tx.delete_inputs_from(first_index))
+    }
+
+    pub fn delete_outputs(&self, input_index: i32) -> Result<(), Error> {
+        self.transaction(|tx| tx.delete_outputs(input_index))
+    }
+
+    pub fn update_input_status(
         &self,
         input_index: i32,
-        output_index: i32,
-        output_enum: OutputEnum,
-    ) -> Result<Option<Proof>, Error> {
-        use schema::proofs::dsl;
-        let mut conn = self.conn()?;
-        dsl::proofs
-            .filter(dsl::input_index.eq(input_index))
-            .filter(dsl::output_index.eq(output_index))
-            .filter(dsl::output_enum.eq(output_enum))
-            .load::<Proof>(&mut conn)
-            .map(|mut proofs| proofs.pop())
-            .context(DatabaseSnafu)
+        status: CompletionStatus,
+    ) -> Result<(), Error> {
+        self.transaction(|tx| tx.update_input_status(input_index, status))
+    }
+
+    pub fn update_voucher_executed(
+        &self,
+        input_index: i32,
+        index: i32,
+        transaction_hash: Vec<u8>,
+    ) -> Result<bool, Error> {
+        self.transaction(|tx| {
+            tx.update_voucher_executed(input_index, index, transaction_hash)
+        })
+    }
+}
+
+/// Write operations that run in the same database transaction
+pub struct Transaction<'a> {
+    conn: &'a mut PgConnection,
+    application_id: i32,
+}
+
+impl Transaction<'_> {
+    /// Run the operations in a savepoint, so their failure only rolls back
+    /// the savepoint instead of the whole transaction
+    pub fn savepoint<T>(
+        &mut self,
+        f: impl FnOnce(&mut Transaction<'_>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let application_id = self.application_id;
+        self.conn.transaction(|conn| {
+            f(&mut Transaction {
+                conn,
+                application_id,
+            })
+        })
+    }
+}
+
+/// Update of the indexer progress
+impl Transaction<'_> {
+    /// Set the last event stored by the indexer from the broker stream
+    /// The progress is written with the data of the event, so the indexer
+    /// resumes after the last event it stored.
+    pub fn set_indexer_progress(
+        &mut self,
+        progress: IndexerProgress,
+    ) -> Result<(), Error> {
+        use schema::indexer_progress;
+        insert_into(indexer_progress::table)
+            .values((
+                indexer_progress::application_id.eq(self.application_id),
+                &progress,
+            ))
+            .on_conflict((
+                indexer_progress::application_id,
+                indexer_progress::stream_key,
+            ))
+            .do_update()
+            .set((
+                indexer_progress::last_event_id.eq(&progress.last_event_id),
+                indexer_progress::rollback_index.eq(progress.rollback_index),
+            ))
+            .execute(self.conn)
+            .context(DatabaseSnafu)?;
+        tracing::trace!(
+            "Set last event {} of stream {}",
+            progress.last_event_id,
+            progress.stream_key
+        );
+        Ok(())
     }
 }
 
 /// Basic queries to insert rollups' outputs
-impl Repository {
-    pub fn insert_input(&self, input: Input) -> Result<(), Error> {
+impl Transaction<'_> {
+    pub fn insert_input(&mut self, input: Input) -> Result<(), Error> {
         use schema::inputs;
-        let mut conn = self.conn()?;
         insert_into(inputs::table)
-            .values(&input)
+            .values((inputs::application_id.eq(self.application_id), &input))
             .on_conflict_do_nothing()
-            .execute(&mut conn)
+            .execute(self.conn)
             .context(DatabaseSnafu)?;
         tracing::trace!("Input {} was written to the db", input.index);
         Ok(())
     }
 
-    pub fn insert_notice(&self, notice: Notice) -> Result<(), Error> {
+    pub fn insert_notice(&mut self, notice: Notice) -> Result<(), Error> {
         use schema::notices;
-        let mut conn = self.conn()?;
         insert_into(notices::table)
-            .values(&notice)
+            .values((notices::application_id.eq(self.application_id), &notice))
             .on_conflict_do_nothing()
-            .execute(&mut conn)
+            .execute(self.conn)
             .context(DatabaseSnafu)?;
         tracing::trace!(
             "Notice {} from Input {} was written to the db",
@@ -173,29 +356,30 @@ impl Repository {
         Ok(())
     }
 
-    pub fn insert_voucher(&self, voucher: Voucher) -> Result<(), Error> {
+    pub fn insert_voucher(&mut self, voucher: Voucher) -> Result<(), Error> {
         use schema::vouchers;
-        let mut conn = self.conn()?;
         insert_into(vouchers::table)
-            .values(&voucher)
+            .values((
+                vouchers::application_id.eq(self.application_id),
+                &voucher,
+            ))
             .on_conflict_do_nothing()
-            .execute(&mut conn)
+            .execute(self.conn)
             .context(DatabaseSnafu)?;
         tracing::trace!(
             "Voucher {} from Input {} was written to the db",
             voucher.index,
             voucher.input_index
         );
-        Ok(())
+        self.apply_voucher_executions(vec![voucher.input_index])
     }
 
-    pub fn insert_report(&self, report: Report) -> Result<(), Error> {
+    pub fn insert_report(&mut self, report: Report) -> Result<(), Error> {
         use schema::reports;
-        let mut conn = self.conn()?;
         insert_into(reports::table)
-            .values(&report)
+            .values((reports::application_id.eq(self.application_id), &report))
             .on_conflict_do_nothing()
-            .execute(&mut conn)
+            .execute(self.conn)
             .context(DatabaseSnafu)?;
         tracing::trace!(
             "Report {} from Input {} was written to the db",
@@ -205,13 +389,12 @@ impl Repository {
         Ok(())
     }
 
-    pub fn insert_proof(&self, proof: Proof) -> Result<(), Error> {
+    pub fn insert_proof(&mut self, proof: Proof) -> Result<(), Error> {
         use schema::proofs;
-        let mut conn = self.conn()?;
         insert_into(proofs::table)
-            .values(&proof)
+            .values((proofs::application_id.eq(self.application_id), &proof))
             .on_conflict_do_nothing()
-            .execute(&mut conn)
+            .execute(self.conn)
             .context(DatabaseSnafu)?;
         tracing::trace!(
             "Proof for {:?} {} of Input {} was written to the db",
@@ -223,30 +406,374 @@ impl Repository {
     }
 }
 
+/// Insert the rows with multi-row inserts, skipping the rows that were
+/// already inserted
+macro_rules! impl_multi_row_insert {
+    ($vis: vis $method: ident, $table: ident, $type: ty) => {
+        impl Transaction<'_> {
+            $vis fn $method(&mut self, rows: Vec<$type>) -> Result<(), Error> {
+                use schema::$table;
+                for chunk in rows.chunks(INSERT_CHUNK_SIZE) {
+                    let values: Vec<_> = chunk
+                        .iter()
+                        .map(|row| {
+                            (
+                                $table::application_id.eq(self.application_id),
+                                row,
+                            )
+                        })
+                        .collect();
+                    insert_into($table::table)
+                        .values(values)
+                        .on_conflict_do_nothing()
+                        .execute(self.conn)
+                        .context(DatabaseSnafu)?;
+                }
+                tracing::trace!(
+                    "{} rows were written to {} in the db",
+                    rows.len(),
+                    stringify!($table)
+                );
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_multi_row_insert!(insert_voucher_rows, vouchers, Voucher);
+impl_multi_row_insert!(pub insert_notices, notices, Notice);
+impl_multi_row_insert!(pub insert_reports, reports, Report);
+impl_multi_row_insert!(pub insert_proofs, proofs, Proof);
+
+impl Transaction<'_> {
+    /// Insert the vouchers and mark the ones that were already executed
+    pub fn insert_vouchers(
+        &mut self,
+        vouchers: Vec<Voucher>,
+    ) -> Result<(), Error> {
+        let mut input_indices: Vec<_> =
+            vouchers.iter().map(|voucher| voucher.input_index).collect();
+        input_indices.dedup();
+        self.insert_voucher_rows(vouchers)?;
+        self.apply_voucher_executions(input_indices)
+    }
+}
+
+/// Queries to insert the claims and their epochs
+impl Transaction<'_> {
+    /// Insert the claim and its epoch, unless the claim was already inserted
+    pub fn insert_claim(
+        &mut self,
+        epoch: Epoch,
+        claim: Claim,
+    ) -> Result<(), Error> {
+        let application_id = self.application_id;
+        self.conn
+            .transaction::<_, DieselError, _>(|conn| {
+                use schema::{claims, epochs};
+                insert_into(epochs::table)
+                    .values((epochs::application_id.eq(application_id), &epoch))
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+                insert_into(claims::table)
+                    .values((claims::application_id.eq(application_id), &claim))
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+                Ok(())
+            })
+            .context(DatabaseSnafu)?;
+        tracing::trace!("Claim of epoch {} was written to the db", epoch.index);
+        Ok(())
+    }
+
+    /// Insert the claim and its epoch, replacing the submission status if the
+    /// claim was already inserted
+    pub fn upsert_claim(
+        &mut self,
+        epoch: Epoch,
+        claim: Claim,
+    ) -> Result<(), Error> {
+        let application_id = self.application_id;
+        self.conn
+            .transaction::<_, DieselError, _>(|conn| {
+                use schema::{claims, epochs};
+                insert_into(epochs::table)
+                    .values((epochs::application_id.eq(application_id), &epoch))
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+                insert_into(claims::table)
+                    .values((claims::application_id.eq(application_id), &claim))
+                    .on_conflict((claims::application_id, claims::epoch_index))
+                    .do_update()
+                    .set((
+                        claims::status.eq(claim.status),
+                        claims::transaction_hash.eq(&claim.transaction_hash),
+                    ))
+                    .execute(conn)?;
+                Ok(())
+            })
+            .context(DatabaseSnafu)?;
+        tracing::trace!(
+            "Set {:?} status to claim of epoch {}",
+            claim.status,
+            epoch.index
+        );
+        Ok(())
+    }
+}
+
+/// Delete operations used to roll back after a reorg
+impl Transaction<'_> {
+    /// Delete the inputs starting from the given index, their outputs, and
+    /// the epochs that contain them
+    pub fn delete_inputs_from(
+        &mut self,
+        first_index: i32,
+    ) -> Result<(), Error> {
+        let application_id = self.application_id;
+        self.conn
+            .transaction::<_, DieselError, _>(|conn| {
+                use schema::{
+                    claims, epochs, inputs, notices, proofs, reports, vouchers,
+                };
+                let replaced_epochs = epochs::table
+                    .filter(epochs::application_id.eq(application_id))
+                    .filter(epochs::last_input_index.ge(first_index))
+                    .select(epochs::index);
+                delete(
+                    claims::table
+                        .filter(claims::application_id.eq(application_id))
+                        .filter(claims::epoch_index.eq_any(replaced_epochs)),
+                )
+                .execute(conn)?;
+                delete(
+                    epochs::table
+                        .filter(epochs::application_id.eq(application_id))
+                        .filter(epochs::last_input_index.ge(first_index)),
+                )
+                .execute(conn)?;
+                delete(
+                    proofs::table
+                        .filter(proofs::application_id.eq(application_id))
+                        .filter(proofs::input_index.ge(first_index)),
+                )
+                .execute(conn)?;
+                delete(
+                    vouchers::table
+                        .filter(vouchers::application_id.eq(application_id))
+                        .filter(vouchers::input_index.ge(first_index)),
+                )
+                .execute(conn)?;
+                delete(
+                    notices::table
+                        .filter(notices::application_id.eq(application_id))
+                        .filter(notices::input_index.ge(first_index)),
+                )
+                .execute(conn)?;
+                delete(
+                    reports::table
+                        .filter(reports::application_id.eq(application_id))
+                        .filter(reports::input_index.ge(first_index)),
+                )
+                .execute(conn)?;
+                delete(
+                    inputs::table
+                        .filter(inputs::application_id.eq(application_id))
+                        .filter(inputs::index.ge(first_index)),
+                )
+                .execute(conn)?;
+                Ok(())
+            })
+            .context(DatabaseSnafu)?;
+        tracing::trace!("Deleted inputs starting from {}", first_index);
+        Ok(())
+    }
+
+    /// Delete the outputs and proofs of the given input
+    pub fn delete_outputs(&mut self, input_index: i32) -> Result<(), Error> {
+        let application_id = self.application_id;
+        self.conn
+            .transaction::<_, DieselError, _>(|conn| {
+                use schema::{notices, proofs, reports, vouchers};
+                delete(
+                    proofs::table
+                        .filter(proofs::application_id.eq(application_id))
+                        .filter(proofs::input_index.eq(input_index)),
+                )
+                .execute(conn)?;
+                delete(
+                    vouchers::table
+                        .filter(vouchers::application_id.eq(application_id))
+                        .filter(vouchers::input_index.eq(input_index)),
+                )
+                .execute(conn)?;
+                delete(
+                    notices::table
+                        .filter(notices::application_id.eq(application_id))
+                        .filter(notices::input_index.eq(input_index)),
+                )
+                .execute(conn)?;
+                delete(
+                    reports::table
+                        .filter(reports::application_id.eq(application_id))
+                        .filter(reports::input_index.eq(input_index)),
+                )
+                .execute(conn)?;
+                Ok(())
+            })
+            .context(DatabaseSnafu)?;
+        tracing::trace!("Deleted outputs of input {}", input_index);
+        Ok(())
+    }
+}
+
 /// Update operations
-impl Repository {
+impl Transaction<'_> {
     pub fn update_input_status(
-        &self,
+        &mut self,
         input_index: i32,
         status: CompletionStatus,
     ) -> Result<(), Error> {
         use schema::inputs;
-        let mut conn = self.conn()?;
         update(inputs::table)
+            .filter(inputs::dsl::application_id.eq(self.application_id))
             .filter(inputs::dsl::index.eq(input_index))
             .set(inputs::status.eq(status))
-            .execute(&mut conn)
+            .execute(self.conn)
             .context(DatabaseSnafu)?;
         tracing::trace!("Set {:?} status to input {}", status, input_index);
         Ok(())
     }
+
+    /// Record that the voucher was executed by the given transaction and mark
+    /// it as executed. If the voucher isn't stored yet, it is marked when it
+    /// is inserted. Return whether the voucher was found.
+    pub fn update_voucher_executed(
+        &mut self,
+        input_index: i32,
+        index: i32,
+        transaction_hash: Vec<u8>,
+    ) -> Result<bool, Error> {
+        use schema::voucher_executions;
+        insert_into(voucher_executions::table)
+            .values((
+                voucher_executions::application_id.eq(self.application_id),
+                voucher_executions::input_index.eq(input_index),
+                voucher_executions::index.eq(index),
+                voucher_executions::transaction_hash.eq(&transaction_hash),
+            ))
+            .on_conflict((
+                voucher_executions::application_id,
+                voucher_executions::input_index,
+                voucher_executions::index,
+            ))
+            .do_update()
+            .set(voucher_executions::transaction_hash.eq(&transaction_hash))
+            .execute(self.conn)
+            .context(DatabaseSnafu)?;
+        self.set_voucher_executed(input_index, index, transaction_hash)
+    }
+
+    /// Mark the vouchers of the inputs with the executions recorded before
+    /// they were inserted
+    fn apply_voucher_executions(
+        &mut self,
+        input_indices: Vec<i32>,
+    ) -> Result<(), Error> {
+        use schema::voucher_executions;
+        if input_indices.is_empty() {
+            return Ok(());
+        }
+        let executions = voucher_executions::table
+            .filter(voucher_executions::application_id.eq(self.application_id))
+            .filter(voucher_executions::input_index.eq_any(input_indices))
+            .select((
+                voucher_executions::input_index,
+                voucher_executions::index,
+                voucher_executions::transaction_hash,
+            ))
+            .load::<(i32, i32, Vec<u8>)>(self.conn)
+            .context(DatabaseSnafu)?;
+        for (input_index, index, transaction_hash) in executions {
+            self.set_voucher_executed(input_index, index, transaction_hash)?;
+        }
+        Ok(())
+    }
+
+    fn set_voucher_executed(
+        &mut self,
+        input_index: i32,
+        index: i32,
+        transaction_hash: Vec<u8>,
+    ) -> Result<bool, Error> {
+        use schema::vouchers;
+        let count = update(vouchers::table)
+            .filter(vouchers::dsl::application_id.eq(self.application_id))
+            .filter(vouchers::dsl::input_index.eq(input_index))
+            .filter(vouchers::dsl::index.eq(index))
+            .set((
+                vouchers::executed.eq(true),
+                vouchers::execution_transaction_hash.eq(transaction_hash),
+            ))
+            .execute(self.conn)
+            .context(DatabaseSnafu)?;
+        tracing::trace!(
+            "Set voucher {} from input {} as executed",
+            index,
+            input_index
+        );
+        Ok(count > 0)
+    }
 }
 
 /// Generate a boxed query from an input query filter
 impl InputQueryFilter {
-    fn to_query(&self) -> schema::inputs::BoxedQuery<'_, Pg> {
+    pub(crate) fn to_query(
+        &self,
+        application_id: i32,
+    ) -> schema::inputs::BoxedQuery<'_, Pg> {
         use schema::inputs::dsl;
-        let mut query = dsl::inputs.into_boxed();
+        let mut query = dsl::inputs
+            .filter(dsl::application_id.eq(application_id))
+            .into_boxed();
+        if let Some(other) = self.index_greater_than {
+            query = query.filter(dsl::index.gt(other));
+        }
+        if let Some(other) = self.index_lower_than {
+            query = query.filter(dsl::index.lt(other));
+        }
+        if let Some(other) = &self.msg_sender {
+            query = query.filter(dsl::msg_sender.eq(other));
+        }
+        if let Some(other) = self.status {
+            query = query.filter(dsl::status.eq(other));
+        }
+        if let Some(other) = self.block_number_greater_than {
+            query = query.filter(dsl::block_number.gt(other));
+        }
+        if let Some(other) = self.block_number_lower_than {
+            query = query.filter(dsl::block_number.lt(other));
+        }
+        if let Some(other) = self.timestamp_greater_than {
+            query = query.filter(dsl::timestamp.gt(other));
+        }
+        if let Some(other) = self.timestamp_lower_than {
+            query = query.filter(dsl::timestamp.lt(other));
+        }
+        query
+    }
+}
+
+/// Generate a boxed query from an epoch query filter
+impl EpochQueryFilter {
+    pub(crate) fn to_query(
+        &self,
+        application_id: i32,
+    ) -> schema::epochs::BoxedQuery<'_, Pg> {
+        use schema::epochs::dsl;
+        let mut query = dsl::epochs
+            .filter(dsl::application_id.eq(application_id))
+            .into_boxed();
         if let Some(other) = self.index_greater_than {
             query = query.filter(dsl::index.gt(other));
         }
@@ -257,62 +784,596 @@ impl InputQueryFilter {
     }
 }
 
+/// Generate a boxed query from a claim query filter
+impl ClaimQueryFilter {
+    pub(crate) fn to_query(
+        &self,
+        application_id: i32,
+    ) -> schema::claims::BoxedQuery<'_, Pg> {
+        use schema::claims::dsl;
+        let mut query = dsl::claims
+            .filter(dsl::application_id.eq(application_id))
+            .into_boxed();
+        if let Some(other) = self.status {
+            query = query.filter(dsl::status.eq(other));
+        }
+        query
+    }
+}
+
+/// Number of payload bytes kept by the payload_prefix function of the
+/// migrations, which indexes the outputs by payload prefix
+const PAYLOAD_PREFIX_LEN: usize = 32;
+
+sql_function! {
+    /// Get the first PAYLOAD_PREFIX_LEN bytes of the payload
+    fn payload_prefix(payload: Binary) -> Binary;
+}
+
+/// Restrict the query to the entries whose payload starts with the prefix.
+/// The range over the indexed payload_prefix lets Postgres scan only a range
+/// of the index; prefixes longer than the indexed bytes also compare the
+/// whole payload.
+macro_rules! filter_payload_prefix {
+    ($query: expr, $table: ident, $prefix: expr) => {{
+        use schema::$table::dsl;
+        let prefix: &[u8] = $prefix;
+        let indexed = &prefix[..prefix.len().min(PAYLOAD_PREFIX_LEN)];
+        let mut query = $query;
+        if !indexed.is_empty() {
+            query = query.filter(payload_prefix(dsl::payload).ge(indexed));
+        }
+        if let Some(end) = prefix_successor(indexed) {
+            query = query.filter(payload_prefix(dsl::payload).lt(end));
+        }
+        if prefix.len() > PAYLOAD_PREFIX_LEN {
+            query = query.filter(dsl::payload.ge(prefix));
+            if let Some(end) = prefix_successor(prefix) {
+                query = query.filter(dsl::payload.lt(end));
+            }
+        }
+        query
+    }};
+}
+
+/// Generate a boxed query from a voucher query filter
+impl VoucherQueryFilter {
+    pub(crate) fn to_query(
+        &self,
+        application_id: i32,
+    ) -> schema::vouchers::BoxedQuery<'_, Pg> {
+        use schema::vouchers::dsl;
+        let mut query = dsl::vouchers
+            .filter(dsl::application_id.eq(application_id))
+            .into_boxed();
+        if let Some(other) = self.input_index {
+            query = query.filter(dsl::input_index.eq(other));
+        }
+        if let Some(other) = self.input_index_greater_than {
+            query = query.filter(dsl::input_index.gt(other));
+        }
+        if let Some(other) = self.input_index_lower_than {
+            query = query.filter(dsl::input_index.lt(other));
+        }
+        if let Some(prefix) = &self.payload_prefix {
+            query = filter_payload_prefix!(query, vouchers, prefix);
+        }
+        if let Some(other) = &self.destination {
+            query = query.filter(dsl::destination.eq(other));
+        }
+        if let Some(other) = self.executed {
+            query = query.filter(dsl::executed.eq(other));
+        }
+        query
+    }
+}
+
 /// Generate a boxed query from an output query filter
 macro_rules! impl_output_filter_to_query {
     ($filter: ty, $table: ident) => {
         impl $filter {
-            fn to_query(&self) -> schema::$table::BoxedQuery<'_, Pg> {
+            pub(crate) fn to_query(
+                &self,
+                application_id: i32,
+            ) -> schema::$table::BoxedQuery<'_, Pg> {
                 use schema::$table::dsl;
-                let mut query = dsl::$table.into_boxed();
+                let mut query = dsl::$table
+                    .filter(dsl::application_id.eq(application_id))
+                    .into_boxed();
                 if let Some(other) = self.input_index {
                     query = query.filter(dsl::input_index.eq(other));
                 }
+                if let Some(other) = self.input_index_greater_than {
+                    query = query.filter(dsl::input_index.gt(other));
+                }
+                if let Some(other) = self.input_index_lower_than {
+                    query = query.filter(dsl::input_index.lt(other));
+                }
+                if let Some(prefix) = &self.payload_prefix {
+                    query = filter_payload_prefix!(query, $table, prefix);
+                }
                 query
             }
         }
     };
 }
 
-impl_output_filter_to_query!(VoucherQueryFilter, vouchers);
 impl_output_filter_to_query!(NoticeQueryFilter, notices);
 impl_output_filter_to_query!(ReportQueryFilter, reports);
 
-/// Implement a paginated query for the given table
+/// Get the smallest byte string greater than every string with the given
+/// prefix, so the prefix filter becomes a range over the payloads.
+/// Return None if there is no such string (the prefix is empty or all 0xff).
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let last = prefix.iter().rposition(|byte| *byte != u8::MAX)?;
+    let mut successor = prefix[..=last].to_vec();
+    successor[last] += 1;
+    Some(successor)
+}
+
+/// Restrict the query to the entries whose key compares to the cursor with the
+/// given operators. Composite keys are compared in lexicographic order; the
+/// bound on the first column lets Postgres scan only a range of the index.
+macro_rules! filter_key {
+    ($query: expr, $table: ident, [$key: ident], $cursor: expr,
+     $bound: ident, $strict: ident, $op: ident) => {
+        $query.filter($crate::schema::$table::dsl::$key.$op($cursor.key()[0]))
+    };
+    ($query: expr, $table: ident, [$first: ident, $second: ident], $cursor: expr,
+     $bound: ident, $strict: ident, $op: ident) => {{
+        use $crate::schema::$table::dsl;
+        let key = $cursor.key();
+        $query
+            .filter(dsl::$first.$bound(key[0]))
+            .filter(dsl::$first.$strict(key[0]).or(dsl::$second.$op(key[1])))
+    }};
+}
+
+pub(crate) use filter_key;
+
+/// Implement the queries that read single rows, and the other queries shared
+/// by the repositories, for the given repository
+/// The `async` and `.await` tokens are given for the async repository.
+macro_rules! impl_read_queries {
+    ($repository: ident, [$($async: tt)?], [$($await: tt)*]) => {
+        /// Basic queries that fetch by primary_key
+        impl $repository {
+            pub $($async)? fn get_input(
+                &self,
+                index: i32,
+            ) -> Result<$crate::types::Input, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::inputs::dsl;
+                use $crate::types::Input;
+                let mut conn = self.conn()$($await)*?;
+                dsl::inputs
+                    .filter(dsl::application_id.eq(self.application_id))
+                    .filter(dsl::index.eq(index))
+                    .select(Input::as_select())
+                    .load::<Input>(&mut conn)
+                    $($await)*
+                    .context(DatabaseSnafu)?
+                    .pop()
+                    .ok_or($crate::Error::ItemNotFound {
+                        item_type: "input".to_owned(),
+                    })
+            }
+
+            pub $($async)? fn get_voucher(
+                &self,
+                index: i32,
+                input_index: i32,
+            ) -> Result<$crate::types::Voucher, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::vouchers::dsl;
+                use $crate::types::Voucher;
+                let mut conn = self.conn()$($await)*?;
+                dsl::vouchers
+                    .filter(dsl::application_id.eq(self.application_id))
+                    .filter(dsl::index.eq(index))
+                    .filter(dsl::input_index.eq(input_index))
+                    .select(Voucher::as_select())
+                    .load::<Voucher>(&mut conn)
+                    $($await)*
+                    .context(DatabaseSnafu)?
+                    .pop()
+                    .ok_or($crate::Error::ItemNotFound {
+                        item_type: "voucher".to_owned(),
+                    })
+            }
+
+            pub $($async)? fn get_notice(
+                &self,
+                index: i32,
+                input_index: i32,
+            ) -> Result<$crate::types::Notice, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::notices::dsl;
+                use $crate::types::Notice;
+                let mut conn = self.conn()$($await)*?;
+                dsl::notices
+                    .filter(dsl::application_id.eq(self.application_id))
+                    .filter(dsl::index.eq(index))
+                    .filter(dsl::input_index.eq(input_index))
+                    .select(Notice::as_select())
+                    .load::<Notice>(&mut conn)
+                    $($await)*
+                    .context(DatabaseSnafu)?
+                    .pop()
+                    .ok_or($crate::Error::ItemNotFound {
+                        item_type: "notice".to_owned(),
+                    })
+            }
+
+            pub $($async)? fn get_report(
+                &self,
+                index: i32,
+                input_index: i32,
+            ) -> Result<$crate::types::Report, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::reports::dsl;
+                use $crate::types::Report;
+                let mut conn = self.conn()$($await)*?;
+                dsl::reports
+                    .filter(dsl::application_id.eq(self.application_id))
+                    .filter(dsl::index.eq(index))
+                    .filter(dsl::input_index.eq(input_index))
+                    .select(Report::as_select())
+                    .load::<Report>(&mut conn)
+                    $($await)*
+                    .context(DatabaseSnafu)?
+                    .pop()
+                    .ok_or($crate::Error::ItemNotFound {
+                        item_type: "report".to_owned(),
+                    })
+            }
+
+            pub $($async)? fn get_proof(
+                &self,
+                input_index: i32,
+                output_index: i32,
+                output_enum: $crate::types::OutputEnum,
+            ) -> Result<Option<$crate::types::Proof>, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::proofs::dsl;
+                use $crate::types::Proof;
+                let mut conn = self.conn()$($await)*?;
+                dsl::proofs
+                    .filter(dsl::application_id.eq(self.application_id))
+                    .filter(dsl::input_index.eq(input_index))
+                    .filter(dsl::output_index.eq(output_index))
+                    .filter(dsl::output_enum.eq(output_enum))
+                    .select(Proof::as_select())
+                    .load::<Proof>(&mut conn)
+                    $($await)*
+                    .map(|mut proofs| proofs.pop())
+                    .context(DatabaseSnafu)
+            }
+
+            pub $($async)? fn get_epoch(
+                &self,
+                index: i32,
+            ) -> Result<$crate::types::Epoch, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::epochs::dsl;
+                use $crate::types::Epoch;
+                let mut conn = self.conn()$($await)*?;
+                dsl::epochs
+                    .filter(dsl::application_id.eq(self.application_id))
+                    .filter(dsl::index.eq(index))
+                    .select(Epoch::as_select())
+                    .load::<Epoch>(&mut conn)
+                    $($await)*
+                    .context(DatabaseSnafu)?
+                    .pop()
+                    .ok_or($crate::Error::ItemNotFound {
+                        item_type: "epoch".to_owned(),
+                    })
+            }
+
+            pub $($async)? fn get_claim(
+                &self,
+                epoch_index: i32,
+            ) -> Result<$crate::types::Claim, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::claims::dsl;
+                use $crate::types::Claim;
+                let mut conn = self.conn()$($await)*?;
+                dsl::claims
+                    .filter(dsl::application_id.eq(self.application_id))
+                    .filter(dsl::epoch_index.eq(epoch_index))
+                    .select(Claim::as_select())
+                    .load::<Claim>(&mut conn)
+                    $($await)*
+                    .context(DatabaseSnafu)?
+                    .pop()
+                    .ok_or($crate::Error::ItemNotFound {
+                        item_type: "claim".to_owned(),
+                    })
+            }
+        }
+
+        /// Queries that relate the inputs to their epochs
+        impl $repository {
+            /// Get the epoch that contains the given input, if it was
+            /// already closed
+            pub $($async)? fn get_input_epoch(
+                &self,
+                input_index: i32,
+            ) -> Result<Option<$crate::types::Epoch>, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::epochs::dsl;
+                use $crate::types::Epoch;
+                let mut conn = self.conn()$($await)*?;
+                dsl::epochs
+                    .filter(dsl::application_id.eq(self.application_id))
+                    .filter(dsl::first_input_index.le(input_index))
+                    .filter(dsl::last_input_index.ge(input_index))
+                    .select(Epoch::as_select())
+                    .load::<Epoch>(&mut conn)
+                    $($await)*
+                    .map(|mut epochs| epochs.pop())
+                    .context(DatabaseSnafu)
+            }
+
+            /// Get the claim of the epoch that contains the given input, if
+            /// any
+            pub $($async)? fn get_input_claim(
+                &self,
+                input_index: i32,
+            ) -> Result<Option<$crate::types::Claim>, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::{claims, epochs};
+                use $crate::types::Claim;
+                let mut conn = self.conn()$($await)*?;
+                claims::table
+                    .inner_join(
+                        epochs::table.on(epochs::application_id
+                            .eq(claims::application_id)
+                            .and(epochs::index.eq(claims::epoch_index))),
+                    )
+                    .filter(claims::application_id.eq(self.application_id))
+                    .filter(epochs::first_input_index.le(input_index))
+                    .filter(epochs::last_input_index.ge(input_index))
+                    .select(Claim::as_select())
+                    .load::<Claim>(&mut conn)
+                    $($await)*
+                    .map(|mut claims| claims.pop())
+                    .context(DatabaseSnafu)
+            }
+        }
+
+        /// Queries of the indexer progress
+        impl $repository {
+            /// Get the last event stored by the indexer from each broker
+            /// stream
+            pub $($async)? fn get_indexer_progress(
+                &self,
+            ) -> Result<Vec<$crate::types::IndexerProgress>, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::indexer_progress::dsl;
+                use $crate::types::IndexerProgress;
+                let mut conn = self.conn()$($await)*?;
+                dsl::indexer_progress
+                    .filter(dsl::application_id.eq(self.application_id))
+                    .select(IndexerProgress::as_select())
+                    .load::<IndexerProgress>(&mut conn)
+                    $($await)*
+                    .context(DatabaseSnafu)
+            }
+        }
+
+        /// Queries of the applications, which scope the rows of every other
+        /// table
+        impl $repository {
+            /// Get the application deployed at the given address
+            /// If the dapp has the same address in several chains, the
+            /// application registered first is returned.
+            pub $($async)? fn get_application(
+                &self,
+                dapp_address: &[u8],
+            ) -> Result<$crate::types::Application, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::applications::dsl;
+                use $crate::types::Application;
+                let mut conn = self.conn()$($await)*?;
+                dsl::applications
+                    .filter(dsl::dapp_address.eq(dapp_address))
+                    .order_by(dsl::id.asc())
+                    .select(Application::as_select())
+                    .first::<Application>(&mut conn)
+                    $($await)*
+                    .optional()
+                    .context(DatabaseSnafu)?
+                    .ok_or($crate::Error::ItemNotFound {
+                        item_type: "application".to_owned(),
+                    })
+            }
+
+            pub $($async)? fn get_applications(
+                &self,
+            ) -> Result<Vec<$crate::types::Application>, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::schema::applications::dsl;
+                use $crate::types::Application;
+                let mut conn = self.conn()$($await)*?;
+                dsl::applications
+                    .order_by(dsl::id.asc())
+                    .select(Application::as_select())
+                    .load::<Application>(&mut conn)
+                    $($await)*
+                    .context(DatabaseSnafu)
+            }
+        }
+    };
+}
+
+pub(crate) use impl_read_queries;
+
+impl_read_queries!(Repository, [], []);
+
+/// Implement a paginated query for the given table, using its primary key as
+/// the pagination key
+/// The `async` and `.await` tokens are given for the async repository.
 macro_rules! impl_paginated_query {
-    ($query: ident, $table: ident, $node: ty, $filter: ty) => {
-        impl Repository {
-            pub fn $query(
+    ($repository: ident, [$($async: tt)?], [$($await: tt)*],
+     $query: ident, $table: ident, $node: ty, $filter: ty,
+     [$($key: ident),+]) => {
+        impl $repository {
+            pub $($async)? fn $query(
                 &self,
                 first: Option<i32>,
                 last: Option<i32>,
                 after: Option<String>,
                 before: Option<String>,
                 filter: $filter,
-            ) -> Result<Connection<$node>, Error> {
-                let mut conn = self.conn()?;
-                let query = filter.to_query().count();
-                let count = query
-                    .get_result::<i64>(&mut conn)
-                    .context(DatabaseSnafu)?;
+                with_total_count: bool,
+            ) -> Result<$crate::Connection<$node>, $crate::Error> {
+                use $crate::error::DatabaseSnafu;
+                use $crate::pagination::{Cursor, Pagination};
+                use $crate::schema::$table::dsl;
+                let key_len = [$(stringify!($key)),+].len();
                 let pagination =
-                    Pagination::new(first, last, after, before, count as i32)?;
-                let nodes = if pagination.limit() > 0 {
-                    let query = filter
-                        .to_query()
-                        .limit(pagination.limit().into())
-                        .offset(pagination.offset().into())
-                        .order(schema::$table::dsl::$table.primary_key());
-                    query.load(&mut conn).context(DatabaseSnafu)?
+                    Pagination::new(first, last, after, before, key_len)?;
+                let mut conn = self.conn()$($await)*?;
+
+                let total_count = if with_total_count {
+                    let query = filter.to_query(self.application_id).count();
+                    let count = query
+                        .get_result::<i64>(&mut conn)
+                        $($await)*
+                        .context(DatabaseSnafu)?;
+                    Some(count as i32)
+                } else {
+                    None
+                };
+
+                let mut query = filter.to_query(self.application_id);
+                if let Some(cursor) = pagination.cursor() {
+                    query = if pagination.is_backward() {
+                        $crate::repository::filter_key!(
+                            query, $table, [$($key),+], cursor, le, lt, lt
+                        )
+                    } else {
+                        $crate::repository::filter_key!(
+                            query, $table, [$($key),+], cursor, ge, gt, gt
+                        )
+                    };
+                }
+                if pagination.is_backward() {
+                    $(query = query.then_order_by(dsl::$key.desc());)+
                 } else {
-                    vec![]
+                    $(query = query.then_order_by(dsl::$key.asc());)+
+                }
+                let nodes: Vec<$node> = query
+                    .select(<$node>::as_select())
+                    .limit(pagination.fetch_limit())
+                    .load(&mut conn)
+                    $($await)*
+                    .context(DatabaseSnafu)?;
+
+                // Look for an entry on the other side of the cursor
+                let has_entries_behind = match pagination.cursor() {
+                    Some(cursor) => {
+                        let query = filter.to_query(self.application_id);
+                        let query = if pagination.is_backward() {
+                            $crate::repository::filter_key!(
+                                query, $table, [$($key),+], cursor, ge, gt, ge
+                            )
+                        } else {
+                            $crate::repository::filter_key!(
+                                query, $table, [$($key),+], cursor, le, lt, le
+                            )
+                        };
+                        let entries: Vec<$node> = query
+                            .select(<$node>::as_select())
+                            .limit(1)
+                            .load(&mut conn)
+                            $($await)*
+                            .context(DatabaseSnafu)?;
+                        !entries.is_empty()
+                    }
+                    None => false,
                 };
-                Ok(pagination.create_connection(nodes))
+                Ok(pagination.create_connection(
+                    nodes,
+                    |node| Cursor::new(vec![$(node.$key),+]),
+                    has_entries_behind,
+                    total_count,
+                ))
             }
         }
     };
 }
 
-impl_paginated_query!(get_inputs, inputs, Input, InputQueryFilter);
-impl_paginated_query!(get_vouchers, vouchers, Voucher, VoucherQueryFilter);
-impl_paginated_query!(get_notices, notices, Notice, NoticeQueryFilter);
-impl_paginated_query!(get_reports, reports, Report, ReportQueryFilter);
+pub(crate) use impl_paginated_query;
+
+/// Implement the paginated queries of every table for the given repository
+macro_rules! impl_paginated_queries {
+    ($repository: ident, [$($async: tt)?], [$($await: tt)*]) => {
+        $crate::repository::impl_paginated_query!(
+            $repository,
+            [$($async)?],
+            [$($await)*],
+            get_inputs,
+            inputs,
+            $crate::types::Input,
+            $crate::types::InputQueryFilter,
+            [index]
+        );
+        $crate::repository::impl_paginated_query!(
+            $repository,
+            [$($async)?],
+            [$($await)*],
+            get_vouchers,
+            vouchers,
+            $crate::types::Voucher,
+            $crate::types::VoucherQueryFilter,
+            [input_index, index]
+        );
+        $crate::repository::impl_paginated_query!(
+            $repository,
+            [$($async)?],
+            [$($await)*],
+            get_notices,
+            notices,
+            $crate::types::Notice,
+            $crate::types::NoticeQueryFilter,
+            [input_index, index]
+        );
+        $crate::repository::impl_paginated_query!(
+            $repository,
+            [$($async)?],
+            [$($await)*],
+            get_reports,
+            reports,
+            $crate::types::Report,
+            $crate::types::ReportQueryFilter,
+            [input_index, index]
+        );
+        $crate::repository::impl_paginated_query!(
+            $repository,
+            [$($async)?],
+            [$($await)*],
+            get_epochs,
+            epochs,
+            $crate::types::Epoch,
+            $crate::types::EpochQueryFilter,
+            [index]
+        );
+        $crate::repository::impl_paginated_query!(
+            $repository,
+            [$($async)?],
+            [$($await)*],
+            get_claims,
+            claims,
+            $crate::types::Claim,
+            $crate::types::ClaimQueryFilter,
+            [epoch_index]
+        );
+    };
+}
+
+pub(crate) use impl_paginated_queries;
+
+impl_paginated_queries!(Repository, [], []);
@@ -1,6 +1,10 @@
 // @generated automatically by Diesel CLI.
 
 pub mod sql_types {
+    #[derive(diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "ClaimStatus"))]
+    pub struct ClaimStatus;
+
     #[derive(diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "CompletionStatus"))]
     pub struct CompletionStatus;
@@ -10,11 +14,53 @@ pub mod sql_types {
     pub struct OutputEnum;
 }
 
+diesel::table! {
+    applications (id) {
+        id -> Int4,
+        chain_id -> Int8,
+        dapp_address -> Bytea,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ClaimStatus;
+
+    claims (application_id, epoch_index) {
+        epoch_index -> Int4,
+        epoch_hash -> Bytea,
+        machine_state_hash -> Bytea,
+        vouchers_epoch_root_hash -> Bytea,
+        notices_epoch_root_hash -> Bytea,
+        status -> ClaimStatus,
+        transaction_hash -> Nullable<Bytea>,
+        application_id -> Int4,
+    }
+}
+
+diesel::table! {
+    epochs (application_id, index) {
+        index -> Int4,
+        first_input_index -> Int4,
+        last_input_index -> Int4,
+        application_id -> Int4,
+    }
+}
+
+diesel::table! {
+    indexer_progress (application_id, stream_key) {
+        stream_key -> Varchar,
+        last_event_id -> Varchar,
+        application_id -> Int4,
+        rollback_index -> Nullable<Int4>,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::CompletionStatus;
 
-    inputs (index) {
+    inputs (application_id, index) {
         index -> Int4,
         msg_sender -> Bytea,
         tx_hash -> Bytea,
@@ -22,14 +68,16 @@ diesel::table! {
         timestamp -> Timestamp,
         payload -> Bytea,
         status -> CompletionStatus,
+        application_id -> Int4,
     }
 }
 
 diesel::table! {
-    notices (input_index, index) {
+    notices (application_id, input_index, index) {
         input_index -> Int4,
         index -> Int4,
         payload -> Bytea,
+        application_id -> Int4,
     }
 }
 
@@ -37,7 +85,7 @@ diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::OutputEnum;
 
-    proofs (input_index, output_index, output_enum) {
+    proofs (application_id, input_index, output_index, output_enum) {
         input_index -> Int4,
         output_index -> Int4,
         output_enum -> OutputEnum,
@@ -50,30 +98,59 @@ diesel::table! {
         validity_output_hash_in_output_hashes_siblings -> Array<Nullable<Bytea>>,
         validity_output_hashes_in_epoch_siblings -> Array<Nullable<Bytea>>,
         context -> Bytea,
+        application_id -> Int4,
     }
 }
 
 diesel::table! {
-    reports (input_index, index) {
+    reports (application_id, input_index, index) {
         input_index -> Int4,
         index -> Int4,
         payload -> Bytea,
+        application_id -> Int4,
+    }
+}
+
+diesel::table! {
+    voucher_executions (application_id, input_index, index) {
+        input_index -> Int4,
+        index -> Int4,
+        transaction_hash -> Bytea,
+        application_id -> Int4,
     }
 }
 
 diesel::table! {
-    vouchers (input_index, index) {
+    vouchers (application_id, input_index, index) {
         input_index -> Int4,
         index -> Int4,
         destination -> Bytea,
         payload -> Bytea,
+        executed -> Bool,
+        execution_transaction_hash -> Nullable<Bytea>,
+        application_id -> Int4,
     }
 }
 
-diesel::joinable!(notices -> inputs (input_index));
-diesel::joinable!(reports -> inputs (input_index));
-diesel::joinable!(vouchers -> inputs (input_index));
+diesel::joinable!(claims -> applications (application_id));
+diesel::joinable!(epochs -> applications (application_id));
+diesel::joinable!(indexer_progress -> applications (application_id));
+diesel::joinable!(inputs -> applications (application_id));
+diesel::joinable!(notices -> applications (application_id));
+diesel::joinable!(proofs -> applications (application_id));
+diesel::joinable!(reports -> applications (application_id));
+diesel::joinable!(voucher_executions -> applications (application_id));
+diesel::joinable!(vouchers -> applications (application_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
-    inputs, notices, proofs, reports, vouchers,
+    applications,
+    claims,
+    epochs,
+    indexer_progress,
+    inputs,
+    notices,
+    proofs,
+    reports,
+    voucher_executions,
+    vouchers,
 );
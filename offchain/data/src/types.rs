@@ -4,15 +4,28 @@
 use diesel::deserialize::{self, FromSql, FromSqlRow};
 use diesel::pg::{Pg, PgValue};
 use diesel::serialize::{self, IsNull, Output, ToSql};
-use diesel::{AsExpression, Insertable, Queryable, QueryableByName};
+use diesel::{
+    AsExpression, Insertable, Queryable, QueryableByName, Selectable,
+};
 use std::io::Write;
 
 use super::schema::{
-    inputs, notices, proofs, reports,
+    applications, claims, epochs, indexer_progress, inputs, notices, proofs,
+    reports, sql_types::ClaimStatus as SQLClaimStatus,
     sql_types::CompletionStatus as SQLCompletionStatus,
     sql_types::OutputEnum as SQLOutputEnum, vouchers,
 };
 
+/// Application whose data is stored in the database
+/// The rows of every other table belong to an application.
+#[derive(Clone, Debug, PartialEq, Queryable, QueryableByName, Selectable)]
+#[diesel(table_name = applications)]
+pub struct Application {
+    pub id: i32,
+    pub chain_id: i64,
+    pub dapp_address: Vec<u8>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy, FromSqlRow, AsExpression)]
 #[diesel(sql_type = SQLCompletionStatus)]
 pub enum CompletionStatus {
@@ -68,7 +81,9 @@ impl FromSql<SQLCompletionStatus, Pg> for CompletionStatus {
     }
 }
 
-#[derive(Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName)]
+#[derive(
+    Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName, Selectable,
+)]
 #[diesel(table_name = inputs)]
 pub struct Input {
     pub index: i32,
@@ -80,7 +95,9 @@ pub struct Input {
     pub status: CompletionStatus,
 }
 
-#[derive(Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName)]
+#[derive(
+    Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName, Selectable,
+)]
 #[diesel(table_name = notices)]
 pub struct Notice {
     pub input_index: i32,
@@ -88,16 +105,22 @@ pub struct Notice {
     pub payload: Vec<u8>,
 }
 
-#[derive(Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName)]
+#[derive(
+    Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName, Selectable,
+)]
 #[diesel(table_name = vouchers)]
 pub struct Voucher {
     pub input_index: i32,
     pub index: i32,
     pub destination: Vec<u8>,
     pub payload: Vec<u8>,
+    pub executed: bool,
+    pub execution_transaction_hash: Option<Vec<u8>>,
 }
 
-#[derive(Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName)]
+#[derive(
+    Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName, Selectable,
+)]
 #[diesel(table_name = reports)]
 pub struct Report {
     pub input_index: i32,
@@ -136,7 +159,9 @@ impl diesel::query_builder::QueryId for SQLOutputEnum {
     type QueryId = SQLOutputEnum;
 }
 
-#[derive(Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName)]
+#[derive(
+    Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName, Selectable,
+)]
 #[diesel(table_name = proofs)]
 pub struct Proof {
     pub input_index: i32,
@@ -153,10 +178,89 @@ pub struct Proof {
     pub context: Vec<u8>,
 }
 
+#[derive(
+    Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName, Selectable,
+)]
+#[diesel(table_name = epochs)]
+pub struct Epoch {
+    pub index: i32,
+    pub first_input_index: i32,
+    pub last_input_index: i32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, FromSqlRow, AsExpression)]
+#[diesel(sql_type = SQLClaimStatus)]
+pub enum ClaimStatus {
+    /// The claim was produced but it wasn't submitted yet
+    Pending,
+    /// The claim was submitted by this node
+    Submitted,
+    /// The claim was already on chain, so it wasn't submitted by this node
+    Duplicated,
+}
+
+impl ToSql<SQLClaimStatus, Pg> for ClaimStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            ClaimStatus::Pending => out.write_all(b"Pending")?,
+            ClaimStatus::Submitted => out.write_all(b"Submitted")?,
+            ClaimStatus::Duplicated => out.write_all(b"Duplicated")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<SQLClaimStatus, Pg> for ClaimStatus {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"Pending" => Ok(ClaimStatus::Pending),
+            b"Submitted" => Ok(ClaimStatus::Submitted),
+            b"Duplicated" => Ok(ClaimStatus::Duplicated),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+impl diesel::query_builder::QueryId for SQLClaimStatus {
+    type QueryId = SQLClaimStatus;
+}
+
+#[derive(
+    Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName, Selectable,
+)]
+#[diesel(table_name = claims)]
+pub struct Claim {
+    pub epoch_index: i32,
+    pub epoch_hash: Vec<u8>,
+    pub machine_state_hash: Vec<u8>,
+    pub vouchers_epoch_root_hash: Vec<u8>,
+    pub notices_epoch_root_hash: Vec<u8>,
+    pub status: ClaimStatus,
+    pub transaction_hash: Option<Vec<u8>>,
+}
+
+/// Last event of a broker stream stored by the indexer
+#[derive(
+    Clone, Debug, Insertable, PartialEq, Queryable, QueryableByName, Selectable,
+)]
+#[diesel(table_name = indexer_progress)]
+pub struct IndexerProgress {
+    pub stream_key: String,
+    pub last_event_id: String,
+    /// First input deleted by a reorg when the event was stored, if any
+    pub rollback_index: Option<i32>,
+}
+
 #[derive(Debug, Default)]
 pub struct InputQueryFilter {
     pub index_greater_than: Option<i32>,
     pub index_lower_than: Option<i32>,
+    pub msg_sender: Option<Vec<u8>>,
+    pub status: Option<CompletionStatus>,
+    pub block_number_greater_than: Option<i64>,
+    pub block_number_lower_than: Option<i64>,
+    pub timestamp_greater_than: Option<std::time::SystemTime>,
+    pub timestamp_lower_than: Option<std::time::SystemTime>,
 }
 
 macro_rules! decl_output_filter {
@@ -164,10 +268,33 @@ macro_rules! decl_output_filter {
         #[derive(Debug, Default)]
         pub struct $name {
             pub input_index: Option<i32>,
+            pub input_index_greater_than: Option<i32>,
+            pub input_index_lower_than: Option<i32>,
+            pub payload_prefix: Option<Vec<u8>>,
         }
     };
 }
 
-decl_output_filter!(VoucherQueryFilter);
 decl_output_filter!(NoticeQueryFilter);
 decl_output_filter!(ReportQueryFilter);
+
+#[derive(Debug, Default)]
+pub struct VoucherQueryFilter {
+    pub input_index: Option<i32>,
+    pub input_index_greater_than: Option<i32>,
+    pub input_index_lower_than: Option<i32>,
+    pub payload_prefix: Option<Vec<u8>>,
+    pub destination: Option<Vec<u8>>,
+    pub executed: Option<bool>,
+}
+
+#[derive(Debug, Default)]
+pub struct EpochQueryFilter {
+    pub index_greater_than: Option<i32>,
+    pub index_lower_than: Option<i32>,
+}
+
+#[derive(Debug, Default)]
+pub struct ClaimQueryFilter {
+    pub status: Option<ClaimStatus>,
+}
@@ -45,8 +45,10 @@ fn run_migrations() {
         .load::<PgTable>(&mut connection)
         .expect("failed to run query");
 
-    let expected_tables =
-        vec!["inputs", "vouchers", "notices", "reports", "proofs"];
+    let expected_tables = vec![
+        "inputs", "vouchers", "notices", "reports", "proofs", "epochs",
+        "claims",
+    ];
     for expected in expected_tables {
         assert!(tables.iter().find(|t| t.tablename == expected).is_some());
     }
@@ -8,9 +8,11 @@ use diesel::{
 };
 use rollups_data::Connection as PaginationConnection;
 use rollups_data::{
-    CompletionStatus, Cursor, Edge, Error, Input, InputQueryFilter, Notice,
-    PageInfo, Proof, RedactedUrl, Report, Repository, RepositoryConfig, Url,
-    Voucher,
+    AsyncRepository, Claim, ClaimQueryFilter, ClaimStatus, CompletionStatus,
+    Cursor, Edge, Epoch, EpochQueryFilter, Error, IndexerProgress, Input,
+    InputQueryFilter, Notice, NoticeQueryFilter, PageInfo, Proof, RedactedUrl,
+    Report, Repository, RepositoryConfig, Url, Voucher, VoucherQueryFilter,
+    DEFAULT_APPLICATION_ID,
 };
 use serial_test::serial;
 use std::io::Write;
@@ -33,6 +35,16 @@ impl TestState<'_> {
     }
 
     pub fn get_repository(&self) -> Repository {
+        Repository::new(self.get_repository_config())
+            .expect("Repository should have connected successfully")
+    }
+
+    pub fn get_async_repository(&self) -> AsyncRepository {
+        AsyncRepository::new(self.get_repository_config())
+            .expect("Failed to create async repository")
+    }
+
+    fn get_repository_config(&self) -> RepositoryConfig {
         let backoff = ExponentialBackoffBuilder::new()
             .with_max_elapsed_time(Some(Duration::from_millis(
                 BACKOFF_DURATION,
@@ -50,12 +62,11 @@ impl TestState<'_> {
             ))
             .expect("failed to generate Postgres endpoint"),
         ));
-        Repository::new(RepositoryConfig {
+        RepositoryConfig {
             redacted_endpoint,
             connection_pool_size: 3,
             backoff,
-        })
-        .expect("Repository should have connected successfully")
+        }
     }
 
     pub fn get_from_sql<T: QueryableByName<Pg> + 'static>(
@@ -99,6 +110,26 @@ pub fn create_input() -> Input {
     }
 }
 
+pub fn create_epoch(index: i32) -> Epoch {
+    Epoch {
+        index,
+        first_input_index: 2 * index,
+        last_input_index: 2 * index + 1,
+    }
+}
+
+pub fn create_claim(epoch_index: i32) -> Claim {
+    Claim {
+        epoch_index,
+        epoch_hash: "epoch-hash".as_bytes().to_vec(),
+        machine_state_hash: "machine-state-hash".as_bytes().to_vec(),
+        vouchers_epoch_root_hash: "vouchers-root".as_bytes().to_vec(),
+        notices_epoch_root_hash: "notices-root".as_bytes().to_vec(),
+        status: ClaimStatus::Pending,
+        transaction_hash: None,
+    }
+}
+
 #[test]
 #[serial]
 fn test_create_repository() {
@@ -269,6 +300,75 @@ fn test_update_input_status() {
     assert_eq!(get_input.status, CompletionStatus::Accepted);
 }
 
+#[test]
+#[serial]
+fn test_delete_inputs_from() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    for index in 0..3 {
+        let mut input = create_input();
+        input.index = index;
+        repo.insert_input(input).expect("Failed to insert input");
+        repo.insert_notice(Notice {
+            input_index: index,
+            index: 0,
+            payload: "notice".as_bytes().to_vec(),
+        })
+        .expect("Failed to insert notice");
+    }
+
+    repo.insert_claim(create_epoch(0), create_claim(0))
+        .expect("Failed to insert claim");
+
+    repo.delete_inputs_from(1).expect("Failed to delete inputs");
+
+    assert!(matches!(repo.get_epoch(0), Err(Error::ItemNotFound { .. })));
+    assert!(matches!(repo.get_claim(0), Err(Error::ItemNotFound { .. })));
+    repo.get_input(0).expect("Input 0 should not be deleted");
+    repo.get_notice(0, 0)
+        .expect("Notice 0 should not be deleted");
+    for index in 1..3 {
+        assert!(matches!(
+            repo.get_input(index),
+            Err(Error::ItemNotFound { .. })
+        ));
+        assert!(matches!(
+            repo.get_notice(0, index),
+            Err(Error::ItemNotFound { .. })
+        ));
+    }
+}
+
+#[test]
+#[serial]
+fn test_delete_outputs() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    insert_test_input(&repo);
+    let voucher = Voucher {
+        input_index: 0,
+        index: 0,
+        destination: "destination".as_bytes().to_vec(),
+        payload: "voucher-0-0".as_bytes().to_vec(),
+        executed: false,
+        execution_transaction_hash: None,
+    };
+    repo.insert_voucher(voucher)
+        .expect("Failed to insert voucher");
+
+    repo.delete_outputs(0).expect("Failed to delete outputs");
+
+    repo.get_input(0).expect("Input should not be deleted");
+    assert!(matches!(
+        repo.get_voucher(0, 0),
+        Err(Error::ItemNotFound { .. })
+    ));
+}
+
 #[test]
 #[serial]
 fn test_insert_notice() {
@@ -336,6 +436,7 @@ fn test_insert_notice_error() {
         .expect_err("Insert notice should fail");
 
     assert!(matches!(notice_error, Error::DatabaseError { source: _ }));
+    assert!(notice_error.is_foreign_key_violation());
 }
 
 #[test]
@@ -378,6 +479,8 @@ fn test_insert_voucher() {
         index: 0,
         destination: "destination".as_bytes().to_vec(),
         payload: "voucher-0-0".as_bytes().to_vec(),
+        executed: false,
+        execution_transaction_hash: None,
     };
 
     repo.insert_voucher(voucher.clone())
@@ -402,6 +505,8 @@ fn test_get_voucher() {
         index: 0,
         destination: "destination".as_bytes().to_vec(),
         payload: "voucher-0-0".as_bytes().to_vec(),
+        executed: false,
+        execution_transaction_hash: None,
     };
     repo.insert_voucher(voucher.clone())
         .expect("Insert voucher should succeed");
@@ -426,6 +531,8 @@ fn test_insert_voucher_error() {
         index: 0,
         destination: "destination".as_bytes().to_vec(),
         payload: "voucher-1-0".as_bytes().to_vec(),
+        executed: false,
+        execution_transaction_hash: None,
     };
     let voucher_error = repo
         .insert_voucher(voucher.clone())
@@ -434,6 +541,40 @@ fn test_insert_voucher_error() {
     assert!(matches!(voucher_error, Error::DatabaseError { source: _ }));
 }
 
+#[test]
+#[serial]
+fn test_insert_vouchers() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    insert_test_input(&repo);
+
+    // More vouchers than the rows of a single insert
+    let vouchers: Vec<_> = (0..2500)
+        .map(|index| Voucher {
+            input_index: 0,
+            index,
+            destination: "destination".as_bytes().to_vec(),
+            payload: format!("voucher-0-{}", index).into_bytes(),
+            executed: false,
+            execution_transaction_hash: None,
+        })
+        .collect();
+    repo.transaction(|tx| tx.insert_vouchers(vouchers.clone()))
+        .expect("Insert vouchers should succeed");
+    // The vouchers that were already inserted are skipped
+    repo.transaction(|tx| tx.insert_vouchers(vouchers.clone()))
+        .expect("Insert vouchers again should succeed");
+
+    for voucher in [&vouchers[0], &vouchers[1000], &vouchers[2499]] {
+        let get_voucher = repo
+            .get_voucher(voucher.index, 0)
+            .expect("Get voucher should succeed");
+        assert_eq!(&get_voucher, voucher);
+    }
+}
+
 #[test]
 #[serial]
 fn test_get_voucher_error() {
@@ -448,6 +589,8 @@ fn test_get_voucher_error() {
         index: 0,
         destination: "destination".as_bytes().to_vec(),
         payload: "voucher-0-0".as_bytes().to_vec(),
+        executed: false,
+        execution_transaction_hash: None,
     };
     repo.insert_voucher(voucher.clone())
         .expect("Insert voucher should succeed");
@@ -461,6 +604,98 @@ fn test_get_voucher_error() {
     ));
 }
 
+#[test]
+#[serial]
+fn test_update_voucher_executed() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    insert_test_input(&repo);
+
+    for index in 0..2 {
+        let voucher = Voucher {
+            input_index: 0,
+            index,
+            destination: "destination".as_bytes().to_vec(),
+            payload: "voucher".as_bytes().to_vec(),
+            executed: false,
+            execution_transaction_hash: None,
+        };
+        repo.insert_voucher(voucher)
+            .expect("Insert voucher should succeed");
+    }
+
+    let found = repo
+        .update_voucher_executed(0, 1, "tx-hash".as_bytes().to_vec())
+        .expect("Update voucher should succeed");
+    assert!(found);
+    let found = repo
+        .update_voucher_executed(0, 2, "tx-hash".as_bytes().to_vec())
+        .expect("Update voucher should succeed");
+    assert!(!found);
+
+    let voucher = repo.get_voucher(1, 0).expect("Get voucher should succeed");
+    assert!(voucher.executed);
+    assert_eq!(
+        voucher.execution_transaction_hash,
+        Some("tx-hash".as_bytes().to_vec())
+    );
+
+    let vouchers = repo
+        .get_vouchers(
+            None,
+            None,
+            None,
+            None,
+            VoucherQueryFilter {
+                executed: Some(false),
+                ..Default::default()
+            },
+            true,
+        )
+        .expect("Get vouchers should succeed");
+    assert_eq!(vouchers.total_count, Some(1));
+    assert_eq!(vouchers.edges[0].node.index, 0);
+}
+
+#[test]
+#[serial]
+fn test_update_voucher_executed_before_insert() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    insert_test_input(&repo);
+
+    let found = repo
+        .update_voucher_executed(0, 1, "tx-hash".as_bytes().to_vec())
+        .expect("Update voucher should succeed");
+    assert!(!found);
+
+    let vouchers = (0..2)
+        .map(|index| Voucher {
+            input_index: 0,
+            index,
+            destination: "destination".as_bytes().to_vec(),
+            payload: "voucher".as_bytes().to_vec(),
+            executed: false,
+            execution_transaction_hash: None,
+        })
+        .collect();
+    repo.transaction(|tx| tx.insert_vouchers(vouchers))
+        .expect("Insert vouchers should succeed");
+
+    let voucher = repo.get_voucher(1, 0).expect("Get voucher should succeed");
+    assert!(voucher.executed);
+    assert_eq!(
+        voucher.execution_transaction_hash,
+        Some("tx-hash".as_bytes().to_vec())
+    );
+    let voucher = repo.get_voucher(0, 0).expect("Get voucher should succeed");
+    assert!(!voucher.executed);
+}
+
 #[test]
 #[serial]
 fn test_insert_report() {
@@ -687,16 +922,17 @@ fn test_pagination_macro() {
     let query_filter = InputQueryFilter {
         index_greater_than: Some(-1),
         index_lower_than: Some(5),
+        ..Default::default()
     };
 
     let pagination_connection = repo
-        .get_inputs(Some(5), None, None, None, query_filter)
+        .get_inputs(Some(5), None, None, None, query_filter, true)
         .expect("The macro should work, creating a pagination connection");
 
     assert_eq!(
         pagination_connection,
         PaginationConnection {
-            total_count: 2,
+            total_count: Some(2),
             edges: vec![
                 Edge {
                     node: input0,
@@ -724,3 +960,670 @@ fn test_pagination_macro() {
         }
     );
 }
+
+#[test]
+#[serial]
+fn test_insert_claim() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    let epoch = create_epoch(0);
+    let claim = create_claim(0);
+    repo.insert_claim(epoch.clone(), claim.clone())
+        .expect("Failed to insert claim");
+
+    let result: Epoch = test.get_from_sql("Select * from epochs");
+    assert_eq!(result, epoch);
+    let result: Claim = test.get_from_sql("Select * from claims");
+    assert_eq!(result, claim);
+}
+
+#[test]
+#[serial]
+fn test_get_claim() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    let epoch = create_epoch(0);
+    let claim = create_claim(0);
+    repo.insert_claim(epoch.clone(), claim.clone())
+        .expect("Failed to insert claim");
+
+    assert_eq!(repo.get_epoch(0).expect("Failed to get epoch"), epoch);
+    assert_eq!(repo.get_claim(0).expect("Failed to get claim"), claim);
+}
+
+#[test]
+#[serial]
+fn test_get_claim_error() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    repo.insert_claim(create_epoch(0), create_claim(0))
+        .expect("Failed to insert claim");
+
+    let epoch_error = repo.get_epoch(1).expect_err("Get epoch should fail");
+    assert!(matches!(
+        epoch_error,
+        Error::ItemNotFound { item_type } if item_type == "epoch"
+    ));
+    let claim_error = repo.get_claim(1).expect_err("Get claim should fail");
+    assert!(matches!(
+        claim_error,
+        Error::ItemNotFound { item_type } if item_type == "claim"
+    ));
+}
+
+#[test]
+#[serial]
+fn test_upsert_claim() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    let mut claim = create_claim(0);
+    claim.status = ClaimStatus::Submitted;
+    claim.transaction_hash = Some("tx-hash".as_bytes().to_vec());
+    repo.upsert_claim(create_epoch(0), claim.clone())
+        .expect("Failed to upsert claim");
+
+    // The claim from the broker doesn't replace the submission status
+    repo.insert_claim(create_epoch(0), create_claim(0))
+        .expect("Failed to insert claim");
+    assert_eq!(repo.get_claim(0).expect("Failed to get claim"), claim);
+
+    let mut claim = create_claim(1);
+    repo.insert_claim(create_epoch(1), claim.clone())
+        .expect("Failed to insert claim");
+    claim.status = ClaimStatus::Duplicated;
+    repo.upsert_claim(create_epoch(1), claim.clone())
+        .expect("Failed to upsert claim");
+    assert_eq!(repo.get_claim(1).expect("Failed to get claim"), claim);
+}
+
+#[test]
+#[serial]
+fn test_get_epochs_and_claims() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    for index in 0..3 {
+        let mut claim = create_claim(index);
+        if index == 1 {
+            claim.status = ClaimStatus::Submitted;
+        }
+        repo.insert_claim(create_epoch(index), claim)
+            .expect("Failed to insert claim");
+    }
+
+    let epochs = repo
+        .get_epochs(
+            None,
+            None,
+            None,
+            None,
+            EpochQueryFilter {
+                index_greater_than: Some(0),
+                ..Default::default()
+            },
+            true,
+        )
+        .expect("Failed to get epochs");
+    assert_eq!(epochs.total_count, Some(2));
+    let nodes: Vec<_> = epochs.edges.into_iter().map(|e| e.node).collect();
+    assert_eq!(nodes, vec![create_epoch(1), create_epoch(2)]);
+
+    let claims = repo
+        .get_claims(
+            None,
+            None,
+            None,
+            None,
+            ClaimQueryFilter {
+                status: Some(ClaimStatus::Pending),
+            },
+            false,
+        )
+        .expect("Failed to get claims");
+    let indices: Vec<_> =
+        claims.edges.iter().map(|e| e.node.epoch_index).collect();
+    assert_eq!(indices, vec![0, 2]);
+}
+
+#[test]
+#[serial]
+fn test_get_input_epoch_and_claim() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    repo.insert_claim(create_epoch(0), create_claim(0))
+        .expect("Failed to insert claim");
+    repo.insert_claim(create_epoch(1), create_claim(1))
+        .expect("Failed to insert claim");
+
+    let epoch = repo.get_input_epoch(2).expect("Failed to get input epoch");
+    assert_eq!(epoch, Some(create_epoch(1)));
+    let claim = repo.get_input_claim(3).expect("Failed to get input claim");
+    assert_eq!(claim, Some(create_claim(1)));
+
+    // The input 4 belongs to an epoch that wasn't closed yet
+    let epoch = repo.get_input_epoch(4).expect("Failed to get input epoch");
+    assert_eq!(epoch, None);
+    let claim = repo.get_input_claim(4).expect("Failed to get input claim");
+    assert_eq!(claim, None);
+}
+
+#[test]
+#[serial]
+fn test_get_inputs_with_filters() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    for index in 0..4 {
+        let mut input = create_input();
+        input.index = index;
+        input.block_number = index as i64;
+        input.timestamp = UNIX_EPOCH + Duration::from_secs(index as u64);
+        if index % 2 == 1 {
+            input.msg_sender = "other-sender".as_bytes().to_vec();
+            input.status = CompletionStatus::Rejected;
+        }
+        repo.insert_input(input).expect("Failed to insert input");
+    }
+
+    let get_indices = |filter: InputQueryFilter| -> Vec<i32> {
+        repo.get_inputs(None, None, None, None, filter, false)
+            .expect("Failed to get inputs")
+            .edges
+            .into_iter()
+            .map(|edge| edge.node.index)
+            .collect()
+    };
+
+    let indices = get_indices(InputQueryFilter {
+        msg_sender: Some("other-sender".as_bytes().to_vec()),
+        ..Default::default()
+    });
+    assert_eq!(indices, vec![1, 3]);
+
+    let indices = get_indices(InputQueryFilter {
+        status: Some(CompletionStatus::Accepted),
+        ..Default::default()
+    });
+    assert_eq!(indices, vec![0, 2]);
+
+    let indices = get_indices(InputQueryFilter {
+        block_number_greater_than: Some(0),
+        block_number_lower_than: Some(3),
+        ..Default::default()
+    });
+    assert_eq!(indices, vec![1, 2]);
+
+    let indices = get_indices(InputQueryFilter {
+        timestamp_greater_than: Some(UNIX_EPOCH + Duration::from_secs(1)),
+        status: Some(CompletionStatus::Rejected),
+        ..Default::default()
+    });
+    assert_eq!(indices, vec![3]);
+}
+
+#[test]
+#[serial]
+fn test_get_outputs_with_filters() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    let long_payload = [0x03u8; 40];
+    let payloads: [&[u8]; 5] = [
+        b"\x01\x02",
+        b"\x01\xff",
+        b"\x02",
+        b"\xff\xff",
+        &long_payload,
+    ];
+    for (index, payload) in payloads.iter().enumerate() {
+        let mut input = create_input();
+        input.index = index as i32;
+        repo.insert_input(input).expect("Failed to insert input");
+        repo.insert_voucher(Voucher {
+            input_index: index as i32,
+            index: 0,
+            destination: vec![index as u8 % 2],
+            payload: payload.to_vec(),
+            executed: false,
+            execution_transaction_hash: None,
+        })
+        .expect("Failed to insert voucher");
+        repo.insert_notice(Notice {
+            input_index: index as i32,
+            index: 0,
+            payload: payload.to_vec(),
+        })
+        .expect("Failed to insert notice");
+    }
+
+    let get_voucher_indices = |filter: VoucherQueryFilter| -> Vec<i32> {
+        repo.get_vouchers(None, None, None, None, filter, false)
+            .expect("Failed to get vouchers")
+            .edges
+            .into_iter()
+            .map(|edge| edge.node.input_index)
+            .collect()
+    };
+
+    let indices = get_voucher_indices(VoucherQueryFilter {
+        destination: Some(vec![1]),
+        ..Default::default()
+    });
+    assert_eq!(indices, vec![1, 3]);
+
+    let indices = get_voucher_indices(VoucherQueryFilter {
+        input_index_greater_than: Some(0),
+        input_index_lower_than: Some(3),
+        ..Default::default()
+    });
+    assert_eq!(indices, vec![1, 2]);
+
+    let indices = get_voucher_indices(VoucherQueryFilter {
+        payload_prefix: Some(vec![0x01]),
+        ..Default::default()
+    });
+    assert_eq!(indices, vec![0, 1]);
+
+    // There is no upper bound for a prefix made only of 0xff bytes
+    let indices = get_voucher_indices(VoucherQueryFilter {
+        payload_prefix: Some(vec![0xff]),
+        ..Default::default()
+    });
+    assert_eq!(indices, vec![3]);
+
+    // Prefixes longer than the indexed bytes compare the whole payload
+    let indices = get_voucher_indices(VoucherQueryFilter {
+        payload_prefix: Some(long_payload[..33].to_vec()),
+        ..Default::default()
+    });
+    assert_eq!(indices, vec![4]);
+
+    let mut other_prefix = long_payload[..33].to_vec();
+    other_prefix[32] = 0x04;
+    let indices = get_voucher_indices(VoucherQueryFilter {
+        payload_prefix: Some(other_prefix),
+        ..Default::default()
+    });
+    assert!(indices.is_empty());
+
+    let notices = repo
+        .get_notices(
+            None,
+            None,
+            None,
+            None,
+            NoticeQueryFilter {
+                input_index_greater_than: Some(0),
+                payload_prefix: Some(vec![0x01, 0xff]),
+                ..Default::default()
+            },
+            false,
+        )
+        .expect("Failed to get notices");
+    let indices: Vec<_> = notices
+        .edges
+        .into_iter()
+        .map(|e| e.node.input_index)
+        .collect();
+    assert_eq!(indices, vec![1]);
+}
+
+#[test]
+#[serial]
+fn test_keyset_pagination() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    let insert_notice = |input_index: i32, index: i32| {
+        repo.insert_notice(Notice {
+            input_index,
+            index,
+            payload: format!("notice-{}-{}", input_index, index).into_bytes(),
+        })
+        .expect("Failed to insert notice");
+    };
+    for input_index in 0..2 {
+        let mut input = create_input();
+        input.index = input_index;
+        repo.insert_input(input).expect("Failed to insert input");
+    }
+    for (input_index, index) in [(0, 1), (0, 2), (1, 0), (1, 1), (1, 2)] {
+        insert_notice(input_index, index);
+    }
+    let get_keys = |connection: &PaginationConnection<Notice>| -> Vec<_> {
+        connection
+            .edges
+            .iter()
+            .map(|edge| (edge.node.input_index, edge.node.index))
+            .collect()
+    };
+
+    let first_page = repo
+        .get_notices(Some(2), None, None, None, Default::default(), false)
+        .expect("Failed to get notices");
+    assert_eq!(get_keys(&first_page), vec![(0, 1), (0, 2)]);
+    assert_eq!(first_page.total_count, None);
+    assert!(first_page.page_info.has_next_page);
+    assert!(!first_page.page_info.has_previous_page);
+
+    // An entry inserted before the cursor doesn't shift the next page
+    insert_notice(0, 0);
+    let after = first_page
+        .page_info
+        .end_cursor
+        .map(|cursor| cursor.encode());
+    let second_page = repo
+        .get_notices(Some(3), None, after, None, Default::default(), true)
+        .expect("Failed to get notices");
+    assert_eq!(get_keys(&second_page), vec![(1, 0), (1, 1), (1, 2)]);
+    assert_eq!(second_page.total_count, Some(6));
+    assert!(!second_page.page_info.has_next_page);
+    assert!(second_page.page_info.has_previous_page);
+
+    let before = second_page
+        .page_info
+        .end_cursor
+        .map(|cursor| cursor.encode());
+    let previous_page = repo
+        .get_notices(None, Some(2), None, before, Default::default(), false)
+        .expect("Failed to get notices");
+    assert_eq!(get_keys(&previous_page), vec![(1, 0), (1, 1)]);
+    assert!(previous_page.page_info.has_next_page);
+    assert!(previous_page.page_info.has_previous_page);
+
+    let last_page = repo
+        .get_notices(None, Some(2), None, None, Default::default(), false)
+        .expect("Failed to get notices");
+    assert_eq!(get_keys(&last_page), vec![(1, 1), (1, 2)]);
+    assert!(!last_page.page_info.has_next_page);
+    assert!(last_page.page_info.has_previous_page);
+}
+
+#[test]
+#[serial]
+fn test_set_indexer_progress() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    let progress =
+        |last_event_id: &str, rollback_index: Option<i32>| IndexerProgress {
+            stream_key: "inputs".to_owned(),
+            last_event_id: last_event_id.to_owned(),
+            rollback_index,
+        };
+    repo.transaction(|tx| {
+        tx.insert_input(create_input())?;
+        tx.set_indexer_progress(progress("1-0", None))
+    })
+    .expect("Failed to insert input with progress");
+    repo.transaction(|tx| tx.set_indexer_progress(progress("2-0", Some(1))))
+        .expect("Failed to update progress");
+
+    let get_progress =
+        repo.get_indexer_progress().expect("Failed to get progress");
+    assert_eq!(get_progress, vec![progress("2-0", Some(1))]);
+    repo.get_input(0).expect("Failed to get input");
+}
+
+#[test]
+#[serial]
+fn test_transaction_rollback() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    let notice = Notice {
+        input_index: 1,
+        index: 0,
+        payload: "notice-0".as_bytes().to_vec(),
+    };
+    let result = repo.transaction(|tx| {
+        tx.insert_input(create_input())?;
+        tx.set_indexer_progress(IndexerProgress {
+            stream_key: "inputs".to_owned(),
+            last_event_id: "1-0".to_owned(),
+            rollback_index: None,
+        })?;
+        tx.insert_notice(notice.clone())
+    });
+    assert!(result
+        .expect_err("Transaction should fail")
+        .is_foreign_key_violation());
+    assert!(matches!(repo.get_input(0), Err(Error::ItemNotFound { .. })));
+    let get_progress =
+        repo.get_indexer_progress().expect("Failed to get progress");
+    assert!(get_progress.is_empty());
+
+    // The failure of a savepoint doesn't roll back the transaction
+    repo.transaction(|tx| {
+        tx.insert_input(create_input())?;
+        let result = tx.savepoint(|tx| tx.insert_notice(notice.clone()));
+        assert!(result.is_err());
+        Ok(())
+    })
+    .expect("Failed to insert input");
+    repo.get_input(0).expect("Failed to get input");
+}
+
+#[test]
+#[serial]
+fn test_register_application() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    // Registering an application never adopts the default application
+    let application = repo
+        .register_application(1, &[0xfa; 20])
+        .expect("Failed to register application");
+    assert_ne!(application.id, DEFAULT_APPLICATION_ID);
+    let registered = repo
+        .register_application(1, &[0xfa; 20])
+        .expect("Failed to register application again");
+    assert_eq!(registered, application);
+
+    let other = repo
+        .register_application(2, &[0xfa; 20])
+        .expect("Failed to register application");
+    assert_ne!(other.id, application.id);
+    assert_eq!(
+        repo.get_application(&[0xfa; 20])
+            .expect("Failed to get application"),
+        application
+    );
+    assert_eq!(
+        repo.find_application(2, &[0xfa; 20])
+            .expect("Failed to find application"),
+        Some(other.clone())
+    );
+    assert_eq!(
+        repo.find_application(3, &[0xfa; 20])
+            .expect("Failed to find application"),
+        None
+    );
+    assert_eq!(
+        repo.get_applications()
+            .expect("Failed to get applications")
+            .into_iter()
+            .filter(|application| application.id != DEFAULT_APPLICATION_ID)
+            .collect::<Vec<_>>(),
+        vec![application, other]
+    );
+    assert!(matches!(
+        repo.get_application(&[0xfb; 20]),
+        Err(Error::ItemNotFound { .. })
+    ));
+}
+
+#[test]
+#[serial]
+fn test_adopt_default_application() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+    insert_test_input(&repo);
+
+    let registered = repo
+        .register_application(1, &[0xfa; 20])
+        .expect("Failed to register application");
+    assert!(matches!(
+        repo.adopt_default_application(1, &[0xfa; 20]),
+        Err(Error::DefaultApplicationAdoptedError {})
+    ));
+
+    let application = repo
+        .adopt_default_application(1, &[0xfb; 20])
+        .expect("Failed to adopt default application");
+    assert_eq!(application.id, DEFAULT_APPLICATION_ID);
+    assert_eq!(
+        repo.adopt_default_application(1, &[0xfb; 20])
+            .expect("Failed to adopt default application again"),
+        application
+    );
+    assert_eq!(
+        repo.register_application(1, &[0xfb; 20])
+            .expect("Failed to register application"),
+        application
+    );
+    assert!(matches!(
+        repo.adopt_default_application(1, &[0xfc; 20]),
+        Err(Error::DefaultApplicationAdoptedError {})
+    ));
+
+    // The rows stored before the migration belong to the adopter
+    repo.for_application(application.id)
+        .get_input(0)
+        .expect("Failed to get input");
+    assert!(matches!(
+        repo.for_application(registered.id).get_input(0),
+        Err(Error::ItemNotFound { .. })
+    ));
+}
+
+#[test]
+#[serial]
+fn test_applications_are_isolated() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+
+    let application = repo
+        .register_application(1, &[0xfa; 20])
+        .expect("Failed to register application");
+    let other = repo
+        .register_application(1, &[0xfb; 20])
+        .expect("Failed to register application");
+    let repo = repo.for_application(application.id);
+    let other_repo = repo.for_application(other.id);
+
+    insert_test_input(&repo);
+    let other_input = Input {
+        payload: "other-input-0".as_bytes().to_vec(),
+        ..create_input()
+    };
+    other_repo
+        .insert_input(other_input.clone())
+        .expect("Failed to insert input");
+    assert_eq!(
+        repo.get_input(0).expect("Failed to get input"),
+        create_input()
+    );
+    assert_eq!(
+        other_repo.get_input(0).expect("Failed to get input"),
+        other_input
+    );
+
+    other_repo
+        .delete_inputs_from(0)
+        .expect("Failed to delete inputs");
+    repo.get_input(0).expect("Failed to get input");
+    assert!(matches!(
+        other_repo.get_input(0),
+        Err(Error::ItemNotFound { .. })
+    ));
+    let inputs = other_repo
+        .get_inputs(None, None, None, None, Default::default(), true)
+        .expect("Failed to get inputs");
+    assert_eq!(inputs.total_count, Some(0));
+}
+
+#[test(tokio::test)]
+#[serial]
+async fn test_async_repository() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker);
+    let repo = test.get_repository();
+    let async_repo = test.get_async_repository();
+
+    insert_test_input(&repo);
+    repo.insert_notice(Notice {
+        input_index: 0,
+        index: 0,
+        payload: "notice-0-0".as_bytes().to_vec(),
+    })
+    .expect("Failed to insert notice");
+
+    // The queries share the connections of the pool
+    let (input, notice, notices) = tokio::join!(
+        async_repo.get_input(0),
+        async_repo.get_notice(0, 0),
+        async_repo.get_notices(
+            None,
+            None,
+            None,
+            None,
+            Default::default(),
+            true
+        ),
+    );
+    assert_eq!(input.expect("Failed to get input"), create_input());
+    assert_eq!(notice.expect("Failed to get notice").payload, b"notice-0-0");
+    let notices = notices.expect("Failed to get notices");
+    assert_eq!(notices.total_count, Some(1));
+    assert_eq!(notices.edges.len(), 1);
+    assert!(matches!(
+        async_repo.get_input(1).await,
+        Err(Error::ItemNotFound { .. })
+    ));
+
+    let application = repo
+        .adopt_default_application(1, &[0xfa; 20])
+        .expect("Failed to adopt default application");
+    let other = repo
+        .register_application(1, &[0xfb; 20])
+        .expect("Failed to register application");
+    assert_eq!(
+        async_repo
+            .get_application(&[0xfb; 20])
+            .await
+            .expect("Failed to get application"),
+        other
+    );
+    assert_eq!(
+        async_repo
+            .for_application(application.id)
+            .get_input(0)
+            .await
+            .expect("Failed to get input"),
+        create_input()
+    );
+    assert!(matches!(
+        async_repo.for_application(other.id).get_input(0).await,
+        Err(Error::ItemNotFound { .. })
+    ));
+}
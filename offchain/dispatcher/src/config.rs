@@ -1,19 +1,37 @@
 // (c) Cartesi and individual authors (see AUTHORS)
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use eth_state_client_lib::config::{
     Error as SCError, SCConfig, SCEnvCLIConfig,
 };
 use http_server::HttpServerConfig;
 use log::{LogConfig, LogEnvCliConfig};
-use snafu::{ResultExt, Snafu};
+use snafu::{OptionExt, ResultExt, Snafu};
+use std::path::PathBuf;
 use types::blockchain_config::{
     BlockchainCLIConfig, BlockchainConfig, BlockchainConfigError,
 };
 
 use rollups_events::{BrokerCLIConfig, BrokerConfig};
 
+use crate::dapps::{DAppEntry, DAppsError};
+use crate::drivers::context::EpochPolicy;
+
+/// Condition used by the dispatcher to close epochs
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum EpochPolicyKind {
+    /// Close the epoch after `rd-epoch-duration` seconds
+    Duration,
+    /// Close the epoch after `rd-epoch-inputs` inputs
+    Inputs,
+    /// Close the epoch after `rd-epoch-blocks` blocks
+    Blocks,
+    /// Close the epoch after the duration or after the inputs or blocks
+    /// limits, if set, whichever comes first
+    Hybrid,
+}
+
 #[derive(Parser)]
 #[command(name = "rd_config")]
 #[command(about = "Configuration for dispatcher")]
@@ -30,13 +48,37 @@ pub struct DispatcherEnvCLIConfig {
     #[command(flatten)]
     pub blockchain_config: BlockchainCLIConfig,
 
+    /// Condition used to close the rollups epochs, for which dispatcher will make claims.
+    #[arg(long, env, value_enum, default_value = "duration")]
+    pub rd_epoch_policy: EpochPolicyKind,
+
     /// Duration of rollups epoch in seconds, for which dispatcher will make claims.
     #[arg(long, env, default_value = "604800")]
     pub rd_epoch_duration: u64,
 
+    /// Maximum number of inputs in a rollups epoch
+    #[arg(long, env, value_parser = clap::value_parser!(u64).range(1..))]
+    pub rd_epoch_inputs: Option<u64>,
+
+    /// Maximum number of blocks in a rollups epoch
+    #[arg(long, env, value_parser = clap::value_parser!(u64).range(1..))]
+    pub rd_epoch_blocks: Option<u64>,
+
     /// Chain ID
     #[arg(long, env)]
     pub chain_id: u64,
+
+    /// Additional dapps served by the dispatcher, defined by a single string
+    /// separated by commas. Each dapp has the format
+    /// `<dapp address>:<deployment block number>`.
+    #[arg(long, env, num_args = 1.., value_delimiter = ',')]
+    pub dapps: Option<Vec<String>>,
+
+    /// Path to a file with additional dapps served by the dispatcher, one
+    /// `<dapp address>:<deployment block number>` per line. The dispatcher
+    /// reloads the file when it is modified.
+    #[arg(long, env)]
+    pub dapps_file: Option<PathBuf>,
 }
 
 #[derive(Clone, Debug)]
@@ -46,8 +88,13 @@ pub struct DispatcherConfig {
     pub log_config: LogConfig,
     pub blockchain_config: BlockchainConfig,
 
-    pub epoch_duration: u64,
+    pub epoch_policy: EpochPolicy,
     pub chain_id: u64,
+
+    /// Dapps served by the dispatcher, starting with the one from the
+    /// blockchain configuration
+    pub dapps: Vec<DAppEntry>,
+    pub dapps_file: Option<PathBuf>,
 }
 
 #[derive(Debug, Snafu)]
@@ -57,6 +104,19 @@ pub enum Error {
 
     #[snafu(display("Blockchain configuration error"))]
     BlockchainError { source: BlockchainConfigError },
+
+    #[snafu(display("DApps configuration error"))]
+    DAppsConfigError { source: DAppsError },
+
+    #[snafu(display(
+        "Epoch policy {:?} requires the {} option",
+        policy,
+        option
+    ))]
+    EpochPolicyError {
+        policy: EpochPolicyKind,
+        option: &'static str,
+    },
 }
 
 #[derive(Debug)]
@@ -81,13 +141,26 @@ impl Config {
 
         let broker_config = BrokerConfig::from(dispatcher_config.broker_config);
 
+        let mut dapps = vec![DAppEntry {
+            address: blockchain_config.dapp_address.clone().into_inner().into(),
+            deployment_block_number: blockchain_config
+                .dapp_deployment_block_number,
+        }];
+        for entry in dispatcher_config.dapps.unwrap_or_default() {
+            dapps.push(entry.parse().context(DAppsConfigSnafu)?);
+        }
+
+        let epoch_policy = build_epoch_policy(&dispatcher_config)?;
+
         let dispatcher_config = DispatcherConfig {
             sc_config,
             broker_config,
             log_config,
             blockchain_config,
-            epoch_duration: dispatcher_config.rd_epoch_duration,
+            epoch_policy,
             chain_id: dispatcher_config.chain_id,
+            dapps,
+            dapps_file: dispatcher_config.dapps_file,
         };
 
         Ok(Config {
@@ -96,3 +169,35 @@ impl Config {
         })
     }
 }
+
+fn build_epoch_policy(
+    config: &DispatcherEnvCLIConfig,
+) -> Result<EpochPolicy, Error> {
+    let policy = config.rd_epoch_policy;
+    let epoch_policy = match policy {
+        EpochPolicyKind::Duration => EpochPolicy {
+            duration: Some(config.rd_epoch_duration),
+            ..Default::default()
+        },
+        EpochPolicyKind::Inputs => EpochPolicy {
+            inputs: Some(config.rd_epoch_inputs.context(EpochPolicySnafu {
+                policy,
+                option: "rd-epoch-inputs",
+            })?),
+            ..Default::default()
+        },
+        EpochPolicyKind::Blocks => EpochPolicy {
+            blocks: Some(config.rd_epoch_blocks.context(EpochPolicySnafu {
+                policy,
+                option: "rd-epoch-blocks",
+            })?),
+            ..Default::default()
+        },
+        EpochPolicyKind::Hybrid => EpochPolicy {
+            duration: Some(config.rd_epoch_duration),
+            inputs: config.rd_epoch_inputs,
+            blocks: config.rd_epoch_blocks,
+        },
+    };
+    Ok(epoch_policy)
+}
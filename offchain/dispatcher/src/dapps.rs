@@ -0,0 +1,188 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Set of dapps served by the dispatcher
+//!
+//! Each dapp is described by an entry with the format
+//! `<dapp address>:<deployment block number>`. Besides the static entries from
+//! the configuration, the dispatcher may read the entries from a file, one per
+//! line, which is reloaded whenever it is modified.
+
+use eth_state_fold_types::ethereum_types::Address;
+use snafu::{OptionExt, ResultExt, Snafu};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+#[derive(Debug, Snafu)]
+pub enum DAppsError {
+    #[snafu(display(
+        "invalid dapp entry `{}`; expected <address>:<block number>",
+        entry
+    ))]
+    InvalidEntryError { entry: String },
+
+    #[snafu(display("failed to read dapps file {}", path.display()))]
+    ReadFileError {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DAppEntry {
+    pub address: Address,
+    pub deployment_block_number: u64,
+}
+
+impl std::str::FromStr for DAppEntry {
+    type Err = DAppsError;
+
+    fn from_str(entry: &str) -> Result<Self, Self::Err> {
+        let parse = || {
+            let (address, block_number) = entry.trim().split_once(':')?;
+            let address = address.trim();
+            let address = address.strip_prefix("0x").unwrap_or(address);
+            Some(Self {
+                address: address.parse().ok()?,
+                deployment_block_number: block_number.trim().parse().ok()?,
+            })
+        };
+        parse().context(InvalidEntrySnafu { entry })
+    }
+}
+
+#[derive(Debug)]
+pub struct DAppsSource {
+    entries: Vec<DAppEntry>,
+    file: Option<PathBuf>,
+    file_modified: Option<SystemTime>,
+    loaded: bool,
+}
+
+impl DAppsSource {
+    pub fn new(entries: Vec<DAppEntry>, file: Option<PathBuf>) -> Self {
+        Self {
+            entries,
+            file,
+            file_modified: None,
+            loaded: false,
+        }
+    }
+
+    /// Get the set of dapps if it changed since the last call, keyed by
+    /// address. The first call always returns the set.
+    /// If an address appears more than once, the first entry is used.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn reload(
+        &mut self,
+    ) -> Result<Option<BTreeMap<Address, DAppEntry>>, DAppsError> {
+        let mut file_entries = vec![];
+        if let Some(path) = &self.file {
+            let modified = fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .context(ReadFileSnafu { path })?;
+            if self.loaded && self.file_modified == Some(modified) {
+                return Ok(None);
+            }
+            tracing::info!(?path, "loading dapps file");
+            let contents =
+                fs::read_to_string(path).context(ReadFileSnafu { path })?;
+            file_entries = parse_file(&contents)?;
+            self.file_modified = Some(modified);
+        } else if self.loaded {
+            return Ok(None);
+        }
+        self.loaded = true;
+
+        let mut dapps = BTreeMap::new();
+        for entry in self.entries.iter().chain(file_entries.iter()) {
+            dapps.entry(entry.address).or_insert(*entry);
+        }
+        Ok(Some(dapps))
+    }
+}
+
+/// Parse one entry per line, ignoring blank lines and `#` comments
+fn parse_file(contents: &str) -> Result<Vec<DAppEntry>, DAppsError> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const DAPP_A: &str = "0x0000000000000000000000000000000000000001";
+    const DAPP_B: &str = "0000000000000000000000000000000000000002";
+
+    fn entry(n: u64, deployment_block_number: u64) -> DAppEntry {
+        DAppEntry {
+            address: Address::from_low_u64_be(n),
+            deployment_block_number,
+        }
+    }
+
+    #[test]
+    fn parse_entry() {
+        let parsed: DAppEntry = format!("{}:10", DAPP_A).parse().unwrap();
+        assert_eq!(parsed, entry(1, 10));
+        let parsed: DAppEntry = format!(" {} : 20 ", DAPP_B).parse().unwrap();
+        assert_eq!(parsed, entry(2, 20));
+    }
+
+    #[test]
+    fn parse_invalid_entry() {
+        assert!(DAPP_A.parse::<DAppEntry>().is_err());
+        assert!(format!("{}:x", DAPP_A).parse::<DAppEntry>().is_err());
+        assert!("0x01:10".parse::<DAppEntry>().is_err());
+    }
+
+    #[test]
+    fn reload_static_entries_once() {
+        let mut source = DAppsSource::new(vec![entry(1, 10)], None);
+        let dapps = source.reload().unwrap().unwrap();
+        assert_eq!(dapps.into_values().collect::<Vec<_>>(), vec![entry(1, 10)]);
+        assert!(source.reload().unwrap().is_none());
+    }
+
+    #[test]
+    fn reload_file_when_modified() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "# dapps\n{}:10\n\n{}:20 # second", DAPP_A, DAPP_B)
+            .unwrap();
+        let mut source =
+            DAppsSource::new(vec![entry(1, 5)], Some(file.path().to_owned()));
+
+        // The static entry takes precedence over the file
+        let dapps = source.reload().unwrap().unwrap();
+        assert_eq!(
+            dapps.into_values().collect::<Vec<_>>(),
+            vec![entry(1, 5), entry(2, 20)]
+        );
+        assert!(source.reload().unwrap().is_none());
+
+        let modified = SystemTime::now() + std::time::Duration::from_secs(10);
+        file.as_file_mut().set_len(0).unwrap();
+        file.as_file_mut().set_modified(modified).unwrap();
+        let dapps = source.reload().unwrap().unwrap();
+        assert_eq!(dapps.into_values().collect::<Vec<_>>(), vec![entry(1, 5)]);
+    }
+
+    #[test]
+    fn reload_invalid_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "invalid").unwrap();
+        let mut source = DAppsSource::new(vec![], Some(file.path().into()));
+        assert!(matches!(
+            source.reload(),
+            Err(DAppsError::InvalidEntryError { .. })
+        ));
+    }
+}
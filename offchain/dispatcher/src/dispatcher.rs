@@ -1,25 +1,42 @@
 // (c) Cartesi and individual authors (see AUTHORS)
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
-use eth_state_client_lib::StateServer;
-use eth_state_fold_types::{Block, BlockStreamItem};
+use eth_state_client_lib::{BlockServer, StateServer};
+use eth_state_fold_types::{ethereum_types::Address, Block, BlockStreamItem};
 use rollups_events::DAppMetadata;
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::task::JoinHandle;
 use tokio_stream::StreamExt;
-use tracing::{error, instrument, trace, warn};
+use tracing::{info, instrument, trace, warn};
 use types::foldables::{InputBox, InputBoxInitialState};
 
 use crate::{
     config::DispatcherConfig,
+    dapps::{DAppEntry, DAppsSource},
     drivers::{machine::MachineDriver, Context},
-    error::{BrokerSnafu, DispatcherError, StateServerSnafu},
-    machine::{rollups_broker::BrokerFacade, BrokerSend},
+    error::{BrokerSnafu, DAppsSnafu, DispatcherError, StateServerSnafu},
+    machine::{rollups_broker::BrokerFacade, BrokerSend, BrokerStatus},
     metrics::DispatcherMetrics,
     setup::{create_block_subscription, create_context, create_state_server},
 };
 
 use snafu::{whatever, ResultExt};
 
+/// State of the dispatcher for each dapp
+struct DAppDispatcher {
+    initial_state: InputBoxInitialState,
+    context: Context,
+    machine_driver: MachineDriver,
+    broker: BrokerFacade,
+    trimmer: JoinHandle<()>,
+}
+
+impl Drop for DAppDispatcher {
+    fn drop(&mut self) {
+        self.trimmer.abort();
+    }
+}
+
 #[instrument(level = "trace", skip_all)]
 pub async fn start(
     config: DispatcherConfig,
@@ -27,11 +44,6 @@ pub async fn start(
 ) -> Result<(), DispatcherError> {
     trace!("Setting up dispatcher");
 
-    let dapp_metadata = DAppMetadata {
-        chain_id: config.chain_id,
-        dapp_address: config.blockchain_config.dapp_address.clone(),
-    };
-
     trace!("Creating state-server connection");
     let state_server = create_state_server(&config.sc_config).await?;
 
@@ -42,50 +54,14 @@ pub async fn start(
     )
     .await?;
 
-    trace!("Creating broker connection");
-    let broker =
-        BrokerFacade::new(config.broker_config.clone(), dapp_metadata.clone())
-            .await
-            .context(BrokerSnafu)?;
-
-    trace!("Creating machine driver and blockchain driver");
-    let mut machine_driver = MachineDriver::new(
-        config
-            .blockchain_config
-            .dapp_address
-            .clone()
-            .into_inner()
-            .into(),
-    );
-
-    let initial_state = InputBoxInitialState {
-        dapp_address: Arc::new(
-            config
-                .blockchain_config
-                .dapp_address
-                .clone()
-                .into_inner()
-                .into(),
-        ),
-        input_box_address: Arc::new(
-            config
-                .blockchain_config
-                .input_box_address
-                .clone()
-                .into_inner()
-                .into(),
-        ),
-    };
-
-    trace!("Creating context");
-    let mut context = create_context(
-        &(config.clone()),
-        &state_server,
-        &broker,
-        dapp_metadata,
-        metrics,
-    )
-    .await?;
+    trace!("Loading dapps");
+    let mut dapps_source =
+        DAppsSource::new(config.dapps.clone(), config.dapps_file.clone());
+    let mut dapps = BTreeMap::new();
+    if let Some(entries) = dapps_source.reload().context(DAppsSnafu)? {
+        sync_dapps(&mut dapps, entries, &config, &state_server, &metrics)
+            .await?;
+    }
 
     trace!("Starting dispatcher...");
     loop {
@@ -98,27 +74,58 @@ pub async fn start(
                     b.hash,
                     b.parent_hash
                 );
-                process_block(
-                    &b,
-                    &state_server,
-                    &initial_state,
-                    &mut context,
-                    &mut machine_driver,
-                    &broker,
-                )
-                .await?
+                match dapps_source.reload() {
+                    Ok(Some(entries)) => {
+                        sync_dapps(
+                            &mut dapps,
+                            entries,
+                            &config,
+                            &state_server,
+                            &metrics,
+                        )
+                        .await?
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!("Failed to reload dapps `{}`; keeping the current ones", e);
+                    }
+                }
+                for dapp in dapps.values_mut() {
+                    process_block(
+                        &b,
+                        &state_server,
+                        &dapp.initial_state,
+                        &mut dapp.context,
+                        &mut dapp.machine_driver,
+                        &dapp.broker,
+                    )
+                    .await?
+                }
             }
 
             Some(Ok(BlockStreamItem::Reorg(bs))) => {
-                error!(
+                warn!(
                     "Deep blockchain reorg of {} blocks; new latest has number {:?}, hash {:?}, and parent {:?}",
                     bs.len(),
                     bs.last().map(|b| b.number),
                     bs.last().map(|b| b.hash),
                     bs.last().map(|b| b.parent_hash)
                 );
-                error!("Bailing...");
-                whatever!("deep blockchain reorg");
+                let b = match bs.last() {
+                    Some(b) => b,
+                    None => continue,
+                };
+                for dapp in dapps.values_mut() {
+                    process_reorg(
+                        b,
+                        &state_server,
+                        &dapp.initial_state,
+                        &mut dapp.context,
+                        &mut dapp.machine_driver,
+                        &dapp.broker,
+                    )
+                    .await?
+                }
             }
 
             Some(Err(e)) => {
@@ -135,6 +142,79 @@ pub async fn start(
     }
 }
 
+/// Start serving the new dapps and stop serving the removed ones
+#[instrument(level = "trace", skip_all)]
+async fn sync_dapps(
+    dapps: &mut BTreeMap<Address, DAppDispatcher>,
+    entries: BTreeMap<Address, DAppEntry>,
+    config: &DispatcherConfig,
+    block_server: &impl BlockServer,
+    metrics: &DispatcherMetrics,
+) -> Result<(), DispatcherError> {
+    dapps.retain(|address, _| {
+        let keep = entries.contains_key(address);
+        if !keep {
+            info!("Removing dapp {:?}", address);
+        }
+        keep
+    });
+
+    for (address, entry) in entries {
+        if dapps.contains_key(&address) {
+            continue;
+        }
+        info!("Adding dapp {:?}", address);
+
+        let dapp_metadata = DAppMetadata {
+            chain_id: config.chain_id,
+            dapp_address: address.to_fixed_bytes().into(),
+        };
+
+        trace!("Creating broker connection");
+        let broker = BrokerFacade::new(
+            config.broker_config.clone(),
+            dapp_metadata.clone(),
+        )
+        .await
+        .context(BrokerSnafu)?;
+
+        trace!("Creating context");
+        let context = create_context(
+            config,
+            &entry,
+            block_server,
+            &broker,
+            dapp_metadata,
+            metrics.clone(),
+        )
+        .await?;
+
+        let initial_state = InputBoxInitialState {
+            dapp_address: Arc::new(address),
+            input_box_address: Arc::new(
+                config
+                    .blockchain_config
+                    .input_box_address
+                    .clone()
+                    .into_inner()
+                    .into(),
+            ),
+        };
+
+        let trimmer = tokio::spawn(broker.stream_trimmer().await.start());
+        let dapp = DAppDispatcher {
+            initial_state,
+            context,
+            machine_driver: MachineDriver::new(address),
+            broker,
+            trimmer,
+        };
+        dapps.insert(address, dapp);
+    }
+
+    Ok(())
+}
+
 #[instrument(level = "trace", skip_all)]
 #[allow(clippy::too_many_arguments)]
 async fn process_block(
@@ -166,3 +246,39 @@ async fn process_block(
 
     Ok(())
 }
+
+#[instrument(level = "trace", skip_all)]
+#[allow(clippy::too_many_arguments)]
+async fn process_reorg(
+    block: &Block,
+
+    state_server: &impl StateServer<
+        InitialState = InputBoxInitialState,
+        State = InputBox,
+    >,
+    initial_state: &InputBoxInitialState,
+
+    context: &mut Context,
+    machine_driver: &mut MachineDriver,
+
+    broker: &(impl BrokerSend + BrokerStatus),
+) -> Result<(), DispatcherError> {
+    trace!("Querying rollup state after reorg");
+    let state = state_server
+        .query_state(initial_state, block.hash)
+        .await
+        .context(StateServerSnafu)?;
+
+    // Drop the inputs that are no longer in the chain and send the new ones
+    trace!("Rewinding `machine_driver` to the new chain");
+    machine_driver
+        .rewind(context, &state.state, broker)
+        .await
+        .context(BrokerSnafu)?;
+    machine_driver
+        .react(context, &state.block, &state.state, broker)
+        .await
+        .context(BrokerSnafu)?;
+
+    Ok(())
+}
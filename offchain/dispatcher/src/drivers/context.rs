@@ -2,22 +2,44 @@
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
 use crate::{
-    machine::{rollups_broker::BrokerFacadeError, BrokerSend, RollupStatus},
+    machine::{
+        rollups_broker::BrokerFacadeError, BrokerSend, BrokerStatus,
+        RollupStatus,
+    },
     metrics::DispatcherMetrics,
 };
 
-use rollups_events::DAppMetadata;
-use types::foldables::Input;
+use rollups_events::{DAppMetadata, EpochBoundary, EpochStart};
+use types::foldables::{DAppInputBox, Input};
+
+/// Conditions to close an epoch; the epoch is closed as soon as any of them is
+/// met. The duration and the blocks are counted from the dapp deployment, so
+/// the epoch boundaries only depend on chain data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EpochPolicy {
+    /// Duration of the epoch in seconds
+    pub duration: Option<u64>,
+    /// Maximum number of inputs in the epoch
+    pub inputs: Option<u64>,
+    /// Number of blocks of the epoch
+    pub blocks: Option<u64>,
+}
 
 #[derive(Debug)]
 pub struct Context {
     inputs_sent_count: u64,
     last_event_is_finish_epoch: bool,
     last_timestamp: u64,
+    last_block_number: u64,
+
+    // (inputs_sent_count, timestamp, block_number) of each finish epoch sent
+    // by this context, starting from the one restored from the broker
+    epoch_checkpoints: Vec<(u64, u64, u64)>,
 
     // constants
     genesis_timestamp: u64,
-    epoch_length: u64,
+    genesis_block_number: u64,
+    epoch_policy: EpochPolicy,
 
     dapp_metadata: DAppMetadata,
     metrics: DispatcherMetrics,
@@ -26,20 +48,27 @@ pub struct Context {
 impl Context {
     pub fn new(
         genesis_timestamp: u64,
-        epoch_length: u64,
+        genesis_block_number: u64,
+        epoch_policy: EpochPolicy,
         dapp_metadata: DAppMetadata,
         metrics: DispatcherMetrics,
         status: RollupStatus,
+        epoch_start: Option<EpochStart>,
     ) -> Self {
-        Self {
+        let mut context = Self {
             inputs_sent_count: status.inputs_sent_count,
             last_event_is_finish_epoch: status.last_event_is_finish_epoch,
             last_timestamp: genesis_timestamp,
+            last_block_number: genesis_block_number,
+            epoch_checkpoints: vec![],
             genesis_timestamp,
-            epoch_length,
+            genesis_block_number,
+            epoch_policy,
             dapp_metadata,
             metrics,
-        }
+        };
+        context.restore_epoch_start(epoch_start);
+        context
     }
 
     pub fn inputs_sent_count(&self) -> u64 {
@@ -49,10 +78,12 @@ impl Context {
     pub async fn finish_epoch_if_needed(
         &mut self,
         event_timestamp: u64,
+        event_block_number: u64,
         broker: &impl BrokerSend,
     ) -> Result<(), BrokerFacadeError> {
-        if self.should_finish_epoch(event_timestamp) {
-            self.finish_epoch(event_timestamp, broker).await?;
+        if self.should_finish_epoch(event_timestamp, event_block_number) {
+            self.finish_epoch(event_timestamp, event_block_number, broker)
+                .await?;
         }
         Ok(())
     }
@@ -71,39 +102,145 @@ impl Context {
         self.last_event_is_finish_epoch = false;
         Ok(())
     }
+
+    /// Rewind the context and the broker to the last input that is still
+    /// valid after a reorg
+    pub async fn rewind(
+        &mut self,
+        dapp_input_box: Option<&DAppInputBox>,
+        broker: &(impl BrokerSend + BrokerStatus),
+    ) -> Result<(), BrokerFacadeError> {
+        let status = match broker.rewind(dapp_input_box).await? {
+            Some(status) => status,
+            None => return Ok(()),
+        };
+        self.metrics.reorgs.get_or_create(&self.dapp_metadata).inc();
+        self.inputs_sent_count = status.inputs_sent_count;
+        self.last_event_is_finish_epoch = status.last_event_is_finish_epoch;
+
+        // The rewound stream ends in an input, so the finish epochs sent after
+        // it were dropped
+        self.epoch_checkpoints.retain(|(inputs_sent_count, _, _)| {
+            *inputs_sent_count < status.inputs_sent_count
+        });
+        match self.epoch_checkpoints.last() {
+            Some(&(_, timestamp, block_number)) => {
+                self.last_timestamp = timestamp;
+                self.last_block_number = block_number;
+            }
+            // The finish epochs sent before the context was created are only
+            // in the broker
+            None => self.restore_epoch_start(broker.epoch_start().await?),
+        }
+        Ok(())
+    }
 }
 
 impl Context {
+    /// Restore the start of the current epoch from the broker, which stores
+    /// the block that finished the previous epoch
+    fn restore_epoch_start(&mut self, epoch_start: Option<EpochStart>) {
+        self.epoch_checkpoints.clear();
+        let Some(epoch_start) = epoch_start else {
+            self.last_timestamp = self.genesis_timestamp;
+            self.last_block_number = self.genesis_block_number;
+            return;
+        };
+        // The finish epochs produced before the boundary was recorded count
+        // the next epoch from the genesis
+        let boundary = epoch_start.boundary.unwrap_or(EpochBoundary {
+            timestamp: self.genesis_timestamp,
+            block_number: self.genesis_block_number,
+        });
+        self.epoch_checkpoints.push((
+            epoch_start.inputs_sent_count,
+            boundary.timestamp,
+            boundary.block_number,
+        ));
+        self.last_timestamp = boundary.timestamp;
+        self.last_block_number = boundary.block_number;
+    }
+
     fn calculate_epoch(&self, timestamp: u64) -> u64 {
         assert!(timestamp >= self.genesis_timestamp);
-        (timestamp - self.genesis_timestamp) / self.epoch_length
+        let duration = self.epoch_policy.duration.expect("no epoch duration");
+        (timestamp - self.genesis_timestamp) / duration
+    }
+
+    fn calculate_block_epoch(&self, block_number: u64) -> u64 {
+        assert!(block_number >= self.genesis_block_number);
+        let blocks = self.epoch_policy.blocks.expect("no epoch blocks");
+        (block_number - self.genesis_block_number) / blocks
     }
 
-    // This logic works because we call this function with `event_timestamp` being equal to the
-    // timestamp of each individual input, rather than just the latest from the blockchain.
-    fn should_finish_epoch(&self, event_timestamp: u64) -> bool {
+    /// Number of inputs sent since the last finish epoch
+    fn epoch_inputs_count(&self) -> u64 {
+        let epoch_start = self
+            .epoch_checkpoints
+            .last()
+            .map(|(inputs_sent_count, _, _)| *inputs_sent_count)
+            .unwrap_or_default();
+        self.inputs_sent_count - epoch_start
+    }
+
+    // This logic works because we call this function with `event_timestamp` and
+    // `event_block_number` being equal to the block of each individual input,
+    // rather than just the latest from the blockchain.
+    fn should_finish_epoch(
+        &self,
+        event_timestamp: u64,
+        event_block_number: u64,
+    ) -> bool {
         if self.inputs_sent_count == 0 || self.last_event_is_finish_epoch {
-            false
-        } else {
+            return false;
+        }
+
+        let policy = &self.epoch_policy;
+        let duration_elapsed = policy.duration.is_some() && {
             let current_epoch = self.calculate_epoch(self.last_timestamp);
             let event_epoch = self.calculate_epoch(event_timestamp);
             event_epoch > current_epoch
-        }
+        };
+        let blocks_elapsed = policy.blocks.is_some() && {
+            let current_epoch =
+                self.calculate_block_epoch(self.last_block_number);
+            let event_epoch = self.calculate_block_epoch(event_block_number);
+            event_epoch > current_epoch
+        };
+        let inputs_reached = policy
+            .inputs
+            .is_some_and(|inputs| self.epoch_inputs_count() >= inputs);
+
+        duration_elapsed || blocks_elapsed || inputs_reached
     }
 
     async fn finish_epoch(
         &mut self,
         event_timestamp: u64,
+        event_block_number: u64,
         broker: &impl BrokerSend,
     ) -> Result<(), BrokerFacadeError> {
         assert!(event_timestamp >= self.genesis_timestamp);
-        broker.finish_epoch(self.inputs_sent_count).await?;
+        assert!(event_block_number >= self.genesis_block_number);
+        let boundary = EpochBoundary {
+            timestamp: event_timestamp,
+            block_number: event_block_number,
+        };
+        broker
+            .finish_epoch(self.inputs_sent_count, boundary)
+            .await?;
         self.metrics
             .finish_epochs_sent
             .get_or_create(&self.dapp_metadata)
             .inc();
         self.last_timestamp = event_timestamp;
+        self.last_block_number = event_block_number;
         self.last_event_is_finish_epoch = true;
+        self.epoch_checkpoints.push((
+            self.inputs_sent_count,
+            event_timestamp,
+            event_block_number,
+        ));
         Ok(())
     }
 }
@@ -112,7 +249,7 @@ impl Context {
 mod private_tests {
     use crate::{drivers::mock, metrics::DispatcherMetrics};
 
-    use super::{Context, DAppMetadata};
+    use super::{Context, DAppMetadata, EpochPolicy};
 
     // --------------------------------------------------------------------------------------------
     // calculate_epoch_for
@@ -126,8 +263,11 @@ mod private_tests {
             inputs_sent_count: 0,
             last_event_is_finish_epoch: false,
             last_timestamp: 0,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp,
-            epoch_length,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(epoch_length),
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         }
@@ -177,12 +317,15 @@ mod private_tests {
             inputs_sent_count: 1,
             last_event_is_finish_epoch: false,
             last_timestamp: 3,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp: 0,
-            epoch_length: 5,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(5),
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
-        assert!(!context.should_finish_epoch(4));
+        assert!(!context.should_finish_epoch(4, 0));
     }
 
     #[test]
@@ -191,12 +334,15 @@ mod private_tests {
             inputs_sent_count: 1,
             last_event_is_finish_epoch: false,
             last_timestamp: 3,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp: 0,
-            epoch_length: 5,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(5),
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
-        assert!(!context.should_finish_epoch(4));
+        assert!(!context.should_finish_epoch(4, 0));
     }
 
     #[test]
@@ -205,12 +351,15 @@ mod private_tests {
             inputs_sent_count: 1,
             last_event_is_finish_epoch: false,
             last_timestamp: 3,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp: 0,
-            epoch_length: 5,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(5),
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
-        assert!(context.should_finish_epoch(5));
+        assert!(context.should_finish_epoch(5, 0));
     }
 
     #[test]
@@ -219,12 +368,95 @@ mod private_tests {
             inputs_sent_count: 1,
             last_event_is_finish_epoch: true,
             last_timestamp: 3,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp: 0,
-            epoch_length: 5,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(5),
+            dapp_metadata: DAppMetadata::default(),
+            metrics: DispatcherMetrics::default(),
+        };
+        assert!(!context.should_finish_epoch(5, 0));
+    }
+
+    fn new_context_for_epoch_policy_test(
+        inputs_sent_count: u64,
+        epoch_checkpoints: Vec<(u64, u64, u64)>,
+        epoch_policy: EpochPolicy,
+    ) -> Context {
+        let (_, last_timestamp, last_block_number) =
+            epoch_checkpoints.last().copied().unwrap_or((0, 10, 100));
+        Context {
+            inputs_sent_count,
+            last_event_is_finish_epoch: false,
+            last_timestamp,
+            last_block_number,
+            epoch_checkpoints,
+            genesis_timestamp: 10,
+            genesis_block_number: 100,
+            epoch_policy,
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
+        }
+    }
+
+    #[test]
+    fn should_finish_epoch_because_of_inputs() {
+        let policy = EpochPolicy {
+            inputs: Some(3),
+            ..Default::default()
+        };
+        let context = new_context_for_epoch_policy_test(2, vec![], policy);
+        assert!(!context.should_finish_epoch(1000, 1000));
+        let context = new_context_for_epoch_policy_test(3, vec![], policy);
+        assert!(context.should_finish_epoch(10, 100));
+        let context =
+            new_context_for_epoch_policy_test(5, vec![(3, 20, 110)], policy);
+        assert!(!context.should_finish_epoch(1000, 1000));
+        let context =
+            new_context_for_epoch_policy_test(6, vec![(3, 20, 110)], policy);
+        assert!(context.should_finish_epoch(20, 110));
+    }
+
+    #[test]
+    fn should_finish_epoch_because_of_blocks() {
+        let policy = EpochPolicy {
+            blocks: Some(5),
+            ..Default::default()
+        };
+        let context = new_context_for_epoch_policy_test(1, vec![], policy);
+        assert!(!context.should_finish_epoch(1000, 104));
+        assert!(context.should_finish_epoch(10, 105));
+        let context =
+            new_context_for_epoch_policy_test(2, vec![(1, 10, 107)], policy);
+        assert!(!context.should_finish_epoch(1000, 109));
+        assert!(context.should_finish_epoch(10, 110));
+    }
+
+    #[test]
+    fn should_finish_epoch_in_hybrid_policy() {
+        let policy = EpochPolicy {
+            duration: Some(10),
+            inputs: Some(3),
+            blocks: Some(5),
+        };
+        let context = new_context_for_epoch_policy_test(1, vec![], policy);
+        assert!(!context.should_finish_epoch(19, 104));
+        assert!(context.should_finish_epoch(20, 104));
+        assert!(context.should_finish_epoch(19, 105));
+        let context = new_context_for_epoch_policy_test(3, vec![], policy);
+        assert!(context.should_finish_epoch(10, 100));
+    }
+
+    #[test]
+    #[should_panic]
+    fn calculate_block_epoch_invalid() {
+        let policy = EpochPolicy {
+            blocks: Some(5),
+            ..Default::default()
         };
-        assert!(!context.should_finish_epoch(5));
+        new_context_for_epoch_policy_test(0, vec![], policy)
+            .calculate_block_epoch(99);
     }
 
     // --------------------------------------------------------------------------------------------
@@ -237,17 +469,21 @@ mod private_tests {
             inputs_sent_count: 0,
             last_event_is_finish_epoch: false,
             last_timestamp: 3,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp: 0,
-            epoch_length: 5,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(5),
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
         let broker = mock::Broker::new(vec![], vec![]);
         let timestamp = 6;
-        let result = context.finish_epoch(timestamp, &broker).await;
+        let result = context.finish_epoch(timestamp, 0, &broker).await;
         assert!(result.is_ok());
         assert_eq!(context.last_timestamp, timestamp);
         assert!(context.last_event_is_finish_epoch);
+        assert_eq!(context.epoch_checkpoints, vec![(0, timestamp, 0)]);
     }
 
     #[tokio::test]
@@ -257,13 +493,16 @@ mod private_tests {
             inputs_sent_count: 0,
             last_event_is_finish_epoch: false,
             last_timestamp: 6,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp: 5,
-            epoch_length: 5,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(5),
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
         let broker = mock::Broker::new(vec![], vec![]);
-        let _ = context.finish_epoch(0, &broker).await;
+        let _ = context.finish_epoch(0, 0, &broker).await;
     }
 
     #[tokio::test]
@@ -274,13 +513,16 @@ mod private_tests {
             inputs_sent_count: 0,
             last_event_is_finish_epoch,
             last_timestamp,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp: 0,
-            epoch_length: 5,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(5),
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
         let broker = mock::Broker::with_finish_epoch_error();
-        let result = context.finish_epoch(6, &broker).await;
+        let result = context.finish_epoch(6, 0, &broker).await;
         assert!(result.is_err());
         assert_eq!(context.last_timestamp, last_timestamp);
         assert_eq!(
@@ -292,13 +534,18 @@ mod private_tests {
 
 #[cfg(test)]
 mod public_tests {
+    use std::sync::Arc;
+
+    use rollups_events::{EpochBoundary, EpochStart};
+    use types::foldables::Input;
+
     use crate::{
         drivers::mock::{self, SendInteraction},
         machine::RollupStatus,
         metrics::DispatcherMetrics,
     };
 
-    use super::{Context, DAppMetadata};
+    use super::{Context, DAppMetadata, EpochPolicy};
 
     // --------------------------------------------------------------------------------------------
     // new
@@ -316,10 +563,12 @@ mod public_tests {
         };
         let context = Context::new(
             genesis_timestamp,
-            epoch_length,
+            0,
+            mock::new_duration_policy(epoch_length),
             DAppMetadata::default(),
             DispatcherMetrics::default(),
             rollup_status,
+            None,
         );
         assert_eq!(context.genesis_timestamp, genesis_timestamp);
         assert_eq!(context.inputs_sent_count, inputs_sent_count);
@@ -340,8 +589,11 @@ mod public_tests {
             inputs_sent_count,
             last_event_is_finish_epoch: false, // ignored
             last_timestamp: 0,                 // ignored
-            genesis_timestamp: 0,              // ignored
-            epoch_length: 0,                   // ignored
+            last_block_number: 0,              // ignored
+            epoch_checkpoints: vec![],
+            genesis_timestamp: 0,                 // ignored
+            genesis_block_number: 0,              // ignored
+            epoch_policy: EpochPolicy::default(), // ignored
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
@@ -358,13 +610,16 @@ mod public_tests {
             inputs_sent_count: 1,
             last_event_is_finish_epoch: false,
             last_timestamp: 2,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp: 0,
-            epoch_length: 4,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(4),
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
         let broker = mock::Broker::new(vec![], vec![]);
-        let result = context.finish_epoch_if_needed(4, &broker).await;
+        let result = context.finish_epoch_if_needed(4, 0, &broker).await;
         assert!(result.is_ok());
         broker
             .assert_send_interactions(vec![SendInteraction::FinishedEpoch(1)]);
@@ -376,13 +631,16 @@ mod public_tests {
             inputs_sent_count: 0,
             last_event_is_finish_epoch: false,
             last_timestamp: 2,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp: 0,
-            epoch_length: 2,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(2),
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
         let broker = mock::Broker::new(vec![], vec![]);
-        let result = context.finish_epoch_if_needed(3, &broker).await;
+        let result = context.finish_epoch_if_needed(3, 0, &broker).await;
         assert!(result.is_ok());
         broker.assert_send_interactions(vec![]);
     }
@@ -393,13 +651,16 @@ mod public_tests {
             inputs_sent_count: 1,
             last_event_is_finish_epoch: false,
             last_timestamp: 2,
+            last_block_number: 0,
+            epoch_checkpoints: vec![],
             genesis_timestamp: 0,
-            epoch_length: 4,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(4),
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
         let broker = mock::Broker::with_finish_epoch_error();
-        let result = context.finish_epoch_if_needed(4, &broker).await;
+        let result = context.finish_epoch_if_needed(4, 0, &broker).await;
         assert!(result.is_err());
     }
 
@@ -414,8 +675,11 @@ mod public_tests {
             inputs_sent_count,
             last_event_is_finish_epoch: true,
             last_timestamp: 0,    // ignored
-            genesis_timestamp: 0, // ignored
-            epoch_length: 0,      // ignored
+            last_block_number: 0, // ignored
+            epoch_checkpoints: vec![],
+            genesis_timestamp: 0,                 // ignored
+            genesis_block_number: 0,              // ignored
+            epoch_policy: EpochPolicy::default(), // ignored
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
@@ -436,8 +700,11 @@ mod public_tests {
             inputs_sent_count: 42,
             last_event_is_finish_epoch: true,
             last_timestamp: 0,    // ignored
-            genesis_timestamp: 0, // ignored
-            epoch_length: 0,      // ignored
+            last_block_number: 0, // ignored
+            epoch_checkpoints: vec![],
+            genesis_timestamp: 0,                 // ignored
+            genesis_block_number: 0,              // ignored
+            epoch_policy: EpochPolicy::default(), // ignored
             dapp_metadata: DAppMetadata::default(),
             metrics: DispatcherMetrics::default(),
         };
@@ -445,4 +712,214 @@ mod public_tests {
         let result = context.enqueue_input(&mock::new_input(2), &broker).await;
         assert!(result.is_err());
     }
+
+    // --------------------------------------------------------------------------------------------
+    // rewind
+    // --------------------------------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn rewind_to_previous_epoch() {
+        let mut context = Context {
+            inputs_sent_count: 5,
+            last_event_is_finish_epoch: true,
+            last_timestamp: 15,
+            last_block_number: 0,
+            epoch_checkpoints: vec![(2, 5, 0), (3, 10, 0), (5, 15, 0)],
+            genesis_timestamp: 0,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(5),
+            dapp_metadata: DAppMetadata::default(),
+            metrics: DispatcherMetrics::default(),
+        };
+        let broker = mock::Broker::with_rewind_status(RollupStatus {
+            inputs_sent_count: 3,
+            last_event_is_finish_epoch: false,
+        });
+        let result = context.rewind(None, &broker).await;
+        assert!(result.is_ok());
+        assert_eq!(context.inputs_sent_count, 3);
+        assert!(!context.last_event_is_finish_epoch);
+        assert_eq!(context.last_timestamp, 5);
+        assert_eq!(context.epoch_checkpoints, vec![(2, 5, 0)]);
+        broker.assert_send_interactions(vec![SendInteraction::Rewound]);
+    }
+
+    #[tokio::test]
+    async fn rewind_to_genesis() {
+        let mut context = Context {
+            inputs_sent_count: 2,
+            last_event_is_finish_epoch: true,
+            last_timestamp: 5,
+            last_block_number: 0,
+            epoch_checkpoints: vec![(2, 5, 0)],
+            genesis_timestamp: 1,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(5),
+            dapp_metadata: DAppMetadata::default(),
+            metrics: DispatcherMetrics::default(),
+        };
+        let broker = mock::Broker::with_rewind_status(RollupStatus::default());
+        let result = context.rewind(None, &broker).await;
+        assert!(result.is_ok());
+        assert_eq!(context.inputs_sent_count, 0);
+        assert!(!context.last_event_is_finish_epoch);
+        assert_eq!(context.last_timestamp, 1);
+        assert!(context.epoch_checkpoints.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rewind_not_needed() {
+        let mut context = Context {
+            inputs_sent_count: 2,
+            last_event_is_finish_epoch: true,
+            last_timestamp: 5,
+            last_block_number: 0,
+            epoch_checkpoints: vec![(2, 5, 0)],
+            genesis_timestamp: 0,
+            genesis_block_number: 0,
+            epoch_policy: mock::new_duration_policy(5),
+            dapp_metadata: DAppMetadata::default(),
+            metrics: DispatcherMetrics::default(),
+        };
+        let broker = mock::Broker::new(vec![], vec![]);
+        let result = context.rewind(None, &broker).await;
+        assert!(result.is_ok());
+        assert_eq!(context.inputs_sent_count, 2);
+        assert!(context.last_event_is_finish_epoch);
+        assert_eq!(context.last_timestamp, 5);
+        assert_eq!(context.epoch_checkpoints, vec![(2, 5, 0)]);
+    }
+
+    // --------------------------------------------------------------------------------------------
+    // restart
+    // --------------------------------------------------------------------------------------------
+
+    fn new_restarted_context(
+        status: RollupStatus,
+        epoch_start: EpochStart,
+    ) -> Context {
+        let policy = EpochPolicy {
+            inputs: Some(3),
+            blocks: Some(5),
+            ..Default::default()
+        };
+        Context::new(
+            10,
+            100,
+            policy,
+            DAppMetadata::default(),
+            DispatcherMetrics::default(),
+            status,
+            Some(epoch_start),
+        )
+    }
+
+    fn new_epoch_start(
+        inputs_sent_count: u64,
+        timestamp: u64,
+        block_number: u64,
+    ) -> EpochStart {
+        EpochStart {
+            inputs_sent_count,
+            boundary: Some(EpochBoundary {
+                timestamp,
+                block_number,
+            }),
+        }
+    }
+
+    fn new_input_at(timestamp: u32, block_number: u64) -> Input {
+        let mut block = mock::new_block(timestamp);
+        block.number = block_number.into();
+        Input {
+            block_added: Arc::new(block),
+            ..mock::new_input(timestamp)
+        }
+    }
+
+    #[tokio::test]
+    async fn restart_partway_through_epoch() {
+        let status = RollupStatus {
+            inputs_sent_count: 5,
+            last_event_is_finish_epoch: false,
+        };
+        let epoch_start = new_epoch_start(3, 20, 107);
+        let mut context = new_restarted_context(status, epoch_start);
+        assert_eq!(context.epoch_checkpoints, vec![(3, 20, 107)]);
+        assert_eq!(context.last_block_number, 107);
+
+        let broker = mock::Broker::new(vec![], vec![]);
+        let result = context.finish_epoch_if_needed(30, 109, &broker).await;
+        assert!(result.is_ok());
+        let result =
+            context.enqueue_input(&new_input_at(30, 109), &broker).await;
+        assert!(result.is_ok());
+        let result = context.finish_epoch_if_needed(30, 109, &broker).await;
+        assert!(result.is_ok());
+        broker.assert_send_interactions(vec![
+            SendInteraction::EnqueuedInput(5),
+            SendInteraction::FinishedEpoch(6),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn restart_after_finish_epoch() {
+        let status = RollupStatus {
+            inputs_sent_count: 3,
+            last_event_is_finish_epoch: true,
+        };
+        let epoch_start = new_epoch_start(3, 20, 107);
+        let mut context = new_restarted_context(status, epoch_start);
+        assert_eq!(context.epoch_checkpoints, vec![(3, 20, 107)]);
+
+        // The epoch is counted from the block that finished the previous one
+        // rather than from its first input
+        let broker = mock::Broker::new(vec![], vec![]);
+        let result =
+            context.enqueue_input(&new_input_at(25, 108), &broker).await;
+        assert!(result.is_ok());
+        assert_eq!(context.epoch_checkpoints, vec![(3, 20, 107)]);
+        assert_eq!(context.last_block_number, 107);
+        assert!(!context.should_finish_epoch(30, 109));
+        assert!(context.should_finish_epoch(30, 110));
+    }
+
+    #[tokio::test]
+    async fn restart_after_finish_epoch_without_boundary() {
+        let status = RollupStatus {
+            inputs_sent_count: 3,
+            last_event_is_finish_epoch: true,
+        };
+        let epoch_start = EpochStart {
+            inputs_sent_count: 3,
+            boundary: None,
+        };
+        let context = new_restarted_context(status, epoch_start);
+        assert_eq!(context.epoch_checkpoints, vec![(3, 10, 100)]);
+        assert_eq!(context.last_timestamp, 10);
+        assert_eq!(context.last_block_number, 100);
+    }
+
+    #[tokio::test]
+    async fn rewind_before_restored_epoch_start() {
+        let status = RollupStatus {
+            inputs_sent_count: 5,
+            last_event_is_finish_epoch: false,
+        };
+        let epoch_start = new_epoch_start(3, 20, 107);
+        let mut context = new_restarted_context(status, epoch_start);
+        let broker = mock::Broker::with_epoch_start(
+            RollupStatus {
+                inputs_sent_count: 2,
+                last_event_is_finish_epoch: false,
+            },
+            new_epoch_start(1, 15, 103),
+        );
+        let result = context.rewind(None, &broker).await;
+        assert!(result.is_ok());
+        assert_eq!(context.inputs_sent_count, 2);
+        assert_eq!(context.epoch_checkpoints, vec![(1, 15, 103)]);
+        assert_eq!(context.last_timestamp, 15);
+        assert_eq!(context.last_block_number, 103);
+    }
 }
@@ -3,7 +3,9 @@
 
 use super::Context;
 
-use crate::machine::{rollups_broker::BrokerFacadeError, BrokerSend};
+use crate::machine::{
+    rollups_broker::BrokerFacadeError, BrokerSend, BrokerStatus,
+};
 
 use eth_state_fold_types::{ethereum_types::Address, Block};
 use types::foldables::{DAppInputBox, Input, InputBox};
@@ -40,11 +42,32 @@ impl MachineDriver {
         self.process_inputs(context, dapp_input_box, broker).await?;
 
         context
-            .finish_epoch_if_needed(block.timestamp.as_u64(), broker)
+            .finish_epoch_if_needed(
+                block.timestamp.as_u64(),
+                block.number.as_u64(),
+                broker,
+            )
             .await?;
 
         Ok(())
     }
+
+    /// Rewind the context and the broker after a reorg, so the next call to
+    /// `react` sends the inputs of the new chain
+    #[instrument(level = "trace", skip_all)]
+    pub async fn rewind(
+        &self,
+        context: &mut Context,
+        input_box: &InputBox,
+        broker: &(impl BrokerSend + BrokerStatus),
+    ) -> Result<(), BrokerFacadeError> {
+        let dapp_input_box = input_box
+            .dapp_input_boxes
+            .get(&self.dapp_address)
+            .map(|d| d.as_ref());
+
+        context.rewind(dapp_input_box, broker).await
+    }
 }
 
 impl MachineDriver {
@@ -80,10 +103,11 @@ impl MachineDriver {
         broker: &impl BrokerSend,
     ) -> Result<(), BrokerFacadeError> {
         let input_timestamp = input.block_added.timestamp.as_u64();
-        trace!(?context, ?input_timestamp);
+        let input_block_number = input.block_added.number.as_u64();
+        trace!(?context, ?input_timestamp, ?input_block_number);
 
         context
-            .finish_epoch_if_needed(input_timestamp, broker)
+            .finish_epoch_if_needed(input_timestamp, input_block_number, broker)
             .await?;
 
         context.enqueue_input(input, broker).await?;
@@ -100,6 +124,7 @@ mod tests {
 
     use crate::{
         drivers::{
+            context::EpochPolicy,
             mock::{self, SendInteraction},
             Context,
         },
@@ -121,10 +146,12 @@ mod tests {
         let broker = mock::Broker::new(vec![rollup_status], Vec::new());
         let mut context = Context::new(
             0,
-            5,
+            0,
+            mock::new_duration_policy(5),
             DAppMetadata::default(),
             DispatcherMetrics::default(),
             rollup_status,
+            None,
         );
         let machine_driver = MachineDriver::new(H160::random());
         for block_timestamp in input_timestamps {
@@ -216,6 +243,43 @@ mod tests {
             .await;
     }
 
+    #[tokio::test]
+    async fn process_input_with_inputs_policy() {
+        let rollup_status = RollupStatus::default();
+        let broker = mock::Broker::new(vec![rollup_status], Vec::new());
+        let epoch_policy = EpochPolicy {
+            inputs: Some(2),
+            ..Default::default()
+        };
+        let mut context = Context::new(
+            0,
+            0,
+            epoch_policy,
+            DAppMetadata::default(),
+            DispatcherMetrics::default(),
+            rollup_status,
+            None,
+        );
+        let machine_driver = MachineDriver::new(H160::random());
+        for _ in 0..5 {
+            let input = mock::new_input(0);
+            let result = machine_driver
+                .process_input(&mut context, &input, &broker)
+                .await;
+            assert!(result.is_ok());
+        }
+
+        broker.assert_send_interactions(vec![
+            SendInteraction::EnqueuedInput(0),
+            SendInteraction::EnqueuedInput(1),
+            SendInteraction::FinishedEpoch(2),
+            SendInteraction::EnqueuedInput(2),
+            SendInteraction::EnqueuedInput(3),
+            SendInteraction::FinishedEpoch(4),
+            SendInteraction::EnqueuedInput(4),
+        ]);
+    }
+
     // --------------------------------------------------------------------------------------------
     // process_inputs
     // --------------------------------------------------------------------------------------------
@@ -228,10 +292,12 @@ mod tests {
         let broker = mock::Broker::new(vec![rollup_status], Vec::new());
         let mut context = Context::new(
             0,
-            5,
+            0,
+            mock::new_duration_policy(5),
             DAppMetadata::default(),
             DispatcherMetrics::default(),
             rollup_status,
+            None,
         );
         let machine_driver = MachineDriver::new(H160::random());
         let dapp_input_box = types::foldables::DAppInputBox {
@@ -303,10 +369,12 @@ mod tests {
         let broker = mock::Broker::new(vec![rollup_status], Vec::new());
         let mut context = Context::new(
             0,
-            5,
+            0,
+            mock::new_duration_policy(5),
             DAppMetadata::default(),
             DispatcherMetrics::default(),
             rollup_status,
+            None,
         );
 
         let dapp_address = H160::random();
@@ -383,10 +451,12 @@ mod tests {
         let broker = mock::Broker::new(vec![rollup_status], Vec::new());
         let mut context = Context::new(
             0,
-            5,
+            0,
+            mock::new_duration_policy(5),
             DAppMetadata::default(),
             DispatcherMetrics::default(),
             rollup_status,
+            None,
         );
         let block = mock::new_block(5);
         let input_box = mock::new_input_box();
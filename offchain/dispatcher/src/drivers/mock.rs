@@ -7,7 +7,7 @@ use eth_state_fold_types::{
     Block,
 };
 use im::{hashmap, Vector};
-use rollups_events::RollupsClaim;
+use rollups_events::{EpochBoundary, EpochStart, RollupsClaim};
 use snafu::whatever;
 use std::{
     collections::VecDeque,
@@ -16,14 +16,25 @@ use std::{
 };
 use types::foldables::{DAppInputBox, Input, InputBox};
 
-use crate::machine::{
-    rollups_broker::BrokerFacadeError, BrokerSend, BrokerStatus, RollupStatus,
+use crate::{
+    drivers::context::EpochPolicy,
+    machine::{
+        rollups_broker::BrokerFacadeError, BrokerSend, BrokerStatus,
+        RollupStatus,
+    },
 };
 
 // ------------------------------------------------------------------------------------------------
 // auxiliary functions
 // ------------------------------------------------------------------------------------------------
 
+pub fn new_duration_policy(duration: u64) -> EpochPolicy {
+    EpochPolicy {
+        duration: Some(duration),
+        ..Default::default()
+    }
+}
+
 pub fn new_block(timestamp: u32) -> Block {
     Block {
         hash: H256::random(),
@@ -80,6 +91,7 @@ pub fn update_input_box(
 pub enum SendInteraction {
     EnqueuedInput(u64),
     FinishedEpoch(u64),
+    Rewound,
 }
 
 #[derive(Debug)]
@@ -87,6 +99,8 @@ pub struct Broker {
     pub rollup_statuses: Mutex<VecDeque<RollupStatus>>,
     pub next_claims: Mutex<VecDeque<RollupsClaim>>,
     pub send_interactions: Mutex<Vec<SendInteraction>>,
+    rewind_status: Option<RollupStatus>,
+    epoch_start: Option<EpochStart>,
     status_error: bool,
     enqueue_input_error: bool,
     finish_epoch_error: bool,
@@ -98,6 +112,8 @@ impl Broker {
             rollup_statuses: Mutex::new(VecDeque::new()),
             next_claims: Mutex::new(VecDeque::new()),
             send_interactions: Mutex::new(Vec::new()),
+            rewind_status: None,
+            epoch_start: None,
             status_error: false,
             enqueue_input_error: false,
             finish_epoch_error: false,
@@ -126,6 +142,21 @@ impl Broker {
         broker
     }
 
+    pub fn with_rewind_status(rewind_status: RollupStatus) -> Self {
+        let mut broker = Self::default();
+        broker.rewind_status = Some(rewind_status);
+        broker
+    }
+
+    pub fn with_epoch_start(
+        rewind_status: RollupStatus,
+        epoch_start: EpochStart,
+    ) -> Self {
+        let mut broker = Self::with_rewind_status(rewind_status);
+        broker.epoch_start = Some(epoch_start);
+        broker
+    }
+
     fn send_interactions_len(&self) -> usize {
         let mutex_guard = self.send_interactions.lock().unwrap();
         mutex_guard.deref().len()
@@ -162,6 +193,12 @@ impl BrokerStatus for Broker {
             Ok(mutex_guard.deref_mut().pop_front().unwrap())
         }
     }
+
+    async fn epoch_start(
+        &self,
+    ) -> Result<Option<EpochStart>, BrokerFacadeError> {
+        Ok(self.epoch_start)
+    }
 }
 
 #[async_trait]
@@ -185,6 +222,7 @@ impl BrokerSend for Broker {
     async fn finish_epoch(
         &self,
         inputs_sent_count: u64,
+        _: EpochBoundary,
     ) -> Result<(), BrokerFacadeError> {
         if self.finish_epoch_error {
             whatever!("finish_epoch error")
@@ -196,4 +234,13 @@ impl BrokerSend for Broker {
             Ok(())
         }
     }
+
+    async fn rewind(
+        &self,
+        _: Option<&DAppInputBox>,
+    ) -> Result<Option<RollupStatus>, BrokerFacadeError> {
+        let mut mutex_guard = self.send_interactions.lock().unwrap();
+        mutex_guard.deref_mut().push(SendInteraction::Rewound);
+        Ok(self.rewind_status)
+    }
 }
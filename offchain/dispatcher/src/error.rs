@@ -6,7 +6,7 @@ use snafu::Snafu;
 use std::net::AddrParseError;
 use tonic::{codegen::http::uri::InvalidUri, transport::Error as TonicError};
 
-use crate::machine;
+use crate::{dapps, machine};
 
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub(crate)))]
@@ -34,6 +34,9 @@ pub enum DispatcherError {
     #[snafu(display("can't start dispatcher with dirty broker"))]
     DirtyBrokerError {},
 
+    #[snafu(display("failed to load dapps"))]
+    DAppsError { source: dapps::DAppsError },
+
     #[snafu(whatever, display("{message}"))]
     Whatever {
         message: String,
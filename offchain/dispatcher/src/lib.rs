@@ -5,6 +5,7 @@ pub mod config;
 pub mod dispatcher;
 pub mod machine;
 
+mod dapps;
 mod drivers;
 mod error;
 mod metrics;
@@ -3,7 +3,8 @@
 
 pub mod rollups_broker;
 
-use types::foldables::Input;
+use rollups_events::{EpochBoundary, EpochStart};
+use types::foldables::{DAppInputBox, Input};
 
 use async_trait::async_trait;
 
@@ -18,6 +19,11 @@ pub struct RollupStatus {
 #[async_trait]
 pub trait BrokerStatus: std::fmt::Debug {
     async fn status(&self) -> Result<RollupStatus, BrokerFacadeError>;
+
+    /// Start of the current epoch, or None if no epoch was finished yet
+    async fn epoch_start(
+        &self,
+    ) -> Result<Option<EpochStart>, BrokerFacadeError>;
 }
 
 #[async_trait]
@@ -27,8 +33,18 @@ pub trait BrokerSend: std::fmt::Debug {
         input_index: u64,
         input: &Input,
     ) -> Result<(), BrokerFacadeError>;
+    /// Finish the epoch at the given block, which starts the next epoch
     async fn finish_epoch(
         &self,
         inputs_sent_count: u64,
+        boundary: EpochBoundary,
     ) -> Result<(), BrokerFacadeError>;
+
+    /// Rewind the inputs stream to the last event that is still valid for the
+    /// given inputs, which come from the chain after a reorg.
+    /// Return the status at that event, or None if all sent inputs are valid.
+    async fn rewind(
+        &self,
+        dapp_input_box: Option<&DAppInputBox>,
+    ) -> Result<Option<RollupStatus>, BrokerFacadeError>;
 }
@@ -2,15 +2,18 @@
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
 use async_trait::async_trait;
-use snafu::{ResultExt, Snafu};
+use snafu::{OptionExt, ResultExt, Snafu};
+use std::collections::VecDeque;
 use tokio::sync::{self, Mutex};
 
 use rollups_events::{
-    Broker, BrokerConfig, BrokerError, DAppMetadata, Event, InputMetadata,
-    RollupsAdvanceStateInput, RollupsData, RollupsInput, RollupsInputsStream,
-    INITIAL_ID,
+    Broker, BrokerConfig, BrokerError, DAppMetadata, EpochBoundary, EpochStart,
+    Event, InputMetadata, RetentionConfig, RollupsAdvanceStateInput,
+    RollupsData, RollupsInput, RollupsInputsCheckpoint,
+    RollupsInputsCheckpointsStream, RollupsInputsStream, StreamTrimmer,
+    DISPATCHER_CONSUMER, INITIAL_ID,
 };
-use types::foldables::Input;
+use types::foldables::{DAppInputBox, Input};
 
 use super::{BrokerSend, BrokerStatus, RollupStatus};
 
@@ -28,6 +31,21 @@ pub enum BrokerFacadeError {
     #[snafu(display("error producing finish-epoch event"))]
     ProduceFinishError { source: BrokerError },
 
+    #[snafu(display("error getting parent event"))]
+    GetParentError { source: BrokerError },
+
+    #[snafu(display("parent event {} not found", id))]
+    ParentNotFoundError { id: String },
+
+    #[snafu(display("error loading the last checkpoint"))]
+    LoadCheckpointError { source: BrokerError },
+
+    #[snafu(display("error storing the checkpoint"))]
+    StoreCheckpointError { source: BrokerError },
+
+    #[snafu(display("error acknowledging the start of the epoch"))]
+    AcknowledgeEpochStartError { source: BrokerError },
+
     #[snafu(whatever, display("{message}"))]
     Whatever {
         message: String,
@@ -40,6 +58,28 @@ pub enum BrokerFacadeError {
 pub struct BrokerFacade {
     broker: Mutex<Broker>,
     inputs_stream: RollupsInputsStream,
+    checkpoints_stream: RollupsInputsCheckpointsStream,
+    rewind: Mutex<Option<RewindState>>,
+    /// Start of the epoch of the last valid event
+    epoch_start: Mutex<Option<EpochStart>>,
+    retention: RetentionConfig,
+}
+
+/// State of the facade after rewinding the inputs stream
+///
+/// While there are dropped events, the end of the stream is not the last
+/// valid event, so the facade keeps the head of the valid chain. The number of
+/// dropped events is stored in the checkpoints stream, so the state survives a
+/// restart of the dispatcher.
+#[derive(Debug)]
+struct RewindState {
+    /// Last valid event; None if the rewind went back to the beginning
+    head: Option<Event<RollupsInput>>,
+
+    /// Events dropped by the rewind, from the oldest to the newest.
+    /// If the dispatcher produces the same events again, it reuses them
+    /// instead of producing new ones, so consumers don't have to roll back.
+    dropped: VecDeque<Event<RollupsInput>>,
 }
 
 struct BrokerStreamStatus {
@@ -55,23 +95,226 @@ impl BrokerFacade {
         dapp_metadata: DAppMetadata,
     ) -> Result<Self, BrokerFacadeError> {
         tracing::trace!(?config, "connection to the broker");
+        let retention = config.retention.clone();
+        let mut broker =
+            Broker::new(config).await.context(BrokerConnectionSnafu)?;
+        let inputs_stream = RollupsInputsStream::new(&dapp_metadata);
+        let checkpoints_stream =
+            RollupsInputsCheckpointsStream::new(&dapp_metadata);
+        let checkpoint = broker
+            .peek_latest(&checkpoints_stream)
+            .await
+            .context(LoadCheckpointSnafu)?
+            .map(|event| event.payload)
+            .unwrap_or_default();
+        tracing::trace!(?checkpoint, "loaded checkpoint");
+        let rewind =
+            load_rewind(&mut broker, &inputs_stream, checkpoint.dropped_count)
+                .await?;
         Ok(Self {
-            broker: Mutex::new(
-                Broker::new(config).await.context(BrokerConnectionSnafu)?,
-            ),
-            inputs_stream: RollupsInputsStream::new(&dapp_metadata),
+            broker: Mutex::new(broker),
+            inputs_stream,
+            checkpoints_stream,
+            rewind: Mutex::new(rewind),
+            epoch_start: Mutex::new(checkpoint.epoch_start),
+            retention,
         })
     }
 
+    /// Create the task that trims the streams of the dispatcher
+    /// Only the latest checkpoint is needed, so the dispatcher acknowledges
+    /// each checkpoint it produces. The inputs stream is trimmed by the
+    /// advance-runner, which waits for the acknowledgement of the dispatcher.
+    pub async fn stream_trimmer(&self) -> StreamTrimmer {
+        let broker = self.broker.lock().await.clone();
+        let mut trimmer = StreamTrimmer::new(broker, self.retention.clone());
+        trimmer.add_stream(&self.checkpoints_stream, &[DISPATCHER_CONSUMER]);
+        trimmer
+    }
+
     #[tracing::instrument(level = "trace", skip_all)]
     async fn broker_status(
         &self,
         broker: &mut sync::MutexGuard<'_, Broker>,
     ) -> Result<BrokerStreamStatus, BrokerFacadeError> {
-        let event = self.peek(broker).await?;
+        let event = self.head(broker).await?;
         Ok(event.into())
     }
 
+    /// Get the last valid event, which is the end of the stream unless the
+    /// stream was rewound
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn head(
+        &self,
+        broker: &mut sync::MutexGuard<'_, Broker>,
+    ) -> Result<Option<Event<RollupsInput>>, BrokerFacadeError> {
+        match self.rewind.lock().await.as_ref() {
+            Some(rewind) => Ok(rewind.head.clone()),
+            None => self.peek(broker).await,
+        }
+    }
+
+    /// Produce the event, or reuse the next dropped event if it is the same
+    /// The event is produced along with a checkpoint if it finishes an epoch
+    /// or ends a rewind.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn produce(
+        &self,
+        broker: &mut sync::MutexGuard<'_, Broker>,
+        event: RollupsInput,
+    ) -> Result<String, BrokerFacadeError> {
+        let produce_error = match event.data {
+            RollupsData::AdvanceStateInput(_) => produce_input_error,
+            RollupsData::FinishEpoch { .. } => produce_finish_error,
+        };
+        let is_input = matches!(event.data, RollupsData::AdvanceStateInput(_));
+        let mut epoch_start = self.epoch_start.lock().await;
+        let next_epoch_start = match event.data {
+            RollupsData::AdvanceStateInput(_) => *epoch_start,
+            RollupsData::FinishEpoch { boundary } => Some(EpochStart {
+                inputs_sent_count: event.inputs_sent_count,
+                boundary,
+            }),
+        };
+
+        let mut rewind_guard = self.rewind.lock().await;
+        let reused = match rewind_guard.as_mut() {
+            Some(rewind) => match rewind.dropped.pop_front() {
+                Some(dropped) if dropped.payload == event => Some(dropped.id),
+                _ => {
+                    rewind.dropped.clear();
+                    None
+                }
+            },
+            None => None,
+        };
+        let checkpoint = RollupsInputsCheckpoint {
+            dropped_count: rewind_guard
+                .as_ref()
+                .map_or(0, |rewind| rewind.dropped.len() as u64),
+            epoch_start: next_epoch_start,
+        };
+
+        let id = match reused {
+            Some(id) => {
+                tracing::trace!(id, "reusing dropped event");
+                self.store_checkpoint(broker, checkpoint).await?;
+                id
+            }
+            // Appending an input doesn't change the checkpoint
+            None if rewind_guard.is_none() && is_input => broker
+                .produce(&self.inputs_stream, event.clone())
+                .await
+                .map_err(produce_error)?,
+            None => {
+                let mut transaction = broker.transaction();
+                transaction
+                    .produce(&self.inputs_stream, event.clone())
+                    .map_err(produce_error)?;
+                transaction
+                    .produce(&self.checkpoints_stream, checkpoint)
+                    .context(StoreCheckpointSnafu)?;
+                let ids =
+                    broker.commit(transaction).await.map_err(produce_error)?;
+                self.acknowledge_checkpoint(broker, &ids[1]).await?;
+                ids[0].clone()
+            }
+        };
+
+        if !is_input {
+            self.acknowledge_epoch_start(broker, &id).await?;
+        }
+
+        *epoch_start = next_epoch_start;
+        if let Some(rewind) = rewind_guard.as_mut() {
+            rewind.head = Some(Event {
+                id: id.clone(),
+                payload: event,
+            });
+            if rewind.dropped.is_empty() {
+                // The end of the stream is the last valid event again
+                *rewind_guard = None;
+            }
+        }
+
+        Ok(id)
+    }
+
+    /// Store the checkpoint in the checkpoints stream
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn store_checkpoint(
+        &self,
+        broker: &mut sync::MutexGuard<'_, Broker>,
+        checkpoint: RollupsInputsCheckpoint,
+    ) -> Result<(), BrokerFacadeError> {
+        tracing::trace!(?checkpoint, "storing checkpoint");
+        let id = broker
+            .produce(&self.checkpoints_stream, checkpoint)
+            .await
+            .context(StoreCheckpointSnafu)?;
+        self.acknowledge_checkpoint(broker, &id).await
+    }
+
+    /// Allow the trimmer to remove the checkpoints before the given one
+    async fn acknowledge_checkpoint(
+        &self,
+        broker: &mut sync::MutexGuard<'_, Broker>,
+        id: &str,
+    ) -> Result<(), BrokerFacadeError> {
+        broker
+            .acknowledge(&self.checkpoints_stream, DISPATCHER_CONSUMER, id)
+            .await
+            .context(StoreCheckpointSnafu)
+    }
+
+    /// Allow the trimmer to remove the inputs before the finish epoch that
+    /// starts the current epoch
+    /// A rewind walks back from the end of the stream, so the dispatcher can't
+    /// rewind the inputs past the trimmed ones.
+    async fn acknowledge_epoch_start(
+        &self,
+        broker: &mut sync::MutexGuard<'_, Broker>,
+        id: &str,
+    ) -> Result<(), BrokerFacadeError> {
+        tracing::trace!(id, "acknowledging the start of the epoch");
+        broker
+            .acknowledge(&self.inputs_stream, DISPATCHER_CONSUMER, id)
+            .await
+            .context(AcknowledgeEpochStartSnafu)
+    }
+
+    /// Find the start of the epoch of the event by walking back to the last
+    /// finish epoch
+    /// This is only needed when a rewind drops the finish epoch that started
+    /// the current epoch.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn find_epoch_start(
+        &self,
+        broker: &mut sync::MutexGuard<'_, Broker>,
+        mut head: Option<Event<RollupsInput>>,
+    ) -> Result<Option<EpochStart>, BrokerFacadeError> {
+        while let Some(event) = head {
+            if let RollupsData::FinishEpoch { boundary } = event.payload.data {
+                return Ok(Some(EpochStart {
+                    inputs_sent_count: event.payload.inputs_sent_count,
+                    boundary,
+                }));
+            }
+            head = self.parent(broker, &event.payload.parent_id).await?;
+        }
+        Ok(None)
+    }
+
+    /// Get the parent of an event, or None if the event is the first one
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn parent(
+        &self,
+        broker: &mut sync::MutexGuard<'_, Broker>,
+        parent_id: &str,
+    ) -> Result<Option<Event<RollupsInput>>, BrokerFacadeError> {
+        get_parent(broker, &self.inputs_stream, parent_id).await
+    }
+
     #[tracing::instrument(level = "trace", skip_all)]
     async fn peek(
         &self,
@@ -98,6 +341,15 @@ impl BrokerStatus for BrokerFacade {
         tracing::trace!(?status, "returning rollup status");
         Ok(status)
     }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn epoch_start(
+        &self,
+    ) -> Result<Option<EpochStart>, BrokerFacadeError> {
+        let epoch_start = *self.epoch_start.lock().await;
+        tracing::trace!(?epoch_start, "returning epoch start");
+        Ok(epoch_start)
+    }
 }
 
 macro_rules! input_sanity_check {
@@ -151,10 +403,7 @@ impl BrokerSend for BrokerFacade {
 
         input_sanity_check!(event, input_index);
 
-        let id = broker
-            .produce(&self.inputs_stream, event)
-            .await
-            .context(ProduceInputSnafu)?;
+        let id = self.produce(&mut broker, event).await?;
         tracing::trace!(id, "produced event with id");
 
         Ok(())
@@ -164,26 +413,147 @@ impl BrokerSend for BrokerFacade {
     async fn finish_epoch(
         &self,
         inputs_sent_count: u64,
+        boundary: EpochBoundary,
     ) -> Result<(), BrokerFacadeError> {
-        tracing::info!(?inputs_sent_count, "finishing epoch");
+        tracing::info!(?inputs_sent_count, ?boundary, "finishing epoch");
 
         let mut broker = self.broker.lock().await;
         let status = self.broker_status(&mut broker).await?;
 
-        let event = build_next_finish_epoch(&status);
+        let event = build_next_finish_epoch(&status, boundary);
         tracing::trace!(?event, "producing finish epoch event");
 
         epoch_sanity_check!(event, inputs_sent_count);
 
-        let id = broker
-            .produce(&self.inputs_stream, event)
-            .await
-            .context(ProduceFinishSnafu)?;
+        let id = self.produce(&mut broker, event).await?;
 
         tracing::trace!(id, "produce event with id");
 
         Ok(())
     }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn rewind(
+        &self,
+        dapp_input_box: Option<&DAppInputBox>,
+    ) -> Result<Option<RollupStatus>, BrokerFacadeError> {
+        tracing::trace!("looking for the last valid event");
+
+        let mut broker = self.broker.lock().await;
+        let mut head = self.head(&mut broker).await?;
+        let mut dropped = VecDeque::new();
+        let mut diverged = false;
+
+        // Walk back from the head until finding an input that is still valid
+        while let Some(event) = head {
+            if let RollupsData::AdvanceStateInput(_) = event.payload.data {
+                let input_index = event.payload.inputs_sent_count - 1;
+                let is_valid = dapp_input_box
+                    .and_then(|d| d.inputs.get(input_index as usize))
+                    .map(|input| {
+                        build_advance_state_input(input, input_index)
+                            == event.payload.data
+                    })
+                    .unwrap_or(false);
+                if is_valid {
+                    head = Some(event);
+                    break;
+                }
+                diverged = true;
+            }
+
+            head = self.parent(&mut broker, &event.payload.parent_id).await?;
+            dropped.push_front(event);
+        }
+
+        if !diverged {
+            tracing::trace!("all sent inputs are still valid");
+            return Ok(None);
+        }
+
+        let mut epoch_start = self.epoch_start.lock().await;
+        let dropped_epoch = dropped.iter().any(|event| {
+            matches!(event.payload.data, RollupsData::FinishEpoch { .. })
+        });
+        let next_epoch_start = if dropped_epoch {
+            self.find_epoch_start(&mut broker, head.clone()).await?
+        } else {
+            *epoch_start
+        };
+
+        let mut rewind = self.rewind.lock().await;
+        if let Some(previous) = rewind.as_ref() {
+            dropped.extend(previous.dropped.iter().cloned());
+        }
+        let status = BrokerStreamStatus::from(head.clone());
+        tracing::info!(
+            parent_id = status.id,
+            dropped = dropped.len(),
+            "rewinding inputs stream"
+        );
+        let checkpoint = RollupsInputsCheckpoint {
+            dropped_count: dropped.len() as u64,
+            epoch_start: next_epoch_start,
+        };
+        self.store_checkpoint(&mut broker, checkpoint).await?;
+        *epoch_start = next_epoch_start;
+        *rewind = Some(RewindState { head, dropped });
+
+        Ok(Some(status.status))
+    }
+}
+
+fn produce_input_error(source: BrokerError) -> BrokerFacadeError {
+    BrokerFacadeError::ProduceInputError { source }
+}
+
+fn produce_finish_error(source: BrokerError) -> BrokerFacadeError {
+    BrokerFacadeError::ProduceFinishError { source }
+}
+
+/// Get the parent of an event, or None if the event is the first one
+async fn get_parent(
+    broker: &mut Broker,
+    inputs_stream: &RollupsInputsStream,
+    parent_id: &str,
+) -> Result<Option<Event<RollupsInput>>, BrokerFacadeError> {
+    if parent_id == INITIAL_ID {
+        return Ok(None);
+    }
+    let parent = broker
+        .get_event(inputs_stream, parent_id)
+        .await
+        .context(GetParentSnafu)?
+        .context(ParentNotFoundSnafu { id: parent_id })?;
+    Ok(Some(parent))
+}
+
+/// Load the rewind state of the latest checkpoint, if it still has dropped
+/// events
+/// The dropped events are the last ones of the chain that ends at the end of
+/// the inputs stream, so they are found by walking back from there.
+async fn load_rewind(
+    broker: &mut Broker,
+    inputs_stream: &RollupsInputsStream,
+    dropped_count: u64,
+) -> Result<Option<RewindState>, BrokerFacadeError> {
+    if dropped_count == 0 {
+        return Ok(None);
+    }
+    tracing::info!(dropped_count, "resuming the rewind of the inputs stream");
+
+    let mut head = broker
+        .peek_latest(inputs_stream)
+        .await
+        .context(LoadCheckpointSnafu)?;
+    let mut dropped = VecDeque::new();
+    for _ in 0..dropped_count {
+        let event = head.context(ParentNotFoundSnafu { id: INITIAL_ID })?;
+        head =
+            get_parent(broker, inputs_stream, &event.payload.parent_id).await?;
+        dropped.push_front(event);
+    }
+    Ok(Some(RewindState { head, dropped }))
 }
 
 impl From<RollupsInput> for RollupStatus {
@@ -240,23 +610,28 @@ impl From<Option<Event<RollupsInput>>> for BrokerStreamStatus {
     }
 }
 
-fn build_next_input(
-    input: &Input,
-    status: &BrokerStreamStatus,
-) -> RollupsInput {
+fn build_advance_state_input(input: &Input, input_index: u64) -> RollupsData {
     let metadata = InputMetadata {
         msg_sender: input.sender.to_fixed_bytes().into(),
         block_number: input.block_added.number.as_u64(),
         timestamp: input.block_added.timestamp.as_u64(),
         epoch_index: 0,
-        input_index: status.status.inputs_sent_count,
+        input_index,
     };
 
-    let data = RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
+    RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
         metadata,
         payload: input.payload.clone().into(),
         tx_hash: input.tx_hash.0.into(),
-    });
+    })
+}
+
+fn build_next_input(
+    input: &Input,
+    status: &BrokerStreamStatus,
+) -> RollupsInput {
+    let data =
+        build_advance_state_input(input, status.status.inputs_sent_count);
 
     RollupsInput {
         parent_id: status.id.clone(),
@@ -266,12 +641,17 @@ fn build_next_input(
     }
 }
 
-fn build_next_finish_epoch(status: &BrokerStreamStatus) -> RollupsInput {
+fn build_next_finish_epoch(
+    status: &BrokerStreamStatus,
+    boundary: EpochBoundary,
+) -> RollupsInput {
     RollupsInput {
         parent_id: status.id.clone(),
         epoch_index: status.epoch_number,
         inputs_sent_count: status.status.inputs_sent_count,
-        data: RollupsData::FinishEpoch {},
+        data: RollupsData::FinishEpoch {
+            boundary: Some(boundary),
+        },
     }
 }
 
@@ -284,13 +664,16 @@ mod broker_facade_tests {
         ethereum_types::{Bloom, H160, H256, U256, U64},
         Block,
     };
+    use im::Vector;
     use rollups_events::{
-        BrokerConfig, BrokerEndpoint, DAppMetadata, Hash, InputMetadata,
-        Payload, RedactedUrl, RollupsAdvanceStateInput, RollupsData, Url,
+        BrokerConfig, BrokerEndpoint, DAppMetadata, EpochBoundary, EpochStart,
+        Hash, InputMetadata, Payload, RedactedUrl, RetentionPolicy,
+        RollupsAdvanceStateInput, RollupsData, Url, DISPATCHER_CONSUMER,
+        INITIAL_ID,
     };
     use test_fixtures::broker::BrokerFixture;
     use testcontainers::clients::Cli;
-    use types::foldables::Input;
+    use types::foldables::{DAppInputBox, Input};
 
     use crate::machine::{
         rollups_broker::BrokerFacadeError, BrokerSend, BrokerStatus,
@@ -298,6 +681,11 @@ mod broker_facade_tests {
 
     use super::BrokerFacade;
 
+    const BOUNDARY: EpochBoundary = EpochBoundary {
+        timestamp: 20,
+        block_number: 10,
+    };
+
     // --------------------------------------------------------------------------------------------
     // new
     // --------------------------------------------------------------------------------------------
@@ -374,6 +762,157 @@ mod broker_facade_tests {
         assert!(status.last_event_is_finish_epoch);
     }
 
+    // --------------------------------------------------------------------------------------------
+    // epoch_start
+    // --------------------------------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn epoch_start_without_finish_epoch() {
+        let docker = Cli::default();
+        let (_fixture, broker) = setup(&docker).await;
+        enqueue_inputs(&broker, 0, 2).await;
+        let epoch_start = broker
+            .epoch_start()
+            .await
+            .expect("'epoch_start' function failed");
+        assert_eq!(epoch_start, None);
+    }
+
+    #[tokio::test]
+    async fn epoch_start_after_finish_epoch() {
+        let docker = Cli::default();
+        let (_fixture, broker) = setup(&docker).await;
+        enqueue_inputs(&broker, 0, 2).await;
+        assert!(broker.finish_epoch(2, BOUNDARY).await.is_ok());
+        let expected = Some(EpochStart {
+            inputs_sent_count: 2,
+            boundary: Some(BOUNDARY),
+        });
+        let epoch_start = broker
+            .epoch_start()
+            .await
+            .expect("'epoch_start' function failed");
+        assert_eq!(epoch_start, expected);
+
+        enqueue_inputs(&broker, 2, 2).await;
+        let epoch_start = broker
+            .epoch_start()
+            .await
+            .expect("'epoch_start' function failed");
+        assert_eq!(epoch_start, expected);
+    }
+
+    #[tokio::test]
+    async fn epoch_start_is_acknowledged() {
+        let docker = Cli::default();
+        let (_fixture, broker) = setup(&docker).await;
+        enqueue_inputs(&broker, 0, 2).await;
+        assert!(broker.finish_epoch(2, BOUNDARY).await.is_ok());
+        enqueue_inputs(&broker, 2, 1).await;
+
+        // The inputs before the finish epoch may be trimmed
+        let count = broker
+            .broker
+            .lock()
+            .await
+            .trim(
+                &broker.inputs_stream,
+                &RetentionPolicy::Acknowledged,
+                &[DISPATCHER_CONSUMER],
+            )
+            .await
+            .expect("failed to trim");
+        assert_eq!(count, 2);
+    }
+
+    #[tokio::test]
+    async fn epoch_start_is_restored_after_restart() {
+        let docker = Cli::default();
+        let (fixture, broker) = setup(&docker).await;
+        enqueue_inputs(&broker, 0, 2).await;
+        assert!(broker.finish_epoch(2, BOUNDARY).await.is_ok());
+        enqueue_inputs(&broker, 2, 1).await;
+        drop(broker);
+
+        let endpoint = fixture.redis_endpoint().clone();
+        let broker = connect(&fixture, endpoint)
+            .await
+            .expect("failed to restart the broker facade");
+        let epoch_start = broker
+            .epoch_start()
+            .await
+            .expect("'epoch_start' function failed");
+        assert_eq!(
+            epoch_start,
+            Some(EpochStart {
+                inputs_sent_count: 2,
+                boundary: Some(BOUNDARY),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn epoch_start_after_rewind_drops_finish_epoch() {
+        let docker = Cli::default();
+        let (fixture, broker) = setup(&docker).await;
+        let mut inputs = enqueue_inputs(&broker, 0, 1).await;
+        assert!(broker.finish_epoch(1, BOUNDARY).await.is_ok());
+        inputs.extend(enqueue_inputs(&broker, 1, 1).await);
+        let boundary = EpochBoundary {
+            timestamp: 30,
+            block_number: 15,
+        };
+        assert!(broker.finish_epoch(2, boundary).await.is_ok());
+        inputs.extend(enqueue_inputs(&broker, 2, 2).await);
+
+        // The rewind keeps the last finish epoch
+        let status = broker
+            .rewind(Some(&new_dapp_input_box(&inputs[..3])))
+            .await
+            .expect("'rewind' function failed")
+            .expect("'rewind' function returned none");
+        assert_eq!(status.inputs_sent_count, 3);
+        let epoch_start = broker
+            .epoch_start()
+            .await
+            .expect("'epoch_start' function failed");
+        assert_eq!(
+            epoch_start,
+            Some(EpochStart {
+                inputs_sent_count: 2,
+                boundary: Some(boundary),
+            })
+        );
+
+        // The rewind drops the last finish epoch
+        let status = broker
+            .rewind(Some(&new_dapp_input_box(&inputs[..2])))
+            .await
+            .expect("'rewind' function failed")
+            .expect("'rewind' function returned none");
+        assert_eq!(status.inputs_sent_count, 2);
+        let expected = Some(EpochStart {
+            inputs_sent_count: 1,
+            boundary: Some(BOUNDARY),
+        });
+        let epoch_start = broker
+            .epoch_start()
+            .await
+            .expect("'epoch_start' function failed");
+        assert_eq!(epoch_start, expected);
+        drop(broker);
+
+        let endpoint = fixture.redis_endpoint().clone();
+        let broker = connect(&fixture, endpoint)
+            .await
+            .expect("failed to restart the broker facade");
+        let epoch_start = broker
+            .epoch_start()
+            .await
+            .expect("'epoch_start' function failed");
+        assert_eq!(epoch_start, expected);
+    }
+
     // --------------------------------------------------------------------------------------------
     // enqueue_input
     // --------------------------------------------------------------------------------------------
@@ -426,9 +965,9 @@ mod broker_facade_tests {
     async fn finish_epoch_ok_1() {
         let docker = Cli::default();
         let (_fixture, broker) = setup(&docker).await;
-        assert!(broker.finish_epoch(0).await.is_ok());
+        assert!(broker.finish_epoch(0, BOUNDARY).await.is_ok());
         // BONUS TEST: testing for a finished epoch with no inputs
-        assert!(broker.finish_epoch(0).await.is_ok());
+        assert!(broker.finish_epoch(0, BOUNDARY).await.is_ok());
     }
 
     #[tokio::test]
@@ -441,7 +980,10 @@ mod broker_facade_tests {
         let second_epoch_inputs = 7;
         produce_advance_state_inputs(&fixture, second_epoch_inputs).await;
         let total_inputs = first_epoch_inputs + second_epoch_inputs;
-        assert!(broker.finish_epoch(total_inputs as u64).await.is_ok());
+        assert!(broker
+            .finish_epoch(total_inputs as u64, BOUNDARY)
+            .await
+            .is_ok());
     }
 
     #[tokio::test]
@@ -451,11 +993,192 @@ mod broker_facade_tests {
     async fn finish_epoch_assertion_error() {
         let docker = Cli::default();
         let (_fixture, broker) = setup(&docker).await;
-        let _ = broker.finish_epoch(1).await;
+        let _ = broker.finish_epoch(1, BOUNDARY).await;
     }
 
     // NOTE: cannot test result error because the dependency is not injectable.
 
+    // --------------------------------------------------------------------------------------------
+    // rewind
+    // --------------------------------------------------------------------------------------------
+
+    #[tokio::test]
+    async fn rewind_with_valid_inputs() {
+        let docker = Cli::default();
+        let (fixture, broker) = setup(&docker).await;
+        let inputs = enqueue_inputs(&broker, 0, 3).await;
+        assert!(broker.finish_epoch(3, BOUNDARY).await.is_ok());
+        let latest = fixture.get_latest_input_event().await;
+        let status = broker
+            .rewind(Some(&new_dapp_input_box(&inputs)))
+            .await
+            .expect("'rewind' function failed");
+        assert!(status.is_none());
+        assert_eq!(fixture.get_latest_input_event().await, latest);
+        let status = broker.status().await.expect("'status' function failed");
+        assert_eq!(status.inputs_sent_count, 3);
+        assert!(status.last_event_is_finish_epoch);
+    }
+
+    #[tokio::test]
+    async fn rewind_with_diverged_inputs() {
+        let docker = Cli::default();
+        let (fixture, broker) = setup(&docker).await;
+        let mut inputs = enqueue_inputs(&broker, 0, 1).await;
+        let parent = fixture.get_latest_input_event().await.unwrap();
+        enqueue_inputs(&broker, 1, 1).await;
+        assert!(broker.finish_epoch(2, BOUNDARY).await.is_ok());
+        enqueue_inputs(&broker, 2, 1).await;
+
+        inputs.push(new_enqueue_input());
+        let status = broker
+            .rewind(Some(&new_dapp_input_box(&inputs)))
+            .await
+            .expect("'rewind' function failed")
+            .expect("'rewind' function returned none");
+        assert_eq!(status.inputs_sent_count, 1);
+        assert!(!status.last_event_is_finish_epoch);
+        let status = broker.status().await.expect("'status' function failed");
+        assert_eq!(status.inputs_sent_count, 1);
+
+        assert!(broker.enqueue_input(1, &inputs[1]).await.is_ok());
+        let event = fixture.get_latest_input_event().await.unwrap();
+        assert_eq!(event.payload.parent_id, parent.id);
+        assert_eq!(event.payload.inputs_sent_count, 2);
+        assert_eq!(event.payload.epoch_index, 0);
+    }
+
+    #[tokio::test]
+    async fn rewind_to_the_beginning() {
+        let docker = Cli::default();
+        let (fixture, broker) = setup(&docker).await;
+        enqueue_inputs(&broker, 0, 2).await;
+        let status = broker
+            .rewind(None)
+            .await
+            .expect("'rewind' function failed")
+            .expect("'rewind' function returned none");
+        assert_eq!(status.inputs_sent_count, 0);
+        assert!(broker.enqueue_input(0, &new_enqueue_input()).await.is_ok());
+        let event = fixture.get_latest_input_event().await.unwrap();
+        assert_eq!(event.payload.parent_id, INITIAL_ID);
+    }
+
+    #[tokio::test]
+    async fn rewind_is_restored_after_restart() {
+        let docker = Cli::default();
+        let (fixture, broker) = setup(&docker).await;
+        let inputs = enqueue_inputs(&broker, 0, 1).await;
+        let parent = fixture.get_latest_input_event().await.unwrap();
+        enqueue_inputs(&broker, 1, 2).await;
+        let status = broker
+            .rewind(Some(&new_dapp_input_box(&inputs)))
+            .await
+            .expect("'rewind' function failed")
+            .expect("'rewind' function returned none");
+        assert_eq!(status.inputs_sent_count, 1);
+        drop(broker);
+
+        let endpoint = fixture.redis_endpoint().clone();
+        let broker = connect(&fixture, endpoint.clone())
+            .await
+            .expect("failed to restart the broker facade");
+        let status = broker.status().await.expect("'status' function failed");
+        assert_eq!(status.inputs_sent_count, 1);
+        assert!(broker.enqueue_input(1, &new_enqueue_input()).await.is_ok());
+        let event = fixture.get_latest_input_event().await.unwrap();
+        assert_eq!(event.payload.parent_id, parent.id);
+        drop(broker);
+
+        // The rewind is over once a new event is produced
+        let broker = connect(&fixture, endpoint)
+            .await
+            .expect("failed to restart the broker facade");
+        let status = broker.status().await.expect("'status' function failed");
+        assert_eq!(status.inputs_sent_count, 2);
+    }
+
+    #[tokio::test]
+    async fn rewind_reuses_dropped_events() {
+        let docker = Cli::default();
+        let (fixture, broker) = setup(&docker).await;
+        let inputs = enqueue_inputs(&broker, 0, 3).await;
+        let latest = fixture.get_latest_input_event().await;
+
+        let status = broker
+            .rewind(Some(&new_dapp_input_box(&inputs[..1])))
+            .await
+            .expect("'rewind' function failed")
+            .expect("'rewind' function returned none");
+        assert_eq!(status.inputs_sent_count, 1);
+
+        // The reorg brought back the same inputs
+        for (i, input) in inputs.iter().enumerate().skip(1) {
+            assert!(broker.enqueue_input(i as u64, input).await.is_ok());
+        }
+        assert_eq!(fixture.get_latest_input_event().await, latest);
+        let status = broker.status().await.expect("'status' function failed");
+        assert_eq!(status.inputs_sent_count, 3);
+    }
+
+    #[tokio::test]
+    async fn rewind_reuses_dropped_events_after_restart() {
+        let docker = Cli::default();
+        let (fixture, broker) = setup(&docker).await;
+        let inputs = enqueue_inputs(&broker, 0, 3).await;
+        let latest = fixture.get_latest_input_event().await;
+        let status = broker
+            .rewind(Some(&new_dapp_input_box(&inputs[..1])))
+            .await
+            .expect("'rewind' function failed")
+            .expect("'rewind' function returned none");
+        assert_eq!(status.inputs_sent_count, 1);
+        assert!(broker.enqueue_input(1, &inputs[1]).await.is_ok());
+        drop(broker);
+
+        let endpoint = fixture.redis_endpoint().clone();
+        let broker = connect(&fixture, endpoint)
+            .await
+            .expect("failed to restart the broker facade");
+        let status = broker.status().await.expect("'status' function failed");
+        assert_eq!(status.inputs_sent_count, 2);
+        assert!(broker.enqueue_input(2, &inputs[2]).await.is_ok());
+        assert_eq!(fixture.get_latest_input_event().await, latest);
+    }
+
+    #[tokio::test]
+    async fn rewind_is_restored_after_consecutive_rewinds() {
+        let docker = Cli::default();
+        let (fixture, broker) = setup(&docker).await;
+        let inputs = enqueue_inputs(&broker, 0, 3).await;
+        assert!(broker
+            .rewind(Some(&new_dapp_input_box(&inputs[..1])))
+            .await
+            .expect("'rewind' function failed")
+            .is_some());
+
+        // The dropped events are no longer the last ones of the stream
+        enqueue_inputs(&broker, 1, 1).await;
+        let latest = fixture.get_latest_input_event().await.unwrap();
+        assert!(broker
+            .rewind(None)
+            .await
+            .expect("'rewind' function failed")
+            .is_some());
+        drop(broker);
+
+        let endpoint = fixture.redis_endpoint().clone();
+        let broker = connect(&fixture, endpoint)
+            .await
+            .expect("failed to restart the broker facade");
+        let status = broker.status().await.expect("'status' function failed");
+        assert_eq!(status.inputs_sent_count, 0);
+        assert!(broker.enqueue_input(0, &inputs[0]).await.is_ok());
+        let status = broker.status().await.expect("'status' function failed");
+        assert_eq!(status.inputs_sent_count, 1);
+        assert_eq!(fixture.get_latest_input_event().await.unwrap(), latest);
+    }
+
     // --------------------------------------------------------------------------------------------
     // auxiliary
     // --------------------------------------------------------------------------------------------
@@ -472,9 +1195,24 @@ mod broker_facade_tests {
         } else {
             fixture.redis_endpoint().clone()
         };
+        let broker = connect(&fixture, redis_endpoint).await?;
+        Ok((fixture, broker))
+    }
+
+    async fn setup(docker: &Cli) -> (BrokerFixture, BrokerFacade) {
+        failable_setup(docker, false).await.unwrap()
+    }
+
+    async fn connect(
+        fixture: &BrokerFixture<'_>,
+        redis_endpoint: BrokerEndpoint,
+    ) -> Result<BrokerFacade, BrokerFacadeError> {
         let config = BrokerConfig {
             redis_endpoint,
             consume_timeout: 300000,
+            retention: Default::default(),
+            payload_encoding: Default::default(),
+            consumer_group: None,
             backoff: ExponentialBackoffBuilder::new()
                 .with_initial_interval(Duration::from_millis(1000))
                 .with_max_elapsed_time(Some(Duration::from_millis(3000)))
@@ -484,12 +1222,7 @@ mod broker_facade_tests {
             chain_id: fixture.chain_id(),
             dapp_address: fixture.dapp_address().clone(),
         };
-        let broker = BrokerFacade::new(config, metadata).await?;
-        Ok((fixture, broker))
-    }
-
-    async fn setup(docker: &Cli) -> (BrokerFixture, BrokerFacade) {
-        failable_setup(docker, false).await.unwrap()
+        BrokerFacade::new(config, metadata).await
     }
 
     fn new_enqueue_input() -> Input {
@@ -508,6 +1241,27 @@ mod broker_facade_tests {
         }
     }
 
+    fn new_dapp_input_box(inputs: &[Input]) -> DAppInputBox {
+        let inputs = inputs.iter().cloned().map(Arc::new);
+        DAppInputBox {
+            inputs: Vector::from_iter(inputs),
+        }
+    }
+
+    async fn enqueue_inputs(
+        broker: &BrokerFacade,
+        first_index: u64,
+        n: u64,
+    ) -> Vec<Input> {
+        let mut inputs = vec![];
+        for i in first_index..first_index + n {
+            let input = new_enqueue_input();
+            assert!(broker.enqueue_input(i, &input).await.is_ok());
+            inputs.push(input);
+        }
+        inputs
+    }
+
     async fn produce_advance_state_inputs(fixture: &BrokerFixture<'_>, n: u32) {
         for _ in 0..n {
             let _ = fixture
@@ -524,7 +1278,7 @@ mod broker_facade_tests {
 
     async fn produce_finish_epoch_input(fixture: &BrokerFixture<'_>) {
         let _ = fixture
-            .produce_input_event(RollupsData::FinishEpoch {})
+            .produce_input_event(RollupsData::FinishEpoch { boundary: None })
             .await;
     }
 }
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
 // NOTE: doesn't support History upgradability.
-// NOTE: doesn't support changing epoch_duration in the middle of things.
+// NOTE: doesn't support changing the epoch policy in the middle of things.
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = dispatcher::config::Config::initialize()?;
@@ -15,6 +15,7 @@ pub struct DispatcherMetrics {
     pub claims_sent: FamilyRef<DAppMetadata, CounterRef>,
     pub advance_inputs_sent: FamilyRef<DAppMetadata, CounterRef>,
     pub finish_epochs_sent: FamilyRef<DAppMetadata, CounterRef>,
+    pub reorgs: FamilyRef<DAppMetadata, CounterRef>,
 }
 
 impl From<DispatcherMetrics> for Registry {
@@ -35,6 +36,11 @@ impl From<DispatcherMetrics> for Registry {
             "Counts the number of <finish_epoch>s sent",
             metrics.finish_epochs_sent,
         );
+        registry.register(
+            prefixed_metrics("reorgs"),
+            "Counts the number of reorgs that rewound the inputs stream",
+            metrics.reorgs,
+        );
         registry
     }
 }
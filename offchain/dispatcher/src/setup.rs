@@ -14,6 +14,7 @@ use types::foldables::{InputBox, InputBoxInitialState};
 
 use crate::{
     config::DispatcherConfig,
+    dapps::DAppEntry,
     drivers::Context,
     error::{
         BrokerSnafu, ChannelSnafu, ConnectSnafu, DirtyBrokerSnafu,
@@ -75,33 +76,33 @@ pub async fn create_block_subscription(
 
 pub async fn create_context(
     config: &DispatcherConfig,
+    dapp: &DAppEntry,
     block_server: &impl BlockServer,
     broker: &impl BrokerStatus,
     dapp_metadata: DAppMetadata,
     metrics: DispatcherMetrics,
 ) -> Result<Context, DispatcherError> {
-    let dapp_deployment_block_number =
-        U64::from(config.blockchain_config.dapp_deployment_block_number);
-    let genesis_timestamp: u64 = block_server
+    let dapp_deployment_block_number = U64::from(dapp.deployment_block_number);
+    let genesis_block = block_server
         .query_block(dapp_deployment_block_number)
         .await
-        .context(StateServerSnafu)?
-        .timestamp
-        .as_u64();
-    let epoch_length = config.epoch_duration;
+        .context(StateServerSnafu)?;
 
     let status = broker.status().await.context(BrokerSnafu)?;
 
     // The dispatcher doesn't work properly if there are inputs in the broker from a previous run.
     // Hence, we make sure that the broker is in a clean state before starting.
     ensure!(status.inputs_sent_count == 0, DirtyBrokerSnafu);
+    let epoch_start = broker.epoch_start().await.context(BrokerSnafu)?;
 
     let context = Context::new(
-        genesis_timestamp,
-        epoch_length,
+        genesis_block.timestamp.as_u64(),
+        genesis_block.number.as_u64(),
+        config.epoch_policy,
         dapp_metadata,
         metrics,
         status,
+        epoch_start,
     );
 
     Ok(context)
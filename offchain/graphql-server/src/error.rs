@@ -13,4 +13,13 @@ pub enum GraphQLServerError {
 
     #[snafu(display("server error"))]
     ServerError { source: std::io::Error },
+
+    #[snafu(display("notifications listener connection error"))]
+    ListenerConnectionError { source: tokio_postgres::Error },
+
+    #[snafu(display("notifications listener disconnected"))]
+    ListenerDisconnectedError {},
+
+    #[snafu(display("repository error"))]
+    RepositoryError { source: rollups_data::Error },
 }
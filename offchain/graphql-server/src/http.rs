@@ -1,17 +1,29 @@
 // (c) Cartesi and individual authors (see AUTHORS)
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
-use crate::schema::{Context, Query, RollupsGraphQLScalarValue, Schema};
+use crate::schema::{
+    Context, Query, RollupsGraphQLScalarValue, Schema, Subscription,
+};
 use actix_cors::Cors;
 use actix_web::dev::Server;
+use actix_web::http::header::{self, HeaderValue};
 use actix_web::{
-    middleware::Logger, web, web::Data, App, HttpResponse, HttpServer,
-    Responder,
+    middleware::Logger, web, web::Data, App, HttpRequest, HttpResponse,
+    HttpServer,
 };
+use actix_ws::Message;
+use futures::{SinkExt, StreamExt};
 use juniper::http::playground::playground_source;
 use juniper::http::GraphQLRequest;
-use juniper::{EmptyMutation, EmptySubscription};
+use juniper::EmptyMutation;
+use juniper_graphql_ws::{
+    ArcSchema, ClientMessage, Connection, ConnectionConfig,
+};
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Interval between the keep-alive messages sent to the subscription clients
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
 
 struct HttpContext {
     schema: Arc<Schema>,
@@ -27,7 +39,7 @@ pub fn start_service(
         let schema = std::sync::Arc::new(Schema::new_with_scalar_value(
             Query,
             EmptyMutation::new(),
-            EmptySubscription::new(),
+            Subscription,
         ));
 
         let http_context = HttpContext {
@@ -43,53 +55,202 @@ pub fn start_service(
             .wrap(cors)
             .service(graphql)
             .service(juniper_playground)
+            .service(application_graphql)
+            .service(application_playground)
     })
     .bind((host, port))?
     .run())
 }
 
+/// Serve the playground, or the subscriptions if the client requests a
+/// WebSocket connection
+/// The `/graphql` endpoint serves the default application.
 #[actix_web::get("/graphql")]
-async fn juniper_playground() -> impl Responder {
+async fn juniper_playground(
+    req: HttpRequest,
+    body: web::Payload,
+    http_context: web::Data<HttpContext>,
+) -> HttpResponse {
+    let context = http_context.context.clone();
+    playground_or_subscriptions(req, body, &http_context.schema, context)
+}
+
+/// Serve the playground, or the subscriptions, of the given dapp
+#[actix_web::get("/graphql/{dapp_address}")]
+async fn application_playground(
+    req: HttpRequest,
+    body: web::Payload,
+    dapp_address: web::Path<String>,
+    http_context: web::Data<HttpContext>,
+) -> HttpResponse {
+    match application_context(&http_context.context, &dapp_address).await {
+        Ok(context) => playground_or_subscriptions(
+            req,
+            body,
+            &http_context.schema,
+            context,
+        ),
+        Err(response) => response,
+    }
+}
+
+fn playground_or_subscriptions(
+    req: HttpRequest,
+    body: web::Payload,
+    schema: &Arc<Schema>,
+    context: Context,
+) -> HttpResponse {
+    if req.headers().contains_key(header::UPGRADE) {
+        return subscriptions(req, body, schema.clone(), context);
+    }
     let html = playground_source("", None);
     HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
         .body(html)
 }
 
+/// Find the application deployed at the given address and create its context
+async fn application_context(
+    context: &Context,
+    dapp_address: &str,
+) -> Result<Context, HttpResponse> {
+    let address =
+        hex::decode(dapp_address.trim_start_matches("0x")).map_err(|err| {
+            let error_message =
+                format!("invalid dapp address, details: {}", err);
+            HttpResponse::BadRequest().body(error_message)
+        })?;
+    match context.repository().get_application(&address).await {
+        Ok(application) => Ok(context.for_application(application.id)),
+        Err(rollups_data::Error::ItemNotFound { .. }) => {
+            let error_message = format!("dapp {} not found", dapp_address);
+            Err(HttpResponse::NotFound().body(error_message))
+        }
+        Err(err) => {
+            let error_message = format!(
+                "unable to find dapp, internal server error, details: {}",
+                err
+            );
+            Err(HttpResponse::InternalServerError().body(error_message))
+        }
+    }
+}
+
+/// Handle the subscriptions with the graphql-ws protocol over WebSocket
+fn subscriptions(
+    req: HttpRequest,
+    body: web::Payload,
+    schema: Arc<Schema>,
+    context: Context,
+) -> HttpResponse {
+    let (mut response, session, mut messages) =
+        match actix_ws::handle(&req, body) {
+            Ok(handle) => handle,
+            Err(err) => return HttpResponse::from_error(err),
+        };
+    response.headers_mut().insert(
+        header::SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_static("graphql-ws"),
+    );
+
+    let config = ConnectionConfig::new(context)
+        .with_keep_alive_interval(KEEP_ALIVE_INTERVAL);
+    let connection = Connection::new(ArcSchema(schema), config);
+    let (mut client_sink, mut server_stream) = connection.split();
+
+    // Forward the server messages to the client
+    let mut server_session = session.clone();
+    actix_web::rt::spawn(async move {
+        while let Some(message) = server_stream.next().await {
+            let text = match serde_json::to_string(&message) {
+                Ok(text) => text,
+                Err(err) => {
+                    tracing::warn!("failed to serialize message ({})", err);
+                    continue;
+                }
+            };
+            if server_session.text(text).await.is_err() {
+                break;
+            }
+        }
+        let _ = server_session.close(None).await;
+    });
+
+    // Forward the client messages to the connection
+    let mut client_session = session;
+    actix_web::rt::spawn(async move {
+        while let Some(Ok(message)) = messages.next().await {
+            match message {
+                Message::Text(text) => {
+                    let message: ClientMessage<RollupsGraphQLScalarValue> =
+                        match serde_json::from_str(&text) {
+                            Ok(message) => message,
+                            Err(err) => {
+                                tracing::warn!(
+                                    "failed to parse client message ({})",
+                                    err
+                                );
+                                continue;
+                            }
+                        };
+                    if client_sink.send(message).await.is_err() {
+                        break;
+                    }
+                }
+                Message::Ping(bytes) => {
+                    if client_session.pong(&bytes).await.is_err() {
+                        break;
+                    }
+                }
+                Message::Close(_) => break,
+                _ => {}
+            }
+        }
+        let _ = client_sink.send(ClientMessage::ConnectionTerminate).await;
+    });
+
+    response
+}
+
 #[actix_web::post("/graphql")]
 async fn graphql(
     query: web::Json<GraphQLRequest<RollupsGraphQLScalarValue>>,
     http_context: web::Data<HttpContext>,
 ) -> HttpResponse {
-    // Execute resolvers in blocking thread as there are lot of blocking diesel db operations
-    let query = Arc::new(query);
-    let return_value: HttpResponse = match tokio::task::spawn_blocking(
-        move || {
-            let res =
-                query.execute_sync(&http_context.schema, &http_context.context);
-            serde_json::to_string(&res)
-        },
-    )
-    .await
-    {
-        Ok(value) => match value {
-            Ok(value) => HttpResponse::Ok()
-                .content_type("application/json")
-                .body(value),
-            Err(err) => {
-                let error_message = format!(
-                            "unable to execute query, internal server error, details: {}", err
-                        );
-                return HttpResponse::BadRequest().body(error_message);
-            }
-        },
+    let context = http_context.context.clone();
+    execute(query, http_context.schema.clone(), context).await
+}
+
+#[actix_web::post("/graphql/{dapp_address}")]
+async fn application_graphql(
+    query: web::Json<GraphQLRequest<RollupsGraphQLScalarValue>>,
+    dapp_address: web::Path<String>,
+    http_context: web::Data<HttpContext>,
+) -> HttpResponse {
+    match application_context(&http_context.context, &dapp_address).await {
+        Ok(context) => {
+            execute(query, http_context.schema.clone(), context).await
+        }
+        Err(response) => response,
+    }
+}
+
+async fn execute(
+    query: web::Json<GraphQLRequest<RollupsGraphQLScalarValue>>,
+    schema: Arc<Schema>,
+    context: Context,
+) -> HttpResponse {
+    let res = query.execute(&schema, &context).await;
+    match serde_json::to_string(&res) {
+        Ok(value) => HttpResponse::Ok()
+            .content_type("application/json")
+            .body(value),
         Err(err) => {
             let error_message = format!(
                 "unable to execute query, internal server error, details: {}",
                 err
             );
-            return HttpResponse::BadRequest().body(error_message);
+            HttpResponse::BadRequest().body(error_message)
         }
-    };
-    return_value
+    }
 }
@@ -11,19 +11,33 @@ pub use schema::Context;
 pub mod config;
 mod error;
 pub mod http;
+pub mod notifications;
 pub mod schema;
 
 #[tracing::instrument(level = "trace", skip_all)]
 pub async fn run(config: GraphQLConfig) -> Result<(), GraphQLServerError> {
-    let repository = rollups_data::Repository::new(config.repository_config)
-        .expect("failed to connect to database");
-    let context = Context::new(repository);
+    let endpoint = config.repository_config.endpoint();
+    let repository =
+        rollups_data::AsyncRepository::new(config.repository_config)
+            .expect("failed to create the database pool");
+    let context = Context::new(repository.clone());
+    let notifier = context.notifier();
     let service_handler =
         start_service(&config.graphql_host, config.graphql_port, context)
             .expect("failed to create server");
 
     let health_handle = http_health_check::start(config.healthcheck_port);
 
+    let listener_handle = async {
+        if endpoint.is_empty() {
+            // The listener can't load the endpoint from the Pg environment
+            tracing::warn!("postgres endpoint not set; subscriptions disabled");
+            std::future::pending().await
+        } else {
+            notifications::listen(endpoint, repository, notifier).await
+        }
+    };
+
     tokio::select! {
         ret = health_handle => {
             ret.context(error::HealthCheckSnafu)
@@ -31,5 +45,8 @@ pub async fn run(config: GraphQLConfig) -> Result<(), GraphQLServerError> {
         ret = service_handler => {
             ret.context(error::ServerSnafu)
         }
+        ret = listener_handle => {
+            ret
+        }
     }
 }
@@ -0,0 +1,183 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Notifications of changes in the rollups data
+//!
+//! The database triggers notify the `rollups_changes` channel with the keys of
+//! the changed rows. A single listener loads each changed row and broadcasts
+//! it to every GraphQL subscription, so the number of subscribers doesn't
+//! increase the database load. Each subscription only forwards the rows of
+//! the application it was created for.
+
+use futures::{stream, StreamExt};
+use rollups_data::{AsyncRepository, Claim, Input, Notice, Report, Voucher};
+use serde::Deserialize;
+use snafu::ResultExt;
+use tokio::sync::{broadcast, mpsc};
+use tokio_postgres::{AsyncMessage, NoTls};
+
+use crate::error::{
+    GraphQLServerError, ListenerConnectionSnafu, ListenerDisconnectedSnafu,
+    RepositorySnafu,
+};
+
+const CHANNEL: &str = "rollups_changes";
+
+/// Changed row of an application
+#[derive(Debug, Clone)]
+pub struct ApplicationNotification {
+    pub application_id: i32,
+    pub notification: Notification,
+}
+
+/// Changed row loaded from the database
+#[derive(Debug, Clone)]
+pub enum Notification {
+    InputAdded(Input),
+    InputStatusChanged(Input),
+    NoticeAdded(Notice),
+    VoucherAdded(Voucher),
+    ReportAdded(Report),
+    ClaimSubmitted(Claim),
+}
+
+/// Payload sent by the `notify_rollups_change` trigger
+#[derive(Debug, Deserialize)]
+struct Change {
+    kind: ChangeKind,
+    application_id: i32,
+    index: Option<i32>,
+    input_index: Option<i32>,
+    epoch_index: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum ChangeKind {
+    InputAdded,
+    InputStatusChanged,
+    NoticeAdded,
+    VoucherAdded,
+    ReportAdded,
+    ClaimSubmitted,
+}
+
+/// Listen to the database notifications and broadcast the changed rows
+#[tracing::instrument(level = "trace", skip_all)]
+pub async fn listen(
+    endpoint: String,
+    repository: AsyncRepository,
+    sender: broadcast::Sender<ApplicationNotification>,
+) -> Result<(), GraphQLServerError> {
+    let (client, mut connection) = tokio_postgres::connect(&endpoint, NoTls)
+        .await
+        .context(ListenerConnectionSnafu)?;
+
+    // The connection must be polled in another task to receive the
+    // notifications, so it forwards the messages to this one
+    let (messages_tx, mut messages) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut stream = stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = stream.next().await {
+            if messages_tx.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    client
+        .batch_execute(&format!("LISTEN {}", CHANNEL))
+        .await
+        .context(ListenerConnectionSnafu)?;
+    tracing::info!("listening to the {} channel", CHANNEL);
+
+    while let Some(message) = messages.recv().await {
+        let notification = match message.context(ListenerConnectionSnafu)? {
+            AsyncMessage::Notification(notification) => notification,
+            message => {
+                tracing::trace!(?message, "ignoring database message");
+                continue;
+            }
+        };
+        let change: Change = match serde_json::from_str(notification.payload())
+        {
+            Ok(change) => change,
+            Err(e) => {
+                tracing::warn!("failed to parse notification ({})", e);
+                continue;
+            }
+        };
+        tracing::trace!(?change, "received change");
+
+        // There is no need to load the row if nobody is subscribed
+        if sender.receiver_count() == 0 {
+            continue;
+        }
+        let application_id = change.application_id;
+        let repository = repository.for_application(application_id);
+        match load(&repository, change).await {
+            Ok(Some(notification)) => {
+                // Sending only fails if every subscriber is gone
+                let _ = sender.send(ApplicationNotification {
+                    application_id,
+                    notification,
+                });
+            }
+            Ok(None) => {}
+            Err(rollups_data::Error::ItemNotFound { item_type }) => {
+                // The row may be deleted by a reorg before it is loaded
+                tracing::warn!("changed {} not found", item_type);
+            }
+            Err(source) => return Err(source).context(RepositorySnafu),
+        }
+    }
+
+    ListenerDisconnectedSnafu.fail()
+}
+
+async fn load(
+    repository: &AsyncRepository,
+    change: Change,
+) -> Result<Option<Notification>, rollups_data::Error> {
+    let (index, input_index, epoch_index) =
+        (change.index, change.input_index, change.epoch_index);
+    let notification = match change.kind {
+        ChangeKind::InputAdded => match index {
+            Some(index) => Some(Notification::InputAdded(
+                repository.get_input(index).await?,
+            )),
+            None => None,
+        },
+        ChangeKind::InputStatusChanged => match index {
+            Some(index) => Some(Notification::InputStatusChanged(
+                repository.get_input(index).await?,
+            )),
+            None => None,
+        },
+        ChangeKind::NoticeAdded => match index.zip(input_index) {
+            Some((index, input_index)) => Some(Notification::NoticeAdded(
+                repository.get_notice(index, input_index).await?,
+            )),
+            None => None,
+        },
+        ChangeKind::VoucherAdded => match index.zip(input_index) {
+            Some((index, input_index)) => Some(Notification::VoucherAdded(
+                repository.get_voucher(index, input_index).await?,
+            )),
+            None => None,
+        },
+        ChangeKind::ReportAdded => match index.zip(input_index) {
+            Some((index, input_index)) => Some(Notification::ReportAdded(
+                repository.get_report(index, input_index).await?,
+            )),
+            None => None,
+        },
+        ChangeKind::ClaimSubmitted => match epoch_index {
+            Some(epoch_index) => Some(Notification::ClaimSubmitted(
+                repository.get_claim(epoch_index).await?,
+            )),
+            None => None,
+        },
+    };
+    Ok(notification)
+}
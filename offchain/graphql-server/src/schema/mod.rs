@@ -3,14 +3,16 @@
 
 mod resolvers;
 mod scalar;
+mod subscriptions;
 
 pub use resolvers::{Context, Query};
 pub use scalar::RollupsGraphQLScalarValue;
+pub use subscriptions::Subscription;
 
 pub type Schema = juniper::RootNode<
     'static,
     Query,
     juniper::EmptyMutation<Context>,
-    juniper::EmptySubscription<Context>,
+    Subscription,
     RollupsGraphQLScalarValue,
 >;
@@ -2,29 +2,71 @@
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
 use juniper::{
-    graphql_object, DefaultScalarValue, FieldError, FieldResult, GraphQLEnum,
-    GraphQLInputObject, GraphQLObject,
+    graphql_object, DefaultScalarValue, Executor, FieldError, FieldResult,
+    GraphQLEnum, GraphQLInputObject, GraphQLObject, LookAheadMethods,
 };
-use std::time::UNIX_EPOCH;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
 
-use rollups_data::Repository;
+use rollups_data::AsyncRepository;
 use rollups_data::{
-    CompletionStatus as DbCompletionStatus, Connection, Edge, Input,
-    InputQueryFilter, Notice, NoticeQueryFilter, OutputEnum,
-    PageInfo as DbPageInfo, Proof, Report, ReportQueryFilter, Voucher,
-    VoucherQueryFilter,
+    Claim, ClaimStatus as DbClaimStatus,
+    CompletionStatus as DbCompletionStatus, Connection, Edge, Epoch,
+    EpochQueryFilter, Input, InputQueryFilter, Notice, NoticeQueryFilter,
+    OutputEnum, PageInfo as DbPageInfo, Proof, Report, ReportQueryFilter,
+    Voucher, VoucherQueryFilter,
 };
 
 use super::scalar::RollupsGraphQLScalarValue;
+use crate::notifications::ApplicationNotification;
 
+/// Number of notifications kept for slow subscribers
+const NOTIFICATIONS_CAPACITY: usize = 1024;
+
+/// Context of the queries, which are scoped by the application of the
+/// repository
 #[derive(Clone)]
 pub struct Context {
-    repository: Repository,
+    repository: AsyncRepository,
+    notifications: broadcast::Sender<ApplicationNotification>,
 }
 
 impl Context {
-    pub fn new(repository: Repository) -> Self {
-        Self { repository }
+    pub fn new(repository: AsyncRepository) -> Self {
+        let (notifications, _) = broadcast::channel(NOTIFICATIONS_CAPACITY);
+        Self {
+            repository,
+            notifications,
+        }
+    }
+
+    /// Create the context of the given application, sharing the repository
+    /// connections and the notifications with this one
+    pub fn for_application(&self, application_id: i32) -> Self {
+        Self {
+            repository: self.repository.for_application(application_id),
+            notifications: self.notifications.clone(),
+        }
+    }
+
+    /// Repository of the application whose data the resolvers load
+    pub fn repository(&self) -> &AsyncRepository {
+        &self.repository
+    }
+
+    /// Sender used to broadcast the notifications to the subscriptions
+    pub fn notifier(&self) -> broadcast::Sender<ApplicationNotification> {
+        self.notifications.clone()
+    }
+
+    pub(super) fn application_id(&self) -> i32 {
+        self.repository.application_id()
+    }
+
+    pub(super) fn subscribe(
+        &self,
+    ) -> broadcast::Receiver<ApplicationNotification> {
+        self.notifications.subscribe()
     }
 }
 
@@ -39,18 +81,19 @@ pub struct Query;
 )]
 impl Query {
     #[graphql(description = "Get input based on its identifier")]
-    fn input(
+    async fn input(
         #[graphql(description = "Input index")] index: i32,
     ) -> FieldResult<Input> {
         executor
             .context()
             .repository
             .get_input(index)
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(description = "Get voucher based on its index")]
-    fn voucher(
+    async fn voucher(
         #[graphql(description = "Voucher index in input")] voucher_index: i32,
         #[graphql(description = "Input index")] input_index: i32,
     ) -> FieldResult<Voucher> {
@@ -58,11 +101,12 @@ impl Query {
             .context()
             .repository
             .get_voucher(voucher_index, input_index)
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(description = "Get notice based on its index")]
-    fn notice(
+    async fn notice(
         #[graphql(description = "Notice index in input")] notice_index: i32,
         #[graphql(description = "Input index")] input_index: i32,
     ) -> FieldResult<Notice> {
@@ -70,11 +114,12 @@ impl Query {
             .context()
             .repository
             .get_notice(notice_index, input_index)
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(description = "Get report based on its index")]
-    fn report(
+    async fn report(
         #[graphql(description = "Report index in input")] report_index: i32,
         #[graphql(description = "Input index")] input_index: i32,
     ) -> FieldResult<Report> {
@@ -82,11 +127,24 @@ impl Query {
             .context()
             .repository
             .get_report(report_index, input_index)
+            .await
+            .map_err(convert_error)
+    }
+
+    #[graphql(description = "Get epoch based on its index")]
+    async fn epoch(
+        #[graphql(description = "Epoch index")] index: i32,
+    ) -> FieldResult<Epoch> {
+        executor
+            .context()
+            .repository
+            .get_epoch(index)
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(description = "Get inputs with support for pagination")]
-    fn inputs(
+    async fn inputs(
         #[graphql(
             description = "Get at most the first `n` entries (forward pagination)"
         )]
@@ -107,16 +165,27 @@ impl Query {
             InputFilter,
         >,
     ) -> FieldResult<Connection<Input>> {
-        let filter = r#where.map(InputFilter::into).unwrap_or_default();
+        let filter = r#where
+            .map(InputQueryFilter::try_from)
+            .transpose()?
+            .unwrap_or_default();
         executor
             .context()
             .repository
-            .get_inputs(first, last, after, before, filter)
+            .get_inputs(
+                first,
+                last,
+                after,
+                before,
+                filter,
+                selects_total_count(executor),
+            )
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(description = "Get vouchers with support for pagination")]
-    fn vouchers(
+    async fn vouchers(
         #[graphql(
             description = "Get at most the first `n` entries (forward pagination)"
         )]
@@ -133,16 +202,31 @@ impl Query {
             description = "Get entries that come before the provided cursor (backward pagination)"
         )]
         before: Option<String>,
+        #[graphql(description = "Filter entries to retrieve")] r#where: Option<
+            VoucherFilter,
+        >,
     ) -> FieldResult<Connection<Voucher>> {
+        let filter = r#where
+            .map(VoucherQueryFilter::try_from)
+            .transpose()?
+            .unwrap_or_default();
         executor
             .context()
             .repository
-            .get_vouchers(first, last, after, before, Default::default())
+            .get_vouchers(
+                first,
+                last,
+                after,
+                before,
+                filter,
+                selects_total_count(executor),
+            )
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(description = "Get notices with support for pagination")]
-    fn notices(
+    async fn notices(
         #[graphql(
             description = "Get at most the first `n` entries (forward pagination)"
         )]
@@ -159,16 +243,31 @@ impl Query {
             description = "Get entries that come before the provided cursor (backward pagination)"
         )]
         before: Option<String>,
+        #[graphql(description = "Filter entries to retrieve")] r#where: Option<
+            NoticeFilter,
+        >,
     ) -> FieldResult<Connection<Notice>> {
+        let filter = r#where
+            .map(NoticeQueryFilter::try_from)
+            .transpose()?
+            .unwrap_or_default();
         executor
             .context()
             .repository
-            .get_notices(first, last, after, before, Default::default())
+            .get_notices(
+                first,
+                last,
+                after,
+                before,
+                filter,
+                selects_total_count(executor),
+            )
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(description = "Get reports with support for pagination")]
-    fn reports(
+    async fn reports(
         #[graphql(
             description = "Get at most the first `n` entries (forward pagination)"
         )]
@@ -185,16 +284,69 @@ impl Query {
             description = "Get entries that come before the provided cursor (backward pagination)"
         )]
         before: Option<String>,
+        #[graphql(description = "Filter entries to retrieve")] r#where: Option<
+            ReportFilter,
+        >,
     ) -> FieldResult<Connection<Report>> {
+        let filter = r#where
+            .map(ReportQueryFilter::try_from)
+            .transpose()?
+            .unwrap_or_default();
+        executor
+            .context()
+            .repository
+            .get_reports(
+                first,
+                last,
+                after,
+                before,
+                filter,
+                selects_total_count(executor),
+            )
+            .await
+            .map_err(convert_error)
+    }
+
+    #[graphql(description = "Get epochs with support for pagination")]
+    async fn epochs(
+        #[graphql(
+            description = "Get at most the first `n` entries (forward pagination)"
+        )]
+        first: Option<i32>,
+        #[graphql(
+            description = "Get at most the last `n` entries (backward pagination)"
+        )]
+        last: Option<i32>,
+        #[graphql(
+            description = "Get entries that come after the provided cursor (forward pagination)"
+        )]
+        after: Option<String>,
+        #[graphql(
+            description = "Get entries that come before the provided cursor (backward pagination)"
+        )]
+        before: Option<String>,
+        #[graphql(description = "Filter entries to retrieve")] r#where: Option<
+            EpochFilter,
+        >,
+    ) -> FieldResult<Connection<Epoch>> {
+        let filter = r#where.map(EpochFilter::into).unwrap_or_default();
         executor
             .context()
             .repository
-            .get_reports(first, last, after, before, Default::default())
+            .get_epochs(
+                first,
+                last,
+                after,
+                before,
+                filter,
+                selects_total_count(executor),
+            )
+            .await
             .map_err(convert_error)
     }
 }
 
-#[derive(GraphQLEnum)]
+#[derive(Debug, Clone, Copy, GraphQLEnum)]
 enum CompletionStatus {
     Unprocessed,
     Accepted,
@@ -229,6 +381,46 @@ impl From<DbCompletionStatus> for CompletionStatus {
     }
 }
 
+impl From<CompletionStatus> for DbCompletionStatus {
+    fn from(status: CompletionStatus) -> DbCompletionStatus {
+        match status {
+            CompletionStatus::Unprocessed => DbCompletionStatus::Unprocessed,
+            CompletionStatus::Accepted => DbCompletionStatus::Accepted,
+            CompletionStatus::Rejected => DbCompletionStatus::Rejected,
+            CompletionStatus::Exception => DbCompletionStatus::Exception,
+            CompletionStatus::MachineHalted => {
+                DbCompletionStatus::MachineHalted
+            }
+            CompletionStatus::CycleLimitExceeded => {
+                DbCompletionStatus::CycleLimitExceeded
+            }
+            CompletionStatus::TimeLimitExceeded => {
+                DbCompletionStatus::TimeLimitExceeded
+            }
+            CompletionStatus::PayloadLengthLimitExceeded => {
+                DbCompletionStatus::PayloadLengthLimitExceeded
+            }
+        }
+    }
+}
+
+#[derive(GraphQLEnum)]
+enum ClaimStatus {
+    Pending,
+    Submitted,
+    Duplicated,
+}
+
+impl From<DbClaimStatus> for ClaimStatus {
+    fn from(status: DbClaimStatus) -> ClaimStatus {
+        match status {
+            DbClaimStatus::Pending => ClaimStatus::Pending,
+            DbClaimStatus::Submitted => ClaimStatus::Submitted,
+            DbClaimStatus::Duplicated => ClaimStatus::Duplicated,
+        }
+    }
+}
+
 #[graphql_object(
     context = Context,
     Scalar = RollupsGraphQLScalarValue,
@@ -277,10 +469,22 @@ impl Input {
         hex_encode(&self.payload)
     }
 
+    #[graphql(
+        description = "Epoch that contains the input, available after the epoch is closed"
+    )]
+    async fn epoch(&self) -> FieldResult<Option<Epoch>> {
+        executor
+            .context()
+            .repository
+            .get_input_epoch(self.index)
+            .await
+            .map_err(convert_error)
+    }
+
     #[graphql(
         description = "Get voucher from this particular input given the voucher's index"
     )]
-    fn voucher(
+    async fn voucher(
         &self,
         #[graphql(description = "Voucher index in input")] index: i32,
     ) -> FieldResult<Voucher> {
@@ -288,13 +492,14 @@ impl Input {
             .context()
             .repository
             .get_voucher(index, self.index)
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(
         description = "Get notice from this particular input given the notice's index"
     )]
-    fn notice(
+    async fn notice(
         &self,
         #[graphql(description = "Notice index in input")] index: i32,
     ) -> FieldResult<Notice> {
@@ -302,13 +507,14 @@ impl Input {
             .context()
             .repository
             .get_notice(index, self.index)
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(
         description = "Get report from this particular input given the report's index"
     )]
-    fn report(
+    async fn report(
         &self,
         #[graphql(description = "Report index in input")] index: i32,
     ) -> FieldResult<Report> {
@@ -316,13 +522,14 @@ impl Input {
             .context()
             .repository
             .get_report(index, self.index)
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(
         description = "Get vouchers from this particular input with support for pagination"
     )]
-    fn vouchers(
+    async fn vouchers(
         &self,
         #[graphql(
             description = "Get at most the first `n` entries (forward pagination)"
@@ -340,21 +547,36 @@ impl Input {
             description = "Get entries that come before the provided cursor (backward pagination)"
         )]
         before: Option<String>,
+        #[graphql(description = "Filter entries to retrieve")] r#where: Option<
+            VoucherFilter,
+        >,
     ) -> FieldResult<Connection<Voucher>> {
         let filter = VoucherQueryFilter {
             input_index: Some(self.index),
+            ..r#where
+                .map(VoucherQueryFilter::try_from)
+                .transpose()?
+                .unwrap_or_default()
         };
         executor
             .context()
             .repository
-            .get_vouchers(first, last, after, before, filter)
+            .get_vouchers(
+                first,
+                last,
+                after,
+                before,
+                filter,
+                selects_total_count(executor),
+            )
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(
         description = "Get notices from this particular input with support for pagination"
     )]
-    fn notices(
+    async fn notices(
         &self,
         #[graphql(
             description = "Get at most the first `n` entries (forward pagination)"
@@ -372,21 +594,36 @@ impl Input {
             description = "Get entries that come before the provided cursor (backward pagination)"
         )]
         before: Option<String>,
+        #[graphql(description = "Filter entries to retrieve")] r#where: Option<
+            NoticeFilter,
+        >,
     ) -> FieldResult<Connection<Notice>> {
         let filter = NoticeQueryFilter {
             input_index: Some(self.index),
+            ..r#where
+                .map(NoticeQueryFilter::try_from)
+                .transpose()?
+                .unwrap_or_default()
         };
         executor
             .context()
             .repository
-            .get_notices(first, last, after, before, filter)
+            .get_notices(
+                first,
+                last,
+                after,
+                before,
+                filter,
+                selects_total_count(executor),
+            )
+            .await
             .map_err(convert_error)
     }
 
     #[graphql(
         description = "Get reports from this particular input with support for pagination"
     )]
-    fn reports(
+    async fn reports(
         &self,
         #[graphql(
             description = "Get at most the first `n` entries (forward pagination)"
@@ -404,14 +641,29 @@ impl Input {
             description = "Get entries that come before the provided cursor (backward pagination)"
         )]
         before: Option<String>,
+        #[graphql(description = "Filter entries to retrieve")] r#where: Option<
+            ReportFilter,
+        >,
     ) -> FieldResult<Connection<Report>> {
         let filter = ReportQueryFilter {
             input_index: Some(self.index),
+            ..r#where
+                .map(ReportQueryFilter::try_from)
+                .transpose()?
+                .unwrap_or_default()
         };
         executor
             .context()
             .repository
-            .get_reports(first, last, after, before, filter)
+            .get_reports(
+                first,
+                last,
+                after,
+                before,
+                filter,
+                selects_total_count(executor),
+            )
+            .await
             .map_err(convert_error)
     }
 }
@@ -430,11 +682,12 @@ impl Voucher {
     }
 
     #[graphql(description = "Input whose processing produced the voucher")]
-    fn input(&self) -> FieldResult<Input> {
+    async fn input(&self) -> FieldResult<Input> {
         executor
             .context()
             .repository
             .get_input(self.input_index)
+            .await
             .map_err(convert_error)
     }
 
@@ -455,13 +708,28 @@ impl Voucher {
     #[graphql(
         description = "Proof object that allows this voucher to be validated and executed on the base layer blockchain"
     )]
-    fn proof(&self) -> FieldResult<Option<Proof>> {
+    async fn proof(&self) -> FieldResult<Option<Proof>> {
         executor
             .context()
             .repository
             .get_proof(self.input_index, self.index, OutputEnum::Voucher)
+            .await
             .map_err(convert_error)
     }
+
+    #[graphql(
+        description = "Whether the voucher was already executed on the base layer blockchain"
+    )]
+    fn executed(&self) -> bool {
+        self.executed
+    }
+
+    #[graphql(
+        description = "Hash of the transaction that executed the voucher, given in Ethereum hex binary format (32 bytes), starting with '0x'"
+    )]
+    fn execution_transaction_hash(&self) -> Option<String> {
+        self.execution_transaction_hash.as_deref().map(hex_encode)
+    }
 }
 
 #[graphql_object(
@@ -478,11 +746,12 @@ impl Notice {
     }
 
     #[graphql(description = "Input whose processing produced the notice")]
-    fn input(&self) -> FieldResult<Input> {
+    async fn input(&self) -> FieldResult<Input> {
         executor
             .context()
             .repository
             .get_input(self.input_index)
+            .await
             .map_err(convert_error)
     }
 
@@ -496,11 +765,12 @@ impl Notice {
     #[graphql(
         description = "Proof object that allows this notice to be validated by the base layer blockchain"
     )]
-    fn proof(&self) -> FieldResult<Option<Proof>> {
+    async fn proof(&self) -> FieldResult<Option<Proof>> {
         executor
             .context()
             .repository
             .get_proof(self.input_index, self.index, OutputEnum::Notice)
+            .await
             .map_err(convert_error)
     }
 }
@@ -519,11 +789,12 @@ impl Report {
     }
 
     #[graphql(description = "Input whose processing produced the report")]
-    fn input(&self) -> FieldResult<Input> {
+    async fn input(&self) -> FieldResult<Input> {
         executor
             .context()
             .repository
             .get_input(self.input_index)
+            .await
             .map_err(convert_error)
     }
 
@@ -575,6 +846,149 @@ impl Proof {
     fn context(&self) -> String {
         hex_encode(&self.context)
     }
+
+    #[graphql(
+        description = "Claim of the epoch the proof depends on; the output can only be validated on the base layer blockchain after this claim is accepted"
+    )]
+    async fn claim(&self) -> FieldResult<Option<Claim>> {
+        executor
+            .context()
+            .repository
+            .get_input_claim(self.input_index)
+            .await
+            .map_err(convert_error)
+    }
+}
+
+#[graphql_object(
+    context = Context,
+    Scalar = RollupsGraphQLScalarValue,
+    description = "Range of inputs whose resulting state is claimed at once on the base layer blockchain"
+)]
+impl Epoch {
+    #[graphql(description = "Epoch index starting from genesis")]
+    fn index(&self) -> i32 {
+        self.index
+    }
+
+    #[graphql(description = "Index of the first input of the epoch")]
+    fn first_input_index(&self) -> i32 {
+        self.first_input_index
+    }
+
+    #[graphql(description = "Index of the last input of the epoch")]
+    fn last_input_index(&self) -> i32 {
+        self.last_input_index
+    }
+
+    #[graphql(description = "Claim of the epoch")]
+    async fn claim(&self) -> FieldResult<Claim> {
+        executor
+            .context()
+            .repository
+            .get_claim(self.index)
+            .await
+            .map_err(convert_error)
+    }
+
+    #[graphql(
+        description = "Get inputs from this particular epoch with support for pagination"
+    )]
+    async fn inputs(
+        &self,
+        #[graphql(
+            description = "Get at most the first `n` entries (forward pagination)"
+        )]
+        first: Option<i32>,
+        #[graphql(
+            description = "Get at most the last `n` entries (backward pagination)"
+        )]
+        last: Option<i32>,
+        #[graphql(
+            description = "Get entries that come after the provided cursor (forward pagination)"
+        )]
+        after: Option<String>,
+        #[graphql(
+            description = "Get entries that come before the provided cursor (backward pagination)"
+        )]
+        before: Option<String>,
+    ) -> FieldResult<Connection<Input>> {
+        let filter = InputQueryFilter {
+            index_greater_than: Some(self.first_input_index - 1),
+            index_lower_than: Some(self.last_input_index + 1),
+            ..Default::default()
+        };
+        executor
+            .context()
+            .repository
+            .get_inputs(
+                first,
+                last,
+                after,
+                before,
+                filter,
+                selects_total_count(executor),
+            )
+            .await
+            .map_err(convert_error)
+    }
+}
+
+#[graphql_object(
+    context = Context,
+    Scalar = RollupsGraphQLScalarValue,
+    description = "Claim of the state of the application at the end of an epoch"
+)]
+impl Claim {
+    #[graphql(description = "Epoch whose state is claimed")]
+    async fn epoch(&self) -> FieldResult<Epoch> {
+        executor
+            .context()
+            .repository
+            .get_epoch(self.epoch_index)
+            .await
+            .map_err(convert_error)
+    }
+
+    #[graphql(
+        description = "Hash of the epoch, given in Ethereum hex binary format (32 bytes), starting with '0x'"
+    )]
+    fn epoch_hash(&self) -> String {
+        hex_encode(&self.epoch_hash)
+    }
+
+    #[graphql(
+        description = "Hash of the machine state at the end of the epoch, given in Ethereum hex binary format (32 bytes), starting with '0x'"
+    )]
+    fn machine_state_hash(&self) -> String {
+        hex_encode(&self.machine_state_hash)
+    }
+
+    #[graphql(
+        description = "Merkle root of all voucher hashes of the epoch, given in Ethereum hex binary format (32 bytes), starting with '0x'"
+    )]
+    fn vouchers_epoch_root_hash(&self) -> String {
+        hex_encode(&self.vouchers_epoch_root_hash)
+    }
+
+    #[graphql(
+        description = "Merkle root of all notice hashes of the epoch, given in Ethereum hex binary format (32 bytes), starting with '0x'"
+    )]
+    fn notices_epoch_root_hash(&self) -> String {
+        hex_encode(&self.notices_epoch_root_hash)
+    }
+
+    #[graphql(description = "Submission status of the claim")]
+    fn status(&self) -> ClaimStatus {
+        self.status.into()
+    }
+
+    #[graphql(
+        description = "Hash of the transaction that submitted the claim, given in Ethereum hex binary format (32 bytes), starting with '0x'"
+    )]
+    fn transaction_hash(&self) -> Option<String> {
+        self.transaction_hash.as_deref().map(hex_encode)
+    }
 }
 
 #[derive(GraphQLObject, Debug, Clone)]
@@ -633,11 +1047,157 @@ pub struct InputFilter {
 
     /// Filter only inputs with index greater than a given value
     pub index_greater_than: Option<i32>,
+
+    /// Filter only inputs submitted by a given address, in Ethereum hex binary format, starting with '0x'
+    pub msg_sender: Option<String>,
+
+    /// Filter only inputs with a given status
+    pub status: Option<CompletionStatus>,
+
+    /// Filter only inputs with block number lower than a given value
+    pub block_number_lower_than: Option<i64>,
+
+    /// Filter only inputs with block number greater than a given value
+    pub block_number_greater_than: Option<i64>,
+
+    /// Filter only inputs with timestamp (in seconds since the Unix epoch) lower than a given value
+    pub timestamp_lower_than: Option<i64>,
+
+    /// Filter only inputs with timestamp (in seconds since the Unix epoch) greater than a given value
+    pub timestamp_greater_than: Option<i64>,
 }
 
-impl From<InputFilter> for InputQueryFilter {
-    fn from(filter: InputFilter) -> InputQueryFilter {
-        InputQueryFilter {
+impl TryFrom<InputFilter> for InputQueryFilter {
+    type Error = FieldError<DefaultScalarValue>;
+
+    fn try_from(filter: InputFilter) -> FieldResult<InputQueryFilter> {
+        Ok(InputQueryFilter {
+            index_lower_than: filter.index_lower_than,
+            index_greater_than: filter.index_greater_than,
+            msg_sender: filter
+                .msg_sender
+                .as_deref()
+                .map(hex_decode)
+                .transpose()?,
+            status: filter.status.map(DbCompletionStatus::from),
+            block_number_lower_than: filter.block_number_lower_than,
+            block_number_greater_than: filter.block_number_greater_than,
+            timestamp_lower_than: filter
+                .timestamp_lower_than
+                .map(timestamp_from_secs)
+                .transpose()?,
+            timestamp_greater_than: filter
+                .timestamp_greater_than
+                .map(timestamp_from_secs)
+                .transpose()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, GraphQLInputObject)]
+#[graphql(scalar = RollupsGraphQLScalarValue)]
+/// Filter object to restrict results depending on voucher properties
+pub struct VoucherFilter {
+    /// Filter only vouchers that were executed or not executed
+    pub executed: Option<bool>,
+
+    /// Filter only vouchers sent to a given address, in Ethereum hex binary format, starting with '0x'
+    pub destination: Option<String>,
+
+    /// Filter only vouchers with input index lower than a given value
+    pub input_index_lower_than: Option<i32>,
+
+    /// Filter only vouchers with input index greater than a given value
+    pub input_index_greater_than: Option<i32>,
+
+    /// Filter only vouchers whose payload starts with the given bytes, in Ethereum hex binary format, starting with '0x'
+    pub payload_prefix: Option<String>,
+}
+
+impl TryFrom<VoucherFilter> for VoucherQueryFilter {
+    type Error = FieldError<DefaultScalarValue>;
+
+    fn try_from(filter: VoucherFilter) -> FieldResult<VoucherQueryFilter> {
+        Ok(VoucherQueryFilter {
+            input_index_lower_than: filter.input_index_lower_than,
+            input_index_greater_than: filter.input_index_greater_than,
+            payload_prefix: filter
+                .payload_prefix
+                .as_deref()
+                .map(hex_decode)
+                .transpose()?,
+            destination: filter
+                .destination
+                .as_deref()
+                .map(hex_decode)
+                .transpose()?,
+            executed: filter.executed,
+            ..Default::default()
+        })
+    }
+}
+
+/// Declare the filter object of an output without specific properties
+macro_rules! decl_output_filter {
+    ($name: ident, $query_filter: ident, $description: literal) => {
+        #[derive(Debug, Clone, GraphQLInputObject)]
+        #[graphql(scalar = RollupsGraphQLScalarValue)]
+        #[doc = $description]
+        pub struct $name {
+            /// Filter only outputs with input index lower than a given value
+            pub input_index_lower_than: Option<i32>,
+
+            /// Filter only outputs with input index greater than a given value
+            pub input_index_greater_than: Option<i32>,
+
+            /// Filter only outputs whose payload starts with the given bytes, in Ethereum hex binary format, starting with '0x'
+            pub payload_prefix: Option<String>,
+        }
+
+        impl TryFrom<$name> for $query_filter {
+            type Error = FieldError<DefaultScalarValue>;
+
+            fn try_from(filter: $name) -> FieldResult<$query_filter> {
+                Ok($query_filter {
+                    input_index_lower_than: filter.input_index_lower_than,
+                    input_index_greater_than: filter.input_index_greater_than,
+                    payload_prefix: filter
+                        .payload_prefix
+                        .as_deref()
+                        .map(hex_decode)
+                        .transpose()?,
+                    ..Default::default()
+                })
+            }
+        }
+    };
+}
+
+decl_output_filter!(
+    NoticeFilter,
+    NoticeQueryFilter,
+    "Filter object to restrict results depending on notice properties"
+);
+decl_output_filter!(
+    ReportFilter,
+    ReportQueryFilter,
+    "Filter object to restrict results depending on report properties"
+);
+
+#[derive(Debug, Clone, GraphQLInputObject)]
+#[graphql(scalar = RollupsGraphQLScalarValue)]
+/// Filter object to restrict results depending on epoch properties
+pub struct EpochFilter {
+    /// Filter only epochs with index lower than a given value
+    pub index_lower_than: Option<i32>,
+
+    /// Filter only epochs with index greater than a given value
+    pub index_greater_than: Option<i32>,
+}
+
+impl From<EpochFilter> for EpochQueryFilter {
+    fn from(filter: EpochFilter) -> EpochQueryFilter {
+        EpochQueryFilter {
             index_lower_than: filter.index_lower_than,
             index_greater_than: filter.index_greater_than,
         }
@@ -691,7 +1251,8 @@ macro_rules! impl_connection {
                 description = "Total number of entries that match the query"
             )]
             fn total_count(&self) -> i32 {
-                self.total_count
+                // The count is only loaded when the field is selected
+                self.total_count.unwrap_or_default()
             }
 
             #[graphql(
@@ -731,6 +1292,15 @@ impl_connection!("InputConnection", "InputEdge", Input);
 impl_connection!("VoucherConnection", "VoucherEdge", Voucher);
 impl_connection!("NoticeConnection", "NoticeEdge", Notice);
 impl_connection!("ReportConnection", "ReportEdge", Report);
+impl_connection!("EpochConnection", "EpochEdge", Epoch);
+
+/// Whether the connection field selects the total count, which requires
+/// counting every entry that matches the query
+fn selects_total_count(
+    executor: &Executor<Context, RollupsGraphQLScalarValue>,
+) -> bool {
+    executor.look_ahead().has_child("totalCount")
+}
 
 fn convert_error(e: rollups_data::Error) -> FieldError<DefaultScalarValue> {
     tracing::warn!("Got error during query: {:?}", e);
@@ -740,3 +1310,15 @@ fn convert_error(e: rollups_data::Error) -> FieldError<DefaultScalarValue> {
 pub fn hex_encode(data: &[u8]) -> String {
     format!("0x{}", hex::encode(data))
 }
+
+fn hex_decode(data: &str) -> FieldResult<Vec<u8>> {
+    let digits = data.strip_prefix("0x").unwrap_or(data);
+    hex::decode(digits)
+        .map_err(|e| format!("invalid hex value `{}` ({})", data, e).into())
+}
+
+fn timestamp_from_secs(secs: i64) -> FieldResult<SystemTime> {
+    u64::try_from(secs)
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+        .map_err(|_| format!("invalid timestamp `{}`", secs).into())
+}
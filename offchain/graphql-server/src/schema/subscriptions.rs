@@ -0,0 +1,122 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+use futures::{future, Stream, StreamExt};
+use juniper::{graphql_subscription, FieldError, Value};
+use rollups_data::{Claim, Input, Notice, Report, Voucher};
+use std::pin::Pin;
+use tokio_stream::wrappers::{
+    errors::BroadcastStreamRecvError, BroadcastStream,
+};
+
+use super::resolvers::Context;
+use super::scalar::RollupsGraphQLScalarValue;
+use crate::notifications::Notification;
+
+type NotificationStream<T> = Pin<
+    Box<
+        dyn Stream<Item = Result<T, FieldError<RollupsGraphQLScalarValue>>>
+            + Send,
+    >,
+>;
+
+pub struct Subscription;
+
+#[graphql_subscription(
+    context = Context,
+    Scalar = RollupsGraphQLScalarValue,
+    description = "Top level subscriptions"
+)]
+impl Subscription {
+    #[graphql(description = "Get the inputs added to the application")]
+    async fn input_added(context: &Context) -> NotificationStream<Input> {
+        subscribe(context, |notification| match notification {
+            Notification::InputAdded(input) => Some(input),
+            _ => None,
+        })
+    }
+
+    #[graphql(
+        description = "Get the inputs whose status changed after they were processed"
+    )]
+    async fn input_status_changed(
+        context: &Context,
+    ) -> NotificationStream<Input> {
+        subscribe(context, |notification| match notification {
+            Notification::InputStatusChanged(input) => Some(input),
+            _ => None,
+        })
+    }
+
+    #[graphql(description = "Get the notices produced by the application")]
+    async fn notice_added(context: &Context) -> NotificationStream<Notice> {
+        subscribe(context, |notification| match notification {
+            Notification::NoticeAdded(notice) => Some(notice),
+            _ => None,
+        })
+    }
+
+    #[graphql(description = "Get the vouchers produced by the application")]
+    async fn voucher_added(context: &Context) -> NotificationStream<Voucher> {
+        subscribe(context, |notification| match notification {
+            Notification::VoucherAdded(voucher) => Some(voucher),
+            _ => None,
+        })
+    }
+
+    #[graphql(description = "Get the reports produced by the application")]
+    async fn report_added(context: &Context) -> NotificationStream<Report> {
+        subscribe(context, |notification| match notification {
+            Notification::ReportAdded(report) => Some(report),
+            _ => None,
+        })
+    }
+
+    #[graphql(
+        description = "Get the claims that were submitted to the base layer blockchain"
+    )]
+    async fn claim_submitted(context: &Context) -> NotificationStream<Claim> {
+        subscribe(context, |notification| match notification {
+            Notification::ClaimSubmitted(claim) => Some(claim),
+            _ => None,
+        })
+    }
+}
+
+/// Subscribe to the notifications selected by the given function
+/// If the subscriber lags behind, the missed notifications are lost, so the
+/// stream ends with an error and the client must subscribe again.
+fn subscribe<T, F>(context: &Context, select: F) -> NotificationStream<T>
+where
+    T: Send + 'static,
+    F: Fn(Notification) -> Option<T> + Send + 'static,
+{
+    let application_id = context.application_id();
+    let stream = BroadcastStream::new(context.subscribe())
+        .filter_map(move |result| {
+            let item = match result {
+                Ok(notification)
+                    if notification.application_id == application_id =>
+                {
+                    select(notification.notification).map(Ok)
+                }
+                Ok(_) => None,
+                Err(BroadcastStreamRecvError::Lagged(count)) => {
+                    tracing::warn!("subscriber lagged {} notifications", count);
+                    Some(Err(FieldError::new(
+                        format!("subscriber missed {} notifications", count),
+                        Value::null(),
+                    )))
+                }
+            };
+            future::ready(item)
+        })
+        .scan(false, |ended, item| {
+            if *ended {
+                return future::ready(None);
+            }
+            *ended = item.is_err();
+            future::ready(Some(item))
+        });
+    Box::pin(stream)
+}
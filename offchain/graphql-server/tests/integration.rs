@@ -4,16 +4,19 @@
 use actix_web::dev::ServerHandle;
 use actix_web::rt::spawn;
 use awc::{Client, ClientRequest};
+use futures::future::join_all;
+use graphql_server::notifications::{self, Notification};
 use graphql_server::{http, schema::Context};
 use rollups_data::{
-    CompletionStatus, Input, Notice, Proof, Report, Repository, Voucher,
+    AsyncRepository, Claim, ClaimStatus, CompletionStatus, Epoch, Input,
+    Notice, Proof, Report, RepositoryConfig, Voucher, DEFAULT_APPLICATION_ID,
 };
 use std::fs::read_to_string;
 use std::str::from_utf8;
 use std::time::{Duration, UNIX_EPOCH};
 use test_fixtures::RepositoryFixture;
 use testcontainers::clients::Cli;
-use tokio::sync::oneshot;
+use tokio::sync::{broadcast, oneshot};
 use tokio::task::JoinHandle;
 
 const QUERY_PATH: &str = "tests/queries/";
@@ -30,8 +33,7 @@ impl TestState<'_> {
     async fn setup(docker: &Cli) -> TestState<'_> {
         let repository = RepositoryFixture::setup(docker);
         let server =
-            GraphQLServerWrapper::spawn_server(repository.repository().clone())
-                .await;
+            GraphQLServerWrapper::spawn_server(repository.config()).await;
         TestState { repository, server }
     }
 
@@ -57,6 +59,8 @@ impl TestState<'_> {
             index: 0,
             destination: "destination".as_bytes().to_vec(),
             payload: "voucher-0-0".as_bytes().to_vec(),
+            executed: false,
+            execution_transaction_hash: None,
         };
 
         let report = Report {
@@ -105,6 +109,22 @@ impl TestState<'_> {
             context: "<context>".as_bytes().to_vec(),
         };
 
+        let epoch = Epoch {
+            index: 0,
+            first_input_index: 0,
+            last_input_index: 0,
+        };
+
+        let claim = Claim {
+            epoch_index: 0,
+            epoch_hash: "epoch-hash".as_bytes().to_vec(),
+            machine_state_hash: "<hash>".as_bytes().to_vec(),
+            vouchers_epoch_root_hash: "<hash>".as_bytes().to_vec(),
+            notices_epoch_root_hash: "<hash>".as_bytes().to_vec(),
+            status: ClaimStatus::Submitted,
+            transaction_hash: Some("tx-hash".as_bytes().to_vec()),
+        };
+
         let repo = self.repository.repository();
 
         repo.insert_input(input.clone())
@@ -124,6 +144,9 @@ impl TestState<'_> {
 
         repo.insert_proof(proof_voucher.clone())
             .expect("Failed to insert voucher type proof");
+
+        repo.insert_claim(epoch.clone(), claim.clone())
+            .expect("Failed to insert claim");
     }
 
     async fn populate_for_pagination(&self) {
@@ -195,7 +218,9 @@ pub struct GraphQLServerWrapper {
 }
 
 impl GraphQLServerWrapper {
-    async fn spawn_server(repository: Repository) -> Self {
+    async fn spawn_server(config: RepositoryConfig) -> Self {
+        let repository = AsyncRepository::new(config)
+            .expect("failed to create async repository");
         let context = Context::new(repository);
         let (tx, rx) = oneshot::channel();
 
@@ -359,6 +384,40 @@ async fn query_vouchers() {
     test.server.stop().await;
 }
 
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_executed_vouchers() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    test.populate_database().await;
+    test.repository
+        .repository()
+        .update_voucher_executed(0, 0, "tx-hash".as_bytes().to_vec())
+        .expect("Failed to update voucher");
+
+    let body = post_query_request("executed_vouchers.json").await;
+    assert_from_body(body, "executed_vouchers.json");
+    let body = post_query_request("unexecuted_vouchers.json").await;
+    assert_from_body(body, "unexecuted_vouchers.json");
+    test.server.stop().await;
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_filtered_outputs() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    test.populate_database().await;
+
+    let body = post_query_request("filtered_vouchers.json").await;
+    assert_from_body(body, "filtered_vouchers.json");
+    let body = post_query_request("filtered_notices.json").await;
+    assert_from_body(body, "filtered_notices.json");
+    let body = post_query_request("filtered_reports.json").await;
+    assert_from_body(body, "filtered_reports.json");
+    test.server.stop().await;
+}
+
 #[actix_web::test]
 #[serial_test::serial]
 async fn query_report() {
@@ -419,6 +478,56 @@ async fn query_input() {
     test.server.stop().await;
 }
 
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_input_concurrently() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    test.populate_database().await;
+
+    // The queries wait for the single connection of the fixture pool
+    let bodies =
+        join_all((0..20).map(|_| post_query_request("input.json"))).await;
+    for body in bodies {
+        assert_from_body(body, "input.json");
+    }
+    test.server.stop().await;
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_input_of_application() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    test.populate_database().await;
+
+    // The configured application adopts the rows of the default one
+    let repository = test.repository.repository();
+    repository
+        .adopt_default_application(1, &[0xfa; 20])
+        .expect("Should adopt default application");
+    repository
+        .register_application(1, &[0xfb; 20])
+        .expect("Should register application");
+
+    let dapp_address = format!("0x{}", "fa".repeat(20));
+    let body =
+        post_application_query_request(&dapp_address, "input.json").await;
+    assert_from_body(body, "input.json");
+
+    let dapp_address = format!("0x{}", "fb".repeat(20));
+    let body =
+        post_application_query_request(&dapp_address, "input.json").await;
+    assert_from_body(body, "input_not_found.json");
+
+    let dapp_address = format!("0x{}", "fc".repeat(20));
+    let req = create_get_request(&format!("graphql/{}", dapp_address));
+    let res = req.send().await.expect("Should get from graphql");
+    test.server.stop().await;
+
+    assert_eq!(res.status(), awc::http::StatusCode::NOT_FOUND);
+}
+
 #[actix_web::test]
 #[serial_test::serial]
 async fn query_input_with_voucher() {
@@ -503,6 +612,68 @@ async fn query_inputs() {
     test.server.stop().await;
 }
 
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_filtered_inputs() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    test.populate_database().await;
+
+    let body = post_query_request("filtered_inputs.json").await;
+    assert_from_body(body, "filtered_inputs.json");
+    let body = post_query_request("excluded_inputs.json").await;
+    assert_from_body(body, "excluded_inputs.json");
+    test.server.stop().await;
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_input_with_epoch() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    test.populate_database().await;
+
+    let body = post_query_request("input_with_epoch.json").await;
+    assert_from_body(body, "input_with_epoch.json");
+    test.server.stop().await;
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_proof_with_claim() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    test.populate_database().await;
+
+    let body = post_query_request("proof_with_claim.json").await;
+    assert_from_body(body, "proof_with_claim.json");
+    test.server.stop().await;
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_epoch() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    test.populate_database().await;
+
+    let body = post_query_request("epoch.json").await;
+    assert_from_body(body, "epoch.json");
+    test.server.stop().await;
+}
+
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_epochs() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    test.populate_database().await;
+
+    let body = post_query_request("epochs.json").await;
+    assert_from_body(body, "epochs.json");
+    test.server.stop().await;
+}
+
 #[actix_web::test]
 #[serial_test::serial]
 async fn query_next_page() {
@@ -527,6 +698,18 @@ async fn query_previous_page() {
     test.server.stop().await;
 }
 
+#[actix_web::test]
+#[serial_test::serial]
+async fn query_page_info() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    test.populate_for_pagination().await;
+
+    let body = post_query_request("page_info.json").await;
+    assert_from_body(body, "page_info.json");
+    test.server.stop().await;
+}
+
 #[actix_web::test]
 #[serial_test::serial]
 async fn query_error_missing_argument() {
@@ -563,6 +746,46 @@ async fn query_error_unknown_field() {
     test.server.stop().await;
 }
 
+#[actix_web::test]
+#[serial_test::serial]
+async fn notify_changes() {
+    let docker = Cli::default();
+    let test = TestState::setup(&docker).await;
+    let endpoint = test.repository.config().endpoint();
+    let repository = AsyncRepository::new(test.repository.config())
+        .expect("failed to create async repository");
+    let (sender, mut receiver) = broadcast::channel(16);
+    let listener = spawn(notifications::listen(endpoint, repository, sender));
+
+    // Wait for the listener to subscribe to the database channel
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    test.populate_database().await;
+
+    let mut notifications = vec![];
+    for _ in 0..5 {
+        let notification =
+            tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                .await
+                .expect("Should receive notification in time")
+                .expect("Should receive notification");
+        assert_eq!(notification.application_id, DEFAULT_APPLICATION_ID);
+        notifications.push(notification.notification);
+    }
+    listener.abort();
+    test.server.stop().await;
+
+    assert!(matches!(
+        notifications.as_slice(),
+        [
+            Notification::InputAdded(input),
+            Notification::NoticeAdded(_),
+            Notification::VoucherAdded(_),
+            Notification::ReportAdded(_),
+            Notification::ClaimSubmitted(claim),
+        ] if input.index == 0 && claim.epoch_index == 0
+    ));
+}
+
 fn create_get_request(endpoint: &str) -> ClientRequest {
     let client = Client::default();
 
@@ -586,6 +809,25 @@ async fn post_query_request(query_file: &str) -> actix_web::web::Bytes {
     body
 }
 
+async fn post_application_query_request(
+    dapp_address: &str,
+    query_file: &str,
+) -> actix_web::web::Bytes {
+    let query = String::from(QUERY_PATH) + query_file;
+    let client = Client::builder().timeout(Duration::from_secs(5)).finish();
+    let mut response = client
+        .post(format!(
+            "http://localhost:{}/graphql/{}",
+            PORT, dapp_address
+        ))
+        .insert_header(("Content-type", "application/json"))
+        .send_body(read_to_string(query).expect("Should read request file"))
+        .await
+        .expect("Should query server");
+
+    response.body().await.expect("Should be body")
+}
+
 fn assert_from_body(body: actix_web::web::Bytes, res_file: &str) {
     let response = String::from(RESPONSE_PATH) + res_file;
     assert_eq!(
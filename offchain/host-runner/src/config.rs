@@ -3,6 +3,9 @@
 
 use clap::Parser;
 use log::{LogConfig, LogEnvCliConfig};
+use std::path::PathBuf;
+
+use crate::limits;
 
 const DEFAULT_ADDRESS: &str = "0.0.0.0";
 #[derive(Debug, Clone)]
@@ -15,6 +18,14 @@ pub struct Config {
     pub http_rollup_server_address: String,
     pub http_rollup_server_port: u16,
     pub finish_timeout: u64,
+    pub advance_deadline: Option<u64>,
+    pub inspect_deadline: Option<u64>,
+    pub rx_buffer_size: usize,
+    pub tx_buffer_size: usize,
+    pub max_outputs_per_input: usize,
+    pub journal_directory: Option<PathBuf>,
+    pub record_file: Option<PathBuf>,
+    pub replay_file: Option<PathBuf>,
     pub healthcheck_port: u16,
 }
 
@@ -54,6 +65,46 @@ pub struct CLIConfig {
     #[arg(long, env, default_value = "10000")]
     pub finish_timeout: u64,
 
+    /// Duration in ms for the backend to finish an advance request
+    /// When exceeded, the input completes with time limit exceeded.
+    #[arg(long, env)]
+    pub advance_deadline: Option<u64>,
+
+    /// Duration in ms for the backend to finish an inspect request
+    /// When exceeded, the inspect completes with time limit exceeded.
+    #[arg(long, env)]
+    pub inspect_deadline: Option<u64>,
+
+    /// Size in bytes of the RX buffer of the machine
+    /// Larger inputs complete with payload length limit exceeded.
+    #[arg(long, env, default_value_t = limits::DEFAULT_BUFFER_SIZE)]
+    pub rx_buffer_size: usize,
+
+    /// Size in bytes of the TX buffer of the machine
+    /// Larger vouchers, notices, reports, and exceptions are rejected.
+    #[arg(long, env, default_value_t = limits::DEFAULT_BUFFER_SIZE)]
+    pub tx_buffer_size: usize,
+
+    /// Maximum number of vouchers, and of notices, of an input
+    #[arg(long, env, default_value_t = limits::DEFAULT_MAX_OUTPUTS_PER_INPUT)]
+    pub max_outputs_per_input: usize,
+
+    /// Directory of the session journals, which are replayed on startup
+    /// If not set, the sessions are lost when the host-runner restarts.
+    #[arg(long, env)]
+    pub journal_directory: Option<PathBuf>,
+
+    /// File where the requests of the backend without session prefix are
+    /// recorded with its responses
+    #[arg(long, env, conflicts_with = "replay_file")]
+    pub record_file: Option<PathBuf>,
+
+    /// Recording whose requests are fed to the backend without session prefix
+    /// The responses are compared with the recorded ones, and the host-runner
+    /// exits once the requests are replayed.
+    #[arg(long, env)]
+    pub replay_file: Option<PathBuf>,
+
     /// Port of health check
     #[arg(long, env = "HOST_RUNNER_HEALTHCHECK_PORT", default_value_t = 8080)]
     pub healthcheck_port: u16,
@@ -70,6 +121,14 @@ impl From<CLIConfig> for Config {
             http_rollup_server_address: cli_config.http_rollup_server_address,
             http_rollup_server_port: cli_config.http_rollup_server_port,
             finish_timeout: cli_config.finish_timeout,
+            advance_deadline: cli_config.advance_deadline,
+            inspect_deadline: cli_config.inspect_deadline,
+            rx_buffer_size: cli_config.rx_buffer_size,
+            tx_buffer_size: cli_config.tx_buffer_size,
+            max_outputs_per_input: cli_config.max_outputs_per_input,
+            journal_directory: cli_config.journal_directory,
+            record_file: cli_config.record_file,
+            replay_file: cli_config.replay_file,
             healthcheck_port: cli_config.healthcheck_port,
         }
     }
@@ -5,11 +5,40 @@ use async_trait::async_trait;
 use snafu::Snafu;
 use std::time::Duration;
 use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
 
+use crate::limits::MachineLimits;
 use crate::model::*;
+use crate::recording::{Entry, Recorder};
 
 const MPSC_BUFFER_SIZE: usize = 1000;
 
+/// Time and machine limits of the requests handled by the controller
+#[derive(Clone, Debug)]
+pub struct ControllerConfig {
+    /// Time for the finish request to wait for the next rollup request
+    pub finish_timeout: Duration,
+    /// Time for the backend to finish an advance request, if limited
+    pub advance_deadline: Option<Duration>,
+    /// Time for the backend to finish an inspect request, if limited
+    pub inspect_deadline: Option<Duration>,
+    /// Payload and output limits of the emulated machine
+    pub limits: MachineLimits,
+}
+
+impl ControllerConfig {
+    /// Config without deadlines for the advance and inspect requests, and
+    /// with the default limits of the machine
+    pub fn new(finish_timeout: Duration) -> Self {
+        Self {
+            finish_timeout,
+            advance_deadline: None,
+            inspect_deadline: None,
+            limits: MachineLimits::default(),
+        }
+    }
+}
+
 /// State-machine that controls the rollup state.
 #[derive(Clone, Debug)]
 pub struct Controller {
@@ -21,10 +50,11 @@ pub struct Controller {
     report_tx: mpsc::Sender<SyncReportRequest>,
     exception_tx: mpsc::Sender<SyncExceptionRequest>,
     shutdown_tx: mpsc::Sender<SyncShutdownRequest>,
+    recorder: Option<Recorder>,
 }
 
 impl Controller {
-    pub fn new(finish_timeout: Duration) -> Self {
+    pub fn new(config: ControllerConfig) -> Self {
         let (advance_tx, advance_rx) = mpsc::channel(MPSC_BUFFER_SIZE);
         let (inspect_tx, inspect_rx) = mpsc::channel(MPSC_BUFFER_SIZE);
         let (voucher_tx, voucher_rx) = mpsc::channel(MPSC_BUFFER_SIZE);
@@ -42,7 +72,10 @@ impl Controller {
             finish_rx,
             exception_rx,
             shutdown_rx,
-            finish_timeout,
+            finish_timeout: config.finish_timeout,
+            advance_deadline: config.advance_deadline,
+            inspect_deadline: config.inspect_deadline,
+            limits: config.limits,
         };
         let service = Service::new(data);
         tokio::spawn(service.run());
@@ -55,21 +88,45 @@ impl Controller {
             finish_tx,
             exception_tx,
             shutdown_tx,
+            recorder: None,
         }
     }
 
+    /// Record the advance and inspect requests with the responses of the
+    /// backend
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
     pub async fn advance(
         &self,
         request: AdvanceStateRequest,
     ) -> oneshot::Receiver<AdvanceResult> {
-        SyncRequest::send(&self.advance_tx, request).await
+        match &self.recorder {
+            Some(recorder) => {
+                let rx =
+                    SyncRequest::send(&self.advance_tx, request.clone()).await;
+                recorder
+                    .forward(rx, move |result| Entry::advance(&request, result))
+            }
+            None => SyncRequest::send(&self.advance_tx, request).await,
+        }
     }
 
     pub async fn inspect(
         &self,
         request: InspectStateRequest,
     ) -> oneshot::Receiver<InspectResult> {
-        SyncRequest::send(&self.inspect_tx, request).await
+        match &self.recorder {
+            Some(recorder) => {
+                let rx =
+                    SyncRequest::send(&self.inspect_tx, request.clone()).await;
+                recorder
+                    .forward(rx, move |result| Entry::inspect(&request, result))
+            }
+            None => SyncRequest::send(&self.inspect_tx, request).await,
+        }
     }
 
     pub async fn finish(
@@ -125,6 +182,10 @@ pub enum ControllerError {
         request_name: String,
         state_name: String,
     },
+    #[snafu(display("{} payload exceeds the TX buffer size", request_name))]
+    PayloadLengthLimitExceeded { request_name: String },
+    #[snafu(display("{} count exceeds the limit per input", request_name))]
+    OutputCountLimitExceeded { request_name: String },
 }
 
 struct Service {
@@ -256,6 +317,50 @@ struct SharedStateData {
     exception_rx: mpsc::Receiver<SyncExceptionRequest>,
     shutdown_rx: mpsc::Receiver<SyncShutdownRequest>,
     finish_timeout: Duration,
+    advance_deadline: Option<Duration>,
+    inspect_deadline: Option<Duration>,
+    limits: MachineLimits,
+}
+
+/// Check the payload of an output against the TX buffer of the machine
+fn check_payload_length(
+    fits: bool,
+    request_name: &str,
+) -> Result<(), ControllerError> {
+    if fits {
+        Ok(())
+    } else {
+        let err = ControllerError::PayloadLengthLimitExceeded {
+            request_name: request_name.into(),
+        };
+        tracing::warn!("{}", err.to_string());
+        Err(err)
+    }
+}
+
+/// Check the number of outputs of the current input against the limit
+fn check_output_count(
+    count: usize,
+    limits: &MachineLimits,
+    request_name: &str,
+) -> Result<(), ControllerError> {
+    if count < limits.max_outputs_per_input {
+        Ok(())
+    } else {
+        let err = ControllerError::OutputCountLimitExceeded {
+            request_name: request_name.into(),
+        };
+        tracing::warn!("{}", err.to_string());
+        Err(err)
+    }
+}
+
+/// Wait until the deadline of the request, or forever if there is none
+async fn sleep_until_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
 }
 
 /// OOP state design-pattern
@@ -345,6 +450,12 @@ impl State for FetchRequestState {
                 tracing::debug!("received inspect request; setting state to inspect");
                 tracing::debug!("request: {:?}", request);
                 let (inspect_request, inspect_response_tx) = request.into_inner();
+                if !self.data.limits.fits_inspect(&inspect_request) {
+                    tracing::warn!("inspect payload exceeds the RX buffer size");
+                    let result = InspectResult::payload_length_limit_exceeded();
+                    send_response(inspect_response_tx, result);
+                    return Some(self);
+                }
                 let rollup_request = RollupRequest::InspectState(inspect_request);
                 send_response(self.finish_response_tx, Ok(rollup_request));
                 Some(Box::new(InspectState::new(self.data, inspect_response_tx)))
@@ -353,6 +464,12 @@ impl State for FetchRequestState {
                 tracing::debug!("received advance request; setting state to advance");
                 tracing::debug!("request: {:?}", request);
                 let (advance_request, advance_response_tx) = request.into_inner();
+                if !self.data.limits.fits_advance(&advance_request) {
+                    tracing::warn!("advance payload exceeds the RX buffer size");
+                    let result = AdvanceResult::payload_length_limit_exceeded();
+                    send_response(advance_response_tx, result);
+                    return Some(self);
+                }
                 let rollup_request = RollupRequest::AdvanceState(advance_request);
                 send_response(self.finish_response_tx, Ok(rollup_request));
                 Some(Box::new(AdvanceState::new(self.data, advance_response_tx)))
@@ -393,6 +510,7 @@ struct InspectState {
     data: SharedStateData,
     inspect_response_tx: oneshot::Sender<InspectResult>,
     reports: Vec<Report>,
+    deadline: Option<Instant>,
 }
 
 impl InspectState {
@@ -400,10 +518,14 @@ impl InspectState {
         data: SharedStateData,
         inspect_response_tx: oneshot::Sender<InspectResult>,
     ) -> Self {
+        let deadline = data
+            .inspect_deadline
+            .map(|deadline| Instant::now() + deadline);
         Self {
             data,
             inspect_response_tx,
             reports: vec![],
+            deadline,
         }
     }
 }
@@ -428,6 +550,8 @@ impl State for InspectState {
                 tracing::debug!("received report request");
                 tracing::debug!("request: {:?}", request);
                 request.process(|report| {
+                    let fits = self.data.limits.fits_output(&report.payload);
+                    check_payload_length(fits, "report")?;
                     self.reports.push(report);
                     Ok(())
                 });
@@ -437,11 +561,22 @@ impl State for InspectState {
                 tracing::debug!("received exception request; setting state to idle");
                 tracing::debug!("request: {:?}", request);
                 let (exception, exception_response_tx) = request.into_inner();
+                let fits = self.data.limits.fits_output(&exception.payload);
+                if let Err(e) = check_payload_length(fits, "exception") {
+                    send_response(exception_response_tx, Err(e));
+                    return Some(self);
+                }
                 let result = InspectResult::exception(self.reports, exception);
                 send_response(self.inspect_response_tx, result);
                 send_response(exception_response_tx, Ok(()));
                 Some(Box::new(IdleState::new(self.data)))
             }
+            _ = sleep_until_deadline(self.deadline) => {
+                tracing::warn!("inspect request exceeded the deadline; setting state to idle");
+                let result = InspectResult::time_limit_exceeded(self.reports);
+                send_response(self.inspect_response_tx, result);
+                Some(Box::new(IdleState::new(self.data)))
+            }
             Some(request) = self.data.voucher_rx.recv() => {
                 Service::handle_invalid(request, self, "voucher")
             }
@@ -466,6 +601,7 @@ struct AdvanceState {
     vouchers: Vec<Voucher>,
     notices: Vec<Notice>,
     reports: Vec<Report>,
+    deadline: Option<Instant>,
 }
 
 impl AdvanceState {
@@ -473,12 +609,16 @@ impl AdvanceState {
         data: SharedStateData,
         advance_response_tx: oneshot::Sender<AdvanceResult>,
     ) -> Self {
+        let deadline = data
+            .advance_deadline
+            .map(|deadline| Instant::now() + deadline);
         Self {
             data,
             advance_response_tx,
             vouchers: vec![],
             notices: vec![],
             reports: vec![],
+            deadline,
         }
     }
 }
@@ -513,6 +653,10 @@ impl State for AdvanceState {
                 tracing::debug!("received voucher request");
                 tracing::debug!("request: {:?}", request);
                 request.process(|voucher| {
+                    let fits = self.data.limits.fits_voucher(&voucher.payload);
+                    check_payload_length(fits, "voucher")?;
+                    let limits = &self.data.limits;
+                    check_output_count(self.vouchers.len(), limits, "voucher")?;
                     self.vouchers.push(voucher);
                     Ok(self.vouchers.len() - 1)
                 });
@@ -522,6 +666,10 @@ impl State for AdvanceState {
                 tracing::debug!("received notice request");
                 tracing::debug!("request: {:?}", request);
                 request.process(|notice| {
+                    let fits = self.data.limits.fits_output(&notice.payload);
+                    check_payload_length(fits, "notice")?;
+                    let limits = &self.data.limits;
+                    check_output_count(self.notices.len(), limits, "notice")?;
                     self.notices.push(notice);
                     Ok(self.notices.len() - 1)
                 });
@@ -531,6 +679,8 @@ impl State for AdvanceState {
                 tracing::debug!("received report request");
                 tracing::debug!("request: {:?}", request);
                 request.process(|report| {
+                    let fits = self.data.limits.fits_output(&report.payload);
+                    check_payload_length(fits, "report")?;
                     self.reports.push(report);
                     Ok(())
                 });
@@ -540,6 +690,11 @@ impl State for AdvanceState {
                 tracing::debug!("received exception request; setting state to idle");
                 tracing::debug!("request: {:?}", request);
                 let (exception, exception_response_tx) = request.into_inner();
+                let fits = self.data.limits.fits_output(&exception.payload);
+                if let Err(e) = check_payload_length(fits, "exception") {
+                    send_response(exception_response_tx, Err(e));
+                    return Some(self);
+                }
                 let result = AdvanceResult::exception(
                     exception,
                     self.reports,
@@ -548,6 +703,12 @@ impl State for AdvanceState {
                 send_response(exception_response_tx, Ok(()));
                 Some(Box::new(IdleState::new(self.data)))
             }
+            _ = sleep_until_deadline(self.deadline) => {
+                tracing::warn!("advance request exceeded the deadline; setting state to idle");
+                let result = AdvanceResult::time_limit_exceeded(self.reports);
+                send_response(self.advance_response_tx, result);
+                Some(Box::new(IdleState::new(self.data)))
+            }
             Some(request) = self.data.shutdown_rx.recv() => {
                 Service::shutdown(request)
             }
@@ -564,9 +725,29 @@ mod tests {
     use super::*;
 
     const TEST_FINISH_TIMEOUT: Duration = Duration::from_millis(100);
+    const TEST_DEADLINE: Duration = Duration::from_millis(100);
 
     fn setup() -> Controller {
-        Controller::new(TEST_FINISH_TIMEOUT)
+        Controller::new(ControllerConfig::new(TEST_FINISH_TIMEOUT))
+    }
+
+    fn setup_with_deadlines() -> Controller {
+        Controller::new(ControllerConfig {
+            finish_timeout: TEST_FINISH_TIMEOUT,
+            advance_deadline: Some(TEST_DEADLINE),
+            inspect_deadline: Some(TEST_DEADLINE),
+            limits: MachineLimits::default(),
+        })
+    }
+
+    fn setup_with_limits() -> Controller {
+        let mut config = ControllerConfig::new(TEST_FINISH_TIMEOUT);
+        config.limits = MachineLimits {
+            rx_buffer_size: 256,
+            tx_buffer_size: 128,
+            max_outputs_per_input: 1,
+        };
+        Controller::new(config)
     }
 
     #[tokio::test]
@@ -900,6 +1081,137 @@ mod tests {
         controller.shutdown().await;
     }
 
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_it_exceeds_the_time_limit_during_advance() {
+        let controller = setup_with_deadlines();
+        // Set state to advance
+        let advance_rx = controller.advance(mock_advance_request()).await;
+        let finish_rx = controller.finish(FinishStatus::Accept).await;
+        let _ = finish_rx.await.unwrap().unwrap();
+        // Insert report and let the deadline pass
+        let report = mock_report();
+        let report_rx = controller.insert_report(report.clone()).await;
+        report_rx.await.unwrap().unwrap();
+        let advance_result = advance_rx.await.unwrap();
+        assert_eq!(
+            advance_result,
+            AdvanceResult::time_limit_exceeded(vec![report])
+        );
+        // The late outputs of the backend are rejected
+        let voucher_rx = controller.insert_voucher(mock_voucher()).await;
+        assert_eq!(
+            voucher_rx.await.unwrap().unwrap_err(),
+            ControllerError::InvalidRequest {
+                request_name: String::from("voucher"),
+                state_name: String::from("idle")
+            }
+        );
+        controller.shutdown().await;
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_it_exceeds_the_time_limit_during_inspect() {
+        let controller = setup_with_deadlines();
+        // Set state to inspect
+        let inspect_rx = controller.inspect(mock_inspect_request()).await;
+        let finish_rx = controller.finish(FinishStatus::Accept).await;
+        let _ = finish_rx.await.unwrap().unwrap();
+        let result = inspect_rx.await.unwrap();
+        assert_eq!(result, InspectResult::time_limit_exceeded(vec![]));
+        controller.shutdown().await;
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_it_advances_state_within_the_deadline() {
+        let controller = setup_with_deadlines();
+        let advance_rx = controller.advance(mock_advance_request()).await;
+        let finish_rx = controller.finish(FinishStatus::Accept).await;
+        let _ = finish_rx.await.unwrap().unwrap();
+        let _ = controller.finish(FinishStatus::Reject).await;
+        let advance_result = advance_rx.await.unwrap();
+        assert_eq!(advance_result, AdvanceResult::rejected(vec![]));
+        controller.shutdown().await;
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_it_exceeds_the_payload_length_limit_of_the_input() {
+        let controller = setup_with_limits();
+        let finish_rx = controller.finish(FinishStatus::Accept).await;
+        let mut request = mock_advance_request();
+        request.payload = vec![0; 64];
+        let advance_rx = controller.advance(request).await;
+        let advance_result = advance_rx.await.unwrap();
+        assert_eq!(
+            advance_result,
+            AdvanceResult::payload_length_limit_exceeded()
+        );
+        let inspect_rx = controller
+            .inspect(InspectStateRequest {
+                payload: vec![0; 193],
+            })
+            .await;
+        let inspect_result = inspect_rx.await.unwrap();
+        assert_eq!(
+            inspect_result,
+            InspectResult::payload_length_limit_exceeded()
+        );
+        // The backend only receives the requests that fit the RX buffer
+        let request = mock_advance_request();
+        let _ = controller.advance(request.clone()).await;
+        let rollup_request = finish_rx.await.unwrap().unwrap();
+        assert_eq!(rollup_request, RollupRequest::AdvanceState(request));
+        controller.shutdown().await;
+    }
+
+    #[tokio::test]
+    #[tracing_test::traced_test]
+    async fn test_it_rejects_outputs_exceeding_the_machine_limits() {
+        let controller = setup_with_limits();
+        let advance_rx = controller.advance(mock_advance_request()).await;
+        let finish_rx = controller.finish(FinishStatus::Accept).await;
+        let _ = finish_rx.await.unwrap().unwrap();
+        let voucher = Voucher::new(rand::random(), vec![0; 33]);
+        let voucher_rx = controller.insert_voucher(voucher).await;
+        assert_eq!(
+            voucher_rx.await.unwrap().unwrap_err(),
+            ControllerError::PayloadLengthLimitExceeded {
+                request_name: String::from("voucher")
+            }
+        );
+        let report = Report {
+            payload: vec![0; 65],
+        };
+        let report_rx = controller.insert_report(report).await;
+        assert_eq!(
+            report_rx.await.unwrap().unwrap_err(),
+            ControllerError::PayloadLengthLimitExceeded {
+                request_name: String::from("report")
+            }
+        );
+        let notice = mock_notice();
+        let notice_rx = controller.insert_notice(notice.clone()).await;
+        assert_eq!(notice_rx.await.unwrap().unwrap(), 0);
+        let notice_rx = controller.insert_notice(mock_notice()).await;
+        assert_eq!(
+            notice_rx.await.unwrap().unwrap_err(),
+            ControllerError::OutputCountLimitExceeded {
+                request_name: String::from("notice")
+            }
+        );
+        // The input completes with the outputs within the limits
+        let _ = controller.finish(FinishStatus::Accept).await;
+        let advance_result = advance_rx.await.unwrap();
+        assert_eq!(
+            advance_result,
+            AdvanceResult::accepted(vec![], vec![notice], vec![])
+        );
+        controller.shutdown().await;
+    }
+
     fn mock_voucher() -> Voucher {
         Voucher::new(rand::random(), rand::random::<[u8; 32]>().into())
     }
@@ -4,6 +4,7 @@
 mod server_manager;
 
 use futures_util::FutureExt;
+use snafu::{ResultExt, Snafu};
 use std::future::Future;
 use tonic::transport::Server;
 use tonic_health::pb::health_server::{Health, HealthServer};
@@ -12,7 +13,19 @@ use grpc_interfaces::cartesi_server_manager::server_manager_server::ServerManage
 use server_manager::ServerManagerService;
 
 use crate::config::Config;
-use crate::controller::Controller;
+use crate::journal::JournalError;
+use crate::registry::ControllerRegistry;
+
+#[derive(Debug, Snafu)]
+pub enum ServiceError {
+    #[snafu(display(
+        "failed to restore the sessions from the journal ({})",
+        source
+    ))]
+    Journal { source: JournalError },
+    #[snafu(display("gRPC transport error ({})", source))]
+    Transport { source: tonic::transport::Error },
+}
 
 /// Create the grpc healthcheck for the host-runner
 ///
@@ -29,19 +42,22 @@ async fn create_health_service() -> HealthServer<impl Health> {
 
 pub async fn start_service<F: Future<Output = ()>>(
     config: &Config,
-    controller: Controller,
+    registry: ControllerRegistry,
     signal: F,
-) -> Result<(), tonic::transport::Error> {
+) -> Result<(), ServiceError> {
     let addr = format!(
         "{}:{}",
         config.grpc_server_manager_address, config.grpc_server_manager_port
     )
     .parse()
     .expect("invalid config");
-    let service = ServerManagerService::new(controller);
+    let service =
+        ServerManagerService::new(registry, config.journal_directory.clone());
+    service.restore().await.context(JournalSnafu)?;
     Server::builder()
         .add_service(create_health_service().await)
         .add_service(ServerManagerServer::new(service))
         .serve_with_shutdown(addr, signal.map(|_| ()))
         .await
+        .context(TransportSnafu)
 }
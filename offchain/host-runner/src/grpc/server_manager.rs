@@ -3,6 +3,7 @@
 
 use crate::controller::Controller;
 use crate::hash::{Hash, HASH_SIZE};
+use crate::journal::{self, Journal, JournalError, Record};
 use crate::merkle_tree::{
     complete::Tree, proof::Proof, Error as MerkleTreeError,
 };
@@ -11,6 +12,7 @@ use crate::model::{
     InspectStateRequest, InspectStatus, Notice, Report, Voucher,
 };
 use crate::proofs::compute_proofs;
+use crate::registry::ControllerRegistry;
 use ethabi::ethereum_types::U256;
 use ethabi::Token;
 use grpc_interfaces::cartesi_machine::{
@@ -30,22 +32,30 @@ use grpc_interfaces::cartesi_server_manager::{
     TaintStatus, Voucher as GrpcVoucher,
 };
 use grpc_interfaces::versioning::{GetVersionResponse, SemanticVersion};
-use std::{collections::HashMap, sync::Arc};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::sync::Mutex;
 use tonic::{Request, Response, Status};
 
 pub struct ServerManagerService {
-    controller: Controller,
     sessions: SessionManager,
 }
 
 impl ServerManagerService {
-    pub fn new(controller: Controller) -> Self {
+    pub fn new(
+        registry: ControllerRegistry,
+        journal_directory: Option<PathBuf>,
+    ) -> Self {
         Self {
-            controller,
-            sessions: SessionManager::new(),
+            sessions: SessionManager::new(registry, journal_directory),
         }
     }
+
+    /// Restore the sessions from the journal directory, if there is one
+    pub async fn restore(&self) -> Result<(), JournalError> {
+        self.sessions.restore().await
+    }
 }
 
 #[tonic::async_trait]
@@ -76,9 +86,9 @@ impl ServerManager for ServerManagerService {
         self.sessions
             .try_set_session(
                 request.session_id,
+                request.machine_directory,
                 request.active_epoch_index,
                 request.processed_input_count,
-                self.controller.clone(),
             )
             .await?;
         let response = StartSessionResponse { config: None };
@@ -152,9 +162,9 @@ impl ServerManager for ServerManagerService {
     ) -> Result<Response<FinishEpochResponse>, Status> {
         let request = request.into_inner();
         tracing::info!("received finish_epoch with id={}", request.session_id);
-        if !request.storage_directory.is_empty() {
-            tracing::warn!("ignoring storage_directory parameter");
-        }
+        let storage_directory = Some(request.storage_directory.as_str())
+            .filter(|directory| !directory.is_empty())
+            .map(Path::new);
         let response = self
             .sessions
             .try_get_session(&request.session_id)
@@ -164,6 +174,7 @@ impl ServerManager for ServerManagerService {
             .try_finish_epoch(
                 request.active_epoch_index,
                 request.processed_input_count_within_epoch,
+                storage_directory,
             )
             .await?;
         Ok(Response::new(response))
@@ -251,56 +262,95 @@ impl ServerManager for ServerManagerService {
     }
 }
 
+/// Sessions of the host-runner, each one with its own controller
 struct SessionManager {
-    entry: Mutex<Option<SessionEntry>>,
+    registry: ControllerRegistry,
+    journal_directory: Option<PathBuf>,
+    sessions: Mutex<HashMap<String, Arc<Mutex<Session>>>>,
 }
 
 impl SessionManager {
-    fn new() -> Self {
+    fn new(
+        registry: ControllerRegistry,
+        journal_directory: Option<PathBuf>,
+    ) -> Self {
         Self {
-            entry: Mutex::new(None),
+            registry,
+            journal_directory,
+            sessions: Mutex::new(HashMap::new()),
         }
     }
 
+    async fn restore(&self) -> Result<(), JournalError> {
+        let directory = match &self.journal_directory {
+            Some(directory) => directory,
+            None => return Ok(()),
+        };
+        let mut sessions = self.sessions.lock().await;
+        for (session_id, records) in journal::load(directory)? {
+            tracing::info!("restoring session {} from the journal", session_id);
+            let controller = self.registry.acquire(&session_id).await;
+            let journal = Journal::open(directory, &session_id)?;
+            let session =
+                Session::restore(&session_id, records, controller, journal)
+                    .await?;
+            sessions.insert(session_id, Arc::new(Mutex::new(session)));
+        }
+        Ok(())
+    }
+
     async fn try_set_session(
         &self,
         session_id: String,
+        machine_directory: String,
         active_epoch_index: u64,
         processed_input_count: u64,
-        controller: Controller,
     ) -> Result<(), Status> {
         if session_id.is_empty() {
             return Err(Status::invalid_argument("session id is empty"));
         }
-        let mut entry = self.entry.lock().await;
-        match *entry {
-            Some(_) => {
-                tracing::warn!(
-                    "the host-runner only supports a single session"
-                );
-                Err(Status::already_exists("session id is taken"))
-            }
-            None => {
-                *entry = Some(SessionEntry::new(
-                    session_id,
+        if !machine_directory.is_empty() {
+            check_snapshot(
+                Path::new(&machine_directory),
+                active_epoch_index,
+                processed_input_count,
+            )?;
+        }
+        let mut sessions = self.sessions.lock().await;
+        if sessions.contains_key(&session_id) {
+            return Err(Status::already_exists("session id is taken"));
+        }
+        let journal = match &self.journal_directory {
+            Some(directory) => {
+                let mut journal = Journal::create(directory, &session_id)?;
+                journal.append(&Record::SessionStarted {
                     active_epoch_index,
                     processed_input_count,
-                    controller,
-                ));
-                Ok(())
+                })?;
+                Some(journal)
             }
-        }
+            None => None,
+        };
+        let controller = self.registry.acquire(&session_id).await;
+        let session = Session::new(
+            active_epoch_index,
+            processed_input_count,
+            controller,
+            journal,
+        );
+        sessions.insert(session_id, Arc::new(Mutex::new(session)));
+        Ok(())
     }
 
     async fn try_get_session(
         &self,
         request_id: &String,
     ) -> Result<Arc<Mutex<Session>>, Status> {
-        self.entry
+        self.sessions
             .lock()
             .await
-            .as_ref()
-            .and_then(|entry| entry.get_session(request_id))
+            .get(request_id)
+            .cloned()
             .ok_or(Status::invalid_argument("session id not found"))
     }
 
@@ -309,54 +359,39 @@ impl SessionManager {
             .await?
             .try_lock()
             .or(Err(Status::aborted("concurrent call in session")))?
-            .check_endable()
+            .try_end()
             .await?;
-        let mut entry = self.entry.lock().await;
-        *entry = None;
+        self.sessions.lock().await.remove(request_id);
+        self.registry.release(request_id).await;
         Ok(())
     }
 
     async fn get_sessions(&self) -> Vec<String> {
-        let mut sessions = Vec::new();
-        if let Some(entry) = self.entry.lock().await.as_ref() {
-            sessions.push(entry.get_id());
-        }
+        let mut sessions: Vec<String> =
+            self.sessions.lock().await.keys().cloned().collect();
+        sessions.sort();
         sessions
     }
 }
 
-struct SessionEntry {
-    id: String,
-    session: Arc<Mutex<Session>>,
-}
-
-impl SessionEntry {
-    fn new(
-        id: String,
-        active_epoch_index: u64,
-        processed_input_count: u64,
-        controller: Controller,
-    ) -> Self {
-        Self {
-            id,
-            session: Arc::new(Mutex::new(Session::new(
-                active_epoch_index,
-                processed_input_count,
-                controller,
-            ))),
-        }
-    }
-
-    fn get_session(&self, request_id: &String) -> Option<Arc<Mutex<Session>>> {
-        if &self.id == request_id {
-            Some(self.session.clone())
-        } else {
-            None
+/// Check that the snapshot in the machine directory, if any, was taken at the
+/// start of the session
+fn check_snapshot(
+    machine_directory: &Path,
+    active_epoch_index: u64,
+    processed_input_count: u64,
+) -> Result<(), Status> {
+    match journal::read_snapshot(machine_directory)? {
+        Some((snapshot_epoch_index, snapshot_input_count))
+            if (snapshot_epoch_index, snapshot_input_count)
+                != (active_epoch_index, processed_input_count) =>
+        {
+            Err(Status::invalid_argument(format!(
+                "session doesn't match the machine snapshot (expected epoch {} with {} processed inputs)",
+                snapshot_epoch_index, snapshot_input_count
+            )))
         }
-    }
-
-    fn get_id(&self) -> String {
-        self.id.clone()
+        _ => Ok(()),
     }
 }
 
@@ -365,6 +400,7 @@ struct Session {
     controller: Controller,
     epochs: HashMap<u64, Arc<Mutex<Epoch>>>,
     tainted: Arc<Mutex<Option<Status>>>,
+    journal: Option<Arc<Mutex<Journal>>>,
 }
 
 impl Session {
@@ -372,6 +408,7 @@ impl Session {
         active_epoch_index: u64,
         processed_input_count: u64,
         controller: Controller,
+        journal: Option<Journal>,
     ) -> Self {
         let epoch = Arc::new(Mutex::new(Epoch::new(processed_input_count)));
         let mut epochs = HashMap::new();
@@ -381,7 +418,106 @@ impl Session {
             controller,
             epochs,
             tainted: Arc::new(Mutex::new(None)),
+            journal: journal.map(|journal| Arc::new(Mutex::new(journal))),
+        }
+    }
+
+    /// Rebuild the session by replaying the records of its journal
+    /// The inputs that were still pending are sent to the backend again.
+    async fn restore(
+        session_id: &str,
+        records: Vec<Record>,
+        controller: Controller,
+        journal: Journal,
+    ) -> Result<Self, JournalError> {
+        let replay_error = |message: &str| JournalError::Replay {
+            session_id: session_id.to_owned(),
+            message: message.to_owned(),
+        };
+        let mut records = records.into_iter();
+        let mut session = match records.next() {
+            Some(Record::SessionStarted {
+                active_epoch_index,
+                processed_input_count,
+            }) => Session::new(
+                active_epoch_index,
+                processed_input_count,
+                controller,
+                None,
+            ),
+            _ => return Err(replay_error("missing start of session")),
+        };
+        let mut pending_inputs: VecDeque<(
+            Arc<Mutex<Epoch>>,
+            AdvanceStateRequest,
+        )> = VecDeque::new();
+        for record in records {
+            match record {
+                Record::SessionStarted { .. } => {
+                    return Err(replay_error("duplicated start of session"));
+                }
+                Record::InputAdvanced {
+                    active_epoch_index,
+                    current_input_index,
+                    request,
+                } => {
+                    session
+                        .check_active_epoch(active_epoch_index)
+                        .map_err(|e| replay_error(e.message()))?;
+                    let epoch = session
+                        .try_get_epoch(active_epoch_index)
+                        .map_err(|e| replay_error(e.message()))?
+                        .clone();
+                    epoch
+                        .lock()
+                        .await
+                        .try_add_pending_input(current_input_index)
+                        .map_err(|e| replay_error(e.message()))?;
+                    pending_inputs.push_back((epoch, request.into()));
+                }
+                Record::InputProcessed { result } => {
+                    let (epoch, _) = pending_inputs.pop_front().ok_or(
+                        replay_error("processed input wasn't advanced"),
+                    )?;
+                    let processed =
+                        epoch.lock().await.add_processed_input(result.into());
+                    if let Err(e) = processed {
+                        tracing::error!(
+                            "failed to add processed input; tainting session"
+                        );
+                        *session.tainted.lock().await = Some(e);
+                    }
+                }
+                Record::EpochFinished {
+                    active_epoch_index,
+                    processed_input_count_within_epoch,
+                } => {
+                    session
+                        .try_finish_epoch(
+                            active_epoch_index,
+                            processed_input_count_within_epoch,
+                            None,
+                        )
+                        .await
+                        .map_err(|e| replay_error(e.message()))?;
+                }
+                Record::EpochDeleted { epoch_index } => {
+                    session
+                        .try_delete_epoch(epoch_index)
+                        .await
+                        .map_err(|e| replay_error(e.message()))?;
+                }
+            }
         }
+        session.journal = Some(Arc::new(Mutex::new(journal)));
+        for (epoch, advance_request) in pending_inputs {
+            tracing::info!(
+                "sending pending input {} to the backend again",
+                advance_request.metadata.input_index
+            );
+            session.dispatch(epoch, advance_request).await;
+        }
+        Ok(session)
     }
 
     async fn try_advance(
@@ -393,21 +529,44 @@ impl Session {
         self.check_epoch_index_overflow()?;
         self.check_tainted().await?;
         self.check_active_epoch(active_epoch_index)?;
-        let epoch = self.try_get_epoch(active_epoch_index)?;
+        let epoch = self.try_get_epoch(active_epoch_index)?.clone();
         epoch
             .lock()
             .await
             .try_add_pending_input(current_input_index)?;
+        self.try_append_record(Record::InputAdvanced {
+            active_epoch_index,
+            current_input_index,
+            request: (&advance_request).into(),
+        })
+        .await?;
+        self.dispatch(epoch, advance_request).await;
+        Ok(())
+    }
+
+    /// Send the advance request to the backend
+    async fn dispatch(
+        &self,
+        epoch: Arc<Mutex<Epoch>>,
+        advance_request: AdvanceStateRequest,
+    ) {
         let rx = self.controller.advance(advance_request).await;
-        let epoch = epoch.clone();
+        let journal = self.journal.clone();
         let tainted = self.tainted.clone();
         // Handle the advance response in another thread
         tokio::spawn(async move {
             match rx.await {
                 Ok(result) => {
-                    if let Err(e) =
-                        epoch.lock().await.add_processed_input(result)
-                    {
+                    let mut epoch = epoch.lock().await;
+                    let record = Record::InputProcessed {
+                        result: (&result).into(),
+                    };
+                    let processed =
+                        match append_record(journal.as_ref(), &record).await {
+                            Ok(_) => epoch.add_processed_input(result),
+                            Err(e) => Err(e.into()),
+                        };
+                    if let Err(e) = processed {
                         tracing::error!(
                             "failed to add processed input; tainting session"
                         );
@@ -419,7 +578,6 @@ impl Session {
                 }
             }
         });
-        Ok(())
     }
 
     async fn try_inspect(
@@ -459,10 +617,16 @@ impl Session {
         &mut self,
         active_epoch_index: u64,
         processed_input_count_within_epoch: u64,
+        storage_directory: Option<&Path>,
     ) -> Result<FinishEpochResponse, Status> {
         self.check_epoch_index_overflow()?;
         self.check_tainted().await?;
         self.check_active_epoch(active_epoch_index)?;
+        if storage_directory.is_some_and(Path::exists) {
+            return Err(Status::already_exists(
+                "storage directory already exists",
+            ));
+        }
         let (response, processed_input_count_since_genesis) = {
             let mut last_epoch =
                 self.try_get_epoch(active_epoch_index)?.lock().await;
@@ -475,11 +639,22 @@ impl Session {
             )
         };
         self.active_epoch_index += 1;
-        let epoch = Arc::new(Mutex::new(Epoch::new(
-            processed_input_count_since_genesis
-                + processed_input_count_within_epoch,
-        )));
+        let processed_input_count = processed_input_count_since_genesis
+            + processed_input_count_within_epoch;
+        let epoch = Arc::new(Mutex::new(Epoch::new(processed_input_count)));
         self.epochs.insert(self.active_epoch_index, epoch);
+        self.try_append_record(Record::EpochFinished {
+            active_epoch_index,
+            processed_input_count_within_epoch,
+        })
+        .await?;
+        if let Some(storage_directory) = storage_directory {
+            journal::write_snapshot(
+                storage_directory,
+                self.active_epoch_index,
+                processed_input_count,
+            )?;
+        }
         Ok(response)
     }
 
@@ -493,6 +668,8 @@ impl Session {
             .await
             .check_finished()?;
         self.epochs.remove(&epoch_index);
+        self.try_append_record(Record::EpochDeleted { epoch_index })
+            .await?;
         Ok(())
     }
 
@@ -541,13 +718,28 @@ impl Session {
             .ok_or(Status::invalid_argument("unknown epoch index"))
     }
 
-    async fn check_endable(&self) -> Result<(), Status> {
+    /// Check whether the session may end and remove its journal
+    async fn try_end(&self) -> Result<(), Status> {
         if self.tainted.lock().await.is_none() {
             self.try_get_epoch(self.active_epoch_index)?
                 .lock()
                 .await
                 .check_endable()?;
         }
+        if let Some(journal) = &self.journal {
+            journal.lock().await.remove()?;
+        }
+        Ok(())
+    }
+
+    /// Append the record to the journal, tainting the session if it fails
+    async fn try_append_record(&self, record: Record) -> Result<(), Status> {
+        if let Err(e) = append_record(self.journal.as_ref(), &record).await {
+            tracing::error!("failed to write journal; tainting session");
+            let status = Status::from(e);
+            *self.tainted.lock().await = Some(status.clone());
+            return Err(status);
+        }
         Ok(())
     }
 
@@ -582,6 +774,16 @@ impl Session {
     }
 }
 
+async fn append_record(
+    journal: Option<&Arc<Mutex<Journal>>>,
+    record: &Record,
+) -> Result<(), JournalError> {
+    match journal {
+        Some(journal) => journal.lock().await.append(record),
+        None => Ok(()),
+    }
+}
+
 /// The keccak output has 32 bytes
 const LOG2_KECCAK_SIZE: usize = 5;
 
@@ -937,6 +1139,12 @@ impl From<&CompletionStatus> for i32 {
             CompletionStatus::Exception { .. } => {
                 GrpcCompletionStatus::Exception
             }
+            CompletionStatus::TimeLimitExceeded => {
+                GrpcCompletionStatus::TimeLimitExceeded
+            }
+            CompletionStatus::PayloadLengthLimitExceeded => {
+                GrpcCompletionStatus::PayloadLengthLimitExceeded
+            }
         };
         status as i32
     }
@@ -948,6 +1156,12 @@ impl From<&InspectStatus> for i32 {
             InspectStatus::Accepted => GrpcCompletionStatus::Accepted,
             InspectStatus::Rejected => GrpcCompletionStatus::Rejected,
             InspectStatus::Exception { .. } => GrpcCompletionStatus::Exception,
+            InspectStatus::TimeLimitExceeded => {
+                GrpcCompletionStatus::TimeLimitExceeded
+            }
+            InspectStatus::PayloadLengthLimitExceeded => {
+                GrpcCompletionStatus::PayloadLengthLimitExceeded
+            }
         };
         status as i32
     }
@@ -972,6 +1186,8 @@ impl From<CompletionStatus> for Option<ProcessedInputOneOf> {
             CompletionStatus::Exception { exception } => {
                 Some(ProcessedInputOneOf::ExceptionData(exception.payload))
             }
+            CompletionStatus::TimeLimitExceeded => None,
+            CompletionStatus::PayloadLengthLimitExceeded => None,
         }
     }
 }
@@ -1034,3 +1250,9 @@ impl From<MerkleTreeError> for Status {
         ))
     }
 }
+
+impl From<JournalError> for Status {
+    fn from(e: JournalError) -> Status {
+        Status::internal(format!("unexpected error in journal ({})", e))
+    }
+}
@@ -6,12 +6,12 @@ pub mod model;
 mod rollup_server;
 
 use crate::config::Config;
-use crate::controller::Controller;
+use crate::registry::ControllerRegistry;
 
 /// Setup the HTTP server that receives requests from the DApp backend
 pub async fn start_services(
     config: &Config,
-    controller: Controller,
+    registry: ControllerRegistry,
 ) -> std::io::Result<()> {
-    rollup_server::start_service(config, controller.clone()).await
+    rollup_server::start_service(config, registry).await
 }
@@ -2,13 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
 use actix_web::{
-    error, error::Result as HttpResult, middleware::Logger, web::Data,
-    web::Json, App, HttpResponse, HttpServer, Responder,
+    error, error::Result as HttpResult, middleware::Logger, web, web::Data,
+    web::Json, App, HttpRequest, HttpResponse, HttpServer, Responder,
 };
 
 use crate::config::Config;
 use crate::controller::{Controller, ControllerError};
 use crate::model::{FinishStatus, Notice, Report, RollupException, Voucher};
+use crate::registry::ControllerRegistry;
 
 use super::model::{
     HttpFinishRequest, HttpIndexResponse, HttpNotice, HttpReport,
@@ -17,12 +18,20 @@ use super::model::{
 
 pub async fn start_service(
     config: &Config,
-    controller: Controller,
+    registry: ControllerRegistry,
 ) -> std::io::Result<()> {
     HttpServer::new(move || {
         App::new()
-            .app_data(Data::new(controller.clone()))
+            .app_data(Data::new(registry.clone()))
             .wrap(Logger::default())
+            .service(
+                web::scope("/sessions/{session_id}")
+                    .service(voucher)
+                    .service(notice)
+                    .service(report)
+                    .service(exception)
+                    .service(finish),
+            )
             .service(voucher)
             .service(notice)
             .service(report)
@@ -40,8 +49,10 @@ pub async fn start_service(
 #[actix_web::post("/voucher")]
 async fn voucher(
     voucher: Json<HttpVoucher>,
-    controller: Data<Controller>,
+    req: HttpRequest,
+    registry: Data<ControllerRegistry>,
 ) -> HttpResult<impl Responder> {
+    let controller = get_controller(&req, &registry).await?;
     let voucher: Voucher = voucher.into_inner().try_into()?;
     let rx = controller.insert_voucher(voucher).await;
     let index = rx.await.map_err(|_| {
@@ -57,8 +68,10 @@ async fn voucher(
 #[actix_web::post("/notice")]
 async fn notice(
     notice: Json<HttpNotice>,
-    controller: Data<Controller>,
+    req: HttpRequest,
+    registry: Data<ControllerRegistry>,
 ) -> HttpResult<impl Responder> {
+    let controller = get_controller(&req, &registry).await?;
     let notice: Notice = notice.into_inner().try_into()?;
     let rx = controller.insert_notice(notice).await;
     let index = rx.await.map_err(|_| {
@@ -74,8 +87,10 @@ async fn notice(
 #[actix_web::post("/report")]
 async fn report(
     report: Json<HttpReport>,
-    controller: Data<Controller>,
+    req: HttpRequest,
+    registry: Data<ControllerRegistry>,
 ) -> HttpResult<impl Responder> {
+    let controller = get_controller(&req, &registry).await?;
     let report: Report = report.into_inner().try_into()?;
     let rx = controller.insert_report(report).await;
     rx.await.map_err(|_| {
@@ -88,8 +103,10 @@ async fn report(
 #[actix_web::post("/exception")]
 async fn exception(
     exception: Json<HttpRollupException>,
-    controller: Data<Controller>,
+    req: HttpRequest,
+    registry: Data<ControllerRegistry>,
 ) -> HttpResult<impl Responder> {
+    let controller = get_controller(&req, &registry).await?;
     let exception: RollupException = exception.into_inner().try_into()?;
     let rx = controller.notify_exception(exception).await;
     rx.await.map_err(|_| {
@@ -102,8 +119,10 @@ async fn exception(
 #[actix_web::post("/finish")]
 async fn finish(
     body: Json<HttpFinishRequest>,
-    controller: Data<Controller>,
+    req: HttpRequest,
+    registry: Data<ControllerRegistry>,
 ) -> HttpResult<impl Responder> {
+    let controller = get_controller(&req, &registry).await?;
     let status: FinishStatus = body.into_inner().try_into()?;
     let rx = controller.finish(status).await;
     let result = rx.await.map_err(|_| {
@@ -118,10 +137,22 @@ async fn finish(
             ControllerError::FetchRequestTimeout => {
                 HttpResponse::Accepted().body(e.to_string())
             }
-            ControllerError::InvalidRequest { .. } => {
-                HttpResponse::BadRequest().body(e.to_string())
-            }
+            _ => HttpResponse::BadRequest().body(e.to_string()),
         },
     };
     Ok(response)
 }
+
+/// Get the controller of the session in the path, or the default controller
+/// Fail with not found if the session in the path is not running.
+async fn get_controller(
+    req: &HttpRequest,
+    registry: &ControllerRegistry,
+) -> HttpResult<Controller> {
+    match req.match_info().get("session_id") {
+        Some(session_id) => registry.get(session_id).await.ok_or_else(|| {
+            error::ErrorNotFound(format!("session {} not found", session_id))
+        }),
+        None => Ok(registry.default_controller()),
+    }
+}
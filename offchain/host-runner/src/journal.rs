@@ -0,0 +1,511 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! On-disk journal of the sessions
+//!
+//! Each session has a journal file in the journal directory, with one JSON
+//! record per line. The journal has the advance requests, the results of the
+//! backend, and the epoch boundaries of the session. When the host-runner
+//! starts, it replays the journals to restore the sessions. The advance
+//! requests that were pending are sent to the backend again.
+//!
+//! When the finish epoch request has a storage directory, the host-runner
+//! writes a snapshot of the session at the epoch boundary in that directory.
+
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::model::{
+    AdvanceMetadata, AdvanceResult, AdvanceStateRequest, CompletionStatus,
+    Notice, Report, RollupException, Voucher, ADDRESS_SIZE,
+};
+
+const JOURNAL_EXTENSION: &str = "journal";
+const SNAPSHOT_FILE: &str = "host-runner.snapshot";
+
+#[derive(Debug, Snafu)]
+pub enum JournalError {
+    #[snafu(display("failed to access journal file {}", path.display()))]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("failed to serialize journal record"))]
+    Serialize { source: serde_json::Error },
+    #[snafu(display("invalid record in journal file {}", path.display()))]
+    Deserialize {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[snafu(display("invalid journal file name {}", path.display()))]
+    InvalidFileName { path: PathBuf },
+    #[snafu(display("invalid snapshot file {}", path.display()))]
+    InvalidSnapshot { path: PathBuf },
+    #[snafu(display(
+        "failed to replay journal of session {} ({})",
+        session_id,
+        message
+    ))]
+    Replay { session_id: String, message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    SessionStarted {
+        active_epoch_index: u64,
+        processed_input_count: u64,
+    },
+    InputAdvanced {
+        active_epoch_index: u64,
+        current_input_index: u64,
+        request: AdvanceRecord,
+    },
+    InputProcessed {
+        result: ResultRecord,
+    },
+    EpochFinished {
+        active_epoch_index: u64,
+        processed_input_count_within_epoch: u64,
+    },
+    EpochDeleted {
+        epoch_index: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdvanceRecord {
+    msg_sender: [u8; ADDRESS_SIZE],
+    epoch_index: u64,
+    input_index: u64,
+    block_number: u64,
+    timestamp: u64,
+    payload: Vec<u8>,
+}
+
+/// Result of an advance request without the hashes and the proofs, which are
+/// computed again when the journal is replayed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ResultRecord {
+    Accepted {
+        vouchers: Vec<VoucherRecord>,
+        notices: Vec<Vec<u8>>,
+        reports: Vec<Vec<u8>>,
+    },
+    Rejected {
+        reports: Vec<Vec<u8>>,
+    },
+    Exception {
+        exception: Vec<u8>,
+        reports: Vec<Vec<u8>>,
+    },
+    TimeLimitExceeded {
+        reports: Vec<Vec<u8>>,
+    },
+    PayloadLengthLimitExceeded,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoucherRecord {
+    pub destination: [u8; ADDRESS_SIZE],
+    pub payload: Vec<u8>,
+}
+
+/// Journal file of a session, where the records are appended
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    /// Create an empty journal for the session, replacing the previous one
+    pub fn create(
+        directory: &Path,
+        session_id: &str,
+    ) -> Result<Self, JournalError> {
+        let path = journal_path(directory, session_id);
+        let file = File::create(&path).context(IoSnafu { path: &path })?;
+        Ok(Self { path, file })
+    }
+
+    /// Open the journal of a restored session to append new records
+    pub fn open(
+        directory: &Path,
+        session_id: &str,
+    ) -> Result<Self, JournalError> {
+        let path = journal_path(directory, session_id);
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .context(IoSnafu { path: &path })?;
+        Ok(Self { path, file })
+    }
+
+    /// Append the record and wait until it reaches the disk
+    pub fn append(&mut self, record: &Record) -> Result<(), JournalError> {
+        let mut line = serde_json::to_vec(record).context(SerializeSnafu)?;
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .and_then(|_| self.file.sync_data())
+            .context(IoSnafu { path: &self.path })
+    }
+
+    /// Remove the journal of an ended session
+    pub fn remove(&self) -> Result<(), JournalError> {
+        fs::remove_file(&self.path).context(IoSnafu { path: &self.path })
+    }
+}
+
+/// Load the records of every session in the journal directory
+pub fn load(
+    directory: &Path,
+) -> Result<Vec<(String, Vec<Record>)>, JournalError> {
+    let entries =
+        fs::read_dir(directory).context(IoSnafu { path: directory })?;
+    let mut sessions = vec![];
+    for entry in entries {
+        let path = entry.context(IoSnafu { path: directory })?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(JOURNAL_EXTENSION)
+        {
+            continue;
+        }
+        let session_id = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| hex::decode(stem).ok())
+            .and_then(|id| String::from_utf8(id).ok())
+            .ok_or(JournalError::InvalidFileName { path: path.clone() })?;
+        let records = read_records(&path)?;
+        sessions.push((session_id, records));
+    }
+    sessions.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(sessions)
+}
+
+/// Write the snapshot of the session at the start of the active epoch
+/// The storage directory must not exist, like in the server-manager.
+pub fn write_snapshot(
+    storage_directory: &Path,
+    active_epoch_index: u64,
+    processed_input_count: u64,
+) -> Result<(), JournalError> {
+    fs::create_dir(storage_directory).context(IoSnafu {
+        path: storage_directory,
+    })?;
+    let path = storage_directory.join(SNAPSHOT_FILE);
+    let mut file = File::create(&path).context(IoSnafu { path: &path })?;
+    let record = Record::SessionStarted {
+        active_epoch_index,
+        processed_input_count,
+    };
+    let mut line = serde_json::to_vec(&record).context(SerializeSnafu)?;
+    line.push(b'\n');
+    file.write_all(&line)
+        .and_then(|_| file.sync_all())
+        .context(IoSnafu { path: &path })
+}
+
+/// Read the snapshot in the machine directory, if there is one
+/// Returns the active epoch index and the processed input count.
+pub fn read_snapshot(
+    machine_directory: &Path,
+) -> Result<Option<(u64, u64)>, JournalError> {
+    let path = machine_directory.join(SNAPSHOT_FILE);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    match read_records(&path)?.as_slice() {
+        [Record::SessionStarted {
+            active_epoch_index,
+            processed_input_count,
+        }] => Ok(Some((*active_epoch_index, *processed_input_count))),
+        _ => Err(JournalError::InvalidSnapshot { path }),
+    }
+}
+
+fn journal_path(directory: &Path, session_id: &str) -> PathBuf {
+    directory
+        .join(hex::encode(session_id))
+        .with_extension(JOURNAL_EXTENSION)
+}
+
+/// Read the records of the journal file
+/// A crash while appending leaves an incomplete record at the end of the file,
+/// which is dropped so the new records are appended after the last complete
+/// one.
+fn read_records(path: &Path) -> Result<Vec<Record>, JournalError> {
+    let data = fs::read(path).context(IoSnafu { path })?;
+    let mut records = vec![];
+    let mut start = 0;
+    while start < data.len() {
+        let end = data[start..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(data.len(), |length| start + length + 1);
+        match data[start..end].strip_suffix(b"\n") {
+            Some(line) if line.is_empty() => {}
+            Some(line) => match serde_json::from_slice(line) {
+                Ok(record) => records.push(record),
+                Err(_) if end == data.len() => {
+                    drop_incomplete_record(path, start)?
+                }
+                Err(source) => {
+                    return Err(source).context(DeserializeSnafu { path })
+                }
+            },
+            None => drop_incomplete_record(path, start)?,
+        }
+        start = end;
+    }
+    Ok(records)
+}
+
+/// Truncate the journal file to the end of its last complete record
+fn drop_incomplete_record(
+    path: &Path,
+    length: usize,
+) -> Result<(), JournalError> {
+    tracing::warn!(
+        "dropping incomplete record at the end of journal file {}",
+        path.display()
+    );
+    OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(|file| {
+            file.set_len(length as u64)?;
+            file.sync_data()
+        })
+        .context(IoSnafu { path })
+}
+
+impl From<&AdvanceStateRequest> for AdvanceRecord {
+    fn from(request: &AdvanceStateRequest) -> Self {
+        Self {
+            msg_sender: request.metadata.msg_sender,
+            epoch_index: request.metadata.epoch_index,
+            input_index: request.metadata.input_index,
+            block_number: request.metadata.block_number,
+            timestamp: request.metadata.timestamp,
+            payload: request.payload.clone(),
+        }
+    }
+}
+
+impl From<AdvanceRecord> for AdvanceStateRequest {
+    fn from(record: AdvanceRecord) -> Self {
+        Self {
+            metadata: AdvanceMetadata {
+                msg_sender: record.msg_sender,
+                epoch_index: record.epoch_index,
+                input_index: record.input_index,
+                block_number: record.block_number,
+                timestamp: record.timestamp,
+            },
+            payload: record.payload,
+        }
+    }
+}
+
+impl From<&AdvanceResult> for ResultRecord {
+    fn from(result: &AdvanceResult) -> Self {
+        let reports = result
+            .reports
+            .iter()
+            .map(|report| report.payload.clone())
+            .collect();
+        match &result.status {
+            CompletionStatus::Accepted { vouchers, notices } => {
+                Self::Accepted {
+                    vouchers: vouchers
+                        .iter()
+                        .map(|voucher| VoucherRecord {
+                            destination: voucher.destination,
+                            payload: voucher.payload.clone(),
+                        })
+                        .collect(),
+                    notices: notices
+                        .iter()
+                        .map(|notice| notice.payload.clone())
+                        .collect(),
+                    reports,
+                }
+            }
+            CompletionStatus::Rejected => Self::Rejected { reports },
+            CompletionStatus::Exception { exception } => Self::Exception {
+                exception: exception.payload.clone(),
+                reports,
+            },
+            CompletionStatus::TimeLimitExceeded => {
+                Self::TimeLimitExceeded { reports }
+            }
+            CompletionStatus::PayloadLengthLimitExceeded => {
+                Self::PayloadLengthLimitExceeded
+            }
+        }
+    }
+}
+
+impl From<ResultRecord> for AdvanceResult {
+    fn from(record: ResultRecord) -> Self {
+        let into_reports = |reports: Vec<Vec<u8>>| {
+            reports
+                .into_iter()
+                .map(|payload| Report { payload })
+                .collect()
+        };
+        match record {
+            ResultRecord::Accepted {
+                vouchers,
+                notices,
+                reports,
+            } => AdvanceResult::accepted(
+                vouchers
+                    .into_iter()
+                    .map(|v| Voucher::new(v.destination, v.payload))
+                    .collect(),
+                notices.into_iter().map(Notice::new).collect(),
+                into_reports(reports),
+            ),
+            ResultRecord::Rejected { reports } => {
+                AdvanceResult::rejected(into_reports(reports))
+            }
+            ResultRecord::Exception { exception, reports } => {
+                AdvanceResult::exception(
+                    RollupException { payload: exception },
+                    into_reports(reports),
+                )
+            }
+            ResultRecord::TimeLimitExceeded { reports } => {
+                AdvanceResult::time_limit_exceeded(into_reports(reports))
+            }
+            ResultRecord::PayloadLengthLimitExceeded => {
+                AdvanceResult::payload_length_limit_exceeded()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_result() -> AdvanceResult {
+        AdvanceResult::accepted(
+            vec![Voucher::new([0xfa; ADDRESS_SIZE], vec![1, 2, 3])],
+            vec![Notice::new(vec![4, 5, 6])],
+            vec![Report {
+                payload: vec![7, 8, 9],
+            }],
+        )
+    }
+
+    #[test]
+    fn test_it_loads_the_appended_records() {
+        let directory = tempfile::tempdir().unwrap();
+        let request = AdvanceStateRequest {
+            metadata: AdvanceMetadata {
+                msg_sender: [0xaa; ADDRESS_SIZE],
+                epoch_index: 0,
+                input_index: 0,
+                block_number: 1,
+                timestamp: 2,
+            },
+            payload: vec![0xde, 0xad],
+        };
+        let records = vec![
+            Record::SessionStarted {
+                active_epoch_index: 0,
+                processed_input_count: 0,
+            },
+            Record::InputAdvanced {
+                active_epoch_index: 0,
+                current_input_index: 0,
+                request: (&request).into(),
+            },
+            Record::InputProcessed {
+                result: (&create_result()).into(),
+            },
+        ];
+        let mut journal =
+            Journal::create(directory.path(), "rollup session").unwrap();
+        for record in records.iter() {
+            journal.append(record).unwrap();
+        }
+        let sessions = load(directory.path()).unwrap();
+        assert_eq!(sessions, vec![("rollup session".to_owned(), records)]);
+    }
+
+    #[test]
+    fn test_it_drops_the_incomplete_last_record() {
+        let directory = tempfile::tempdir().unwrap();
+        let records = vec![
+            Record::SessionStarted {
+                active_epoch_index: 0,
+                processed_input_count: 0,
+            },
+            Record::EpochFinished {
+                active_epoch_index: 0,
+                processed_input_count_within_epoch: 0,
+            },
+        ];
+        let mut journal =
+            Journal::create(directory.path(), "rollup session").unwrap();
+        journal.append(&records[0]).unwrap();
+        // Simulate a crash in the middle of the append
+        let line = serde_json::to_vec(&records[1]).unwrap();
+        journal.file.write_all(&line[..line.len() / 2]).unwrap();
+        drop(journal);
+
+        let sessions = load(directory.path()).unwrap();
+        assert_eq!(sessions[0].1, records[..1]);
+        let mut journal =
+            Journal::open(directory.path(), "rollup session").unwrap();
+        journal.append(&records[1]).unwrap();
+        let sessions = load(directory.path()).unwrap();
+        assert_eq!(sessions, vec![("rollup session".to_owned(), records)]);
+    }
+
+    #[test]
+    fn test_it_fails_to_load_a_corrupt_record() {
+        let directory = tempfile::tempdir().unwrap();
+        let record = Record::EpochDeleted { epoch_index: 0 };
+        let mut journal = Journal::create(directory.path(), "session").unwrap();
+        journal.file.write_all(b"{\"type\":\n").unwrap();
+        journal.append(&record).unwrap();
+        let err = load(directory.path()).unwrap_err();
+        assert!(matches!(err, JournalError::Deserialize { .. }));
+    }
+
+    #[test]
+    fn test_it_recomputes_the_hashes_of_the_result() {
+        let result = create_result();
+        let record = ResultRecord::from(&result);
+        assert_eq!(AdvanceResult::from(record), result);
+    }
+
+    #[test]
+    fn test_it_removes_the_journal() {
+        let directory = tempfile::tempdir().unwrap();
+        let journal = Journal::create(directory.path(), "session").unwrap();
+        journal.remove().unwrap();
+        assert!(load(directory.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_it_reads_the_written_snapshot() {
+        let directory = tempfile::tempdir().unwrap();
+        let storage_directory = directory.path().join("1_2");
+        assert_eq!(read_snapshot(&storage_directory).unwrap(), None);
+        write_snapshot(&storage_directory, 1, 2).unwrap();
+        assert_eq!(read_snapshot(&storage_directory).unwrap(), Some((1, 2)));
+        write_snapshot(&storage_directory, 1, 2)
+            .expect_err("storage directory should not exist");
+    }
+}
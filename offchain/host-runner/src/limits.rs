@@ -0,0 +1,109 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Limits of the Cartesi machine emulated by the host-runner
+//!
+//! The machine receives the requests ABI-encoded in its RX buffer, and the
+//! backend writes each output ABI-encoded to its TX buffer. The hashes of the
+//! vouchers and of the notices of an input are stored in memory ranges of the
+//! machine, which limit their number.
+
+use crate::hash::HASH_SIZE;
+use crate::model::{AdvanceStateRequest, InspectStateRequest};
+
+/// Size of the RX and TX buffers of the machine
+pub const DEFAULT_BUFFER_SIZE: usize = 2 << 20;
+
+/// Number of hashes that fit in the voucher and in the notice hashes ranges
+pub const DEFAULT_MAX_OUTPUTS_PER_INPUT: usize =
+    DEFAULT_BUFFER_SIZE / HASH_SIZE;
+
+/// Number of words of the advance metadata
+const METADATA_WORDS: usize = 5;
+
+#[derive(Clone, Debug)]
+pub struct MachineLimits {
+    pub rx_buffer_size: usize,
+    pub tx_buffer_size: usize,
+    /// Number of vouchers, and of notices, that an input may produce
+    pub max_outputs_per_input: usize,
+}
+
+impl Default for MachineLimits {
+    fn default() -> Self {
+        Self {
+            rx_buffer_size: DEFAULT_BUFFER_SIZE,
+            tx_buffer_size: DEFAULT_BUFFER_SIZE,
+            max_outputs_per_input: DEFAULT_MAX_OUTPUTS_PER_INPUT,
+        }
+    }
+}
+
+impl MachineLimits {
+    pub fn fits_advance(&self, request: &AdvanceStateRequest) -> bool {
+        METADATA_WORDS * HASH_SIZE + encoded_payload_size(&request.payload)
+            <= self.rx_buffer_size
+    }
+
+    pub fn fits_inspect(&self, request: &InspectStateRequest) -> bool {
+        encoded_payload_size(&request.payload) <= self.rx_buffer_size
+    }
+
+    /// The voucher has the destination before its payload
+    pub fn fits_voucher(&self, payload: &[u8]) -> bool {
+        HASH_SIZE + encoded_payload_size(payload) <= self.tx_buffer_size
+    }
+
+    /// Notices, reports, and exceptions only have the payload
+    pub fn fits_output(&self, payload: &[u8]) -> bool {
+        encoded_payload_size(payload) <= self.tx_buffer_size
+    }
+}
+
+/// Size of the offset, the length, and the padded payload
+fn encoded_payload_size(payload: &[u8]) -> usize {
+    2 * HASH_SIZE + payload.len().div_ceil(HASH_SIZE) * HASH_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(buffer_size: usize) -> MachineLimits {
+        MachineLimits {
+            rx_buffer_size: buffer_size,
+            tx_buffer_size: buffer_size,
+            max_outputs_per_input: 1,
+        }
+    }
+
+    #[test]
+    fn test_it_pads_the_payload() {
+        assert_eq!(encoded_payload_size(&[]), 64);
+        assert_eq!(encoded_payload_size(&[0; 1]), 96);
+        assert_eq!(encoded_payload_size(&[0; 32]), 96);
+        assert_eq!(encoded_payload_size(&[0; 33]), 128);
+    }
+
+    #[test]
+    fn test_it_checks_the_outputs_against_the_tx_buffer() {
+        let limits = limits(128);
+        assert!(limits.fits_output(&[0; 64]));
+        assert!(!limits.fits_output(&[0; 65]));
+        assert!(limits.fits_voucher(&[0; 32]));
+        assert!(!limits.fits_voucher(&[0; 33]));
+    }
+
+    #[test]
+    fn test_it_checks_the_inputs_against_the_rx_buffer() {
+        let limits = limits(256);
+        let inspect = InspectStateRequest {
+            payload: vec![0; 192],
+        };
+        assert!(limits.fits_inspect(&inspect));
+        let inspect = InspectStateRequest {
+            payload: vec![0; 193],
+        };
+        assert!(!limits.fits_inspect(&inspect));
+    }
+}
@@ -8,9 +8,13 @@ mod driver;
 mod grpc;
 mod hash;
 mod http;
+mod journal;
+mod limits;
 mod merkle_tree;
 mod model;
 mod proofs;
+mod recording;
+mod registry;
 
 use futures_util::FutureExt;
 use std::sync::{atomic::AtomicBool, atomic::Ordering, Arc};
@@ -19,7 +23,10 @@ use tokio::sync::oneshot;
 
 use clap::Parser;
 use config::{CLIConfig, Config};
-use controller::Controller;
+use controller::ControllerConfig;
+use limits::MachineLimits;
+use recording::Recorder;
+use registry::ControllerRegistry;
 
 fn log_result<T, E: std::error::Error>(name: &str, result: Result<T, E>) {
     let prefix = format!("http {} terminated ", name);
@@ -37,20 +44,39 @@ async fn main() {
 
     log::log_service_start(&config, "Host Runner");
 
-    let controller =
-        Controller::new(Duration::from_millis(config.finish_timeout));
+    let registry = {
+        let registry = ControllerRegistry::new(ControllerConfig {
+            finish_timeout: Duration::from_millis(config.finish_timeout),
+            advance_deadline: config
+                .advance_deadline
+                .map(Duration::from_millis),
+            inspect_deadline: config
+                .inspect_deadline
+                .map(Duration::from_millis),
+            limits: MachineLimits {
+                rx_buffer_size: config.rx_buffer_size,
+                tx_buffer_size: config.tx_buffer_size,
+                max_outputs_per_input: config.max_outputs_per_input,
+            },
+        });
+        match &config.record_file {
+            Some(path) => registry.with_recorder(
+                Recorder::create(path).expect("failed to create recording"),
+            ),
+            None => registry,
+        }
+    };
     let http_service_running = Arc::new(AtomicBool::new(true));
     let (grpc_shutdown_tx, grpc_shutdown_rx) = oneshot::channel::<()>();
     let grpc_service = {
-        let controller = controller.clone();
+        let registry = registry.clone();
         let config = config.clone();
         let shutdown = grpc_shutdown_rx.map(|_| ());
         let http_service_running = http_service_running.clone();
         tokio::spawn(async move {
             log_result(
                 "gRPC service",
-                grpc::start_service(&config, controller.clone(), shutdown)
-                    .await,
+                grpc::start_service(&config, registry, shutdown).await,
             );
             if http_service_running.load(Ordering::Relaxed) {
                 panic!("gRPC service terminated before shutdown signal");
@@ -59,8 +85,19 @@ async fn main() {
     };
 
     // We run the actix-web in the main thread because it handles the SIGINT
-    let host_runner_handle = http::start_services(&config, controller.clone());
+    let host_runner_handle = http::start_services(&config, registry.clone());
     let health_handle = http_health_check::start(config.healthcheck_port);
+    let replay_handle = {
+        let controller = registry.default_controller();
+        let replay_file = config.replay_file.clone();
+        async move {
+            match replay_file {
+                Some(path) => recording::replay(&controller, &path).await,
+                None => std::future::pending().await,
+            }
+        }
+    };
+    let mut replay_failed = false;
     tokio::select! {
         result = health_handle => {
             log_result("http health check", result);
@@ -68,17 +105,27 @@ async fn main() {
         result = host_runner_handle => {
             log_result("http service", result);
         }
+        result = replay_handle => {
+            match result {
+                Ok(diverged) => replay_failed = !diverged.is_empty(),
+                Err(e) => {
+                    tracing::error!("failed to replay recording ({})", e);
+                    replay_failed = true;
+                }
+            }
+        }
     }
     http_service_running.store(false, Ordering::Relaxed);
 
     // Shutdown the other services
-    if let Err(e) = controller.shutdown().await.await {
-        tracing::error!("failed to shutdown controller ({})", e);
-    }
+    registry.shutdown().await;
     if grpc_shutdown_tx.send(()).is_err() {
         tracing::error!("failed to send the shutdown signal to grpc");
     }
     if let Err(e) = grpc_service.await {
         tracing::error!("failed to shutdown the grpc service ({})", e);
     }
+    if replay_failed {
+        std::process::exit(1);
+    }
 }
@@ -7,7 +7,7 @@ use crate::hash::Hash;
 use crate::merkle_tree::proof::Proof;
 use crate::proofs::Proofable;
 
-const ADDRESS_SIZE: usize = 20;
+pub const ADDRESS_SIZE: usize = 20;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct AdvanceStateRequest {
@@ -53,6 +53,14 @@ impl AdvanceResult {
         Self::new(status, reports)
     }
 
+    pub fn time_limit_exceeded(reports: Vec<Report>) -> Self {
+        Self::new(CompletionStatus::TimeLimitExceeded, reports)
+    }
+
+    pub fn payload_length_limit_exceeded() -> Self {
+        Self::new(CompletionStatus::PayloadLengthLimitExceeded, vec![])
+    }
+
     fn new(status: CompletionStatus, reports: Vec<Report>) -> Self {
         Self {
             status,
@@ -75,6 +83,8 @@ pub enum CompletionStatus {
     Exception {
         exception: RollupException,
     },
+    TimeLimitExceeded,
+    PayloadLengthLimitExceeded,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -109,6 +119,20 @@ impl InspectResult {
             reports,
         }
     }
+
+    pub fn time_limit_exceeded(reports: Vec<Report>) -> Self {
+        Self {
+            status: InspectStatus::TimeLimitExceeded,
+            reports,
+        }
+    }
+
+    pub fn payload_length_limit_exceeded() -> Self {
+        Self {
+            status: InspectStatus::PayloadLengthLimitExceeded,
+            reports: vec![],
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -116,6 +140,8 @@ pub enum InspectStatus {
     Accepted,
     Rejected,
     Exception { exception: RollupException },
+    TimeLimitExceeded,
+    PayloadLengthLimitExceeded,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
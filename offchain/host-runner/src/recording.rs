@@ -0,0 +1,531 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Record and replay of the requests handled by a controller
+//!
+//! The recorder writes each rollup request with the response of the backend
+//! to the recording file, with one JSON entry per line. The replay feeds the
+//! recorded requests to a backend and compares its responses with the
+//! recorded ones, so the bugs of the backend can be reproduced.
+
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{oneshot, Mutex};
+
+use crate::controller::Controller;
+use crate::conversions::encode_ethereum_binary;
+use crate::journal::{AdvanceRecord, ResultRecord, VoucherRecord};
+use crate::model::{
+    AdvanceResult, AdvanceStateRequest, InspectResult, InspectStateRequest,
+    InspectStatus,
+};
+
+#[derive(Debug, Snafu)]
+pub enum RecordingError {
+    #[snafu(display("failed to access recording file {}", path.display()))]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("failed to serialize recording entry"))]
+    Serialize { source: serde_json::Error },
+    #[snafu(display("invalid entry in recording file {}", path.display()))]
+    Deserialize {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[snafu(display("backend didn't respond to request {}", index))]
+    MissingResponse { index: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Entry {
+    Advance {
+        request: AdvanceRecord,
+        result: ResultRecord,
+    },
+    Inspect {
+        payload: Vec<u8>,
+        result: InspectResultRecord,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum InspectResultRecord {
+    Accepted {
+        reports: Vec<Vec<u8>>,
+    },
+    Rejected {
+        reports: Vec<Vec<u8>>,
+    },
+    Exception {
+        exception: Vec<u8>,
+        reports: Vec<Vec<u8>>,
+    },
+    TimeLimitExceeded {
+        reports: Vec<Vec<u8>>,
+    },
+    PayloadLengthLimitExceeded,
+}
+
+/// Writer of the recording file, shared by the clones of a controller
+#[derive(Clone, Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+impl Recorder {
+    /// Create the recording file, replacing the previous one
+    pub fn create(path: &Path) -> Result<Self, RecordingError> {
+        let file = File::create(path).context(IoSnafu { path })?;
+        Ok(Self {
+            path: path.to_owned(),
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Record the response of the backend once it arrives, forwarding it to
+    /// the returned receiver
+    pub fn forward<T, F>(
+        &self,
+        rx: oneshot::Receiver<T>,
+        to_entry: F,
+    ) -> oneshot::Receiver<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&T) -> Entry + Send + 'static,
+    {
+        let (tx, forwarded_rx) = oneshot::channel();
+        let recorder = self.clone();
+        tokio::spawn(async move {
+            if let Ok(result) = rx.await {
+                if let Err(e) = recorder.record(&to_entry(&result)).await {
+                    tracing::error!("failed to record request ({})", e);
+                }
+                if tx.send(result).is_err() {
+                    tracing::warn!("failed to send response (channel dropped)");
+                }
+            }
+        });
+        forwarded_rx
+    }
+
+    async fn record(&self, entry: &Entry) -> Result<(), RecordingError> {
+        let mut line = serde_json::to_vec(entry).context(SerializeSnafu)?;
+        line.push(b'\n');
+        self.file
+            .lock()
+            .await
+            .write_all(&line)
+            .context(IoSnafu { path: &self.path })
+    }
+}
+
+/// Read the entries of the recording file
+pub fn load(path: &Path) -> Result<Vec<Entry>, RecordingError> {
+    let file = File::open(path).context(IoSnafu { path })?;
+    let mut entries = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line.context(IoSnafu { path })?;
+        if line.is_empty() {
+            continue;
+        }
+        let entry =
+            serde_json::from_str(&line).context(DeserializeSnafu { path })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Feed the recorded requests to the backend of the controller, in order
+/// Returns the indices of the requests whose responses diverged from the
+/// recording.
+pub async fn replay(
+    controller: &Controller,
+    path: &Path,
+) -> Result<Vec<usize>, RecordingError> {
+    let entries = load(path)?;
+    tracing::info!(
+        "replaying {} requests from {}",
+        entries.len(),
+        path.display()
+    );
+    let mut diverged = vec![];
+    for (index, entry) in entries.into_iter().enumerate() {
+        let differences = match entry {
+            Entry::Advance { request, result } => {
+                let rx = controller.advance(request.into()).await;
+                let actual = rx
+                    .await
+                    .map_err(|_| RecordingError::MissingResponse { index })?;
+                let actual = ResultRecord::from(&actual);
+                Outputs::from(&result).diff(&Outputs::from(&actual))
+            }
+            Entry::Inspect { payload, result } => {
+                let rx =
+                    controller.inspect(InspectStateRequest { payload }).await;
+                let actual = rx
+                    .await
+                    .map_err(|_| RecordingError::MissingResponse { index })?;
+                let actual = InspectResultRecord::from(&actual);
+                Outputs::from(&result).diff(&Outputs::from(&actual))
+            }
+        };
+        if differences.is_empty() {
+            tracing::info!("request {} matches the recording", index);
+        } else {
+            for difference in differences {
+                tracing::warn!(
+                    "request {} diverged from the recording: {}",
+                    index,
+                    difference
+                );
+            }
+            diverged.push(index);
+        }
+    }
+    tracing::info!(
+        "replay finished with {} diverging requests",
+        diverged.len()
+    );
+    Ok(diverged)
+}
+
+impl Entry {
+    pub fn advance(
+        request: &AdvanceStateRequest,
+        result: &AdvanceResult,
+    ) -> Self {
+        Self::Advance {
+            request: request.into(),
+            result: result.into(),
+        }
+    }
+
+    pub fn inspect(
+        request: &InspectStateRequest,
+        result: &InspectResult,
+    ) -> Self {
+        Self::Inspect {
+            payload: request.payload.clone(),
+            result: result.into(),
+        }
+    }
+}
+
+impl From<&InspectResult> for InspectResultRecord {
+    fn from(result: &InspectResult) -> Self {
+        let reports = result
+            .reports
+            .iter()
+            .map(|report| report.payload.clone())
+            .collect();
+        match &result.status {
+            InspectStatus::Accepted => Self::Accepted { reports },
+            InspectStatus::Rejected => Self::Rejected { reports },
+            InspectStatus::Exception { exception } => Self::Exception {
+                exception: exception.payload.clone(),
+                reports,
+            },
+            InspectStatus::TimeLimitExceeded => {
+                Self::TimeLimitExceeded { reports }
+            }
+            InspectStatus::PayloadLengthLimitExceeded => {
+                Self::PayloadLengthLimitExceeded
+            }
+        }
+    }
+}
+
+/// Outputs of the backend for a request, compared during the replay
+#[derive(Debug, Default)]
+struct Outputs<'a> {
+    status: &'static str,
+    exception: Option<&'a [u8]>,
+    vouchers: &'a [VoucherRecord],
+    notices: &'a [Vec<u8>],
+    reports: &'a [Vec<u8>],
+}
+
+impl<'a> Outputs<'a> {
+    fn diff(&self, actual: &Outputs) -> Vec<String> {
+        let mut differences = vec![];
+        if self.status != actual.status {
+            differences.push(format!(
+                "status (expected {}, got {})",
+                self.status, actual.status
+            ));
+        }
+        if self.exception != actual.exception {
+            differences.push(format!(
+                "exception (expected {}, got {})",
+                self.exception.map_or("none".into(), encode_ethereum_binary),
+                actual
+                    .exception
+                    .map_or("none".into(), encode_ethereum_binary),
+            ));
+        }
+        let vouchers = |vouchers: &[VoucherRecord]| -> Vec<String> {
+            vouchers
+                .iter()
+                .map(|voucher| {
+                    format!(
+                        "{} to {}",
+                        encode_ethereum_binary(&voucher.payload),
+                        encode_ethereum_binary(&voucher.destination)
+                    )
+                })
+                .collect()
+        };
+        let payloads = |payloads: &[Vec<u8>]| -> Vec<String> {
+            payloads.iter().map(|p| encode_ethereum_binary(p)).collect()
+        };
+        diff_outputs(
+            &mut differences,
+            "voucher",
+            &vouchers(self.vouchers),
+            &vouchers(actual.vouchers),
+        );
+        diff_outputs(
+            &mut differences,
+            "notice",
+            &payloads(self.notices),
+            &payloads(actual.notices),
+        );
+        diff_outputs(
+            &mut differences,
+            "report",
+            &payloads(self.reports),
+            &payloads(actual.reports),
+        );
+        differences
+    }
+}
+
+fn diff_outputs(
+    differences: &mut Vec<String>,
+    name: &str,
+    expected: &[String],
+    actual: &[String],
+) {
+    if expected.len() != actual.len() {
+        differences.push(format!(
+            "number of {}s (expected {}, got {})",
+            name,
+            expected.len(),
+            actual.len()
+        ));
+    }
+    for (index, (expected, actual)) in
+        expected.iter().zip(actual.iter()).enumerate()
+    {
+        if expected != actual {
+            differences.push(format!(
+                "{} {} (expected {}, got {})",
+                name, index, expected, actual
+            ));
+        }
+    }
+}
+
+impl<'a> From<&'a ResultRecord> for Outputs<'a> {
+    fn from(result: &'a ResultRecord) -> Self {
+        match result {
+            ResultRecord::Accepted {
+                vouchers,
+                notices,
+                reports,
+            } => Self {
+                status: "accepted",
+                vouchers,
+                notices,
+                reports,
+                ..Default::default()
+            },
+            ResultRecord::Rejected { reports } => Self {
+                status: "rejected",
+                reports,
+                ..Default::default()
+            },
+            ResultRecord::Exception { exception, reports } => Self {
+                status: "exception",
+                exception: Some(exception),
+                reports,
+                ..Default::default()
+            },
+            ResultRecord::TimeLimitExceeded { reports } => Self {
+                status: "time limit exceeded",
+                reports,
+                ..Default::default()
+            },
+            ResultRecord::PayloadLengthLimitExceeded => Self {
+                status: "payload length limit exceeded",
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a InspectResultRecord> for Outputs<'a> {
+    fn from(result: &'a InspectResultRecord) -> Self {
+        match result {
+            InspectResultRecord::Accepted { reports } => Self {
+                status: "accepted",
+                reports,
+                ..Default::default()
+            },
+            InspectResultRecord::Rejected { reports } => Self {
+                status: "rejected",
+                reports,
+                ..Default::default()
+            },
+            InspectResultRecord::Exception { exception, reports } => Self {
+                status: "exception",
+                exception: Some(exception),
+                reports,
+                ..Default::default()
+            },
+            InspectResultRecord::TimeLimitExceeded { reports } => Self {
+                status: "time limit exceeded",
+                reports,
+                ..Default::default()
+            },
+            InspectResultRecord::PayloadLengthLimitExceeded => Self {
+                status: "payload length limit exceeded",
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::ControllerConfig;
+    use crate::model::{AdvanceMetadata, FinishStatus, Notice, Report};
+    use std::time::Duration;
+
+    const TEST_FINISH_TIMEOUT: Duration = Duration::from_millis(100);
+
+    fn create_advance_request(input_index: u64) -> AdvanceStateRequest {
+        AdvanceStateRequest {
+            metadata: AdvanceMetadata {
+                msg_sender: [0xaa; 20],
+                epoch_index: 0,
+                input_index,
+                block_number: 1,
+                timestamp: 2,
+            },
+            payload: vec![0xde, 0xad],
+        }
+    }
+
+    /// Accept each request of the controller with the given notices
+    async fn run_backend(controller: &Controller, notices: Vec<Vec<Vec<u8>>>) {
+        controller
+            .finish(FinishStatus::Accept)
+            .await
+            .await
+            .unwrap()
+            .unwrap();
+        for request_notices in notices {
+            for payload in request_notices {
+                controller
+                    .insert_notice(Notice::new(payload))
+                    .await
+                    .await
+                    .unwrap()
+                    .unwrap();
+            }
+            // The last finish times out because there are no more requests
+            let _ = controller.finish(FinishStatus::Accept).await.await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_it_records_the_requests_with_the_responses() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("recording");
+        let recorder = Recorder::create(&path).unwrap();
+        let controller =
+            Controller::new(ControllerConfig::new(TEST_FINISH_TIMEOUT))
+                .with_recorder(recorder);
+        let request = create_advance_request(0);
+        let rx = controller.advance(request.clone()).await;
+        run_backend(&controller, vec![vec![vec![1, 2]]]).await;
+        let result = rx.await.unwrap();
+        assert_eq!(
+            load(&path).unwrap(),
+            vec![Entry::advance(&request, &result)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_it_flags_the_diverging_requests() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("recording");
+        let recorder = Recorder::create(&path).unwrap();
+        for (input_index, payload) in [vec![1], vec![2]].into_iter().enumerate()
+        {
+            let request = create_advance_request(input_index as u64);
+            let result = AdvanceResult::accepted(
+                vec![],
+                vec![Notice::new(payload)],
+                vec![],
+            );
+            recorder
+                .record(&Entry::advance(&request, &result))
+                .await
+                .unwrap();
+        }
+        let inspect = InspectResult::accepted(vec![Report { payload: vec![] }]);
+        recorder
+            .record(&Entry::inspect(
+                &InspectStateRequest { payload: vec![] },
+                &inspect,
+            ))
+            .await
+            .unwrap();
+
+        let controller =
+            Controller::new(ControllerConfig::new(TEST_FINISH_TIMEOUT));
+        let replay = {
+            let controller = controller.clone();
+            tokio::spawn(async move { replay(&controller, &path).await })
+        };
+        run_backend(&controller, vec![vec![vec![1]], vec![vec![3]], vec![]])
+            .await;
+        assert_eq!(replay.await.unwrap().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_it_describes_the_differences() {
+        let expected = ResultRecord::Accepted {
+            vouchers: vec![],
+            notices: vec![vec![1]],
+            reports: vec![],
+        };
+        let actual = ResultRecord::Exception {
+            exception: vec![2],
+            reports: vec![vec![3]],
+        };
+        let differences =
+            Outputs::from(&expected).diff(&Outputs::from(&actual));
+        assert_eq!(
+            differences,
+            vec![
+                "status (expected accepted, got exception)",
+                "exception (expected none, got 0x02)",
+                "number of notices (expected 1, got 0)",
+                "number of reports (expected 0, got 1)",
+            ]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Controllers of the sessions, shared by the gRPC and the HTTP services
+//!
+//! Each session has its own controller, which is reached by its DApp backend
+//! through the `/sessions/{session_id}` prefix of the rollup server while the
+//! session is running. The routes without prefix reach the default controller,
+//! which is taken by the first session that starts while no other session
+//! holds it. So, a single backend works without the prefix.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::controller::{Controller, ControllerConfig};
+use crate::recording::Recorder;
+
+#[derive(Clone, Debug)]
+pub struct ControllerRegistry {
+    default: Controller,
+    state: Arc<Mutex<RegistryState>>,
+    config: ControllerConfig,
+}
+
+#[derive(Debug, Default)]
+struct RegistryState {
+    /// Session that took the default controller
+    default_session: Option<String>,
+    /// Controllers of the running sessions
+    controllers: HashMap<String, Controller>,
+}
+
+impl ControllerRegistry {
+    pub fn new(config: ControllerConfig) -> Self {
+        Self {
+            default: Controller::new(config.clone()),
+            state: Default::default(),
+            config,
+        }
+    }
+
+    /// Record the requests handled by the default controller
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.default = self.default.with_recorder(recorder);
+        self
+    }
+
+    /// Controller reached by the routes without the session prefix
+    pub fn default_controller(&self) -> Controller {
+        self.default.clone()
+    }
+
+    /// Get the controller reached by the backend of the session, or None if
+    /// the session is not running
+    pub async fn get(&self, session_id: &str) -> Option<Controller> {
+        self.state.lock().await.controllers.get(session_id).cloned()
+    }
+
+    /// Get the controller of a starting session
+    /// The session takes the default controller if no other session took it.
+    pub async fn acquire(&self, session_id: &str) -> Controller {
+        let mut state = self.state.lock().await;
+        if let Some(controller) = state.controllers.get(session_id) {
+            return controller.clone();
+        }
+        let controller = if state.default_session.is_none() {
+            tracing::info!(
+                "session {} took the default controller",
+                session_id
+            );
+            state.default_session = Some(session_id.to_owned());
+            self.default.clone()
+        } else {
+            tracing::info!("creating controller of session {}", session_id);
+            Controller::new(self.config.clone())
+        };
+        state
+            .controllers
+            .insert(session_id.to_owned(), controller.clone());
+        controller
+    }
+
+    /// Release the controller of an ended session
+    /// The default controller is kept for the next session; the other ones
+    /// are shut down.
+    pub async fn release(&self, session_id: &str) {
+        let mut state = self.state.lock().await;
+        let controller = match state.controllers.remove(session_id) {
+            Some(controller) => controller,
+            None => return,
+        };
+        if state.default_session.as_deref() == Some(session_id) {
+            state.default_session = None;
+            return;
+        }
+        tracing::info!("shutting down controller of session {}", session_id);
+        if let Err(e) = controller.shutdown().await.await {
+            tracing::error!("failed to shutdown controller ({})", e);
+        }
+    }
+
+    /// Shutdown every controller
+    pub async fn shutdown(&self) {
+        let state = self.state.lock().await;
+        let controllers = state
+            .controllers
+            .iter()
+            .filter(|(id, _)| state.default_session.as_ref() != Some(id))
+            .map(|(_, controller)| controller)
+            .chain(std::iter::once(&self.default));
+        for controller in controllers {
+            if let Err(e) = controller.shutdown().await.await {
+                tracing::error!("failed to shutdown controller ({})", e);
+            }
+        }
+    }
+}
@@ -18,6 +18,14 @@ pub fn get_http_rollup_server_address() -> String {
     format!("http://127.0.0.1:{}", HTTP_ROLLUP_SERVER_PORT)
 }
 
+pub fn get_http_session_address(session_id: &str) -> String {
+    format!(
+        "{}/sessions/{}",
+        get_http_rollup_server_address(),
+        session_id
+    )
+}
+
 pub fn get_host_runner_path() -> String {
     std::env::var("CARTESI_HOST_RUNNER_PATH")
         .unwrap_or(String::from("../target/debug/cartesi-rollups-host-runner"))
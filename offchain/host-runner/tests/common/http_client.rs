@@ -55,6 +55,19 @@ pub async fn finish(status: String) -> Result<RollupHttpRequest, HttpError> {
     handle_json_response(response).await
 }
 
+pub async fn finish_session(
+    session_id: &str,
+    status: String,
+) -> Result<RollupHttpRequest, HttpError> {
+    let url =
+        format!("{}/finish", config::get_http_session_address(session_id));
+    let mut request = HashMap::new();
+    request.insert("status", status);
+    let client = reqwest::Client::new();
+    let response = client.post(url).json(&request).send().await.unwrap();
+    handle_json_response(response).await
+}
+
 pub async fn insert_voucher(
     destination: String,
     payload: String,
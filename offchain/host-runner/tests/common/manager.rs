@@ -1,6 +1,8 @@
 // (c) Cartesi and individual authors (see AUTHORS)
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
+use std::ffi::OsStr;
+use std::path::Path;
 use std::process::{Child, Command, Stdio};
 use std::time::Duration;
 
@@ -14,6 +16,20 @@ pub struct Wrapper {
 impl Wrapper {
     /// Start the manager and waits until it is ready to answer
     pub async fn new() -> Self {
+        Self::with_args::<&str>(&[]).await
+    }
+
+    /// Start the manager with the journal in the given directory
+    pub async fn with_journal(journal_directory: &Path) -> Self {
+        Self::with_args(&[
+            OsStr::new("--journal-directory"),
+            journal_directory.as_os_str(),
+        ])
+        .await
+    }
+
+    /// Start the manager with additional command line arguments
+    pub async fn with_args<S: AsRef<OsStr>>(args: &[S]) -> Self {
         let mut command = Command::new(config::get_host_runner_path());
         command
             .env("RUST_LOG", "host_runner=debug,info")
@@ -24,7 +40,8 @@ impl Wrapper {
             .arg("--http-rollup-server-port")
             .arg(config::HTTP_ROLLUP_SERVER_PORT.to_string())
             .arg("--finish-timeout")
-            .arg(config::FINISH_TIMEOUT.to_string());
+            .arg(config::FINISH_TIMEOUT.to_string())
+            .args(args);
         if !config::get_test_verbose() {
             command.stdout(Stdio::null()).stderr(Stdio::null());
         }
@@ -13,5 +13,6 @@ mod grpc_tests {
     mod get_status;
     mod get_version;
     mod inspect_state;
+    mod journal;
     mod start_session;
 }
@@ -56,3 +56,55 @@ async fn test_it_fails_to_advance_request_with_wrong_parameters() {
         assert_eq!(err.code(), tonic::Code::InvalidArgument);
     }
 }
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_it_exceeds_the_advance_deadline() {
+    let _manager =
+        manager::Wrapper::with_args(&["--advance-deadline", "100"]).await;
+    let mut grpc_client = grpc_client::connect().await;
+    // The backend receives the input but never finishes it
+    setup_advance_state(&mut grpc_client, "rollup session").await;
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    let response = grpc_client
+        .get_epoch_status(grpc_client::GetEpochStatusRequest {
+            session_id: "rollup session".into(),
+            epoch_index: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.pending_input_count, 0);
+    assert_eq!(response.processed_inputs.len(), 1);
+    assert_eq!(
+        response.processed_inputs[0].status,
+        grpc_client::CompletionStatus::TimeLimitExceeded as i32
+    );
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_it_exceeds_the_payload_length_limit() {
+    let _manager =
+        manager::Wrapper::with_args(&["--rx-buffer-size", "256"]).await;
+    let mut grpc_client = grpc_client::connect().await;
+    grpc_client
+        .start_session(grpc_client::create_start_session_request(
+            "rollup session",
+        ))
+        .await
+        .unwrap();
+    let mut request =
+        grpc_client::create_advance_state_request("rollup session", 0, 0);
+    request.input_payload = vec![0; 64];
+    grpc_client.advance_state(request).await.unwrap();
+    // The input completes without reaching the backend
+    let processed = finish_advance_state(&mut grpc_client, "rollup session")
+        .await
+        .unwrap();
+    assert_eq!(
+        processed.status,
+        grpc_client::CompletionStatus::PayloadLengthLimitExceeded as i32
+    );
+    assert!(processed.processed_input_one_of.is_none());
+}
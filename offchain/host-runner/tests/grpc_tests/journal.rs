@@ -0,0 +1,155 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+use crate::common::*;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_it_restores_the_sessions_from_the_journal() {
+    let journal_directory = tempfile::tempdir().unwrap();
+    let manager =
+        manager::Wrapper::with_journal(journal_directory.path()).await;
+    let mut grpc_client = grpc_client::connect().await;
+    setup_advance_state(&mut grpc_client, "rollup session").await;
+    let processed = finish_advance_state(&mut grpc_client, "rollup session")
+        .await
+        .expect("failed to process input");
+    grpc_client
+        .finish_epoch(grpc_client::FinishEpochRequest {
+            session_id: "rollup session".into(),
+            active_epoch_index: 0,
+            processed_input_count_within_epoch: 1,
+            storage_directory: "".into(),
+        })
+        .await
+        .unwrap();
+
+    drop(manager);
+    let _manager =
+        manager::Wrapper::with_journal(journal_directory.path()).await;
+    let mut grpc_client = grpc_client::connect().await;
+    let response = grpc_client
+        .get_session_status(grpc_client::GetSessionStatusRequest {
+            session_id: "rollup session".into(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.active_epoch_index, 1);
+    assert_eq!(response.epoch_index, vec![0, 1]);
+    let response = grpc_client
+        .get_epoch_status(grpc_client::GetEpochStatusRequest {
+            session_id: "rollup session".into(),
+            epoch_index: 0,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(response.state, grpc_client::EpochState::Finished as i32);
+    assert_eq!(response.processed_inputs, vec![processed]);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_it_sends_the_pending_inputs_again_after_restart() {
+    let journal_directory = tempfile::tempdir().unwrap();
+    let manager =
+        manager::Wrapper::with_journal(journal_directory.path()).await;
+    let mut grpc_client = grpc_client::connect().await;
+    setup_advance_state(&mut grpc_client, "rollup session").await;
+
+    drop(manager);
+    let _manager =
+        manager::Wrapper::with_journal(journal_directory.path()).await;
+    let mut grpc_client = grpc_client::connect().await;
+    // The backend receives the pending input again
+    http_client::finish("accept".into()).await.unwrap();
+    let processed =
+        finish_advance_state(&mut grpc_client, "rollup session").await;
+    assert!(processed.is_some());
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_it_ends_the_session_removing_its_journal() {
+    let journal_directory = tempfile::tempdir().unwrap();
+    let manager =
+        manager::Wrapper::with_journal(journal_directory.path()).await;
+    let mut grpc_client = grpc_client::connect().await;
+    grpc_client
+        .start_session(grpc_client::create_start_session_request(
+            "rollup session",
+        ))
+        .await
+        .unwrap();
+    grpc_client
+        .end_session(grpc_client::EndSessionRequest {
+            session_id: "rollup session".into(),
+        })
+        .await
+        .unwrap();
+
+    drop(manager);
+    let _manager =
+        manager::Wrapper::with_journal(journal_directory.path()).await;
+    let mut grpc_client = grpc_client::connect().await;
+    let response = grpc_client
+        .get_status(grpc_client::Void {})
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.session_id.is_empty());
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_it_writes_the_snapshot_to_the_storage_directory() {
+    let snapshots = tempfile::tempdir().unwrap();
+    let storage_directory = snapshots.path().join("0_0");
+    let _manager = manager::Wrapper::new().await;
+    let mut grpc_client = grpc_client::connect().await;
+    grpc_client
+        .start_session(grpc_client::create_start_session_request(
+            "rollup session",
+        ))
+        .await
+        .unwrap();
+    grpc_client
+        .finish_epoch(grpc_client::FinishEpochRequest {
+            session_id: "rollup session".into(),
+            active_epoch_index: 0,
+            processed_input_count_within_epoch: 0,
+            storage_directory: storage_directory.to_str().unwrap().into(),
+        })
+        .await
+        .unwrap();
+    assert!(storage_directory.is_dir());
+    let err = grpc_client
+        .finish_epoch(grpc_client::FinishEpochRequest {
+            session_id: "rollup session".into(),
+            active_epoch_index: 1,
+            processed_input_count_within_epoch: 0,
+            storage_directory: storage_directory.to_str().unwrap().into(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::AlreadyExists);
+    grpc_client
+        .end_session(grpc_client::EndSessionRequest {
+            session_id: "rollup session".into(),
+        })
+        .await
+        .unwrap();
+
+    // Start the session from the snapshot
+    let mut request =
+        grpc_client::create_start_session_request("rollup session");
+    request.machine_directory = storage_directory.to_str().unwrap().into();
+    let err = grpc_client
+        .start_session(request.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    request.active_epoch_index = 1;
+    grpc_client.start_session(request).await.unwrap();
+}
@@ -8,5 +8,6 @@ mod http_tests {
     mod finish;
     mod notice;
     mod report;
+    mod sessions;
     mod voucher;
 }
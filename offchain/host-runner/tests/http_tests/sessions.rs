@@ -0,0 +1,81 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+use crate::common::*;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_it_serves_each_session_at_its_prefix() {
+    let _manager = manager::Wrapper::new().await;
+    let mut grpc_client = grpc_client::connect().await;
+    for session_id in ["session-1", "session-2"] {
+        grpc_client
+            .start_session(grpc_client::create_start_session_request(
+                session_id,
+            ))
+            .await
+            .unwrap();
+    }
+    grpc_client
+        .advance_state(grpc_client::create_advance_state_request(
+            "session-2",
+            0,
+            0,
+        ))
+        .await
+        .unwrap();
+    let response = http_client::finish_session("session-2", "accept".into())
+        .await
+        .unwrap();
+    assert!(matches!(
+        response,
+        http_client::RollupHttpRequest::Advance { .. }
+    ));
+    // The first session took the default controller, which has no requests
+    let err = http_client::finish("accept".into()).await.unwrap_err();
+    assert_eq!(err.status, 202);
+
+    // The prefixed routes of the first session reach the default controller
+    grpc_client
+        .advance_state(grpc_client::create_advance_state_request(
+            "session-1",
+            0,
+            0,
+        ))
+        .await
+        .unwrap();
+    let response = http_client::finish_session("session-1", "accept".into())
+        .await
+        .unwrap();
+    assert!(matches!(
+        response,
+        http_client::RollupHttpRequest::Advance { .. }
+    ));
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_it_fails_when_the_session_is_not_running() {
+    let _manager = manager::Wrapper::new().await;
+    let mut grpc_client = grpc_client::connect().await;
+    let err = http_client::finish_session("session-1", "accept".into())
+        .await
+        .unwrap_err();
+    assert_eq!(err.status, 404);
+
+    // The session is not reached after it ends
+    grpc_client
+        .start_session(grpc_client::create_start_session_request("session-1"))
+        .await
+        .unwrap();
+    grpc_client
+        .end_session(grpc_client::EndSessionRequest {
+            session_id: "session-1".into(),
+        })
+        .await
+        .unwrap();
+    let err = http_client::finish_session("session-1", "accept".into())
+        .await
+        .unwrap_err();
+    assert_eq!(err.status, 404);
+}
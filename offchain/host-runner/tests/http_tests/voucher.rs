@@ -71,3 +71,24 @@ async fn test_it_fails_to_insert_voucher_during_idle_state() {
         })
     );
 }
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_it_fails_to_insert_voucher_exceeding_the_tx_buffer() {
+    let _manager =
+        manager::Wrapper::with_args(&["--tx-buffer-size", "128"]).await;
+    let mut grpc_client = grpc_client::connect().await;
+    setup_advance_state(&mut grpc_client, "rollup session").await;
+    let response = http_client::insert_voucher(
+        http_client::create_address(),
+        http_client::convert_binary_to_hex(&vec![0; 33]),
+    )
+    .await;
+    assert_eq!(
+        response,
+        Err(http_client::HttpError {
+            status: 400,
+            message: "voucher payload exceeds the TX buffer size".into(),
+        })
+    );
+}
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
 use clap::Parser;
+use std::time::Duration;
 
 use log::{LogConfig, LogEnvCliConfig};
 pub use rollups_data::{RepositoryCLIConfig, RepositoryConfig};
@@ -14,8 +15,19 @@ pub struct IndexerConfig {
     pub repository_config: RepositoryConfig,
     pub dapp_metadata: DAppMetadata,
     pub broker_config: BrokerConfig,
+    pub voucher_execution_config: Option<VoucherExecutionConfig>,
     pub log_config: LogConfig,
     pub healthcheck_port: u16,
+    pub adopt_default_application: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct VoucherExecutionConfig {
+    pub provider_http_endpoint: String,
+    pub dapp_deployment_block_number: u64,
+    pub confirmations: u64,
+    pub poll_interval: Duration,
+    pub max_block_range: u64,
 }
 
 #[derive(Parser)]
@@ -31,6 +43,9 @@ pub struct CLIConfig {
     #[command(flatten)]
     broker_config: BrokerCLIConfig,
 
+    #[command(flatten)]
+    voucher_execution_config: VoucherExecutionCLIConfig,
+
     #[command(flatten)]
     pub log_config: LogEnvCliConfig,
 
@@ -41,6 +56,11 @@ pub struct CLIConfig {
         default_value_t = 8080
     )]
     pub healthcheck_port: u16,
+
+    /// Adopt the rows stored before the database had applications, which
+    /// belong to the dapp of the single indexer that used it
+    #[arg(long, env)]
+    pub adopt_default_application: bool,
 }
 
 impl From<CLIConfig> for IndexerConfig {
@@ -49,8 +69,55 @@ impl From<CLIConfig> for IndexerConfig {
             repository_config: cli_config.repository_config.into(),
             dapp_metadata: cli_config.dapp_metadata_config.into(),
             broker_config: cli_config.broker_config.into(),
+            voucher_execution_config: cli_config
+                .voucher_execution_config
+                .into(),
             log_config: cli_config.log_config.into(),
             healthcheck_port: cli_config.healthcheck_port,
+            adopt_default_application: cli_config.adopt_default_application,
         }
     }
 }
+
+#[derive(Parser, Debug)]
+#[command(name = "voucher_execution_config")]
+pub struct VoucherExecutionCLIConfig {
+    /// HTTP endpoint of the blockchain provider used to follow the execution
+    /// of vouchers. If not set, vouchers are never marked as executed.
+    #[arg(long, env)]
+    provider_http_endpoint: Option<String>,
+
+    /// Number of the block in which the dapp was deployed
+    #[arg(long, env, default_value_t = 0)]
+    dapp_deployment_block_number: u64,
+
+    /// Number of confirmations before considering a voucher executed
+    #[arg(long, env, default_value_t = 1)]
+    voucher_execution_confirmations: u64,
+
+    /// Interval in seconds between queries for voucher executions
+    #[arg(long, env, default_value_t = 5)]
+    voucher_execution_poll_interval: u64,
+
+    /// Maximum number of blocks in each query for voucher executions, which
+    /// the providers usually limit
+    #[arg(long, env, default_value_t = 1000)]
+    voucher_execution_max_block_range: u64,
+}
+
+impl From<VoucherExecutionCLIConfig> for Option<VoucherExecutionConfig> {
+    fn from(cli_config: VoucherExecutionCLIConfig) -> Self {
+        cli_config
+            .provider_http_endpoint
+            .map(|provider_http_endpoint| VoucherExecutionConfig {
+                provider_http_endpoint,
+                dapp_deployment_block_number: cli_config
+                    .dapp_deployment_block_number,
+                confirmations: cli_config.voucher_execution_confirmations,
+                poll_interval: Duration::from_secs(
+                    cli_config.voucher_execution_poll_interval,
+                ),
+                max_block_range: cli_config.voucher_execution_max_block_range,
+            })
+    }
+}
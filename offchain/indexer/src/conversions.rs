@@ -7,12 +7,14 @@
 use std::time::{Duration, UNIX_EPOCH};
 
 use rollups_events::{
-    RollupsAdvanceStateInput, RollupsCompletionStatus, RollupsNotice,
-    RollupsOutputEnum, RollupsProof, RollupsReport, RollupsVoucher,
+    RollupsAdvanceStateInput, RollupsClaim, RollupsCompletionStatus,
+    RollupsNotice, RollupsOutputEnum, RollupsProof, RollupsReport,
+    RollupsVoucher,
 };
 
 use rollups_data::{
-    CompletionStatus, Input, Notice, OutputEnum, Proof, Report, Voucher,
+    Claim, ClaimStatus, CompletionStatus, Epoch, Input, Notice, OutputEnum,
+    Proof, Report, Voucher,
 };
 
 pub fn convert_status(status: RollupsCompletionStatus) -> CompletionStatus {
@@ -54,6 +56,8 @@ pub fn convert_voucher(voucher: RollupsVoucher) -> Voucher {
         index: voucher.index as i32,
         destination: voucher.destination.into_inner().into(),
         payload: voucher.payload.into_inner(),
+        executed: false,
+        execution_transaction_hash: None,
     }
 }
 
@@ -124,3 +128,29 @@ pub fn convert_proof(proof: RollupsProof) -> Proof {
         context: proof.context.into_inner(),
     }
 }
+
+/// The claim produced by the advance-runner is pending until the
+/// authority-claimer submits it
+pub fn convert_claim(claim: RollupsClaim) -> (Epoch, Claim) {
+    let epoch = Epoch {
+        index: claim.epoch_index as i32,
+        first_input_index: claim.first_index as i32,
+        last_input_index: claim.last_index as i32,
+    };
+    let claim = Claim {
+        epoch_index: claim.epoch_index as i32,
+        epoch_hash: claim.epoch_hash.into_inner().into(),
+        machine_state_hash: claim.machine_state_hash.into_inner().into(),
+        vouchers_epoch_root_hash: claim
+            .vouchers_epoch_root_hash
+            .into_inner()
+            .into(),
+        notices_epoch_root_hash: claim
+            .notices_epoch_root_hash
+            .into_inner()
+            .into(),
+        status: ClaimStatus::Pending,
+        transaction_hash: None,
+    };
+    (epoch, claim)
+}
@@ -24,4 +24,24 @@ pub enum IndexerError {
 
     #[snafu(display("join error"))]
     JoinError { source: tokio::task::JoinError },
+
+    #[snafu(display("failed to parse provider endpoint"))]
+    ParseEndpointError { source: url::ParseError },
+
+    #[snafu(display("failed to call provider"))]
+    ProviderError {
+        source: ethers::providers::ProviderError,
+    },
+
+    #[snafu(display("invalid voucher id {}", voucher_id))]
+    InvalidVoucherIdError { voucher_id: ethers::types::U256 },
+
+    #[snafu(display("failed to query voucher executions"))]
+    ContractError {
+        source: ethers::contract::ContractError<
+            ethers::providers::Provider<
+                ethers::providers::RetryClient<ethers::providers::Http>,
+            >,
+        >,
+    },
 }
@@ -0,0 +1,237 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+use contracts::cartesi_dapp::CartesiDApp;
+use ethers::{
+    providers::{
+        Http, HttpRateLimitRetryPolicy, Middleware, Provider, RetryClient,
+    },
+    types::{H160, U256},
+};
+use rollups_data::{IndexerProgress, Repository};
+use rollups_events::Address;
+use snafu::ResultExt;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+use crate::config::VoucherExecutionConfig;
+use crate::error::{
+    ContractSnafu, IndexerError, InvalidVoucherIdSnafu, JoinSnafu,
+    ParseEndpointSnafu, ProviderSnafu, RepositorySnafu,
+};
+
+const MAX_RETRIES: u32 = 10;
+const INITIAL_BACKOFF: u64 = 1000;
+
+/// Key of the indexer progress row with the next block to read
+const PROGRESS_KEY: &str = "voucher-executions";
+
+type ProviderClient = Provider<RetryClient<Http>>;
+
+/// Follows the `VoucherExecuted` events of the dapp and marks the executed
+/// vouchers in the repository
+pub struct VoucherExecutionTracker {
+    repository: Repository,
+    provider: Arc<ProviderClient>,
+    dapp: CartesiDApp<ProviderClient>,
+    confirmations: u64,
+    poll_interval: Duration,
+    max_block_range: u64,
+    next_block_to_read: u64,
+}
+
+impl VoucherExecutionTracker {
+    pub fn new(
+        config: VoucherExecutionConfig,
+        dapp_address: &Address,
+        repository: Repository,
+    ) -> Result<Self, IndexerError> {
+        let url = Url::parse(&config.provider_http_endpoint)
+            .context(ParseEndpointSnafu)?;
+        let retry_client = RetryClient::new(
+            Http::new(url),
+            Box::new(HttpRateLimitRetryPolicy),
+            MAX_RETRIES,
+            INITIAL_BACKOFF,
+        );
+        let provider = Arc::new(Provider::new(retry_client));
+        let dapp =
+            CartesiDApp::new(H160(*dapp_address.inner()), provider.clone());
+        Ok(Self {
+            repository,
+            provider,
+            dapp,
+            confirmations: config.confirmations,
+            poll_interval: config.poll_interval,
+            max_block_range: config.max_block_range.max(1),
+            next_block_to_read: config.dapp_deployment_block_number,
+        })
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn start(mut self) -> Result<(), IndexerError> {
+        self.resume().await?;
+        tracing::info!(
+            self.next_block_to_read,
+            "starting voucher execution tracker"
+        );
+        loop {
+            let caught_up = self.update().await?;
+            if caught_up {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+        }
+    }
+
+    /// Resume from the block stored in the indexer progress, if there is one
+    async fn resume(&mut self) -> Result<(), IndexerError> {
+        let repository = self.repository.clone();
+        let progress = tokio::task::spawn_blocking(move || {
+            repository.get_indexer_progress()
+        })
+        .await
+        .context(JoinSnafu)?
+        .context(RepositorySnafu)?;
+        let stored = progress
+            .into_iter()
+            .find(|progress| progress.stream_key == PROGRESS_KEY)
+            .and_then(|progress| progress.last_event_id.parse::<u64>().ok());
+        if let Some(block) = stored {
+            self.next_block_to_read = self.next_block_to_read.max(block);
+        }
+        Ok(())
+    }
+
+    /// Store the next block to read, so the tracker resumes from it
+    async fn store_progress(&self) -> Result<(), IndexerError> {
+        let repository = self.repository.clone();
+        let progress = IndexerProgress {
+            stream_key: PROGRESS_KEY.to_owned(),
+            last_event_id: self.next_block_to_read.to_string(),
+            rollback_index: None,
+        };
+        tokio::task::spawn_blocking(move || {
+            repository.transaction(|tx| tx.set_indexer_progress(progress))
+        })
+        .await
+        .context(JoinSnafu)?
+        .context(RepositorySnafu)
+    }
+
+    /// Read the `VoucherExecuted` events of the next range of blocks and mark
+    /// their vouchers. Returns whether the tracker reached the latest block.
+    /// If a voucher wasn't indexed yet, its execution is recorded, so the
+    /// voucher is marked when the indexer stores it.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn update(&mut self) -> Result<bool, IndexerError> {
+        let latest = self
+            .provider
+            .get_block_number()
+            .await
+            .context(ProviderSnafu)?
+            .as_u64();
+        if latest < self.confirmations {
+            return Ok(true);
+        }
+        let latest = latest - self.confirmations;
+        if latest < self.next_block_to_read {
+            tracing::trace!(
+                self.next_block_to_read,
+                latest,
+                "no new blocks to read"
+            );
+            return Ok(true);
+        }
+        let to_block =
+            latest.min(self.next_block_to_read + self.max_block_range - 1);
+
+        let events = self
+            .dapp
+            .voucher_executed_filter()
+            .from_block(self.next_block_to_read)
+            .to_block(to_block)
+            .query_with_meta()
+            .await
+            .context(ContractSnafu)?;
+        tracing::trace!(
+            from = self.next_block_to_read,
+            to = to_block,
+            "read {} voucher executions",
+            events.len()
+        );
+
+        for (event, meta) in events {
+            let (input_index, index) = decode_voucher_id(event.voucher_id)?;
+            let transaction_hash = meta.transaction_hash.0.to_vec();
+            let repository = self.repository.clone();
+            let found = tokio::task::spawn_blocking(move || {
+                repository.update_voucher_executed(
+                    input_index,
+                    index,
+                    transaction_hash,
+                )
+            })
+            .await
+            .context(JoinSnafu)?
+            .context(RepositorySnafu)?;
+            if found {
+                tracing::info!(input_index, index, "voucher was executed");
+            } else {
+                tracing::info!(
+                    input_index,
+                    index,
+                    "executed voucher wasn't indexed yet"
+                );
+            }
+        }
+
+        self.next_block_to_read = to_block + 1;
+        self.store_progress().await?;
+        Ok(to_block == latest)
+    }
+}
+
+/// Run the tracker if it is enabled; otherwise, wait forever
+pub async fn track(
+    tracker: Option<VoucherExecutionTracker>,
+) -> Result<(), IndexerError> {
+    match tracker {
+        Some(tracker) => tracker.start().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Split the voucher id into the input index and the voucher index
+/// The CartesiDApp contract identifies the voucher by its position in the
+/// bitmask of executed vouchers: `(voucher index << 128) | input index`.
+fn decode_voucher_id(voucher_id: U256) -> Result<(i32, i32), IndexerError> {
+    let input_index = i32::try_from(voucher_id & U256::from(u128::MAX));
+    let index = i32::try_from(voucher_id >> 128);
+    match (input_index, index) {
+        (Ok(input_index), Ok(index)) => Ok((input_index, index)),
+        _ => InvalidVoucherIdSnafu { voucher_id }.fail(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_voucher_id_splits_indices() {
+        let voucher_id = (U256::from(3) << 128) | U256::from(42);
+        assert_eq!(decode_voucher_id(voucher_id).unwrap(), (42, 3));
+        assert_eq!(decode_voucher_id(U256::zero()).unwrap(), (0, 0));
+    }
+
+    #[test]
+    fn decode_voucher_id_fails_on_wide_indices() {
+        let voucher_id = U256::from(u64::MAX);
+        assert!(decode_voucher_id(voucher_id).is_err());
+        let voucher_id = U256::from(1) << 200;
+        assert!(decode_voucher_id(voucher_id).is_err());
+        let voucher_id = U256::from(i32::MAX as u64 + 1) << 128;
+        assert!(decode_voucher_id(voucher_id).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 // (c) Cartesi and individual authors (see AUTHORS)
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
-use rollups_data::Repository;
+use rollups_data::{IndexerProgress, Repository, Transaction};
 use rollups_events::indexer::{IndexerEvent, IndexerState};
 use rollups_events::{
     Broker, BrokerError, RollupsData, RollupsInput, RollupsOutput,
@@ -12,12 +12,18 @@ use crate::conversions::*;
 use crate::error::{
     BrokerSnafu, IndexerError, JoinSnafu, MigrationsSnafu, RepositorySnafu,
 };
+use crate::executions::{self, VoucherExecutionTracker};
 use crate::IndexerConfig;
 
 pub struct Indexer {
     repository: Repository,
     broker: Broker,
     state: IndexerState,
+
+    /// First input index deleted by a reorg rollback, if any
+    /// It is stored with the progress, because the outputs of the deleted
+    /// inputs may still be in the stream when the indexer restarts.
+    rollback_index: Option<i32>,
 }
 
 impl Indexer {
@@ -28,8 +34,22 @@ impl Indexer {
         rollups_data::run_migrations(&endpoint).context(MigrationsSnafu)?;
 
         tracing::info!("runned migrations; connecting to DB");
-        let repository = tokio::task::spawn_blocking(|| {
-            Repository::new(config.repository_config)
+        let chain_id = config.dapp_metadata.chain_id as i64;
+        let dapp_address = config.dapp_metadata.dapp_address.inner().to_vec();
+        let adopt_default_application = config.adopt_default_application;
+        let repository = tokio::task::spawn_blocking(move || {
+            // The rows of the dapp are stored under its application, so
+            // several indexers may share the same database
+            let repository = Repository::new(config.repository_config)?;
+            let application = if adopt_default_application {
+                repository.adopt_default_application(chain_id, &dapp_address)?
+            } else {
+                repository.register_application(chain_id, &dapp_address)?
+            };
+            tracing::info!(application.id, "registered application");
+            Ok::<_, rollups_data::Error>(
+                repository.for_application(application.id),
+            )
         })
         .await
         .context(JoinSnafu)?
@@ -40,31 +60,148 @@ impl Indexer {
             .await
             .context(BrokerSnafu)?;
 
-        let state = IndexerState::new(&config.dapp_metadata);
-        let mut indexer = Indexer {
+        let tracker = match config.voucher_execution_config {
+            Some(tracker_config) => Some(VoucherExecutionTracker::new(
+                tracker_config,
+                &config.dapp_metadata.dapp_address,
+                repository.clone(),
+            )?),
+            None => {
+                tracing::info!("voucher execution tracking is disabled");
+                None
+            }
+        };
+
+        let mut state = IndexerState::new(&config.dapp_metadata);
+        let progress = {
+            let repository = repository.clone();
+            tokio::task::spawn_blocking(move || {
+                repository.get_indexer_progress()
+            })
+            .await
+            .context(JoinSnafu)?
+            .context(RepositorySnafu)?
+        };
+        tracing::info!(?progress, "resuming from the stored events");
+        let rollback_index = progress
+            .iter()
+            .filter_map(|progress| progress.rollback_index)
+            .min();
+        let last_ids = progress
+            .into_iter()
+            .map(|progress| (progress.stream_key, progress.last_event_id))
+            .collect();
+        state.resume(&last_ids);
+
+        let indexer = Indexer {
             repository,
             broker,
             state,
+            rollback_index,
         };
 
         tracing::info!("connected to broker; starting main loop");
+        tokio::select! {
+            ret = indexer.main_loop() => ret,
+            ret = executions::track(tracker) => ret,
+        }
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn main_loop(mut self) -> Result<(), IndexerError> {
         loop {
-            let event = indexer.consume_event().await?;
-            let repository = indexer.repository.clone();
-            tokio::task::spawn_blocking(move || match event {
-                IndexerEvent::Input(input) => {
-                    store_input(&repository, input.payload)
-                }
-                IndexerEvent::Output(output) => {
-                    store_output(&repository, output.payload)
+            let inputs_last_id = self.state.inputs_last_id().to_owned();
+            let events = self.consume_events().await?;
+            let rollback_index = match &events[0] {
+                IndexerEvent::Input(input)
+                    if input.payload.parent_id != inputs_last_id =>
+                {
+                    let index = first_replaced_index(&input.payload);
+                    tracing::warn!(
+                        input.payload.parent_id,
+                        index,
+                        "found reorg; deleting replaced inputs"
+                    );
+                    self.rollback_index = Some(
+                        self.rollback_index.map_or(index, |i| i.min(index)),
+                    );
+                    Some(index)
                 }
+                _ => None,
+            };
+            let min_rollback_index = self.rollback_index;
+            let repository = self.repository.clone();
+            // The progress is stored with the data of the events, so the
+            // indexer resumes after the last stored event when it restarts.
+            // The events of a group come from the same stream.
+            let last_event = events.last().expect("empty group of events");
+            let progress = IndexerProgress {
+                stream_key: self.state.stream_key(last_event).to_owned(),
+                last_event_id: last_event.id().to_owned(),
+                rollback_index: min_rollback_index,
+            };
+            tokio::task::spawn_blocking(move || {
+                repository.transaction(|tx| {
+                    store_events(
+                        tx,
+                        events,
+                        rollback_index,
+                        min_rollback_index,
+                    )?;
+                    tx.set_indexer_progress(progress)
+                })
             })
             .await
             .context(JoinSnafu)?
             .context(RepositorySnafu)?;
+
+            // The events are only acknowledged after they are stored, once
+            // per batch of events read from the broker
+            if !self.state.has_pending() {
+                self.broker
+                    .indexer_acknowledge(&self.state)
+                    .await
+                    .context(BrokerSnafu)?;
+            }
         }
     }
 
+    /// Consume the next event and the other events of its group
+    /// When a group of outputs reaches the end of the events read from the
+    /// broker, the following outputs are read ahead, so the group is stored
+    /// in a single transaction even if it is split between two reads.
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_events(
+        &mut self,
+    ) -> Result<Vec<IndexerEvent>, IndexerError> {
+        let mut events = vec![self.consume_event().await?];
+        let mut read_ahead = false;
+        loop {
+            let previous = events.last().expect("empty group of events");
+            if !self.state.has_pending()
+                && matches!(previous, IndexerEvent::Output(_))
+            {
+                read_ahead = true;
+                self.broker
+                    .indexer_read_outputs(&mut self.state)
+                    .await
+                    .context(BrokerSnafu)?;
+            }
+            match self.state.peek_pending() {
+                Some(next) if continues_group(previous, next) => {
+                    events.push(self.consume_event().await?)
+                }
+                _ => break,
+            }
+        }
+        // The inputs go before the outputs that were read ahead
+        if read_ahead {
+            self.state.discard_pending();
+        }
+        tracing::trace!(count = events.len(), "consumed group of events");
+        Ok(events)
+    }
+
     #[tracing::instrument(level = "trace", skip_all)]
     async fn consume_event(&mut self) -> Result<IndexerEvent, IndexerError> {
         tracing::info!(?self.state, "waiting for next event");
@@ -88,43 +225,152 @@ impl Indexer {
     }
 }
 
+/// Whether the next event belongs to the group of the previous one
+/// The outputs of an input, starting with its advance result, are a group,
+/// and so are the proofs of an epoch; every other event is a group by itself.
+fn continues_group(previous: &IndexerEvent, next: &IndexerEvent) -> bool {
+    let (previous, next) = match (previous, next) {
+        (IndexerEvent::Output(previous), IndexerEvent::Output(next)) => {
+            (&previous.payload, &next.payload)
+        }
+        _ => return false,
+    };
+    match (previous, next) {
+        (RollupsOutput::Proof(_), RollupsOutput::Proof(_)) => true,
+        (RollupsOutput::Proof(_), _) | (_, RollupsOutput::Proof(_)) => false,
+        (_, RollupsOutput::AdvanceResult(_)) => false,
+        (previous, next) => {
+            output_input_index(previous) == output_input_index(next)
+        }
+    }
+}
+
+fn output_input_index(output: &RollupsOutput) -> i32 {
+    let index = match output {
+        RollupsOutput::AdvanceResult(result) => result.input_index,
+        RollupsOutput::Voucher(voucher) => voucher.input_index,
+        RollupsOutput::Notice(notice) => notice.input_index,
+        RollupsOutput::Report(report) => report.input_index,
+        RollupsOutput::Proof(proof) => proof.input_index,
+    };
+    index as i32
+}
+
+#[tracing::instrument(level = "trace", skip_all)]
+fn store_events(
+    tx: &mut Transaction<'_>,
+    events: Vec<IndexerEvent>,
+    rollback_index: Option<i32>,
+    min_rollback_index: Option<i32>,
+) -> Result<(), rollups_data::Error> {
+    let mut outputs = vec![];
+    for event in events {
+        match event {
+            IndexerEvent::Input(input) => {
+                if let Some(index) = rollback_index {
+                    tx.delete_inputs_from(index)?;
+                }
+                store_input(tx, input.payload)?;
+            }
+            IndexerEvent::Output(output) => outputs.push(output.payload),
+            IndexerEvent::Claim(claim) => {
+                let (epoch, claim) = convert_claim(claim.payload);
+                tx.insert_claim(epoch, claim)?;
+            }
+        }
+    }
+    if outputs.is_empty() {
+        Ok(())
+    } else {
+        store_outputs(tx, outputs, min_rollback_index)
+    }
+}
+
 #[tracing::instrument(level = "trace", skip_all)]
 fn store_input(
-    repository: &Repository,
+    tx: &mut Transaction<'_>,
     input: RollupsInput,
 ) -> Result<(), rollups_data::Error> {
     match input.data {
         RollupsData::AdvanceStateInput(input) => {
-            repository.insert_input(convert_input(input))
+            tx.insert_input(convert_input(input))
         }
-        RollupsData::FinishEpoch {} => {
+        RollupsData::FinishEpoch { .. } => {
             tracing::trace!("ignoring finish epoch");
             Ok(())
         }
     }
 }
 
+/// Index of the first input replaced by a reorg
+/// The input event doesn't follow the last one, so every input starting from
+/// the one it adds (or from the end of the epoch it finishes) was replaced.
+fn first_replaced_index(input: &RollupsInput) -> i32 {
+    let index = match input.data {
+        RollupsData::AdvanceStateInput(_) => input.inputs_sent_count - 1,
+        RollupsData::FinishEpoch { .. } => input.inputs_sent_count,
+    };
+    index as i32
+}
+
+/// Store a group of outputs with a multi-row insert for each kind of output
+/// The outputs of a group belong to the same input, unless they are proofs.
 #[tracing::instrument(level = "trace", skip_all)]
-fn store_output(
-    repository: &Repository,
-    output: RollupsOutput,
+fn store_outputs(
+    tx: &mut Transaction<'_>,
+    outputs: Vec<RollupsOutput>,
+    rollback_index: Option<i32>,
 ) -> Result<(), rollups_data::Error> {
-    match output {
-        RollupsOutput::AdvanceResult(result) => repository.update_input_status(
-            result.input_index as i32,
-            convert_status(result.status),
-        ),
-        RollupsOutput::Voucher(voucher) => {
-            repository.insert_voucher(convert_voucher(voucher))
-        }
-        RollupsOutput::Notice(notice) => {
-            repository.insert_notice(convert_notice(notice))
-        }
-        RollupsOutput::Report(report) => {
-            repository.insert_report(convert_report(report))
+    let input_index = outputs.iter().map(output_input_index).min();
+    // The savepoint keeps the transaction usable if the outputs are ignored
+    let result = tx.savepoint(|tx| {
+        let mut vouchers = vec![];
+        let mut notices = vec![];
+        let mut reports = vec![];
+        let mut proofs = vec![];
+        for output in outputs {
+            match output {
+                RollupsOutput::AdvanceResult(result) => {
+                    // The input may have been processed before a reorg, so
+                    // the outputs of the previous run are replaced by the
+                    // following ones
+                    let index = result.input_index as i32;
+                    tx.delete_outputs(index)?;
+                    tx.update_input_status(
+                        index,
+                        convert_status(result.status),
+                    )?;
+                }
+                RollupsOutput::Voucher(voucher) => {
+                    vouchers.push(convert_voucher(voucher))
+                }
+                RollupsOutput::Notice(notice) => {
+                    notices.push(convert_notice(notice))
+                }
+                RollupsOutput::Report(report) => {
+                    reports.push(convert_report(report))
+                }
+                RollupsOutput::Proof(proof) => {
+                    proofs.push(convert_proof(proof))
+                }
+            }
         }
-        RollupsOutput::Proof(proof) => {
-            repository.insert_proof(convert_proof(proof))
+        tx.insert_vouchers(vouchers)?;
+        tx.insert_notices(notices)?;
+        tx.insert_reports(reports)?;
+        tx.insert_proofs(proofs)
+    });
+    match result {
+        Err(e)
+            if e.is_foreign_key_violation()
+                && input_index.is_some_and(|index| {
+                    rollback_index.is_some_and(|i| index >= i)
+                }) =>
+        {
+            // The outputs of inputs deleted by a reorg are still in the stream
+            tracing::warn!(?input_index, "ignoring outputs of deleted input");
+            Ok(())
         }
+        result => result,
     }
 }
@@ -9,6 +9,7 @@ pub use error::IndexerError;
 pub mod config;
 mod conversions;
 mod error;
+mod executions;
 mod indexer;
 
 #[tracing::instrument(level = "trace", skip_all)]
@@ -5,13 +5,15 @@ use indexer::IndexerError;
 use log::LogConfig;
 use rand::Rng;
 use rollups_data::{
-    Input, Notice, OutputEnum, Proof, Report, RepositoryConfig, Voucher,
+    Claim, ClaimStatus, CompletionStatus, Epoch, Input, Notice, OutputEnum,
+    Proof, Report, RepositoryConfig, Voucher,
 };
 use rollups_events::{
     BrokerConfig, BrokerEndpoint, DAppMetadata, InputMetadata,
-    RollupsAdvanceStateInput, RollupsData, RollupsNotice, RollupsOutput,
-    RollupsOutputEnum, RollupsOutputValidityProof, RollupsProof, RollupsReport,
-    RollupsVoucher,
+    RollupsAdvanceResult, RollupsAdvanceStateInput, RollupsClaim,
+    RollupsCompletionStatus, RollupsData, RollupsInput, RollupsNotice,
+    RollupsOutput, RollupsOutputEnum, RollupsOutputValidityProof, RollupsProof,
+    RollupsReport, RollupsVoucher,
 };
 use serial_test::serial;
 use std::time::UNIX_EPOCH;
@@ -21,8 +23,8 @@ use tokio::task::JoinHandle;
 
 const BROKER_CONSUME_TIMEOUT: usize = 100;
 
-/// Starts one container with the broker, one container with the database,
-/// and the indexer in a background thread.
+/// Starts the in-memory broker, one container with the database, and the
+/// indexer in a background thread.
 struct TestState<'d> {
     broker: BrokerFixture<'d>,
     repository: RepositoryFixture<'d>,
@@ -70,6 +72,73 @@ async fn indexer_inserts_vouchers() {
     }
 }
 
+#[test_log::test(tokio::test)]
+#[serial]
+async fn indexer_inserts_outputs_of_input_together() {
+    let docker = Cli::default();
+    let state = TestState::setup(&docker).await;
+    assert_outputs_of_input_inserted_together(&state, 10).await;
+}
+
+#[test_log::test(tokio::test)]
+#[serial]
+async fn indexer_inserts_outputs_of_input_larger_than_batch_together() {
+    let docker = Cli::default();
+    let state = TestState::setup(&docker).await;
+    // The broker reads up to 100 events at once
+    assert_outputs_of_input_inserted_together(&state, 150).await;
+}
+
+/// Produce the outputs of an input at once, with `n` vouchers and notices
+async fn assert_outputs_of_input_inserted_together(
+    state: &TestState<'_>,
+    n: u64,
+) {
+    state.produce_input_in_broker(0).await;
+    let vouchers: Vec<_> = (0..n)
+        .map(|i| RollupsVoucher {
+            index: i,
+            input_index: 0,
+            destination: random_array().into(),
+            payload: random_array::<32>().to_vec().into(),
+        })
+        .collect();
+    let notices: Vec<_> = (0..n)
+        .map(|i| RollupsNotice {
+            index: i,
+            input_index: 0,
+            payload: random_array::<32>().to_vec().into(),
+        })
+        .collect();
+    let mut outputs =
+        vec![RollupsOutput::AdvanceResult(RollupsAdvanceResult {
+            input_index: 0,
+            status: RollupsCompletionStatus::Accepted,
+        })];
+    outputs.extend(vouchers.iter().cloned().map(RollupsOutput::Voucher));
+    outputs.extend(notices.iter().cloned().map(RollupsOutput::Notice));
+    state.broker.produce_outputs(outputs).await;
+
+    // The outputs are stored in a single transaction, so every output is in
+    // the database once the first one is
+    state.get_voucher_from_database(&vouchers[0]).await;
+    let repository = state.repository.repository();
+    let input = repository.get_input(0).expect("failed to get input");
+    assert_eq!(input.status, CompletionStatus::Accepted);
+    for voucher_sent in vouchers.iter() {
+        let voucher_read = repository
+            .get_voucher(voucher_sent.index as i32, 0)
+            .expect("failed to get voucher");
+        assert_voucher_eq(voucher_sent, &voucher_read);
+    }
+    for notice_sent in notices.iter() {
+        let notice_read = repository
+            .get_notice(notice_sent.index as i32, 0)
+            .expect("failed to get notice");
+        assert_notice_eq(notice_sent, &notice_read);
+    }
+}
+
 #[test_log::test(tokio::test)]
 #[serial]
 async fn indexer_inserts_notices() {
@@ -153,7 +222,7 @@ async fn indexer_ignores_finish_epoch_and_insert_input_after() {
     let state = TestState::setup(&docker).await;
 
     tracing::info!("producing finish epoch");
-    let data = RollupsData::FinishEpoch {};
+    let data = RollupsData::FinishEpoch { boundary: None };
     state.broker.produce_input_event(data).await;
 
     let input_sent = state.produce_input_in_broker(0).await;
@@ -201,9 +270,112 @@ async fn indexer_fails_to_insert_output_when_input_does_not_exist() {
     assert!(matches!(error, IndexerError::RepositoryError { .. }));
 }
 
+#[test_log::test(tokio::test)]
+#[serial]
+async fn indexer_replaces_inputs_after_reorg() {
+    let docker = Cli::default();
+    let state = TestState::setup(&docker).await;
+
+    state.produce_input_in_broker(0).await;
+    let parent = state.broker.get_latest_input_event().await.unwrap();
+    state.produce_input_in_broker(1).await;
+    let replaced_input = state.produce_input_in_broker(2).await;
+    state.get_input_from_database(&replaced_input).await;
+
+    let input = state
+        .produce_input_after_reorg_in_broker(parent.id, 1)
+        .await;
+    let input_read = state.get_replacing_input_from_database(&input).await;
+    assert_input_eq(&input, &input_read);
+    assert!(matches!(
+        state.repository.repository().get_input(2),
+        Err(rollups_data::Error::ItemNotFound { .. })
+    ));
+
+    tracing::info!("producing outputs of the deleted input");
+    state.produce_voucher_in_broker(2, 0).await;
+    let voucher_sent = state.produce_voucher_in_broker(1, 0).await;
+    let voucher_read = state.get_voucher_from_database(&voucher_sent).await;
+    assert_voucher_eq(&voucher_sent, &voucher_read);
+}
+
+#[test_log::test(tokio::test)]
+#[serial]
+async fn indexer_ignores_outputs_of_deleted_inputs_after_restart() {
+    let docker = Cli::default();
+    let mut state = TestState::setup(&docker).await;
+
+    state.produce_input_in_broker(0).await;
+    let parent = state.broker.get_latest_input_event().await.unwrap();
+    state.produce_input_in_broker(1).await;
+    let replaced_input = state.produce_input_in_broker(2).await;
+    state.get_input_from_database(&replaced_input).await;
+    let input = state
+        .produce_input_after_reorg_in_broker(parent.id, 1)
+        .await;
+    state.get_replacing_input_from_database(&input).await;
+
+    // The rollback is stored, so the indexer still ignores the outputs of
+    // the deleted inputs after it restarts
+    state.restart_indexer().await;
+
+    tracing::info!("producing outputs of the deleted input");
+    state.produce_voucher_in_broker(2, 0).await;
+    let voucher_sent = state.produce_voucher_in_broker(1, 0).await;
+    let voucher_read = state.get_voucher_from_database(&voucher_sent).await;
+    assert_voucher_eq(&voucher_sent, &voucher_read);
+}
+
+#[test_log::test(tokio::test)]
+#[serial]
+async fn indexer_inserts_claims() {
+    let docker = Cli::default();
+    let state = TestState::setup(&docker).await;
+
+    const N: u64 = 3;
+    let mut claims = vec![];
+    for i in 0..N {
+        let claim = state.produce_claim_in_broker(i).await;
+        claims.push(claim);
+    }
+
+    for claim_sent in claims.into_iter() {
+        let (epoch_read, claim_read) =
+            state.get_claim_from_database(&claim_sent).await;
+        assert_claim_eq(&claim_sent, &epoch_read, &claim_read);
+    }
+}
+
+#[test_log::test(tokio::test)]
+#[serial]
+async fn indexer_resumes_after_the_stored_events() {
+    let docker = Cli::default();
+    let mut state = TestState::setup(&docker).await;
+
+    let input = state.produce_input_in_broker(0).await;
+    state.get_input_from_database(&input).await;
+
+    // The input is only stored again if the indexer reads the stream from
+    // the start
+    state
+        .repository
+        .repository()
+        .delete_inputs_from(0)
+        .expect("failed to delete input");
+    state.restart_indexer().await;
+
+    let input_sent = state.produce_input_in_broker(1).await;
+    let input_read = state.get_input_from_database(&input_sent).await;
+    assert_input_eq(&input_sent, &input_read);
+    assert!(matches!(
+        state.repository.repository().get_input(0),
+        Err(rollups_data::Error::ItemNotFound { .. })
+    ));
+}
+
 impl TestState<'_> {
     async fn setup(docker: &Cli) -> TestState<'_> {
-        let broker = BrokerFixture::setup(docker).await;
+        let broker = BrokerFixture::setup_memory().await;
         let repository = RepositoryFixture::setup(docker);
         let indexer = spawn_indexer(
             repository.config(),
@@ -218,6 +390,18 @@ impl TestState<'_> {
         }
     }
 
+    async fn restart_indexer(&mut self) {
+        tracing::info!("restarting indexer");
+        self.indexer.abort();
+        let _ = (&mut self.indexer).await;
+        self.indexer = spawn_indexer(
+            self.repository.config(),
+            self.broker.redis_endpoint().to_owned(),
+            self.broker.dapp_metadata(),
+        )
+        .await;
+    }
+
     /// Wait for the indexer to fail and return the error
     async fn get_indexer_error(self) -> IndexerError {
         tracing::info!("waiting for indexer to fail");
@@ -272,6 +456,57 @@ impl TestState<'_> {
         self.repository.retry(move |r| r.get_input(index)).await
     }
 
+    /// Produce an input whose parent isn't the latest input event, which
+    /// replaces the inputs after the parent
+    async fn produce_input_after_reorg_in_broker(
+        &self,
+        parent_id: String,
+        input_index: u64,
+    ) -> RollupsAdvanceStateInput {
+        let input = RollupsAdvanceStateInput {
+            metadata: InputMetadata {
+                input_index,
+                ..Default::default()
+            },
+            payload: random_array::<32>().to_vec().into(),
+            tx_hash: random_array().into(),
+        };
+
+        tracing::info!(?input, "producing input after reorg");
+        self.broker
+            .produce_raw_input_event(RollupsInput {
+                parent_id,
+                epoch_index: 0,
+                inputs_sent_count: input_index + 1,
+                data: RollupsData::AdvanceStateInput(input.clone()),
+            })
+            .await;
+        input
+    }
+
+    /// Wait for the input that replaces the one with the same index
+    async fn get_replacing_input_from_database(
+        &self,
+        input_sent: &RollupsAdvanceStateInput,
+    ) -> Input {
+        tracing::info!("waiting for replacing input in database");
+        let index = input_sent.metadata.input_index as i32;
+        let payload = input_sent.payload.inner().clone();
+        self.repository
+            .retry(move |r| {
+                r.get_input(index).and_then(|input| {
+                    if input.payload == payload {
+                        Ok(input)
+                    } else {
+                        Err(rollups_data::Error::ItemNotFound {
+                            item_type: "input".to_owned(),
+                        })
+                    }
+                })
+            })
+            .await
+    }
+
     async fn produce_voucher_in_broker(
         &self,
         input_index: u64,
@@ -416,6 +651,40 @@ impl TestState<'_> {
             })
             .await
     }
+
+    async fn produce_claim_in_broker(&self, epoch_index: u64) -> RollupsClaim {
+        let claim = RollupsClaim {
+            dapp_address: self.broker.dapp_metadata().dapp_address,
+            epoch_index,
+            epoch_hash: random_array().into(),
+            first_index: 2 * epoch_index as u128,
+            last_index: 2 * epoch_index as u128 + 1,
+            machine_state_hash: random_array().into(),
+            vouchers_epoch_root_hash: random_array().into(),
+            notices_epoch_root_hash: random_array().into(),
+        };
+
+        tracing::info!(?claim, "producing claim");
+        self.broker.produce_rollups_claim(claim.clone()).await;
+        claim
+    }
+
+    async fn get_claim_from_database(
+        &self,
+        claim_sent: &RollupsClaim,
+    ) -> (Epoch, Claim) {
+        tracing::info!("waiting for claim in database");
+        let epoch_index = claim_sent.epoch_index as i32;
+        let claim = self
+            .repository
+            .retry(move |r| r.get_claim(epoch_index))
+            .await;
+        let epoch = self
+            .repository
+            .retry(move |r| r.get_epoch(epoch_index))
+            .await;
+        (epoch, claim)
+    }
 }
 
 async fn spawn_indexer(
@@ -426,6 +695,9 @@ async fn spawn_indexer(
     let broker_config = BrokerConfig {
         redis_endpoint,
         consume_timeout: BROKER_CONSUME_TIMEOUT,
+        retention: Default::default(),
+        payload_encoding: Default::default(),
+        consumer_group: None,
         backoff: Default::default(),
     };
 
@@ -433,8 +705,10 @@ async fn spawn_indexer(
         repository_config,
         dapp_metadata,
         broker_config,
+        voucher_execution_config: None,
         healthcheck_port: 0,
         log_config: LogConfig::default(),
+        adopt_default_application: true,
     };
     tokio::spawn(async move {
         indexer::run(indexer_config).await.map_err(|e| {
@@ -541,3 +815,29 @@ fn assert_proof_eq(proof_sent: &RollupsProof, proof_read: &Proof) {
     }
     assert_eq!(&proof_read.context, proof_sent.context.inner());
 }
+
+fn assert_claim_eq(
+    claim_sent: &RollupsClaim,
+    epoch_read: &Epoch,
+    claim_read: &Claim,
+) {
+    assert_eq!(claim_sent.epoch_index as i32, epoch_read.index);
+    assert_eq!(claim_sent.first_index as i32, epoch_read.first_input_index);
+    assert_eq!(claim_sent.last_index as i32, epoch_read.last_input_index);
+    assert_eq!(claim_sent.epoch_index as i32, claim_read.epoch_index);
+    assert_eq!(claim_sent.epoch_hash.inner(), &claim_read.epoch_hash[..]);
+    assert_eq!(
+        claim_sent.machine_state_hash.inner(),
+        &claim_read.machine_state_hash[..]
+    );
+    assert_eq!(
+        claim_sent.vouchers_epoch_root_hash.inner(),
+        &claim_read.vouchers_epoch_root_hash[..]
+    );
+    assert_eq!(
+        claim_sent.notices_epoch_root_hash.inner(),
+        &claim_read.notices_epoch_root_hash[..]
+    );
+    assert_eq!(claim_read.status, ClaimStatus::Pending);
+    assert_eq!(claim_read.transaction_hash, None);
+}
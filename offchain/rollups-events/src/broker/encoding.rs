@@ -0,0 +1,151 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Encoding of the event payloads
+//!
+//! Each stream entry records the encoding of its payload in the `encoding`
+//! field and the version of the payload schema in the `version` field. The
+//! entries produced before these fields existed don't have them, so they are
+//! read as the first version in JSON. This allows the consumers to read the
+//! events of every previous release during a rolling upgrade; the producers
+//! should only switch to the binary encoding or to a new schema version after
+//! every consumer is upgraded.
+
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use snafu::ResultExt;
+use std::cmp::Ordering;
+
+use super::{
+    BrokerError, DecodePayloadSnafu, EncodePayloadSnafu, InvalidPayloadSnafu,
+    UnsupportedVersionSnafu, VersionedPayload,
+};
+
+/// Field of the stream entry with the encoding of the payload
+pub(super) const ENCODING_FIELD: &str = "encoding";
+
+/// Field of the stream entry with the version of the payload schema
+pub(super) const VERSION_FIELD: &str = "version";
+
+/// Field of the stream entry with the encoded payload
+pub(super) const PAYLOAD_FIELD: &str = "payload";
+
+/// Version of the payloads of the entries without the version field
+pub const LEGACY_VERSION: u32 = 1;
+
+/// Encoding of the event payloads
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PayloadEncoding {
+    /// JSON, with the binary fields as hex and base64 strings
+    #[default]
+    Json,
+    /// CBOR, with the binary fields as byte strings
+    Cbor,
+}
+
+impl PayloadEncoding {
+    /// Name of the encoding in the stream entries
+    /// The version in the name changes whenever the layout of the encoded
+    /// payloads changes.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Cbor => "cbor-v1",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self, BrokerError> {
+        [Self::Json, Self::Cbor]
+            .into_iter()
+            .find(|encoding| encoding.name() == name)
+            .ok_or_else(|| BrokerError::UnknownEncoding {
+                encoding: name.to_owned(),
+            })
+    }
+
+    /// Encode the payload with the current version of its schema
+    pub fn encode<P: VersionedPayload>(
+        &self,
+        payload: &P,
+    ) -> Result<EncodedPayload, BrokerError> {
+        let data = match self {
+            Self::Json => {
+                serde_json::to_vec(payload).context(InvalidPayloadSnafu)?
+            }
+            Self::Cbor => {
+                let mut data = vec![];
+                ciborium::into_writer(payload, &mut data)
+                    .context(EncodePayloadSnafu)?;
+                data
+            }
+        };
+        Ok(EncodedPayload {
+            encoding: *self,
+            version: P::VERSION,
+            data,
+        })
+    }
+}
+
+/// Payload of a stream entry with its encoding and schema version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedPayload {
+    pub encoding: PayloadEncoding,
+    pub version: u32,
+    pub data: Vec<u8>,
+}
+
+impl EncodedPayload {
+    /// Get the encoded payload from the fields of a stream entry
+    pub(super) fn from_fields(
+        encoding: Option<String>,
+        version: Option<u32>,
+        data: Option<Vec<u8>>,
+    ) -> Result<Self, BrokerError> {
+        let encoding = match encoding {
+            Some(name) => PayloadEncoding::from_name(&name)?,
+            None => PayloadEncoding::Json,
+        };
+        let version = version.unwrap_or(LEGACY_VERSION);
+        let data = data.ok_or(BrokerError::InvalidEvent)?;
+        Ok(Self {
+            encoding,
+            version,
+            data,
+        })
+    }
+
+    /// Fields of the stream entry
+    pub fn fields(&self) -> [(&str, Vec<u8>); 3] {
+        [
+            (ENCODING_FIELD, self.encoding.name().into()),
+            (VERSION_FIELD, self.version.to_string().into()),
+            (PAYLOAD_FIELD, self.data.clone()),
+        ]
+    }
+
+    /// Decode the payload, upgrading it if it has a previous version
+    pub fn decode<P: VersionedPayload>(&self) -> Result<P, BrokerError> {
+        match self.version.cmp(&P::VERSION) {
+            Ordering::Equal => self.decode_as(),
+            Ordering::Less => P::upgrade(self.version, self),
+            Ordering::Greater => UnsupportedVersionSnafu {
+                version: self.version,
+            }
+            .fail(),
+        }
+    }
+
+    /// Decode the payload as the given type regardless of its version
+    pub fn decode_as<T: DeserializeOwned>(&self) -> Result<T, BrokerError> {
+        match self.encoding {
+            PayloadEncoding::Json => {
+                serde_json::from_slice(&self.data).context(InvalidPayloadSnafu)
+            }
+            PayloadEncoding::Cbor => {
+                ciborium::from_reader(self.data.as_slice())
+                    .context(DecodePayloadSnafu)
+            }
+        }
+    }
+}
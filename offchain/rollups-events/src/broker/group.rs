@@ -0,0 +1,135 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Consumer groups of the broker streams
+//!
+//! The broker records the events delivered to each consumer of a group until
+//! the consumer acknowledges them, so a restarted consumer gets back the
+//! events it didn't finish processing, and the consumers of the same group
+//! share the events of the stream. An event is delivered again if its
+//! consumer doesn't acknowledge it, so the events are processed at least once.
+
+use std::time::Duration;
+
+use super::{Broker, BrokerError, BrokerStream, Event, INITIAL_ID};
+
+/// Consumer of a consumer group
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerGroup {
+    /// Name of the group, shared by the replicas of the same service
+    pub name: String,
+    /// Name of the consumer in the group
+    /// A restarted consumer must keep its name to get its pending events.
+    pub consumer: String,
+    /// Time after which the pending events of another consumer are claimed
+    pub claim_idle: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsumerGroupConfig {
+    pub consumer: String,
+    pub claim_idle: Duration,
+}
+
+impl ConsumerGroupConfig {
+    /// Create the consumer of the given group
+    pub fn group(&self, name: impl Into<String>) -> ConsumerGroup {
+        ConsumerGroup {
+            name: name.into(),
+            consumer: self.consumer.clone(),
+            claim_idle: self.claim_idle,
+        }
+    }
+}
+
+impl Broker {
+    /// Create the consumer group of the stream if it doesn't exist
+    /// A new group starts from the first event in the stream.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn create_group<S: BrokerStream>(
+        &mut self,
+        stream: &S,
+        group: &ConsumerGroup,
+    ) -> Result<(), BrokerError> {
+        self.backend
+            .create_group(stream.key(), &group.name, INITIAL_ID)
+            .await
+    }
+
+    /// Consume up to `max` events of the stream as a consumer of the group
+    ///
+    /// This function returns the events delivered to the consumer that it
+    /// didn't acknowledge yet, then the events that other consumers of the
+    /// group left idle, and then the events not delivered to the group.
+    /// So, the consumer must acknowledge the events before consuming again.
+    ///
+    /// This function blocks until at least one event is available
+    /// and retries whenever a timeout happens instead of returning an error.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn consume_group<S: BrokerStream>(
+        &mut self,
+        stream: &S,
+        group: &ConsumerGroup,
+        max: usize,
+    ) -> Result<Vec<Event<S::Payload>>, BrokerError> {
+        let max = max.max(1);
+        loop {
+            let mut events = self
+                .backend
+                .read_pending(stream.key(), &group.name, &group.consumer, max)
+                .await?;
+            if events.is_empty() {
+                events = self
+                    .backend
+                    .claim_pending(
+                        stream.key(),
+                        &group.name,
+                        &group.consumer,
+                        group.claim_idle,
+                        max,
+                    )
+                    .await?;
+            }
+            if events.is_empty() {
+                let result = self
+                    .backend
+                    .consume_group(
+                        stream.key(),
+                        &group.name,
+                        &group.consumer,
+                        max,
+                    )
+                    .await;
+                match result {
+                    Err(BrokerError::ConsumeTimeout) => {
+                        tracing::trace!("consume timed out, retrying");
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                    Ok(new_events) => events = new_events,
+                }
+            }
+            if !events.is_empty() {
+                tracing::trace!(count = events.len(), "parsing events");
+                return events.into_iter().map(Event::try_from).collect();
+            }
+        }
+    }
+
+    /// Acknowledge the events consumed by the group, so they are not
+    /// delivered again, and return the number of acknowledged events
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn acknowledge_group<S: BrokerStream>(
+        &mut self,
+        stream: &S,
+        group: &ConsumerGroup,
+        ids: &[&str],
+    ) -> Result<usize, BrokerError> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        self.backend
+            .acknowledge_group(stream.key(), &group.name, ids)
+            .await
+    }
+}
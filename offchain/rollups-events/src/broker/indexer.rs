@@ -5,31 +5,48 @@
 //!
 //! It would be too complex to implement the indexer extension as a generic broker method.
 //! Instead, we decided to implement the extension that we need for the indexer as a submodule.
-//! This extension should be in this crate because it accesses the Redis interface directly.
-//! (All Redis interaction should be hidden in this crate.)
-use backoff::future::retry;
-use redis::streams::{StreamReadOptions, StreamReadReply};
-use redis::AsyncCommands;
-use snafu::ResultExt;
-
-use super::ConnectionSnafu;
+//! This extension should be in this crate because it accesses the broker backend directly.
+//! (All backend interaction should be hidden in this crate.)
+use std::collections::{HashMap, VecDeque};
+
 use crate::{
-    Broker, BrokerError, BrokerStream, DAppMetadata, Event, RollupsInput,
-    RollupsInputsStream, RollupsOutput, RollupsOutputsStream, INITIAL_ID,
+    Address, Broker, BrokerError, BrokerStream, DAppMetadata, Event,
+    RollupsClaim, RollupsClaimsStream, RollupsInput, RollupsInputsStream,
+    RollupsOutput, RollupsOutputsStream, INDEXER_CONSUMER, INITIAL_ID,
 };
 
+/// Max number of events read from each stream at once
+const BATCH_SIZE: usize = 100;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum IndexerEvent {
     Input(Event<RollupsInput>),
     Output(Event<RollupsOutput>),
+    Claim(Event<RollupsClaim>),
+}
+
+impl IndexerEvent {
+    /// Id of the event in its stream
+    pub fn id(&self) -> &str {
+        match self {
+            IndexerEvent::Input(input) => &input.id,
+            IndexerEvent::Output(output) => &output.id,
+            IndexerEvent::Claim(claim) => &claim.id,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct IndexerState {
     inputs_last_id: String,
     outputs_last_id: String,
+    claims_last_id: String,
     inputs_stream: RollupsInputsStream,
     outputs_stream: RollupsOutputsStream,
+    claims_stream: RollupsClaimsStream,
+    dapp_address: Address,
+    /// Events read from the broker that weren't returned yet
+    pending: VecDeque<IndexerEvent>,
 }
 
 impl IndexerState {
@@ -37,67 +54,205 @@ impl IndexerState {
         Self {
             inputs_last_id: INITIAL_ID.to_owned(),
             outputs_last_id: INITIAL_ID.to_owned(),
+            claims_last_id: INITIAL_ID.to_owned(),
             inputs_stream: RollupsInputsStream::new(dapp_metadata),
             outputs_stream: RollupsOutputsStream::new(dapp_metadata),
+            claims_stream: RollupsClaimsStream::new(dapp_metadata.chain_id),
+            dapp_address: dapp_metadata.dapp_address.clone(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Resume after the last consumed event of each stream, given by the
+    /// stream keys
+    /// The streams that aren't in the map are consumed from the start.
+    pub fn resume(&mut self, last_ids: &HashMap<String, String>) {
+        let streams = [
+            (self.inputs_stream.key(), &mut self.inputs_last_id),
+            (self.outputs_stream.key(), &mut self.outputs_last_id),
+            (self.claims_stream.key(), &mut self.claims_last_id),
+        ];
+        for (stream_key, last_id) in streams {
+            if let Some(id) = last_ids.get(stream_key) {
+                tracing::trace!(stream_key, id, "resuming stream");
+                *last_id = id.clone();
+            }
+        }
+        self.pending.clear();
+    }
+
+    /// Key of the stream of the event
+    pub fn stream_key(&self, event: &IndexerEvent) -> &str {
+        match event {
+            IndexerEvent::Input(_) => self.inputs_stream.key(),
+            IndexerEvent::Output(_) => self.outputs_stream.key(),
+            IndexerEvent::Claim(_) => self.claims_stream.key(),
         }
     }
+
+    /// Id of the last consumed input event
+    pub fn inputs_last_id(&self) -> &str {
+        &self.inputs_last_id
+    }
+
+    /// Whether there are events read from the broker that weren't consumed
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Next event read from the broker that wasn't consumed yet, if any
+    /// The claims of other dapps are skipped when they are consumed.
+    pub fn peek_pending(&self) -> Option<&IndexerEvent> {
+        self.pending.front()
+    }
+
+    /// Drop the events read from the broker that weren't consumed yet, so
+    /// they are read again after the last consumed events
+    pub fn discard_pending(&mut self) {
+        self.pending.clear();
+    }
 }
 
 impl Broker {
     /// Consume an event from the Input stream and if there is none,
-    /// consume from the Output stream. This is a blocking operation.
-    /// Return IndexerEvent::Input if present or IndexerEvent::Output otherwise
+    /// consume from the Output stream, and then from the Claim stream.
+    /// This is a blocking operation.
+    /// Return IndexerEvent::Input if present, IndexerEvent::Output if present,
+    /// or IndexerEvent::Claim otherwise.
+    /// The events are read from the broker in batches, and the last consumed
+    /// ids only advance when the events are returned.
+    /// The claims stream is shared by the chain, so the claims of other dapps
+    /// are skipped.
     #[tracing::instrument(level = "trace", skip_all)]
     pub async fn indexer_consume(
         &self,
         state: &mut IndexerState,
     ) -> Result<IndexerEvent, BrokerError> {
-        let input_stream_key = state.inputs_stream.key();
-        let output_stream_key = state.outputs_stream.key();
-        let mut reply = retry(self.backoff.clone(), || async {
-            let stream_keys = [&input_stream_key, &output_stream_key];
-            let last_consumed_ids =
-                [&state.inputs_last_id, &state.outputs_last_id];
-            tracing::trace!(
-                ?stream_keys,
-                ?last_consumed_ids,
-                "consuming event"
-            );
-            let opts = StreamReadOptions::default()
-                .count(1)
-                .block(self.consume_timeout);
-            let reply: StreamReadReply = self
-                .connection
-                .clone()
-                .xread_options(&stream_keys, &last_consumed_ids, &opts)
+        loop {
+            let event = match state.pending.pop_front() {
+                Some(event) => event,
+                None => {
+                    state.pending = self.indexer_consume_batch(state).await?;
+                    continue;
+                }
+            };
+            match &event {
+                IndexerEvent::Input(input) => {
+                    state.inputs_last_id = input.id.clone();
+                }
+                IndexerEvent::Output(output) => {
+                    state.outputs_last_id = output.id.clone();
+                }
+                IndexerEvent::Claim(claim) => {
+                    state.claims_last_id = claim.id.clone();
+                    if claim.payload.dapp_address != state.dapp_address {
+                        tracing::trace!("skipping claim of another dapp");
+                        continue;
+                    }
+                }
+            }
+            return Ok(event);
+        }
+    }
+
+    /// Read the output events after the pending ones without waiting for them
+    /// The outputs of a group are produced at once, so the rest of a group
+    /// split between two reads is already in the stream. The outputs are read
+    /// ahead of the input events, so the pending events that don't belong to
+    /// the group should be discarded.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn indexer_read_outputs(
+        &self,
+        state: &mut IndexerState,
+    ) -> Result<(), BrokerError> {
+        let last_id = match state.pending.back() {
+            Some(IndexerEvent::Output(output)) => output.id.clone(),
+            Some(_) => return Ok(()),
+            None => state.outputs_last_id.clone(),
+        };
+        let events = self
+            .backend
+            .consume_batch_nonblocking(
+                state.outputs_stream.key(),
+                &last_id,
+                BATCH_SIZE,
+            )
+            .await?;
+        tracing::trace!(count = events.len(), "read output events ahead");
+        for stream_id in events {
+            let event = stream_id.try_into().map(IndexerEvent::Output)?;
+            state.pending.push_back(event);
+        }
+        Ok(())
+    }
+
+    /// Record the last consumed events in the broker, so the streams may be
+    /// trimmed up to them.
+    /// The claims stream is shared by the chain, so it is not acknowledged.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn indexer_acknowledge(
+        &self,
+        state: &IndexerState,
+    ) -> Result<(), BrokerError> {
+        let streams = [
+            (state.inputs_stream.key(), &state.inputs_last_id),
+            (state.outputs_stream.key(), &state.outputs_last_id),
+        ];
+        for (stream_key, last_id) in streams {
+            self.backend
+                .acknowledge(stream_key, INDEXER_CONSUMER, last_id)
                 .await?;
-            Ok(reply)
-        })
-        .await
-        .context(ConnectionSnafu)?;
-
-        let input_stream_id = reply
-            .keys
-            .iter_mut()
-            .find(|stream| stream.key == input_stream_key)
-            .and_then(|stream| stream.ids.pop());
-        if let Some(stream_id) = input_stream_id {
-            tracing::trace!("found input event; parsing it");
-            let event: Event<RollupsInput> = stream_id.try_into()?;
-            state.inputs_last_id = event.id.clone();
-            return Ok(IndexerEvent::Input(event));
+        }
+        Ok(())
+    }
+
+    /// Read a batch of events from the first stream that has any
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn indexer_consume_batch(
+        &self,
+        state: &IndexerState,
+    ) -> Result<VecDeque<IndexerEvent>, BrokerError> {
+        let stream_keys = [
+            state.inputs_stream.key(),
+            state.outputs_stream.key(),
+            state.claims_stream.key(),
+        ];
+        let last_consumed_ids = [
+            state.inputs_last_id.as_str(),
+            state.outputs_last_id.as_str(),
+            state.claims_last_id.as_str(),
+        ];
+        let events = self
+            .backend
+            .indexer_consume(&stream_keys, &last_consumed_ids, BATCH_SIZE)
+            .await?;
+        let [input_stream_ids, output_stream_ids, claim_stream_ids]: [_; 3] =
+            events
+                .try_into()
+                .map_err(|_| BrokerError::FailedToConsume)?;
+
+        if !input_stream_ids.is_empty() {
+            tracing::trace!("found input events; parsing them");
+            return input_stream_ids
+                .into_iter()
+                .map(|stream_id| stream_id.try_into().map(IndexerEvent::Input))
+                .collect();
+        }
+
+        if !output_stream_ids.is_empty() {
+            tracing::trace!("found output events; parsing them");
+            return output_stream_ids
+                .into_iter()
+                .map(|stream_id| stream_id.try_into().map(IndexerEvent::Output))
+                .collect();
         }
 
-        let output_stream_id = reply
-            .keys
-            .iter_mut()
-            .find(|stream| stream.key == output_stream_key)
-            .and_then(|stream| stream.ids.pop());
-        if let Some(stream_id) = output_stream_id {
-            tracing::trace!("found output event; parsing it");
-            let event: Event<RollupsOutput> = stream_id.try_into()?;
-            state.outputs_last_id = event.id.clone();
-            return Ok(IndexerEvent::Output(event));
+        if !claim_stream_ids.is_empty() {
+            tracing::trace!("found claim events; parsing them");
+            return claim_stream_ids
+                .into_iter()
+                .map(|stream_id| stream_id.try_into().map(IndexerEvent::Claim))
+                .collect();
         }
 
         tracing::trace!("indexer consume timed out");
@@ -0,0 +1,880 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! In-memory broker backend
+//!
+//! The streams are kept in the memory of the process, so only the services
+//! running in the same process share them. This backend is meant for
+//! single-binary deployments and for tests that don't need a Redis server.
+
+use async_trait::async_trait;
+use redis::streams::StreamId;
+use redis::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+
+use super::{parse_id, BrokerBackend, BrokerError, EncodedPayload};
+
+/// Streams of the in-memory backend
+#[derive(Debug)]
+struct Log {
+    streams: Mutex<Streams>,
+    /// Number of produced events, used to wake up the blocked consumers
+    produced: watch::Sender<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Streams {
+    entries: HashMap<String, Vec<StreamId>>,
+    /// Last event acknowledged by each consumer of each stream
+    acks: HashMap<String, HashMap<String, String>>,
+    /// Consumer groups of each stream
+    groups: HashMap<String, HashMap<String, Group>>,
+    last_id: (u64, u64),
+}
+
+#[derive(Debug)]
+struct Group {
+    /// Id of the last entry delivered to the group
+    last_delivered_id: String,
+    /// Entries delivered to the consumers but not acknowledged yet
+    pending: BTreeMap<(u64, u64), PendingEntry>,
+}
+
+#[derive(Debug)]
+struct PendingEntry {
+    consumer: String,
+    delivered_at: Instant,
+}
+
+impl PendingEntry {
+    fn new(consumer: &str) -> Self {
+        Self {
+            consumer: consumer.to_owned(),
+            delivered_at: Instant::now(),
+        }
+    }
+}
+
+/// Parse the id of an entry generated by the backend
+fn entry_id(entry: &StreamId) -> (u64, u64) {
+    parse_id(&entry.id).expect("invalid id generated by the memory backend")
+}
+
+impl Streams {
+    /// Generate the id of a new entry with the Redis format `<ms>-<seq>`
+    fn next_id(&mut self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let (ms, seq) = self.last_id;
+        self.last_id = if now > ms { (now, 0) } else { (ms, seq + 1) };
+        format!("{}-{}", self.last_id.0, self.last_id.1)
+    }
+
+    fn stream(&self, stream_key: &str) -> &[StreamId] {
+        self.entries
+            .get(stream_key)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Position of the first entry of the stream whose id is not lower than
+    /// the given one
+    /// The ids of the entries are increasing, so the position is found with a
+    /// binary search.
+    fn position(&self, stream_key: &str, id: (u64, u64)) -> usize {
+        self.stream(stream_key)
+            .partition_point(|entry| entry_id(entry) < id)
+    }
+
+    /// Get the entry of the stream with the given id
+    fn find(&self, stream_key: &str, id: (u64, u64)) -> Option<&StreamId> {
+        let position = self.position(stream_key, id);
+        self.stream(stream_key)
+            .get(position)
+            .filter(|entry| entry_id(entry) == id)
+    }
+
+    /// Get up to `count` entries of the stream after the given id
+    fn next_entries(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        let last_consumed_id = parse_id(last_consumed_id)?;
+        let stream = self.stream(stream_key);
+        let start =
+            stream.partition_point(|entry| entry_id(entry) <= last_consumed_id);
+        let end = start.saturating_add(count).min(stream.len());
+        Ok(stream[start..end].to_vec())
+    }
+
+    /// Get the first entry of the stream after the given id
+    fn next_entry(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+    ) -> Result<Option<StreamId>, BrokerError> {
+        let mut entries = self.next_entries(stream_key, last_consumed_id, 1)?;
+        Ok(entries.pop())
+    }
+
+    /// Append an entry with the payload to the stream and return its id
+    fn push(&mut self, stream_key: &str, payload: &EncodedPayload) -> String {
+        let id = self.next_id();
+        let map = payload
+            .fields()
+            .into_iter()
+            .map(|(field, value)| (field.to_owned(), Value::Data(value)))
+            .collect();
+        let entry = StreamId {
+            id: id.clone(),
+            map,
+        };
+        self.entries
+            .entry(stream_key.to_owned())
+            .or_default()
+            .push(entry);
+        id
+    }
+
+    fn group(
+        &self,
+        stream_key: &str,
+        group: &str,
+    ) -> Result<&Group, BrokerError> {
+        self.groups
+            .get(stream_key)
+            .and_then(|groups| groups.get(group))
+            .ok_or_else(|| BrokerError::UnknownGroup {
+                group: group.to_owned(),
+            })
+    }
+
+    fn group_mut(
+        &mut self,
+        stream_key: &str,
+        group: &str,
+    ) -> Result<&mut Group, BrokerError> {
+        self.groups
+            .get_mut(stream_key)
+            .and_then(|groups| groups.get_mut(group))
+            .ok_or_else(|| BrokerError::UnknownGroup {
+                group: group.to_owned(),
+            })
+    }
+
+    /// Deliver up to `count` entries that weren't delivered to the group yet
+    /// to the consumer
+    fn deliver(
+        &mut self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        let last_id = self.group(stream_key, group)?.last_delivered_id.clone();
+        let entries = self.next_entries(stream_key, &last_id, count)?;
+        let group = self.group_mut(stream_key, group)?;
+        for entry in &entries {
+            group
+                .pending
+                .insert(parse_id(&entry.id)?, PendingEntry::new(consumer));
+            group.last_delivered_id = entry.id.clone();
+        }
+        Ok(entries)
+    }
+
+    /// Deliver up to `count` pending entries of the group that match the
+    /// filter to the consumer again
+    /// The pending entries that were trimmed from the stream are dropped.
+    fn redeliver(
+        &mut self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+        filter: impl Fn(&PendingEntry) -> bool,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        let ids: Vec<_> = self
+            .group(stream_key, group)?
+            .pending
+            .iter()
+            .filter(|(_, pending)| filter(pending))
+            .map(|(id, _)| *id)
+            .collect();
+        let mut entries = vec![];
+        let mut trimmed = vec![];
+        for id in ids {
+            if entries.len() == count {
+                break;
+            }
+            match self.find(stream_key, id) {
+                Some(entry) => entries.push(entry.clone()),
+                None => trimmed.push(id),
+            }
+        }
+        let group = self.group_mut(stream_key, group)?;
+        for id in trimmed {
+            group.pending.remove(&id);
+        }
+        for entry in &entries {
+            group
+                .pending
+                .insert(parse_id(&entry.id)?, PendingEntry::new(consumer));
+        }
+        Ok(entries)
+    }
+
+    /// Remove the entries of the stream before the given position and
+    /// return the number of removed entries
+    fn drain(&mut self, stream_key: &str, end: usize) -> usize {
+        match self.entries.get_mut(stream_key) {
+            Some(entries) => entries.drain(..end.min(entries.len())).count(),
+            None => 0,
+        }
+    }
+}
+
+/// Backend that stores the streams in the memory of the process
+#[derive(Debug, Clone)]
+pub struct MemoryBackend {
+    log: Arc<Log>,
+    consume_timeout: Duration,
+}
+
+impl MemoryBackend {
+    /// Create a backend with its own streams
+    pub fn new(consume_timeout: Duration) -> Self {
+        let (produced, _) = watch::channel(0);
+        let log = Log {
+            streams: Mutex::new(Streams::default()),
+            produced,
+        };
+        Self {
+            log: Arc::new(log),
+            consume_timeout,
+        }
+    }
+
+    /// Create a backend that shares the streams with the other shared
+    /// backends of the process
+    pub fn shared(consume_timeout: Duration) -> Self {
+        static SHARED: OnceLock<MemoryBackend> = OnceLock::new();
+        let backend = SHARED.get_or_init(|| Self::new(consume_timeout));
+        Self {
+            log: backend.log.clone(),
+            consume_timeout,
+        }
+    }
+
+    fn streams(&self) -> std::sync::MutexGuard<'_, Streams> {
+        // The lock is never held across a panic, so it can't be poisoned
+        self.log
+            .streams
+            .lock()
+            .expect("broker streams lock poisoned")
+    }
+
+    /// Wait until `read` returns an event or the consume timeout expires
+    async fn wait_for<T>(
+        &self,
+        mut read: impl FnMut(&mut Streams) -> Result<Option<T>, BrokerError>,
+    ) -> Result<T, BrokerError> {
+        let mut produced = self.log.produced.subscribe();
+        let deadline = tokio::time::Instant::now() + self.consume_timeout;
+        loop {
+            // Mark the current events as seen before reading them, so an
+            // event produced after the read wakes up this consumer
+            produced.borrow_and_update();
+            let event = read(&mut self.streams())?;
+            if let Some(event) = event {
+                return Ok(event);
+            }
+            match tokio::time::timeout_at(deadline, produced.changed()).await {
+                Ok(Ok(())) => {}
+                // The sender lives as long as the log, so it is never closed
+                Ok(Err(_)) | Err(_) => return Err(BrokerError::ConsumeTimeout),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl BrokerBackend for MemoryBackend {
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn produce(
+        &self,
+        stream_key: &str,
+        payload: &EncodedPayload,
+    ) -> Result<String, BrokerError> {
+        tracing::trace!(stream_key, ?payload, "producing event");
+        let id = self.streams().push(stream_key, payload);
+        self.log.produced.send_modify(|produced| *produced += 1);
+        Ok(id)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn produce_many(
+        &self,
+        stream_key: &str,
+        payloads: &[EncodedPayload],
+    ) -> Result<Vec<String>, BrokerError> {
+        tracing::trace!(stream_key, count = payloads.len(), "producing events");
+        let ids = {
+            let mut streams = self.streams();
+            payloads
+                .iter()
+                .map(|payload| streams.push(stream_key, payload))
+                .collect()
+        };
+        self.log.produced.send_modify(|produced| *produced += 1);
+        Ok(ids)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn produce_atomic(
+        &self,
+        entries: &[(String, EncodedPayload)],
+    ) -> Result<Vec<String>, BrokerError> {
+        tracing::trace!(count = entries.len(), "producing events");
+        let ids = {
+            let mut streams = self.streams();
+            entries
+                .iter()
+                .map(|(stream_key, payload)| streams.push(stream_key, payload))
+                .collect()
+        };
+        self.log.produced.send_modify(|produced| *produced += 1);
+        Ok(ids)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn peek_latest(
+        &self,
+        stream_key: &str,
+    ) -> Result<Option<StreamId>, BrokerError> {
+        Ok(self.streams().stream(stream_key).last().cloned())
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn peek_previous(
+        &self,
+        stream_key: &str,
+        before_id: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        let before_id = before_id.map(parse_id).transpose()?;
+        let streams = self.streams();
+        let stream = streams.stream(stream_key);
+        let end = match before_id {
+            Some(id) => streams.position(stream_key, id),
+            None => stream.len(),
+        };
+        let start = end.saturating_sub(count);
+        Ok(stream[start..end].iter().rev().cloned().collect())
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn get_event(
+        &self,
+        stream_key: &str,
+        id: &str,
+    ) -> Result<Option<StreamId>, BrokerError> {
+        let id = parse_id(id)?;
+        Ok(self.streams().find(stream_key, id).cloned())
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_blocking(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+    ) -> Result<StreamId, BrokerError> {
+        self.wait_for(|streams| {
+            streams.next_entry(stream_key, last_consumed_id)
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_batch(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        self.wait_for(|streams| {
+            let entries =
+                streams.next_entries(stream_key, last_consumed_id, count)?;
+            Ok(Some(entries).filter(|entries| !entries.is_empty()))
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_nonblocking(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+    ) -> Result<Option<StreamId>, BrokerError> {
+        self.streams().next_entry(stream_key, last_consumed_id)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_batch_nonblocking(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        self.streams()
+            .next_entries(stream_key, last_consumed_id, count)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn indexer_consume(
+        &self,
+        stream_keys: &[&str],
+        last_consumed_ids: &[&str],
+        count: usize,
+    ) -> Result<Vec<Vec<StreamId>>, BrokerError> {
+        self.wait_for(|streams| {
+            let events = stream_keys
+                .iter()
+                .zip(last_consumed_ids)
+                .map(|(stream_key, last_consumed_id)| {
+                    streams.next_entries(stream_key, last_consumed_id, count)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if events.iter().any(|events| !events.is_empty()) {
+                Ok(Some(events))
+            } else {
+                Ok(None)
+            }
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn acknowledge(
+        &self,
+        stream_key: &str,
+        consumer: &str,
+        id: &str,
+    ) -> Result<(), BrokerError> {
+        tracing::trace!(stream_key, consumer, id, "acknowledging event");
+        self.streams()
+            .acks
+            .entry(stream_key.to_owned())
+            .or_default()
+            .insert(consumer.to_owned(), id.to_owned());
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn acknowledged_ids(
+        &self,
+        stream_key: &str,
+    ) -> Result<HashMap<String, String>, BrokerError> {
+        let acks = self.streams().acks.get(stream_key).cloned();
+        Ok(acks.unwrap_or_default())
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn trim_max_len(
+        &self,
+        stream_key: &str,
+        max_len: usize,
+    ) -> Result<usize, BrokerError> {
+        let mut streams = self.streams();
+        let len = streams.stream(stream_key).len();
+        Ok(streams.drain(stream_key, len.saturating_sub(max_len)))
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn trim_min_id(
+        &self,
+        stream_key: &str,
+        min_id: &str,
+    ) -> Result<usize, BrokerError> {
+        let min_id = parse_id(min_id)?;
+        let mut streams = self.streams();
+        let end = streams.position(stream_key, min_id);
+        Ok(streams.drain(stream_key, end))
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn create_group(
+        &self,
+        stream_key: &str,
+        group: &str,
+        start_id: &str,
+    ) -> Result<(), BrokerError> {
+        parse_id(start_id)?;
+        tracing::trace!(stream_key, group, start_id, "creating group");
+        self.streams()
+            .groups
+            .entry(stream_key.to_owned())
+            .or_default()
+            .entry(group.to_owned())
+            .or_insert_with(|| Group {
+                last_delivered_id: start_id.to_owned(),
+                pending: BTreeMap::new(),
+            });
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_group(
+        &self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        self.wait_for(|streams| {
+            let entries =
+                streams.deliver(stream_key, group, consumer, count)?;
+            Ok(Some(entries).filter(|entries| !entries.is_empty()))
+        })
+        .await
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn read_pending(
+        &self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        self.streams().redeliver(
+            stream_key,
+            group,
+            consumer,
+            count,
+            |pending| pending.consumer == consumer,
+        )
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn claim_pending(
+        &self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: Duration,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        self.streams().redeliver(
+            stream_key,
+            group,
+            consumer,
+            count,
+            |pending| pending.delivered_at.elapsed() >= min_idle,
+        )
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn acknowledge_group(
+        &self,
+        stream_key: &str,
+        group: &str,
+        ids: &[&str],
+    ) -> Result<usize, BrokerError> {
+        tracing::trace!(stream_key, group, ?ids, "acknowledging events");
+        let mut streams = self.streams();
+        let group = streams.group_mut(stream_key, group)?;
+        let mut count = 0;
+        for id in ids {
+            if group.pending.remove(&parse_id(id)?).is_some() {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Broker, BrokerStream, ConsumerGroup, PayloadEncoding, RetentionPolicy,
+        VersionedPayload, INITIAL_ID,
+    };
+    use serde::{Deserialize, Serialize};
+
+    const CONSUME_TIMEOUT: Duration = Duration::from_millis(100);
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct MockPayload {
+        data: String,
+    }
+
+    impl VersionedPayload for MockPayload {
+        const VERSION: u32 = 1;
+    }
+
+    struct MockStream(&'static str);
+
+    impl BrokerStream for MockStream {
+        type Payload = MockPayload;
+
+        fn key(&self) -> &str {
+            self.0
+        }
+    }
+
+    fn payload(data: &str) -> MockPayload {
+        MockPayload {
+            data: data.to_owned(),
+        }
+    }
+
+    fn encoded(data: &str) -> EncodedPayload {
+        PayloadEncoding::Json.encode(&payload(data)).unwrap()
+    }
+
+    fn create_broker() -> Broker {
+        Broker::with_backend(MemoryBackend::new(CONSUME_TIMEOUT))
+    }
+
+    #[tokio::test]
+    async fn it_consumes_produced_events_in_order() {
+        let mut broker = create_broker();
+        let stream = MockStream("stream");
+        let first = broker.produce(&stream, payload("0")).await.unwrap();
+        let second = broker.produce(&stream, payload("1")).await.unwrap();
+        assert!(parse_id(&first).unwrap() < parse_id(&second).unwrap());
+
+        let event = broker.consume_blocking(&stream, INITIAL_ID).await.unwrap();
+        assert_eq!(event.id, first);
+        assert_eq!(event.payload, payload("0"));
+        let event = broker.consume_nonblocking(&stream, &first).await.unwrap();
+        assert_eq!(event.map(|event| event.payload), Some(payload("1")));
+        let event = broker.consume_nonblocking(&stream, &second).await.unwrap();
+        assert_eq!(event, None);
+
+        let latest = broker.peek_latest(&stream).await.unwrap().unwrap();
+        assert_eq!(latest.id, second);
+        let event = broker.get_event(&stream, &first).await.unwrap().unwrap();
+        assert_eq!(event.payload, payload("0"));
+        let missing = broker.get_event(&stream, "1-0").await.unwrap();
+        assert_eq!(missing, None);
+        let empty = broker.peek_latest(&MockStream("other")).await.unwrap();
+        assert_eq!(empty, None);
+    }
+
+    #[tokio::test]
+    async fn it_peeks_previous_events_from_the_newest() {
+        let mut broker = create_broker();
+        let stream = MockStream("stream");
+        let payloads = (0..5).map(|i| payload(&i.to_string())).collect();
+        let ids = broker.produce_many(&stream, payloads).await.unwrap();
+
+        let events = broker.peek_previous(&stream, None, 2).await.unwrap();
+        let peeked: Vec<_> = events.into_iter().map(|event| event.id).collect();
+        assert_eq!(peeked, vec![ids[4].clone(), ids[3].clone()]);
+        let events = broker
+            .peek_previous(&stream, Some(ids[3].as_str()), 5)
+            .await
+            .unwrap();
+        let peeked: Vec<_> = events.into_iter().map(|event| event.id).collect();
+        assert_eq!(
+            peeked,
+            vec![ids[2].clone(), ids[1].clone(), ids[0].clone()]
+        );
+        let events = broker
+            .peek_previous(&stream, Some(ids[0].as_str()), 5)
+            .await
+            .unwrap();
+        assert!(events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn it_waits_for_produced_event() {
+        let mut broker = create_broker();
+        let mut producer = broker.clone();
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(CONSUME_TIMEOUT / 2).await;
+            producer.produce(&MockStream("stream"), payload("0")).await
+        });
+        let event = broker
+            .consume_blocking(&MockStream("stream"), INITIAL_ID)
+            .await
+            .unwrap();
+        assert_eq!(event.id, task.await.unwrap().unwrap());
+    }
+
+    #[tokio::test]
+    async fn it_times_out_when_indexer_consumes_empty_streams() {
+        let backend = MemoryBackend::new(CONSUME_TIMEOUT);
+        let result = backend
+            .indexer_consume(&["a", "b"], &[INITIAL_ID, INITIAL_ID], 1)
+            .await;
+        assert!(matches!(result, Err(BrokerError::ConsumeTimeout)));
+
+        let id = backend.produce("b", &encoded("0")).await.unwrap();
+        let events = backend
+            .indexer_consume(&["a", "b"], &[INITIAL_ID, INITIAL_ID], 1)
+            .await
+            .unwrap();
+        assert!(events[0].is_empty());
+        assert_eq!(events[1].len(), 1);
+        assert_eq!(events[1][0].id, id);
+    }
+
+    #[tokio::test]
+    async fn it_consumes_batches_of_produced_events() {
+        let mut broker = create_broker();
+        let stream = MockStream("stream");
+        let payloads = (0..5).map(|i| payload(&i.to_string())).collect();
+        let ids = broker.produce_many(&stream, payloads).await.unwrap();
+        assert_eq!(ids.len(), 5);
+
+        let events =
+            broker.consume_batch(&stream, INITIAL_ID, 3).await.unwrap();
+        let data: Vec<_> = events.iter().map(|e| &e.payload.data).collect();
+        assert_eq!(data, ["0", "1", "2"]);
+        let events = broker.consume_batch(&stream, &ids[2], 3).await.unwrap();
+        let event_ids: Vec<_> = events.into_iter().map(|e| e.id).collect();
+        assert_eq!(event_ids, ids[3..]);
+    }
+
+    #[tokio::test]
+    async fn it_commits_events_of_transaction() {
+        let mut broker = create_broker();
+        let (first, second) = (MockStream("first"), MockStream("second"));
+        let transaction = broker.transaction();
+        assert!(broker.commit(transaction).await.unwrap().is_empty());
+
+        let mut transaction = broker.transaction();
+        transaction.produce(&first, payload("0")).unwrap();
+        transaction.produce(&second, payload("1")).unwrap();
+        transaction.produce(&first, payload("2")).unwrap();
+        let ids = broker.commit(transaction).await.unwrap();
+        assert_eq!(ids.len(), 3);
+
+        let events = broker.consume_batch(&first, INITIAL_ID, 3).await.unwrap();
+        let event_ids: Vec<_> = events.iter().map(|e| &e.id).collect();
+        assert_eq!(event_ids, [&ids[0], &ids[2]]);
+        let event = broker.peek_latest(&second).await.unwrap().unwrap();
+        assert_eq!(event.id, ids[1]);
+        assert_eq!(event.payload, payload("1"));
+    }
+
+    #[tokio::test]
+    async fn it_consumes_events_of_every_encoding() {
+        let backend = MemoryBackend::new(CONSUME_TIMEOUT);
+        let mut json_broker = Broker::with_backend(backend.clone());
+        let mut cbor_broker = Broker::with_backend(backend.clone())
+            .with_encoding(PayloadEncoding::Cbor);
+        let stream = MockStream("stream");
+        json_broker.produce(&stream, payload("0")).await.unwrap();
+        cbor_broker.produce(&stream, payload("1")).await.unwrap();
+
+        let entry = backend.peek_latest("stream").await.unwrap().unwrap();
+        let encoding: String = entry.get("encoding").unwrap();
+        assert_eq!(encoding, "cbor-v1");
+        let events = json_broker
+            .consume_batch(&stream, INITIAL_ID, 2)
+            .await
+            .unwrap();
+        let payloads: Vec<_> = events.into_iter().map(|e| e.payload).collect();
+        assert_eq!(payloads, [payload("0"), payload("1")]);
+    }
+
+    #[tokio::test]
+    async fn it_trims_streams_according_to_retention_policy() {
+        let mut broker = create_broker();
+        let stream = MockStream("stream");
+        let payloads = (0..5).map(|i| payload(&i.to_string())).collect();
+        let ids = broker.produce_many(&stream, payloads).await.unwrap();
+
+        let policy = RetentionPolicy::MaxLen(4);
+        assert_eq!(broker.trim(&stream, &policy, &[]).await.unwrap(), 1);
+        let policy = RetentionPolicy::Acknowledged;
+        let consumers = ["a", "b"];
+        broker.acknowledge(&stream, "a", &ids[3]).await.unwrap();
+        let count = broker.trim(&stream, &policy, &consumers).await.unwrap();
+        assert_eq!(count, 0);
+        broker.acknowledge(&stream, "b", &ids[2]).await.unwrap();
+        let count = broker.trim(&stream, &policy, &consumers).await.unwrap();
+        assert_eq!(count, 1);
+
+        let event = broker.consume_blocking(&stream, INITIAL_ID).await.unwrap();
+        assert_eq!(event.id, ids[2]);
+        let policy = RetentionPolicy::Keep;
+        assert_eq!(broker.trim(&stream, &policy, &[]).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn it_redelivers_events_not_acknowledged_by_group_consumers() {
+        let mut broker = create_broker();
+        let stream = MockStream("stream");
+        let payloads = (0..3).map(|i| payload(&i.to_string())).collect();
+        let ids = broker.produce_many(&stream, payloads).await.unwrap();
+        let consumer = |name: &str| ConsumerGroup {
+            name: "group".to_owned(),
+            consumer: name.to_owned(),
+            claim_idle: Duration::from_secs(60),
+        };
+        let (a, b) = (consumer("a"), consumer("b"));
+        let result = broker.consume_group(&stream, &a, 1).await;
+        assert!(matches!(result, Err(BrokerError::UnknownGroup { .. })));
+        broker.create_group(&stream, &a).await.unwrap();
+
+        // The consumers share the events of the group
+        let events = broker.consume_group(&stream, &a, 1).await.unwrap();
+        assert_eq!(events[0].id, ids[0]);
+        let events = broker.consume_group(&stream, &b, 1).await.unwrap();
+        assert_eq!(events[0].id, ids[1]);
+
+        // A consumer gets back its events until it acknowledges them
+        let events = broker.consume_group(&stream, &a, 2).await.unwrap();
+        assert_eq!(events[0].id, ids[0]);
+        let count = broker
+            .acknowledge_group(&stream, &a, &[&ids[0]])
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        let events = broker.consume_group(&stream, &a, 2).await.unwrap();
+        assert_eq!(events[0].id, ids[2]);
+
+        // The idle events of a consumer are claimed by the others
+        let c = ConsumerGroup {
+            claim_idle: Duration::ZERO,
+            ..consumer("c")
+        };
+        let events = broker.consume_group(&stream, &c, 2).await.unwrap();
+        let event_ids: Vec<_> = events.into_iter().map(|e| e.id).collect();
+        assert_eq!(event_ids, ids[1..]);
+    }
+
+    #[tokio::test]
+    async fn it_shares_streams_between_shared_backends() {
+        let producer = MemoryBackend::shared(CONSUME_TIMEOUT);
+        let consumer = MemoryBackend::shared(CONSUME_TIMEOUT);
+        let id = producer
+            .produce("shared-stream", &encoded("0"))
+            .await
+            .unwrap();
+        let event = consumer
+            .consume_nonblocking("shared-stream", INITIAL_ID)
+            .await
+            .unwrap();
+        assert_eq!(event.map(|event| event.id), Some(id));
+        let private = MemoryBackend::new(CONSUME_TIMEOUT);
+        let event = private
+            .consume_nonblocking("shared-stream", INITIAL_ID)
+            .await
+            .unwrap();
+        assert!(event.is_none());
+    }
+}
@@ -1,126 +1,259 @@
 // (c) Cartesi and individual authors (see AUTHORS)
 // SPDX-License-Identifier: Apache-2.0 (see LICENSE)
 
-use backoff::{future::retry, ExponentialBackoff, ExponentialBackoffBuilder};
-use clap::Parser;
-use redis::aio::{ConnectionLike, ConnectionManager};
-use redis::cluster::ClusterClient;
-use redis::cluster_async::ClusterConnection;
-use redis::streams::{
-    StreamId, StreamRangeReply, StreamReadOptions, StreamReadReply,
-};
-use redis::{
-    AsyncCommands, Client, Cmd, Pipeline, RedisError, RedisFuture, Value,
-};
+use ::redis::streams::StreamId;
+use ::redis::RedisError;
+use async_trait::async_trait;
+use backoff::{ExponentialBackoff, ExponentialBackoffBuilder};
+use clap::{Parser, ValueEnum};
 use serde::{de::DeserializeOwned, Serialize};
-use snafu::{ResultExt, Snafu};
+use snafu::Snafu;
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 
 pub use redacted::{RedactedUrl, Url};
 
+mod encoding;
+mod group;
 pub mod indexer;
+mod memory;
+mod redis;
+mod retention;
+mod transaction;
+
+pub use self::encoding::{EncodedPayload, PayloadEncoding, LEGACY_VERSION};
+use self::encoding::{ENCODING_FIELD, PAYLOAD_FIELD, VERSION_FIELD};
+pub use self::group::{ConsumerGroup, ConsumerGroupConfig};
+pub use self::memory::MemoryBackend;
+pub use self::redis::RedisBackend;
+pub use self::retention::{
+    RetentionConfig, RetentionPolicy, RetentionPolicyKind, StreamTrimmer,
+    ADVANCE_RUNNER_CONSUMER, AUTHORITY_CLAIMER_CONSUMER, DISPATCHER_CONSUMER,
+    INDEXER_CONSUMER,
+};
+pub use self::transaction::BrokerTransaction;
 
 pub const INITIAL_ID: &str = "0";
 
-/// The `BrokerConnection` enum implements the `ConnectionLike` trait
-/// to satisfy the `AsyncCommands` trait bounds.
-/// As `AsyncCommands` requires its implementors to be `Sized`, we couldn't
-/// use a trait object instead.
-#[derive(Clone)]
-enum BrokerConnection {
-    ConnectionManager(ConnectionManager),
-    ClusterConnection(ClusterConnection),
-}
+/// Storage of the broker streams
+///
+/// The backend handles the raw entries of the streams, in the format of Redis
+/// stream entries, while the `Broker` serializes and parses their payloads.
+#[async_trait]
+pub trait BrokerBackend: fmt::Debug + Send + Sync {
+    /// Append an entry with the payload to the stream and return its id
+    async fn produce(
+        &self,
+        stream_key: &str,
+        payload: &EncodedPayload,
+    ) -> Result<String, BrokerError>;
+
+    /// Append an entry for each payload to the stream in a single round-trip
+    /// and return their ids
+    async fn produce_many(
+        &self,
+        stream_key: &str,
+        payloads: &[EncodedPayload],
+    ) -> Result<Vec<String>, BrokerError>;
+
+    /// Append the entries to their streams in a single transaction and return
+    /// their ids
+    async fn produce_atomic(
+        &self,
+        entries: &[(String, EncodedPayload)],
+    ) -> Result<Vec<String>, BrokerError>;
+
+    /// Get the last entry of the stream, if any
+    async fn peek_latest(
+        &self,
+        stream_key: &str,
+    ) -> Result<Option<StreamId>, BrokerError>;
+
+    /// Get up to `count` entries before `before_id`, or before the end of the
+    /// stream if there is no id, from the newest to the oldest
+    async fn peek_previous(
+        &self,
+        stream_key: &str,
+        before_id: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError>;
+
+    /// Get the entry with the given id, if any
+    async fn get_event(
+        &self,
+        stream_key: &str,
+        id: &str,
+    ) -> Result<Option<StreamId>, BrokerError>;
+
+    /// Get the entry after `last_consumed_id`, waiting for it until the
+    /// consume timeout; return `ConsumeTimeout` if there is none
+    async fn consume_blocking(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+    ) -> Result<StreamId, BrokerError>;
+
+    /// Get up to `count` entries after `last_consumed_id`, waiting for at
+    /// least one of them until the consume timeout; return `ConsumeTimeout` if
+    /// there is none
+    async fn consume_batch(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError>;
 
-impl ConnectionLike for BrokerConnection {
-    fn req_packed_command<'a>(
-        &'a mut self,
-        cmd: &'a Cmd,
-    ) -> RedisFuture<'a, Value> {
-        match self {
-            Self::ConnectionManager(connection) => {
-                connection.req_packed_command(cmd)
-            }
-            Self::ClusterConnection(connection) => {
-                connection.req_packed_command(cmd)
-            }
-        }
-    }
+    /// Get the entry after `last_consumed_id` without waiting for it
+    async fn consume_nonblocking(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+    ) -> Result<Option<StreamId>, BrokerError>;
 
-    fn req_packed_commands<'a>(
-        &'a mut self,
-        cmd: &'a Pipeline,
-        offset: usize,
+    /// Get up to `count` entries after `last_consumed_id` without waiting for
+    /// them
+    async fn consume_batch_nonblocking(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
         count: usize,
-    ) -> RedisFuture<'a, Vec<Value>> {
-        match self {
-            Self::ConnectionManager(connection) => {
-                connection.req_packed_commands(cmd, offset, count)
-            }
-            Self::ClusterConnection(connection) => {
-                connection.req_packed_commands(cmd, offset, count)
-            }
-        }
-    }
-
-    fn get_db(&self) -> i64 {
-        match self {
-            Self::ConnectionManager(connection) => connection.get_db(),
-            Self::ClusterConnection(connection) => connection.get_db(),
-        }
-    }
+    ) -> Result<Vec<StreamId>, BrokerError>;
+
+    /// Get up to `count` entries after the last consumed id of each stream,
+    /// in the same order of the stream keys, waiting until the consume timeout
+    /// for at least one of them; return `ConsumeTimeout` if there is none
+    async fn indexer_consume(
+        &self,
+        stream_keys: &[&str],
+        last_consumed_ids: &[&str],
+        count: usize,
+    ) -> Result<Vec<Vec<StreamId>>, BrokerError>;
+
+    /// Record the last event acknowledged by the consumer of the stream
+    async fn acknowledge(
+        &self,
+        stream_key: &str,
+        consumer: &str,
+        id: &str,
+    ) -> Result<(), BrokerError>;
+
+    /// Get the last event acknowledged by each consumer of the stream
+    async fn acknowledged_ids(
+        &self,
+        stream_key: &str,
+    ) -> Result<HashMap<String, String>, BrokerError>;
+
+    /// Remove the oldest entries of the stream until it has up to `max_len`
+    /// entries and return the number of removed entries
+    async fn trim_max_len(
+        &self,
+        stream_key: &str,
+        max_len: usize,
+    ) -> Result<usize, BrokerError>;
+
+    /// Remove the entries of the stream whose ids are lower than `min_id` and
+    /// return the number of removed entries
+    async fn trim_min_id(
+        &self,
+        stream_key: &str,
+        min_id: &str,
+    ) -> Result<usize, BrokerError>;
+
+    /// Create the consumer group of the stream, if it doesn't exist, so it
+    /// delivers the entries after `start_id`
+    async fn create_group(
+        &self,
+        stream_key: &str,
+        group: &str,
+        start_id: &str,
+    ) -> Result<(), BrokerError>;
+
+    /// Get up to `count` entries that weren't delivered to the group yet and
+    /// add them to the pending entries of the consumer, waiting for at least
+    /// one of them until the consume timeout; return `ConsumeTimeout` if there
+    /// is none
+    async fn consume_group(
+        &self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError>;
+
+    /// Get up to `count` pending entries of the consumer without waiting for
+    /// them
+    async fn read_pending(
+        &self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError>;
+
+    /// Transfer up to `count` pending entries of the group that were idle for
+    /// at least `min_idle` to the consumer and return them
+    async fn claim_pending(
+        &self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: Duration,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError>;
+
+    /// Remove the entries from the pending entries of the group and return
+    /// the number of removed entries
+    async fn acknowledge_group(
+        &self,
+        stream_key: &str,
+        group: &str,
+        ids: &[&str],
+    ) -> Result<usize, BrokerError>;
 }
 
 /// Client that connects to the broker
 #[derive(Clone)]
 pub struct Broker {
-    connection: BrokerConnection,
-    backoff: ExponentialBackoff,
-    consume_timeout: usize,
+    backend: Arc<dyn BrokerBackend>,
+    encoding: PayloadEncoding,
 }
 
 impl Broker {
     /// Create a new client
     /// The broker_address should be in the format redis://host:port/db.
+    /// If the endpoint is `BrokerEndpoint::Memory`, the client uses the
+    /// in-memory streams shared by the process.
     #[tracing::instrument(level = "trace", skip_all)]
     pub async fn new(config: BrokerConfig) -> Result<Self, BrokerError> {
-        tracing::trace!(?config, "connecting to broker");
-
-        let connection = retry(config.backoff.clone(), || async {
-            match config.redis_endpoint.clone() {
-                BrokerEndpoint::Single(endpoint) => {
-                    tracing::trace!("creating Redis Client");
-                    let client = Client::open(endpoint.inner().as_str())?;
+        if let BrokerEndpoint::Memory = config.redis_endpoint {
+            tracing::trace!("using in-memory broker");
+            let consume_timeout =
+                Duration::from_millis(config.consume_timeout as u64);
+            let backend = MemoryBackend::shared(consume_timeout);
+            return Ok(Self::with_backend(backend)
+                .with_encoding(config.payload_encoding));
+        }
+        let encoding = config.payload_encoding;
+        let backend = RedisBackend::new(config).await?;
+        Ok(Self::with_backend(backend).with_encoding(encoding))
+    }
 
-                    tracing::trace!("creating Redis ConnectionManager");
-                    let connection = ConnectionManager::new(client).await?;
+    /// Create a client with the given backend
+    /// The client produces the events with the JSON encoding.
+    pub fn with_backend(backend: impl BrokerBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            encoding: PayloadEncoding::default(),
+        }
+    }
 
-                    Ok(BrokerConnection::ConnectionManager(connection))
-                }
-                BrokerEndpoint::Cluster(endpoints) => {
-                    tracing::trace!("creating Redis Cluster Client");
-                    let client = ClusterClient::new(
-                        endpoints
-                            .iter()
-                            .map(|endpoint| endpoint.inner().as_str())
-                            .collect::<Vec<_>>(),
-                    )?;
-                    tracing::trace!("connecting to Redis Cluster");
-                    let connection = client.get_async_connection().await?;
-                    Ok(BrokerConnection::ClusterConnection(connection))
-                }
-            }
-        })
-        .await
-        .context(ConnectionSnafu)?;
-
-        tracing::trace!("returning successful connection");
-        Ok(Self {
-            connection,
-            backoff: config.backoff,
-            consume_timeout: config.consume_timeout,
-        })
+    /// Set the encoding of the produced events
+    /// The consumed events are decoded with the encoding they were produced.
+    pub fn with_encoding(mut self, encoding: PayloadEncoding) -> Self {
+        self.encoding = encoding;
+        self
     }
 
     /// Produce an event and return its id
@@ -130,31 +263,39 @@ impl Broker {
         stream: &S,
         payload: S::Payload,
     ) -> Result<String, BrokerError> {
-        tracing::trace!("converting payload to JSON string");
-        let payload =
-            serde_json::to_string(&payload).context(InvalidPayloadSnafu)?;
-
-        let event_id = retry(self.backoff.clone(), || async {
-            tracing::trace!(
-                stream_key = stream.key(),
-                payload,
-                "producing event"
-            );
-            let event_id = self
-                .connection
-                .clone()
-                .xadd(stream.key(), "*", &[("payload", &payload)])
-                .await?;
-
-            Ok(event_id)
-        })
-        .await
-        .context(ConnectionSnafu)?;
+        tracing::trace!(encoding = ?self.encoding, "encoding payload");
+        let payload = self.encoding.encode(&payload)?;
+
+        let event_id = self.backend.produce(stream.key(), &payload).await?;
 
         tracing::trace!(event_id, "returning event id");
         Ok(event_id)
     }
 
+    /// Produce the events in a single round-trip and return their ids
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn produce_many<S: BrokerStream>(
+        &mut self,
+        stream: &S,
+        payloads: Vec<S::Payload>,
+    ) -> Result<Vec<String>, BrokerError> {
+        if payloads.is_empty() {
+            return Ok(vec![]);
+        }
+
+        tracing::trace!(encoding = ?self.encoding, "encoding payloads");
+        let payloads = payloads
+            .iter()
+            .map(|payload| self.encoding.encode(payload))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let event_ids =
+            self.backend.produce_many(stream.key(), &payloads).await?;
+
+        tracing::trace!(?event_ids, "returning event ids");
+        Ok(event_ids)
+    }
+
     /// Peek at the end of the stream
     /// This function doesn't block; if there is no event in the stream it returns None.
     #[tracing::instrument(level = "trace", skip_all)]
@@ -162,20 +303,7 @@ impl Broker {
         &mut self,
         stream: &S,
     ) -> Result<Option<Event<S::Payload>>, BrokerError> {
-        let mut reply = retry(self.backoff.clone(), || async {
-            tracing::trace!(stream_key = stream.key(), "peeking at the stream");
-            let reply: StreamRangeReply = self
-                .connection
-                .clone()
-                .xrevrange_count(stream.key(), "+", "-", 1)
-                .await?;
-
-            Ok(reply)
-        })
-        .await
-        .context(ConnectionSnafu)?;
-
-        if let Some(event) = reply.ids.pop() {
+        if let Some(event) = self.backend.peek_latest(stream.key()).await? {
             tracing::trace!("parsing received event");
             Some(event.try_into()).transpose()
         } else {
@@ -184,40 +312,48 @@ impl Broker {
         }
     }
 
+    /// Peek at up to `count` events before the given id, or at the end of the
+    /// stream if there is no id, from the newest to the oldest
+    /// This function doesn't block; it returns an empty list when it reaches
+    /// the beginning of the stream.
     #[tracing::instrument(level = "trace", skip_all)]
-    async fn _consume_blocking<S: BrokerStream>(
+    pub async fn peek_previous<S: BrokerStream>(
         &mut self,
         stream: &S,
-        last_consumed_id: &str,
-    ) -> Result<Event<S::Payload>, BrokerError> {
-        let mut reply = retry(self.backoff.clone(), || async {
-            tracing::trace!(
-                stream_key = stream.key(),
-                last_consumed_id,
-                "consuming event"
-            );
-            let opts = StreamReadOptions::default()
-                .count(1)
-                .block(self.consume_timeout);
-            let reply: StreamReadReply = self
-                .connection
-                .clone()
-                .xread_options(&[stream.key()], &[last_consumed_id], &opts)
-                .await?;
-
-            Ok(reply)
-        })
-        .await
-        .context(ConnectionSnafu)?;
-
-        tracing::trace!("checking for timeout");
-        let mut events = reply.keys.pop().ok_or(BrokerError::ConsumeTimeout)?;
-
-        tracing::trace!("checking if event was received");
-        let event = events.ids.pop().ok_or(BrokerError::FailedToConsume)?;
-
-        tracing::trace!("parsing received event");
-        event.try_into()
+        before_id: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<Event<S::Payload>>, BrokerError> {
+        if let Some(id) = before_id {
+            parse_id(id)?;
+        }
+        let events = self
+            .backend
+            .peek_previous(stream.key(), before_id, count.max(1))
+            .await?;
+        tracing::trace!(count = events.len(), "parsing events");
+        events.into_iter().map(Event::try_from).collect()
+    }
+
+    /// Get the event with the given id
+    /// This function doesn't block; if there is no such event in the stream it returns None.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn get_event<S: BrokerStream>(
+        &mut self,
+        stream: &S,
+        id: &str,
+    ) -> Result<Option<Event<S::Payload>>, BrokerError> {
+        if !is_valid_id(id) {
+            tracing::trace!(id, "invalid event id");
+            return Ok(None);
+        }
+
+        if let Some(event) = self.backend.get_event(stream.key(), id).await? {
+            tracing::trace!("parsing received event");
+            Some(event.try_into()).transpose()
+        } else {
+            tracing::trace!("event not found");
+            Ok(None)
+        }
     }
 
     /// Consume the next event in stream
@@ -233,12 +369,52 @@ impl Broker {
         last_consumed_id: &str,
     ) -> Result<Event<S::Payload>, BrokerError> {
         loop {
-            let result = self._consume_blocking(stream, last_consumed_id).await;
+            let result = self
+                .backend
+                .consume_blocking(stream.key(), last_consumed_id)
+                .await;
+
+            match result {
+                Err(BrokerError::ConsumeTimeout) => {
+                    tracing::trace!("consume timed out, retrying");
+                }
+                Err(e) => return Err(e),
+                Ok(event) => {
+                    tracing::trace!("parsing received event");
+                    return event.try_into();
+                }
+            }
+        }
+    }
 
-            if let Err(BrokerError::ConsumeTimeout) = result {
-                tracing::trace!("consume timed out, retrying");
-            } else {
-                return result;
+    /// Consume up to `max` events in stream
+    ///
+    /// This function blocks until at least one new event is available
+    /// and retries whenever a timeout happens instead of returning an error.
+    ///
+    /// To consume the first events in the stream, `last_consumed_id` should be `INITIAL_ID`.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn consume_batch<S: BrokerStream>(
+        &mut self,
+        stream: &S,
+        last_consumed_id: &str,
+        max: usize,
+    ) -> Result<Vec<Event<S::Payload>>, BrokerError> {
+        loop {
+            let result = self
+                .backend
+                .consume_batch(stream.key(), last_consumed_id, max.max(1))
+                .await;
+
+            match result {
+                Err(BrokerError::ConsumeTimeout) => {
+                    tracing::trace!("consume timed out, retrying");
+                }
+                Err(e) => return Err(e),
+                Ok(events) => {
+                    tracing::trace!(count = events.len(), "parsing events");
+                    return events.into_iter().map(Event::try_from).collect();
+                }
             }
         }
     }
@@ -252,27 +428,11 @@ impl Broker {
         stream: &S,
         last_consumed_id: &str,
     ) -> Result<Option<Event<S::Payload>>, BrokerError> {
-        let mut reply = retry(self.backoff.clone(), || async {
-            tracing::trace!(
-                stream_key = stream.key(),
-                last_consumed_id,
-                "consuming event (non-blocking)"
-            );
-            let opts = StreamReadOptions::default().count(1);
-            let reply: StreamReadReply = self
-                .connection
-                .clone()
-                .xread_options(&[stream.key()], &[last_consumed_id], &opts)
-                .await?;
-
-            Ok(reply)
-        })
-        .await
-        .context(ConnectionSnafu)?;
-
-        tracing::trace!("checking if event was received");
-        if let Some(mut events) = reply.keys.pop() {
-            let event = events.ids.pop().ok_or(BrokerError::FailedToConsume)?;
+        let event = self
+            .backend
+            .consume_nonblocking(stream.key(), last_consumed_id)
+            .await?;
+        if let Some(event) = event {
             tracing::trace!("parsing received event");
             Some(event.try_into()).transpose()
         } else {
@@ -282,21 +442,53 @@ impl Broker {
     }
 }
 
-/// Custom implementation of Debug because ConnectionManager doesn't implement debug
+/// Check whether the id has the Redis stream id format `<ms>-<seq>`
+fn is_valid_id(id: &str) -> bool {
+    parse_id(id).is_ok()
+}
+
+/// Parse the id with the Redis stream id format `<ms>-<seq>`, where the
+/// sequence number is optional, so the ids can be compared
+fn parse_id(id: &str) -> Result<(u64, u64), BrokerError> {
+    let (ms, seq) = id.split_once('-').unwrap_or((id, "0"));
+    match (ms.parse(), seq.parse()) {
+        (Ok(ms), Ok(seq)) => Ok((ms, seq)),
+        _ => InvalidIdSnafu { id }.fail(),
+    }
+}
+
 impl fmt::Debug for Broker {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Broker")
-            .field("consume_timeout", &self.consume_timeout)
+            .field("backend", &self.backend)
             .finish()
     }
 }
 
 /// Trait that defines the type of a stream
 pub trait BrokerStream {
-    type Payload: Serialize + DeserializeOwned + Clone + Eq + PartialEq;
+    type Payload: VersionedPayload + Clone + Eq + PartialEq;
     fn key(&self) -> &str;
 }
 
+/// Payload whose schema is tagged with a version in the stream entries
+///
+/// The version must be increased whenever the schema changes in a way that
+/// the previous consumers can't read, and `upgrade` must convert the payloads
+/// of every previous version to the current one.
+pub trait VersionedPayload: Serialize + DeserializeOwned {
+    /// Version of the schema of the produced payloads
+    const VERSION: u32;
+
+    /// Decode a payload of a previous version and upgrade it
+    fn upgrade(
+        version: u32,
+        _payload: &EncodedPayload,
+    ) -> Result<Self, BrokerError> {
+        UnsupportedVersionSnafu { version }.fail()
+    }
+}
+
 /// Event that goes through the broker
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Event<P: Serialize + DeserializeOwned + Clone + Eq + PartialEq> {
@@ -304,7 +496,7 @@ pub struct Event<P: Serialize + DeserializeOwned + Clone + Eq + PartialEq> {
     pub payload: P,
 }
 
-impl<P: Serialize + DeserializeOwned + Clone + Eq + PartialEq> TryFrom<StreamId>
+impl<P: VersionedPayload + Clone + Eq + PartialEq> TryFrom<StreamId>
     for Event<P>
 {
     type Error = BrokerError;
@@ -312,16 +504,23 @@ impl<P: Serialize + DeserializeOwned + Clone + Eq + PartialEq> TryFrom<StreamId>
     #[tracing::instrument(level = "trace", skip_all)]
     fn try_from(stream_id: StreamId) -> Result<Event<P>, BrokerError> {
         tracing::trace!("getting event payload");
-        let payload = stream_id
-            .get::<String>("payload")
-            .ok_or(BrokerError::InvalidEvent)?;
+        let payload = EncodedPayload::from_fields(
+            stream_id.get(ENCODING_FIELD),
+            stream_id.get(VERSION_FIELD),
+            stream_id.get(PAYLOAD_FIELD),
+        )?;
         let id = stream_id.id;
 
-        tracing::trace!(id, payload, "received event");
+        tracing::trace!(
+            id,
+            encoding = ?payload.encoding,
+            version = payload.version,
+            len = payload.data.len(),
+            "received event"
+        );
 
-        tracing::trace!("parsing JSON payload");
-        let payload =
-            serde_json::from_str(&payload).context(InvalidPayloadSnafu)?;
+        tracing::trace!("decoding payload");
+        let payload = payload.decode()?;
 
         tracing::trace!("returning event");
         Ok(Event { id, payload })
@@ -344,6 +543,28 @@ pub enum BrokerError {
 
     #[snafu(display("error parsing event payload"))]
     InvalidPayload { source: serde_json::Error },
+
+    #[snafu(display("error encoding binary event payload"))]
+    EncodePayload {
+        source: ciborium::ser::Error<std::io::Error>,
+    },
+
+    #[snafu(display("error decoding binary event payload"))]
+    DecodePayload {
+        source: ciborium::de::Error<std::io::Error>,
+    },
+
+    #[snafu(display("unknown event payload encoding {}", encoding))]
+    UnknownEncoding { encoding: String },
+
+    #[snafu(display("unsupported event payload version {}", version))]
+    UnsupportedVersion { version: u32 },
+
+    #[snafu(display("invalid event id {}", id))]
+    InvalidId { id: String },
+
+    #[snafu(display("unknown consumer group {}", group))]
+    UnknownGroup { group: String },
 }
 
 #[derive(Debug, Parser)]
@@ -367,12 +588,58 @@ pub struct BrokerCLIConfig {
     /// The max elapsed time for backoff in ms
     #[arg(long, env, default_value = "120000")]
     broker_backoff_max_elapsed_duration: u64,
+
+    /// Storage of the broker streams. The memory backend is only shared by
+    /// the services that run in the same process.
+    #[arg(long, env, value_enum, default_value = "redis")]
+    broker_backend: BrokerBackendKind,
+
+    /// Retention policy of the streams. Trimming the streams limits how deep
+    /// a reorg may go, since the replaced events must still be in the streams.
+    #[arg(long, env, value_enum, default_value = "keep")]
+    broker_retention_policy: RetentionPolicyKind,
+
+    /// Max number of events kept in each stream by the max-len policy
+    #[arg(long, env, default_value = "100000")]
+    broker_retention_max_len: usize,
+
+    /// Interval between the trims of the streams in ms
+    #[arg(long, env, default_value = "60000")]
+    broker_retention_interval: u64,
+
+    /// Encoding of the produced events. Every consumer must be able to read
+    /// the binary encoding before it is enabled.
+    #[arg(long, env, value_enum, default_value = "json")]
+    broker_payload_encoding: PayloadEncoding,
+
+    /// Name of the consumer in the consumer groups of the streams. If
+    /// present, the consumers resume from the events they didn't acknowledge
+    /// and share the events with the replicas of the same service. Each
+    /// replica must have a different name, which it keeps across restarts.
+    #[arg(long, env)]
+    broker_consumer_name: Option<String>,
+
+    /// Time after which the events not acknowledged by a consumer are
+    /// delivered to other consumers of the group in ms
+    #[arg(long, env, default_value = "60000")]
+    broker_consumer_claim_idle: u64,
+}
+
+/// Storage of the broker streams
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BrokerBackendKind {
+    /// Store the streams in Redis
+    Redis,
+    /// Store the streams in the memory of the process
+    Memory,
 }
 
 #[derive(Debug, Clone)]
 pub enum BrokerEndpoint {
     Single(RedactedUrl),
     Cluster(Vec<RedactedUrl>),
+    /// In-memory streams shared by the process
+    Memory,
 }
 
 #[derive(Debug, Clone)]
@@ -380,6 +647,9 @@ pub struct BrokerConfig {
     pub redis_endpoint: BrokerEndpoint,
     pub consume_timeout: usize,
     pub backoff: ExponentialBackoff,
+    pub retention: RetentionConfig,
+    pub payload_encoding: PayloadEncoding,
+    pub consumer_group: Option<ConsumerGroupConfig>,
 }
 
 impl From<BrokerCLIConfig> for BrokerConfig {
@@ -391,7 +661,9 @@ impl From<BrokerCLIConfig> for BrokerConfig {
             .with_max_elapsed_time(Some(max_elapsed_time))
             .build();
         let redis_endpoint =
-            if let Some(endpoints) = cli_config.redis_cluster_endpoints {
+            if cli_config.broker_backend == BrokerBackendKind::Memory {
+                BrokerEndpoint::Memory
+            } else if let Some(endpoints) = cli_config.redis_cluster_endpoints {
                 let urls = endpoints
                     .iter()
                     .map(|endpoint| {
@@ -408,10 +680,34 @@ impl From<BrokerCLIConfig> for BrokerConfig {
                     .expect("failed to parse Redis URL");
                 BrokerEndpoint::Single(url)
             };
+        let policy = match cli_config.broker_retention_policy {
+            RetentionPolicyKind::Keep => RetentionPolicy::Keep,
+            RetentionPolicyKind::MaxLen => {
+                RetentionPolicy::MaxLen(cli_config.broker_retention_max_len)
+            }
+            RetentionPolicyKind::Acknowledged => RetentionPolicy::Acknowledged,
+        };
+        let retention = RetentionConfig {
+            policy,
+            interval: Duration::from_millis(
+                cli_config.broker_retention_interval,
+            ),
+        };
+        let consumer_group = cli_config.broker_consumer_name.map(|consumer| {
+            ConsumerGroupConfig {
+                consumer,
+                claim_idle: Duration::from_millis(
+                    cli_config.broker_consumer_claim_idle,
+                ),
+            }
+        });
         BrokerConfig {
             redis_endpoint,
             consume_timeout: cli_config.broker_consume_timeout,
             backoff,
+            retention,
+            payload_encoding: cli_config.broker_payload_encoding,
+            consumer_group,
         }
     }
 }
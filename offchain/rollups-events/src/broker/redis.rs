@@ -0,0 +1,665 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Redis broker backend
+//!
+//! The streams are stored as Redis streams, and the payload of each event is
+//! stored in the `payload` field of the entry.
+
+use async_trait::async_trait;
+use backoff::{future::retry, ExponentialBackoff};
+use redis::aio::{ConnectionLike, ConnectionManager};
+use redis::cluster::ClusterClient;
+use redis::cluster_async::ClusterConnection;
+use redis::streams::{
+    StreamId, StreamMaxlen, StreamRangeReply, StreamReadOptions,
+    StreamReadReply,
+};
+use redis::{
+    AsyncCommands, Client, Cmd, ErrorKind, FromRedisValue, Pipeline,
+    RedisError, RedisFuture, Value,
+};
+use snafu::ResultExt;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use super::{
+    BrokerBackend, BrokerConfig, BrokerEndpoint, BrokerError, ConnectionSnafu,
+    EncodedPayload,
+};
+
+/// The `BrokerConnection` enum implements the `ConnectionLike` trait
+/// to satisfy the `AsyncCommands` trait bounds.
+/// As `AsyncCommands` requires its implementors to be `Sized`, we couldn't
+/// use a trait object instead.
+#[derive(Clone)]
+enum BrokerConnection {
+    ConnectionManager(ConnectionManager),
+    ClusterConnection(ClusterConnection),
+}
+
+impl ConnectionLike for BrokerConnection {
+    fn req_packed_command<'a>(
+        &'a mut self,
+        cmd: &'a Cmd,
+    ) -> RedisFuture<'a, Value> {
+        match self {
+            Self::ConnectionManager(connection) => {
+                connection.req_packed_command(cmd)
+            }
+            Self::ClusterConnection(connection) => {
+                connection.req_packed_command(cmd)
+            }
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match self {
+            Self::ConnectionManager(connection) => {
+                connection.req_packed_commands(cmd, offset, count)
+            }
+            Self::ClusterConnection(connection) => {
+                connection.req_packed_commands(cmd, offset, count)
+            }
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        match self {
+            Self::ConnectionManager(connection) => connection.get_db(),
+            Self::ClusterConnection(connection) => connection.get_db(),
+        }
+    }
+}
+
+/// Key of the hash with the last event acknowledged by each consumer
+/// The key shares the hash tag of the stream, so both are in the same node
+/// of a Redis cluster.
+fn acks_key(stream_key: &str) -> String {
+    format!("{}:acks", stream_key)
+}
+
+/// Backend that stores the streams in Redis
+#[derive(Clone)]
+pub struct RedisBackend {
+    connection: BrokerConnection,
+    backoff: ExponentialBackoff,
+    consume_timeout: usize,
+}
+
+impl RedisBackend {
+    /// Connect to Redis
+    /// The broker_address should be in the format redis://host:port/db.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn new(config: BrokerConfig) -> Result<Self, BrokerError> {
+        tracing::trace!(?config, "connecting to broker");
+
+        let connection = retry(config.backoff.clone(), || async {
+            match config.redis_endpoint.clone() {
+                BrokerEndpoint::Single(endpoint) => {
+                    tracing::trace!("creating Redis Client");
+                    let client = Client::open(endpoint.inner().as_str())?;
+
+                    tracing::trace!("creating Redis ConnectionManager");
+                    let connection = ConnectionManager::new(client).await?;
+
+                    Ok(BrokerConnection::ConnectionManager(connection))
+                }
+                BrokerEndpoint::Cluster(endpoints) => {
+                    tracing::trace!("creating Redis Cluster Client");
+                    let client = ClusterClient::new(
+                        endpoints
+                            .iter()
+                            .map(|endpoint| endpoint.inner().as_str())
+                            .collect::<Vec<_>>(),
+                    )?;
+                    tracing::trace!("connecting to Redis Cluster");
+                    let connection = client.get_async_connection().await?;
+                    Ok(BrokerConnection::ClusterConnection(connection))
+                }
+                BrokerEndpoint::Memory => {
+                    let error = RedisError::from((
+                        ErrorKind::InvalidClientConfig,
+                        "the in-memory broker has no Redis endpoint",
+                    ));
+                    Err(backoff::Error::permanent(error))
+                }
+            }
+        })
+        .await
+        .context(ConnectionSnafu)?;
+
+        tracing::trace!("returning successful connection");
+        Ok(Self {
+            connection,
+            backoff: config.backoff,
+            consume_timeout: config.consume_timeout,
+        })
+    }
+
+    async fn xread(
+        &self,
+        stream_keys: &[&str],
+        last_consumed_ids: &[&str],
+        count: usize,
+        block: bool,
+    ) -> Result<StreamReadReply, BrokerError> {
+        retry(self.backoff.clone(), || async {
+            tracing::trace!(
+                ?stream_keys,
+                ?last_consumed_ids,
+                count,
+                block,
+                "consuming events"
+            );
+            let mut opts = StreamReadOptions::default().count(count);
+            if block {
+                opts = opts.block(self.consume_timeout);
+            }
+            let reply: StreamReadReply = self
+                .connection
+                .clone()
+                .xread_options(stream_keys, last_consumed_ids, &opts)
+                .await?;
+
+            Ok(reply)
+        })
+        .await
+        .context(ConnectionSnafu)
+    }
+
+    async fn xreadgroup(
+        &self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        id: &str,
+        count: usize,
+        block: bool,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        let mut reply = retry(self.backoff.clone(), || async {
+            tracing::trace!(
+                stream_key,
+                group,
+                consumer,
+                id,
+                count,
+                block,
+                "consuming group events"
+            );
+            let mut opts = StreamReadOptions::default()
+                .group(group, consumer)
+                .count(count);
+            if block {
+                opts = opts.block(self.consume_timeout);
+            }
+            let reply: StreamReadReply = self
+                .connection
+                .clone()
+                .xread_options(&[stream_key], &[id], &opts)
+                .await?;
+
+            Ok(reply)
+        })
+        .await
+        .context(ConnectionSnafu)?;
+
+        match reply.keys.pop() {
+            Some(events) => Ok(events.ids),
+            None if block => Err(BrokerError::ConsumeTimeout),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+#[async_trait]
+impl BrokerBackend for RedisBackend {
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn produce(
+        &self,
+        stream_key: &str,
+        payload: &EncodedPayload,
+    ) -> Result<String, BrokerError> {
+        retry(self.backoff.clone(), || async {
+            tracing::trace!(stream_key, ?payload, "producing event");
+            let event_id = self
+                .connection
+                .clone()
+                .xadd(stream_key, "*", &payload.fields())
+                .await?;
+
+            Ok(event_id)
+        })
+        .await
+        .context(ConnectionSnafu)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn produce_many(
+        &self,
+        stream_key: &str,
+        payloads: &[EncodedPayload],
+    ) -> Result<Vec<String>, BrokerError> {
+        retry(self.backoff.clone(), || async {
+            tracing::trace!(
+                stream_key,
+                count = payloads.len(),
+                "producing events"
+            );
+            // The transaction prevents a retry from duplicating the events
+            // of a partially applied pipeline
+            let mut pipeline = redis::pipe();
+            pipeline.atomic();
+            for payload in payloads {
+                pipeline.xadd(stream_key, "*", &payload.fields());
+            }
+            let event_ids: Vec<String> =
+                pipeline.query_async(&mut self.connection.clone()).await?;
+
+            Ok(event_ids)
+        })
+        .await
+        .context(ConnectionSnafu)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn produce_atomic(
+        &self,
+        entries: &[(String, EncodedPayload)],
+    ) -> Result<Vec<String>, BrokerError> {
+        retry(self.backoff.clone(), || async {
+            tracing::trace!(count = entries.len(), "producing events");
+            let mut pipeline = redis::pipe();
+            pipeline.atomic();
+            for (stream_key, payload) in entries {
+                pipeline.xadd(stream_key, "*", &payload.fields());
+            }
+            let event_ids: Vec<String> =
+                pipeline.query_async(&mut self.connection.clone()).await?;
+
+            Ok(event_ids)
+        })
+        .await
+        .context(ConnectionSnafu)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn peek_latest(
+        &self,
+        stream_key: &str,
+    ) -> Result<Option<StreamId>, BrokerError> {
+        let mut reply = retry(self.backoff.clone(), || async {
+            tracing::trace!(stream_key, "peeking at the stream");
+            let reply: StreamRangeReply = self
+                .connection
+                .clone()
+                .xrevrange_count(stream_key, "+", "-", 1)
+                .await?;
+
+            Ok(reply)
+        })
+        .await
+        .context(ConnectionSnafu)?;
+
+        Ok(reply.ids.pop())
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn peek_previous(
+        &self,
+        stream_key: &str,
+        before_id: Option<&str>,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        // The parenthesis makes the range exclusive
+        let end = before_id.map_or("+".to_owned(), |id| format!("({}", id));
+        let reply = retry(self.backoff.clone(), || async {
+            tracing::trace!(stream_key, end, count, "peeking at the stream");
+            let reply: StreamRangeReply = self
+                .connection
+                .clone()
+                .xrevrange_count(stream_key, &end, "-", count)
+                .await?;
+
+            Ok(reply)
+        })
+        .await
+        .context(ConnectionSnafu)?;
+
+        Ok(reply.ids)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn get_event(
+        &self,
+        stream_key: &str,
+        id: &str,
+    ) -> Result<Option<StreamId>, BrokerError> {
+        let mut reply = retry(self.backoff.clone(), || async {
+            tracing::trace!(stream_key, id, "getting event");
+            let reply: StreamRangeReply = self
+                .connection
+                .clone()
+                .xrange_count(stream_key, id, id, 1)
+                .await?;
+
+            Ok(reply)
+        })
+        .await
+        .context(ConnectionSnafu)?;
+
+        Ok(reply.ids.pop())
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_blocking(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+    ) -> Result<StreamId, BrokerError> {
+        let mut reply = self
+            .xread(&[stream_key], &[last_consumed_id], 1, true)
+            .await?;
+
+        tracing::trace!("checking for timeout");
+        let mut events = reply.keys.pop().ok_or(BrokerError::ConsumeTimeout)?;
+
+        tracing::trace!("checking if event was received");
+        events.ids.pop().ok_or(BrokerError::FailedToConsume)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_batch(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        let mut reply = self
+            .xread(&[stream_key], &[last_consumed_id], count, true)
+            .await?;
+
+        tracing::trace!("checking for timeout");
+        let events = reply.keys.pop().ok_or(BrokerError::ConsumeTimeout)?;
+
+        tracing::trace!("checking if events were received");
+        if events.ids.is_empty() {
+            return Err(BrokerError::FailedToConsume);
+        }
+        Ok(events.ids)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_nonblocking(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+    ) -> Result<Option<StreamId>, BrokerError> {
+        let mut reply = self
+            .xread(&[stream_key], &[last_consumed_id], 1, false)
+            .await?;
+
+        tracing::trace!("checking if event was received");
+        if let Some(mut events) = reply.keys.pop() {
+            let event = events.ids.pop().ok_or(BrokerError::FailedToConsume)?;
+            Ok(Some(event))
+        } else {
+            tracing::trace!("stream is empty");
+            Ok(None)
+        }
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_batch_nonblocking(
+        &self,
+        stream_key: &str,
+        last_consumed_id: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        let mut reply = self
+            .xread(&[stream_key], &[last_consumed_id], count, false)
+            .await?;
+
+        tracing::trace!("checking if events were received");
+        Ok(reply
+            .keys
+            .pop()
+            .map(|events| events.ids)
+            .unwrap_or_default())
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn indexer_consume(
+        &self,
+        stream_keys: &[&str],
+        last_consumed_ids: &[&str],
+        count: usize,
+    ) -> Result<Vec<Vec<StreamId>>, BrokerError> {
+        let mut reply = self
+            .xread(stream_keys, last_consumed_ids, count, true)
+            .await?;
+        if reply.keys.is_empty() {
+            tracing::trace!("indexer consume timed out");
+            return Err(BrokerError::ConsumeTimeout);
+        }
+
+        let events = stream_keys
+            .iter()
+            .map(|stream_key| {
+                reply
+                    .keys
+                    .iter_mut()
+                    .find(|stream| stream.key == *stream_key)
+                    .map(|stream| std::mem::take(&mut stream.ids))
+                    .unwrap_or_default()
+            })
+            .collect();
+        Ok(events)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn acknowledge(
+        &self,
+        stream_key: &str,
+        consumer: &str,
+        id: &str,
+    ) -> Result<(), BrokerError> {
+        retry(self.backoff.clone(), || async {
+            tracing::trace!(stream_key, consumer, id, "acknowledging event");
+            let _: () = self
+                .connection
+                .clone()
+                .hset(acks_key(stream_key), consumer, id)
+                .await?;
+
+            Ok(())
+        })
+        .await
+        .context(ConnectionSnafu)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn acknowledged_ids(
+        &self,
+        stream_key: &str,
+    ) -> Result<HashMap<String, String>, BrokerError> {
+        retry(self.backoff.clone(), || async {
+            tracing::trace!(stream_key, "getting acknowledged events");
+            let ids: HashMap<String, String> = self
+                .connection
+                .clone()
+                .hgetall(acks_key(stream_key))
+                .await?;
+
+            Ok(ids)
+        })
+        .await
+        .context(ConnectionSnafu)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn trim_max_len(
+        &self,
+        stream_key: &str,
+        max_len: usize,
+    ) -> Result<usize, BrokerError> {
+        retry(self.backoff.clone(), || async {
+            tracing::trace!(stream_key, max_len, "trimming stream");
+            let count: usize = self
+                .connection
+                .clone()
+                .xtrim(stream_key, StreamMaxlen::Equals(max_len))
+                .await?;
+
+            Ok(count)
+        })
+        .await
+        .context(ConnectionSnafu)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn trim_min_id(
+        &self,
+        stream_key: &str,
+        min_id: &str,
+    ) -> Result<usize, BrokerError> {
+        retry(self.backoff.clone(), || async {
+            tracing::trace!(stream_key, min_id, "trimming stream");
+            let count: usize = redis::cmd("XTRIM")
+                .arg(stream_key)
+                .arg("MINID")
+                .arg(min_id)
+                .query_async(&mut self.connection.clone())
+                .await?;
+
+            Ok(count)
+        })
+        .await
+        .context(ConnectionSnafu)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn create_group(
+        &self,
+        stream_key: &str,
+        group: &str,
+        start_id: &str,
+    ) -> Result<(), BrokerError> {
+        retry(self.backoff.clone(), || async {
+            tracing::trace!(stream_key, group, start_id, "creating group");
+            let result: Result<(), RedisError> = self
+                .connection
+                .clone()
+                .xgroup_create_mkstream(stream_key, group, start_id)
+                .await;
+            match result {
+                Err(e) if e.code() == Some("BUSYGROUP") => {
+                    tracing::trace!(stream_key, group, "group already exists");
+                    Ok(())
+                }
+                result => Ok(result?),
+            }
+        })
+        .await
+        .context(ConnectionSnafu)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn consume_group(
+        &self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        self.xreadgroup(stream_key, group, consumer, ">", count, true)
+            .await
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn read_pending(
+        &self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        self.xreadgroup(stream_key, group, consumer, "0", count, false)
+            .await
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn claim_pending(
+        &self,
+        stream_key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: Duration,
+        count: usize,
+    ) -> Result<Vec<StreamId>, BrokerError> {
+        let reply = retry(self.backoff.clone(), || async {
+            tracing::trace!(
+                stream_key,
+                group,
+                consumer,
+                ?min_idle,
+                count,
+                "claiming pending events"
+            );
+            let reply: Vec<Value> = redis::cmd("XAUTOCLAIM")
+                .arg(stream_key)
+                .arg(group)
+                .arg(consumer)
+                .arg(min_idle.as_millis() as u64)
+                .arg("0-0")
+                .arg("COUNT")
+                .arg(count)
+                .query_async(&mut self.connection.clone())
+                .await?;
+
+            Ok(reply)
+        })
+        .await
+        .context(ConnectionSnafu)?;
+
+        // The reply has the next cursor, the claimed entries, and, since
+        // Redis 7, the ids of the claimed entries that were trimmed
+        let entries = reply.get(1).ok_or(BrokerError::FailedToConsume)?;
+        let entries = StreamRangeReply::from_redis_value(entries)
+            .map_err(|_| BrokerError::InvalidEvent)?;
+        Ok(entries.ids)
+    }
+
+    #[tracing::instrument(level = "trace", skip_all)]
+    async fn acknowledge_group(
+        &self,
+        stream_key: &str,
+        group: &str,
+        ids: &[&str],
+    ) -> Result<usize, BrokerError> {
+        retry(self.backoff.clone(), || async {
+            tracing::trace!(stream_key, group, ?ids, "acknowledging events");
+            let count: usize =
+                self.connection.clone().xack(stream_key, group, ids).await?;
+
+            Ok(count)
+        })
+        .await
+        .context(ConnectionSnafu)
+    }
+}
+
+/// Custom implementation of Debug because ConnectionManager doesn't implement debug
+impl fmt::Debug for RedisBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedisBackend")
+            .field("consume_timeout", &self.consume_timeout)
+            .finish()
+    }
+}
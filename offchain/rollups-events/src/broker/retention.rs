@@ -0,0 +1,190 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Retention policy of the broker streams
+//!
+//! The consumers record the id of the last event they no longer need in the
+//! broker, so the streams can be trimmed below the events that every known
+//! consumer acknowledged. Trimming a stream also limits how deep a reorg may
+//! go, since the events of the replaced branch must still be in the stream.
+
+use clap::ValueEnum;
+use std::time::Duration;
+
+use super::{parse_id, Broker, BrokerBackend, BrokerError, BrokerStream};
+
+/// Name of the indexer in the acknowledgements of the streams
+pub const INDEXER_CONSUMER: &str = "indexer";
+
+/// Name of the advance-runner in the acknowledgements of the streams
+pub const ADVANCE_RUNNER_CONSUMER: &str = "advance-runner";
+
+/// Name of the dispatcher in the acknowledgements of the streams
+pub const DISPATCHER_CONSUMER: &str = "dispatcher";
+
+/// Name of the authority-claimer in the acknowledgements of the streams
+pub const AUTHORITY_CLAIMER_CONSUMER: &str = "authority-claimer";
+
+/// How many events the broker keeps in each stream
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RetentionPolicy {
+    /// Keep every event
+    #[default]
+    Keep,
+    /// Keep the latest events up to the given length
+    MaxLen(usize),
+    /// Remove the events before the ones acknowledged by all the consumers
+    /// of the stream
+    Acknowledged,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum RetentionPolicyKind {
+    Keep,
+    MaxLen,
+    Acknowledged,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    pub policy: RetentionPolicy,
+    /// Interval between the trims of the streams
+    pub interval: Duration,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            policy: RetentionPolicy::Keep,
+            interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Broker {
+    /// Record that the consumer no longer needs the events up to the given id
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn acknowledge<S: BrokerStream>(
+        &mut self,
+        stream: &S,
+        consumer: &str,
+        id: &str,
+    ) -> Result<(), BrokerError> {
+        parse_id(id)?;
+        self.backend.acknowledge(stream.key(), consumer, id).await
+    }
+
+    /// Trim the stream according to the policy and return the number of
+    /// removed events
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn trim<S: BrokerStream>(
+        &mut self,
+        stream: &S,
+        policy: &RetentionPolicy,
+        consumers: &[&str],
+    ) -> Result<usize, BrokerError> {
+        trim_stream(self.backend.as_ref(), stream.key(), policy, consumers)
+            .await
+    }
+}
+
+async fn trim_stream(
+    backend: &dyn BrokerBackend,
+    stream_key: &str,
+    policy: &RetentionPolicy,
+    consumers: &[impl AsRef<str>],
+) -> Result<usize, BrokerError> {
+    match policy {
+        RetentionPolicy::Keep => Ok(0),
+        RetentionPolicy::MaxLen(max_len) => {
+            backend.trim_max_len(stream_key, *max_len).await
+        }
+        RetentionPolicy::Acknowledged => {
+            let acknowledged = backend.acknowledged_ids(stream_key).await?;
+            let mut min_id = None;
+            for consumer in consumers {
+                let consumer = consumer.as_ref();
+                let Some(id) = acknowledged.get(consumer) else {
+                    tracing::trace!(
+                        stream_key,
+                        consumer,
+                        "consumer didn't acknowledge any event yet"
+                    );
+                    return Ok(0);
+                };
+                let id = (parse_id(id)?, id);
+                min_id = Some(min_id.map_or(id, |min_id| id.min(min_id)));
+            }
+            match min_id {
+                Some((_, id)) => backend.trim_min_id(stream_key, id).await,
+                None => Ok(0),
+            }
+        }
+    }
+}
+
+/// Maintenance task that trims the streams periodically
+#[derive(Debug)]
+pub struct StreamTrimmer {
+    broker: Broker,
+    config: RetentionConfig,
+    /// Key and consumers of each trimmed stream
+    streams: Vec<(String, Vec<String>)>,
+}
+
+impl StreamTrimmer {
+    pub fn new(broker: Broker, config: RetentionConfig) -> Self {
+        Self {
+            broker,
+            config,
+            streams: vec![],
+        }
+    }
+
+    /// Add the stream to the ones trimmed by the task
+    /// The acknowledged policy only removes the events acknowledged by every
+    /// one of the given consumers.
+    pub fn add_stream<S: BrokerStream>(
+        &mut self,
+        stream: &S,
+        consumers: &[&str],
+    ) {
+        let consumers = consumers.iter().map(|c| c.to_string()).collect();
+        self.streams.push((stream.key().to_owned(), consumers));
+    }
+
+    /// Trim the streams at each interval
+    /// The task keeps running if it fails to trim a stream, as the streams
+    /// are trimmed again in the next interval.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn start(self) {
+        if self.config.policy == RetentionPolicy::Keep {
+            tracing::info!("stream retention is disabled");
+            return;
+        }
+        tracing::info!(?self.config, "starting stream trimmer");
+        let mut interval = tokio::time::interval(self.config.interval);
+        loop {
+            interval.tick().await;
+            for (stream_key, consumers) in &self.streams {
+                let result = trim_stream(
+                    self.broker.backend.as_ref(),
+                    stream_key,
+                    &self.config.policy,
+                    consumers,
+                )
+                .await;
+                match result {
+                    Ok(count) => {
+                        tracing::trace!(stream_key, count, "trimmed stream")
+                    }
+                    Err(e) => tracing::warn!(
+                        stream_key,
+                        "failed to trim stream ({})",
+                        e
+                    ),
+                }
+            }
+        }
+    }
+}
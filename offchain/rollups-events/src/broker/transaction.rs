@@ -0,0 +1,66 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Transactions of the broker
+//!
+//! A transaction produces events in several streams at once, so either every
+//! event is produced or none of them is. The streams of a transaction must
+//! share the hash tag of their keys, like the streams of the same DApp, so
+//! they are in the same node of a Redis cluster.
+
+use super::{
+    Broker, BrokerError, BrokerStream, EncodedPayload, PayloadEncoding,
+};
+
+/// Events produced together by `Broker::commit`
+#[derive(Debug)]
+pub struct BrokerTransaction {
+    encoding: PayloadEncoding,
+    events: Vec<(String, EncodedPayload)>,
+}
+
+impl BrokerTransaction {
+    /// Add an event to the transaction
+    pub fn produce<S: BrokerStream>(
+        &mut self,
+        stream: &S,
+        payload: S::Payload,
+    ) -> Result<(), BrokerError> {
+        let payload = self.encoding.encode(&payload)?;
+        self.events.push((stream.key().to_owned(), payload));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl Broker {
+    /// Start a transaction with the encoding of the client
+    pub fn transaction(&self) -> BrokerTransaction {
+        BrokerTransaction {
+            encoding: self.encoding,
+            events: vec![],
+        }
+    }
+
+    /// Produce the events of the transaction atomically and return their ids,
+    /// in the order they were added
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn commit(
+        &mut self,
+        transaction: BrokerTransaction,
+    ) -> Result<Vec<String>, BrokerError> {
+        if transaction.is_empty() {
+            return Ok(vec![]);
+        }
+
+        tracing::trace!(count = transaction.events.len(), "committing events");
+        let event_ids =
+            self.backend.produce_atomic(&transaction.events).await?;
+
+        tracing::trace!(?event_ids, "returning event ids");
+        Ok(event_ids)
+    }
+}
@@ -4,6 +4,7 @@
 use base64::{engine::general_purpose::STANDARD as base64_engine, Engine as _};
 use prometheus_client::encoding::EncodeLabelValue;
 use prometheus_client::encoding::LabelValueEncoder;
+use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt::Write;
 
@@ -12,7 +13,27 @@ pub const HASH_SIZE: usize = 32;
 
 const PAYLOAD_DEBUG_MAX_LEN: usize = 100;
 
+/// Visitor that reads the byte strings of the binary formats
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a byte string")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+        Ok(v)
+    }
+}
+
 /// A binary array that is converted to a hex string when serialized
+/// The binary formats keep it as a byte string instead.
 #[derive(Clone, Hash, Eq, PartialEq)]
 pub struct HexArray<const N: usize>([u8; N]);
 
@@ -45,6 +66,9 @@ impl<const N: usize> Serialize for HexArray<N> {
     where
         S: Serializer,
     {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_bytes(self.inner());
+        }
         String::serialize(&hex::encode(self.inner()), serializer)
     }
 }
@@ -54,6 +78,13 @@ impl<'de, const N: usize> Deserialize<'de> for HexArray<N> {
     where
         D: Deserializer<'de>,
     {
+        if !deserializer.is_human_readable() {
+            let vec_data = deserializer.deserialize_bytes(BytesVisitor)?;
+            let data = vec_data
+                .try_into()
+                .or(Err(de::Error::custom("incorrect array size")))?;
+            return Ok(Self::new(data));
+        }
         let mut string_data = String::deserialize(deserializer)?;
         // The hex crate doesn't decode '0x' at the start, so we treat the value before decoding
         if string_data[..2].eq("0x") {
@@ -97,7 +128,8 @@ pub type Hash = HexArray<HASH_SIZE>;
 pub type Address = HexArray<ADDRESS_SIZE>;
 
 /// Rollups payload.
-/// When serialized, it is converted to a base64 string
+/// When serialized, it is converted to a base64 string, except in the binary
+/// formats, which keep it as a byte string
 #[derive(Default, Clone, Eq, PartialEq)]
 pub struct Payload(Vec<u8>);
 
@@ -130,6 +162,9 @@ impl Serialize for Payload {
     where
         S: Serializer,
     {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_bytes(self.inner());
+        }
         String::serialize(&base64_engine.encode(self.inner()), serializer)
     }
 }
@@ -139,6 +174,10 @@ impl<'de> Deserialize<'de> for Payload {
     where
         D: Deserializer<'de>,
     {
+        if !deserializer.is_human_readable() {
+            let data = deserializer.deserialize_byte_buf(BytesVisitor)?;
+            return Ok(Payload::new(data));
+        }
         let string_data = String::deserialize(deserializer)?;
         let data = base64_engine.decode(string_data).map_err(|e| {
             serde::de::Error::custom(format!("fail to decode base64 ({})", e))
@@ -225,4 +264,36 @@ mod tests {
             .to_string()
             .contains("fail to decode base64"));
     }
+
+    #[test]
+    fn serialize_binary_array_and_payload() {
+        let mut data = vec![];
+        ciborium::into_writer(&Address::new([0xfa; ADDRESS_SIZE]), &mut data)
+            .unwrap();
+        // CBOR byte string header followed by the address bytes
+        assert_eq!(data[0], 0x54);
+        assert_eq!(&data[1..], [0xfa; ADDRESS_SIZE]);
+
+        let payload = Payload::new(vec![0xfa; 20]);
+        let mut data = vec![];
+        ciborium::into_writer(&payload, &mut data).unwrap();
+        assert_eq!(data.len(), 21);
+        let decoded: Payload = ciborium::from_reader(data.as_slice()).unwrap();
+        assert_eq!(decoded, payload);
+    }
+
+    #[test]
+    fn deserialize_binary_array() {
+        let hash = Hash::new([0xfa; HASH_SIZE]);
+        let mut data = vec![];
+        ciborium::into_writer(&hash, &mut data).unwrap();
+        let decoded: Hash = ciborium::from_reader(data.as_slice()).unwrap();
+        assert_eq!(decoded, hash);
+        let mut data = vec![];
+        ciborium::into_writer(&Payload::new(vec![0xff]), &mut data).unwrap();
+        assert!(ciborium::from_reader::<Hash, _>(data.as_slice())
+            .unwrap_err()
+            .to_string()
+            .contains("incorrect array size"));
+    }
 }
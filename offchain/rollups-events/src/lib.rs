@@ -9,14 +9,20 @@ mod rollups_outputs;
 mod rollups_stream;
 
 pub use broker::{
-    indexer, Broker, BrokerCLIConfig, BrokerConfig, BrokerEndpoint,
-    BrokerError, BrokerStream, Event, RedactedUrl, Url, INITIAL_ID,
+    indexer, Broker, BrokerBackend, BrokerBackendKind, BrokerCLIConfig,
+    BrokerConfig, BrokerEndpoint, BrokerError, BrokerStream, BrokerTransaction,
+    ConsumerGroup, ConsumerGroupConfig, EncodedPayload, Event, MemoryBackend,
+    PayloadEncoding, RedactedUrl, RedisBackend, RetentionConfig,
+    RetentionPolicy, RetentionPolicyKind, StreamTrimmer, Url, VersionedPayload,
+    ADVANCE_RUNNER_CONSUMER, AUTHORITY_CLAIMER_CONSUMER, DISPATCHER_CONSUMER,
+    INDEXER_CONSUMER, INITIAL_ID, LEGACY_VERSION,
 };
 pub use common::{Address, Hash, Payload, ADDRESS_SIZE, HASH_SIZE};
 pub use rollups_claims::{RollupsClaim, RollupsClaimsStream};
 pub use rollups_inputs::{
-    InputMetadata, RollupsAdvanceStateInput, RollupsData, RollupsInput,
-    RollupsInputsStream,
+    EpochBoundary, EpochStart, InputMetadata, RollupsAdvanceStateInput,
+    RollupsData, RollupsInput, RollupsInputsCheckpoint,
+    RollupsInputsCheckpointsStream, RollupsInputsStream,
 };
 pub use rollups_outputs::{
     RollupsAdvanceResult, RollupsCompletionStatus, RollupsNotice,
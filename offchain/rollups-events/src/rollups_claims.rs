@@ -3,7 +3,9 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::{Address, BrokerStream, Hash};
+use crate::{
+    Address, BrokerError, BrokerStream, EncodedPayload, Hash, VersionedPayload,
+};
 
 #[derive(Debug)]
 pub struct RollupsClaimsStream {
@@ -43,4 +45,52 @@ pub struct RollupsClaim {
 
     /// Index of the last input of the Epoch
     pub last_index: u128,
+
+    /// Hash of the machine state at the end of the Epoch
+    pub machine_state_hash: Hash,
+
+    /// Merkle root of the vouchers of the Epoch
+    pub vouchers_epoch_root_hash: Hash,
+
+    /// Merkle root of the notices of the Epoch
+    pub notices_epoch_root_hash: Hash,
+}
+
+/// Version 2 added the hashes the claim is computed from
+impl VersionedPayload for RollupsClaim {
+    const VERSION: u32 = 2;
+
+    fn upgrade(
+        version: u32,
+        payload: &EncodedPayload,
+    ) -> Result<Self, BrokerError> {
+        match version {
+            1 => payload.decode_as::<RollupsClaimV1>().map(Self::from),
+            _ => Err(BrokerError::UnsupportedVersion { version }),
+        }
+    }
+}
+
+/// Claim event produced up to release 1.4
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+struct RollupsClaimV1 {
+    dapp_address: Address,
+    epoch_index: u64,
+    epoch_hash: Hash,
+    first_index: u128,
+    last_index: u128,
+}
+
+/// The hashes of the claim are unknown, so they are left empty
+impl From<RollupsClaimV1> for RollupsClaim {
+    fn from(claim: RollupsClaimV1) -> Self {
+        Self {
+            dapp_address: claim.dapp_address,
+            epoch_index: claim.epoch_index,
+            epoch_hash: claim.epoch_hash,
+            first_index: claim.first_index,
+            last_index: claim.last_index,
+            ..Default::default()
+        }
+    }
 }
@@ -3,9 +3,17 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::{rollups_stream::decl_broker_stream, Address, Hash, Payload};
+use crate::{
+    rollups_stream::decl_broker_stream, Address, Hash, Payload,
+    VersionedPayload,
+};
 
 decl_broker_stream!(RollupsInputsStream, RollupsInput, "rollups-inputs");
+decl_broker_stream!(
+    RollupsInputsCheckpointsStream,
+    RollupsInputsCheckpoint,
+    "rollups-inputs-checkpoints"
+);
 
 /// Cartesi Rollups event
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -27,6 +35,31 @@ pub struct RollupsInput {
     pub data: RollupsData,
 }
 
+impl VersionedPayload for RollupsInput {
+    const VERSION: u32 = 1;
+}
+
+/// Checkpoint of the dispatcher in the inputs stream
+/// The dispatcher produces a checkpoint whenever it finishes an epoch or a
+/// rewind after a reorg changes the last valid event of the inputs stream, so
+/// it can resume from the latest checkpoint after a restart.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RollupsInputsCheckpoint {
+    /// Number of events at the end of the chain of the inputs stream that
+    /// were dropped by a rewind and weren't produced again. The last valid
+    /// event is the parent of the oldest of them.
+    pub dropped_count: u64,
+
+    /// Start of the epoch of the last valid event; None if no epoch was
+    /// finished before it
+    #[serde(default)]
+    pub epoch_start: Option<EpochStart>,
+}
+
+impl VersionedPayload for RollupsInputsCheckpoint {
+    const VERSION: u32 = 1;
+}
+
 /// Rollups data enumeration
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum RollupsData {
@@ -34,7 +67,34 @@ pub enum RollupsData {
     AdvanceStateInput(RollupsAdvanceStateInput),
 
     /// End of an Cartesi Rollups epoch
-    FinishEpoch {},
+    FinishEpoch {
+        /// Block that finished the epoch, which starts the next one; None if
+        /// the event was produced before the boundary was recorded
+        #[serde(default)]
+        boundary: Option<EpochBoundary>,
+    },
+}
+
+/// Block that finished an epoch
+#[derive(
+    Default, Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize,
+)]
+pub struct EpochBoundary {
+    /// Timestamp of the block
+    pub timestamp: u64,
+
+    /// Block number
+    pub block_number: u64,
+}
+
+/// Start of an epoch in the inputs stream
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct EpochStart {
+    /// Number of inputs sent before the epoch
+    pub inputs_sent_count: u64,
+
+    /// Block that finished the previous epoch, if it was recorded
+    pub boundary: Option<EpochBoundary>,
 }
 
 /// Input that advances the Cartesi Rollups epoch
@@ -5,7 +5,10 @@
 //! `offchain/graphql-server/schema.graphql`
 use serde::{Deserialize, Serialize};
 
-use crate::{rollups_stream::decl_broker_stream, Address, Hash, Payload};
+use crate::{
+    rollups_stream::decl_broker_stream, Address, Hash, Payload,
+    VersionedPayload,
+};
 
 decl_broker_stream!(RollupsOutputsStream, RollupsOutput, "rollups-outputs");
 
@@ -19,6 +22,10 @@ pub enum RollupsOutput {
     Proof(RollupsProof),
 }
 
+impl VersionedPayload for RollupsOutput {
+    const VERSION: u32 = 1;
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct RollupsAdvanceResult {
     pub input_index: u64,
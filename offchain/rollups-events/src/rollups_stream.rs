@@ -108,6 +108,10 @@ mod tests {
     #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
     pub struct MockPayload;
 
+    impl crate::VersionedPayload for MockPayload {
+        const VERSION: u32 = 1;
+    }
+
     decl_broker_stream!(MockStream, MockPayload, "rollups-mock");
 
     #[test]
@@ -6,9 +6,10 @@ use rollups_events::indexer::{IndexerEvent, IndexerState};
 use rollups_events::{
     Address, Broker, BrokerConfig, BrokerEndpoint, BrokerError, BrokerStream,
     DAppMetadata, Event, Hash, RedactedUrl, RollupsAdvanceStateInput,
-    RollupsData, RollupsInput, RollupsInputsStream, RollupsOutput,
-    RollupsOutputsStream, Url,
+    RollupsClaim, RollupsClaimsStream, RollupsData, RollupsInput,
+    RollupsInputsStream, RollupsOutput, RollupsOutputsStream, Url,
 };
+use std::collections::HashMap;
 use testcontainers::{
     clients::Cli, core::WaitFor, images::generic::GenericImage, Container,
 };
@@ -43,6 +44,9 @@ impl TestState<'_> {
         let config = BrokerConfig {
             redis_endpoint: BrokerEndpoint::Single(self.redis_endpoint.clone()),
             consume_timeout: CONSUME_TIMEOUT,
+            retention: Default::default(),
+            payload_encoding: Default::default(),
+            consumer_group: None,
             backoff,
         };
         Broker::new(config)
@@ -116,6 +120,43 @@ async fn it_consumes_output_events() {
     }
 }
 
+#[test_log::test(tokio::test)]
+async fn it_reads_output_events_ahead() {
+    let docker = Cli::default();
+    let state = TestState::setup(&docker).await;
+    let mut broker = state.create_broker().await;
+    let outputs = generate_outputs();
+    let metadata = dapp_metadata();
+    let stream = RollupsOutputsStream::new(&metadata);
+    produce_all(&mut broker, &stream, &outputs[..1]).await;
+    // Consume the first output, then read the following ones ahead
+    let mut indexer_state = IndexerState::new(&metadata);
+    broker
+        .indexer_consume(&mut indexer_state)
+        .await
+        .expect("failed to consume indexer payload");
+    assert!(!indexer_state.has_pending());
+    produce_all(&mut broker, &stream, &outputs[1..]).await;
+    broker
+        .indexer_read_outputs(&mut indexer_state)
+        .await
+        .expect("failed to read outputs");
+    assert!(matches!(indexer_state.peek_pending(),
+        Some(IndexerEvent::Output(Event { payload, .. }))
+        if payload == &outputs[1]
+    ));
+    // The discarded events are consumed again
+    indexer_state.discard_pending();
+    let event = broker
+        .indexer_consume(&mut indexer_state)
+        .await
+        .expect("failed to consume indexer payload");
+    assert!(matches!(event,
+        IndexerEvent::Output(Event { payload, .. })
+        if payload == outputs[1]
+    ));
+}
+
 #[test_log::test(tokio::test)]
 async fn it_consumes_inputs_before_outputs() {
     let docker = Cli::default();
@@ -157,6 +198,82 @@ async fn it_consumes_inputs_before_outputs() {
     }
 }
 
+#[test_log::test(tokio::test)]
+async fn it_consumes_claim_events_of_the_dapp() {
+    let docker = Cli::default();
+    let state = TestState::setup(&docker).await;
+    let mut broker = state.create_broker().await;
+    // Produce claim events for this and for another dapp
+    let claims = vec![
+        RollupsClaim {
+            dapp_address: Address::new([0xfb; 20]),
+            epoch_index: 0,
+            ..Default::default()
+        },
+        RollupsClaim {
+            dapp_address: DAPP_ADDRESS.to_owned(),
+            epoch_index: 1,
+            ..Default::default()
+        },
+    ];
+    let stream = RollupsClaimsStream::new(CHAIN_ID);
+    produce_all(&mut broker, &stream, &claims).await;
+    // Consume indexer events
+    let metadata = dapp_metadata();
+    let consumed_events = consume_all(&mut broker, &metadata, 1).await;
+    assert!(matches!(&consumed_events[0],
+        IndexerEvent::Claim(
+            Event {
+                payload,
+                ..
+            }
+        )
+        if payload == &claims[1]
+    ));
+}
+
+#[test_log::test(tokio::test)]
+async fn it_resumes_after_the_last_consumed_events() {
+    let docker = Cli::default();
+    let state = TestState::setup(&docker).await;
+    let mut broker = state.create_broker().await;
+    // Produce input and output events
+    let metadata = dapp_metadata();
+    let inputs = generate_inputs();
+    let inputs_stream = RollupsInputsStream::new(&metadata);
+    produce_all(&mut broker, &inputs_stream, &inputs).await;
+    let outputs = generate_outputs();
+    let outputs_stream = RollupsOutputsStream::new(&metadata);
+    produce_all(&mut broker, &outputs_stream, &outputs).await;
+    // Consume the first input and resume from it in a new state
+    let mut indexer_state = IndexerState::new(&metadata);
+    let event = broker
+        .indexer_consume(&mut indexer_state)
+        .await
+        .expect("failed to consume indexer payload");
+    assert_eq!(indexer_state.stream_key(&event), inputs_stream.key());
+    let last_ids = HashMap::from([(
+        indexer_state.stream_key(&event).to_owned(),
+        event.id().to_owned(),
+    )]);
+    let mut indexer_state = IndexerState::new(&metadata);
+    indexer_state.resume(&last_ids);
+    assert_eq!(indexer_state.inputs_last_id(), event.id());
+    let event = broker
+        .indexer_consume(&mut indexer_state)
+        .await
+        .expect("failed to consume indexer payload");
+    assert!(matches!(event,
+        IndexerEvent::Input(
+            Event {
+                payload,
+                ..
+            }
+        )
+        if payload == inputs[1]
+    ));
+}
+
 fn dapp_metadata() -> DAppMetadata {
     DAppMetadata {
         chain_id: CHAIN_ID,
@@ -188,7 +305,7 @@ fn generate_inputs() -> Vec<RollupsInput> {
             parent_id: "".to_owned(),
             epoch_index: 0,
             inputs_sent_count: 1,
-            data: RollupsData::FinishEpoch {},
+            data: RollupsData::FinishEpoch { boundary: None },
         },
     ]
 }
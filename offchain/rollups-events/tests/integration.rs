@@ -12,7 +12,8 @@ use testcontainers::{
 
 use rollups_events::{
     Broker, BrokerConfig, BrokerEndpoint, BrokerError, BrokerStream,
-    RedactedUrl, Url, INITIAL_ID,
+    ConsumerGroup, PayloadEncoding, RedactedUrl, RetentionPolicy, Url,
+    VersionedPayload, INITIAL_ID,
 };
 
 const STREAM_KEY: &'static str = "test-stream";
@@ -56,6 +57,9 @@ impl TestState<'_> {
             redis_endpoint: BrokerEndpoint::Single(self.redis_endpoint.clone()),
             backoff: self.backoff.clone(),
             consume_timeout: CONSUME_TIMEOUT,
+            retention: Default::default(),
+            payload_encoding: Default::default(),
+            consumer_group: None,
         };
         Broker::new(config)
             .await
@@ -68,6 +72,10 @@ struct MockPayload {
     data: String,
 }
 
+impl VersionedPayload for MockPayload {
+    const VERSION: u32 = 1;
+}
+
 struct MockStream {}
 
 impl BrokerStream for MockStream {
@@ -110,6 +118,109 @@ async fn test_it_produces_events() {
     }
 }
 
+#[test_log::test(tokio::test)]
+async fn test_it_produces_many_events() {
+    let docker = Cli::default();
+    let mut state = TestState::setup(&docker).await;
+    let mut broker = state.create_broker().await;
+    // Produce events using the Broker struct
+    const N: usize = 3;
+    let payloads = (0..N)
+        .map(|i| MockPayload {
+            data: i.to_string(),
+        })
+        .collect();
+    let ids = broker
+        .produce_many(&MockStream {}, payloads)
+        .await
+        .expect("failed to produce");
+    // Check the events directly in Redis
+    let reply: StreamRangeReply = state
+        .conn
+        .xrange(STREAM_KEY, "-", "+")
+        .await
+        .expect("failed to read");
+    assert_eq!(reply.ids.len(), N);
+    for i in 0..N {
+        let expected = format!(r#"{{"data":"{}"}}"#, i);
+        assert_eq!(reply.ids[i].id, ids[i]);
+        assert_eq!(reply.ids[i].get::<String>("payload").unwrap(), expected);
+    }
+}
+
+#[test_log::test(tokio::test)]
+async fn test_it_commits_transaction() {
+    let docker = Cli::default();
+    let mut state = TestState::setup(&docker).await;
+    let mut broker = state.create_broker().await;
+    // Produce events using a transaction
+    const N: usize = 3;
+    let mut transaction = broker.transaction();
+    for i in 0..N {
+        let data = MockPayload {
+            data: i.to_string(),
+        };
+        transaction
+            .produce(&MockStream {}, data)
+            .expect("failed to add event");
+    }
+    let ids = broker.commit(transaction).await.expect("failed to commit");
+    // Check the events directly in Redis
+    let reply: StreamRangeReply = state
+        .conn
+        .xrange(STREAM_KEY, "-", "+")
+        .await
+        .expect("failed to read");
+    assert_eq!(reply.ids.len(), N);
+    for i in 0..N {
+        let expected = format!(r#"{{"data":"{}"}}"#, i);
+        assert_eq!(reply.ids[i].id, ids[i]);
+        assert_eq!(reply.ids[i].get::<String>("payload").unwrap(), expected);
+    }
+}
+
+#[test_log::test(tokio::test)]
+async fn test_it_produces_and_consumes_binary_events() {
+    let docker = Cli::default();
+    let mut state = TestState::setup(&docker).await;
+    // Produce an event with each encoding using the Broker struct
+    let mut json_broker = state.create_broker().await;
+    let mut cbor_broker = state
+        .create_broker()
+        .await
+        .with_encoding(PayloadEncoding::Cbor);
+    for (i, broker) in
+        [&mut json_broker, &mut cbor_broker].into_iter().enumerate()
+    {
+        let data = MockPayload {
+            data: i.to_string(),
+        };
+        broker
+            .produce(&MockStream {}, data)
+            .await
+            .expect("failed to produce");
+    }
+    // Check the encodings directly in Redis
+    let reply: StreamRangeReply = state
+        .conn
+        .xrange(STREAM_KEY, "-", "+")
+        .await
+        .expect("failed to read");
+    let encodings: Vec<String> = reply
+        .ids
+        .iter()
+        .map(|event| event.get("encoding").unwrap())
+        .collect();
+    assert_eq!(encodings, ["json", "cbor-v1"]);
+    // Consume both events using the Broker struct
+    let events = json_broker
+        .consume_batch(&MockStream {}, INITIAL_ID, 2)
+        .await
+        .expect("failed to consume");
+    let data: Vec<_> = events.into_iter().map(|e| e.payload.data).collect();
+    assert_eq!(data, ["0", "1"]);
+}
+
 #[test_log::test(tokio::test)]
 async fn test_it_peeks_in_stream_with_no_events() {
     let docker = Cli::default();
@@ -151,6 +262,45 @@ async fn test_it_peeks_in_stream_with_multiple_events() {
     }
 }
 
+#[test_log::test(tokio::test)]
+async fn test_it_peeks_previous_events() {
+    let docker = Cli::default();
+    let mut state = TestState::setup(&docker).await;
+    // Produce multiple events directly in Redis
+    const N: usize = 5;
+    for i in 0..N {
+        let id = format!("1-{}", i);
+        let data = format!(r#"{{"data":"{}"}}"#, i);
+        let _: String = state
+            .conn
+            .xadd(STREAM_KEY, id, &[("payload", data)])
+            .await
+            .expect("failed to add events");
+    }
+    // Peek the events backwards using the Broker struct
+    let mut broker = state.create_broker().await;
+    let events = broker
+        .peek_previous(&MockStream {}, None, 2)
+        .await
+        .expect("failed to peek");
+    let ids: Vec<_> = events.iter().map(|event| event.id.as_str()).collect();
+    assert_eq!(ids, vec!["1-4", "1-3"]);
+    let events = broker
+        .peek_previous(&MockStream {}, Some("1-3"), 5)
+        .await
+        .expect("failed to peek");
+    let data: Vec<_> = events
+        .iter()
+        .map(|event| event.payload.data.as_str())
+        .collect();
+    assert_eq!(data, vec!["2", "1", "0"]);
+    let events = broker
+        .peek_previous(&MockStream {}, Some("1-0"), 5)
+        .await
+        .expect("failed to peek");
+    assert!(events.is_empty());
+}
+
 #[test_log::test(tokio::test)]
 async fn test_it_fails_to_peek_event_in_invalid_format() {
     let docker = Cli::default();
@@ -218,6 +368,40 @@ async fn test_it_consumes_events() {
     }
 }
 
+#[test_log::test(tokio::test)]
+async fn test_it_consumes_batches_of_events() {
+    let docker = Cli::default();
+    let mut state = TestState::setup(&docker).await;
+    // Produce multiple events directly in Redis
+    const N: usize = 5;
+    for i in 0..N {
+        let id = format!("1-{}", i);
+        let data = format!(r#"{{"data":"{}"}}"#, i);
+        let _: String = state
+            .conn
+            .xadd(STREAM_KEY, id, &[("payload", data)])
+            .await
+            .expect("failed to add events");
+    }
+    // Consume events in batches using the Broker struct
+    let mut broker = state.create_broker().await;
+    let events = broker
+        .consume_batch(&MockStream {}, INITIAL_ID, 3)
+        .await
+        .expect("failed to consume");
+    assert_eq!(events.len(), 3);
+    for (i, event) in events.iter().enumerate() {
+        assert_eq!(event.id, format!("1-{}", i));
+        assert_eq!(event.payload.data, i.to_string());
+    }
+    let events = broker
+        .consume_batch(&MockStream {}, "1-2", 3)
+        .await
+        .expect("failed to consume");
+    let ids: Vec<_> = events.into_iter().map(|event| event.id).collect();
+    assert_eq!(ids, ["1-3", "1-4"]);
+}
+
 #[test_log::test(tokio::test)]
 async fn test_it_blocks_until_event_is_produced() {
     let docker = Cli::default();
@@ -286,3 +470,147 @@ async fn test_it_does_not_block_when_consuming_empty_stream() {
         .expect("failed to peek");
     assert!(matches!(event, None));
 }
+
+#[test_log::test(tokio::test)]
+async fn test_it_gets_event_by_id() {
+    let docker = Cli::default();
+    let mut state = TestState::setup(&docker).await;
+    // Produce multiple events directly in Redis
+    const N: usize = 3;
+    for i in 0..N {
+        let id = format!("1-{}", i);
+        let data = format!(r#"{{"data":"{}"}}"#, i);
+        let _: String = state
+            .conn
+            .xadd(STREAM_KEY, id, &[("payload", data)])
+            .await
+            .expect("failed to add events");
+    }
+    // Get the events using the Broker struct
+    let mut broker = state.create_broker().await;
+    let event = broker
+        .get_event(&MockStream {}, "1-1")
+        .await
+        .expect("failed to get event")
+        .expect("expected event, got None");
+    assert_eq!(event.id, "1-1");
+    assert_eq!(event.payload.data, "1");
+    let event = broker
+        .get_event(&MockStream {}, "1-3")
+        .await
+        .expect("failed to get event");
+    assert!(matches!(event, None));
+    let event = broker
+        .get_event(&MockStream {}, "invalid")
+        .await
+        .expect("failed to get event");
+    assert!(matches!(event, None));
+}
+
+#[test_log::test(tokio::test)]
+async fn test_it_trims_stream() {
+    let docker = Cli::default();
+    let mut state = TestState::setup(&docker).await;
+    // Produce multiple events directly in Redis
+    const N: usize = 5;
+    for i in 0..N {
+        let id = format!("1-{}", i);
+        let data = format!(r#"{{"data":"{}"}}"#, i);
+        let _: String = state
+            .conn
+            .xadd(STREAM_KEY, id, &[("payload", data)])
+            .await
+            .expect("failed to add events");
+    }
+    // Trim the stream using the Broker struct
+    let mut broker = state.create_broker().await;
+    let policy = RetentionPolicy::MaxLen(4);
+    let count = broker
+        .trim(&MockStream {}, &policy, &[])
+        .await
+        .expect("failed to trim");
+    assert_eq!(count, 1);
+    let consumers = ["indexer", "advance-runner"];
+    broker
+        .acknowledge(&MockStream {}, "indexer", "1-3")
+        .await
+        .expect("failed to acknowledge");
+    broker
+        .acknowledge(&MockStream {}, "advance-runner", "1-2")
+        .await
+        .expect("failed to acknowledge");
+    let count = broker
+        .trim(&MockStream {}, &RetentionPolicy::Acknowledged, &consumers)
+        .await
+        .expect("failed to trim");
+    assert_eq!(count, 1);
+    // Check the events directly in Redis
+    let reply: StreamRangeReply = state
+        .conn
+        .xrange(STREAM_KEY, "-", "+")
+        .await
+        .expect("failed to read");
+    let ids: Vec<_> = reply.ids.into_iter().map(|event| event.id).collect();
+    assert_eq!(ids, ["1-2", "1-3", "1-4"]);
+}
+
+#[test_log::test(tokio::test)]
+async fn test_it_consumes_events_as_group() {
+    let docker = Cli::default();
+    let mut state = TestState::setup(&docker).await;
+    // Produce multiple events directly in Redis
+    const N: usize = 3;
+    for i in 0..N {
+        let id = format!("1-{}", i);
+        let data = format!(r#"{{"data":"{}"}}"#, i);
+        let _: String = state
+            .conn
+            .xadd(STREAM_KEY, id, &[("payload", data)])
+            .await
+            .expect("failed to add events");
+    }
+    // Consume the events as consumers of the same group
+    let mut broker = state.create_broker().await;
+    let consumer = |name: &str| ConsumerGroup {
+        name: "group".to_owned(),
+        consumer: name.to_owned(),
+        claim_idle: std::time::Duration::from_secs(60),
+    };
+    let (a, b) = (consumer("a"), consumer("b"));
+    broker
+        .create_group(&MockStream {}, &a)
+        .await
+        .expect("failed to create group");
+    broker
+        .create_group(&MockStream {}, &b)
+        .await
+        .expect("failed to create existing group");
+    let events = broker
+        .consume_group(&MockStream {}, &a, 2)
+        .await
+        .expect("failed to consume");
+    let ids: Vec<_> = events.into_iter().map(|event| event.id).collect();
+    assert_eq!(ids, ["1-0", "1-1"]);
+    let count = broker
+        .acknowledge_group(&MockStream {}, &a, &["1-0"])
+        .await
+        .expect("failed to acknowledge");
+    assert_eq!(count, 1);
+    let events = broker
+        .consume_group(&MockStream {}, &b, 2)
+        .await
+        .expect("failed to consume");
+    let ids: Vec<_> = events.into_iter().map(|event| event.id).collect();
+    assert_eq!(ids, ["1-2"]);
+    // Claim the events left by the other consumers
+    let c = ConsumerGroup {
+        claim_idle: std::time::Duration::ZERO,
+        ..consumer("c")
+    };
+    let events = broker
+        .consume_group(&MockStream {}, &c, 2)
+        .await
+        .expect("failed to claim");
+    let ids: Vec<_> = events.into_iter().map(|event| event.id).collect();
+    assert_eq!(ids, ["1-1", "1-2"]);
+}
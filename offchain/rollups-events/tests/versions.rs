@@ -0,0 +1,200 @@
+// (c) Cartesi and individual authors (see AUTHORS)
+// SPDX-License-Identifier: Apache-2.0 (see LICENSE)
+
+//! Decode the events produced by the previous releases
+//!
+//! The events in `tests/events/<release>` were produced by each release, so
+//! they must be read by the current consumers during a rolling upgrade.
+
+use redis::streams::StreamId;
+use redis::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use rollups_events::{
+    Address, BrokerError, EncodedPayload, Event, Hash, InputMetadata, Payload,
+    PayloadEncoding, RollupsAdvanceResult, RollupsAdvanceStateInput,
+    RollupsClaim, RollupsCompletionStatus, RollupsData, RollupsInput,
+    RollupsNotice, RollupsOutput, RollupsOutputEnum,
+    RollupsOutputValidityProof, RollupsProof, RollupsReport, RollupsVoucher,
+    VersionedPayload, ADDRESS_SIZE, HASH_SIZE, INITIAL_ID,
+};
+
+const ADDRESS: Address = Address::new([0xfa; ADDRESS_SIZE]);
+const HASH: Hash = Hash::new([0xab; HASH_SIZE]);
+
+/// Read the event of a previous release as a stream entry
+fn read_event<P: VersionedPayload + Clone + Eq>(
+    release: &str,
+    name: &str,
+) -> Result<Event<P>, BrokerError> {
+    let path: PathBuf = [
+        env!("CARGO_MANIFEST_DIR"),
+        "tests",
+        "events",
+        release,
+        &format!("{}.json", name),
+    ]
+    .iter()
+    .collect();
+    let payload = std::fs::read(&path).expect("failed to read event");
+    let stream_id = StreamId {
+        id: "1-0".to_owned(),
+        map: HashMap::from([("payload".to_owned(), Value::Data(payload))]),
+    };
+    Event::try_from(stream_id)
+}
+
+/// Create the stream entry of the encoded payload
+fn stream_entry(payload: &EncodedPayload) -> StreamId {
+    StreamId {
+        id: "1-0".to_owned(),
+        map: payload
+            .fields()
+            .into_iter()
+            .map(|(field, value)| (field.to_owned(), Value::Data(value)))
+            .collect(),
+    }
+}
+
+fn payload() -> Payload {
+    Payload::new(b"hello".to_vec())
+}
+
+#[test]
+fn test_it_reads_inputs_of_release_1_4() {
+    let event: Event<RollupsInput> =
+        read_event("1.4", "rollups_input_advance").unwrap();
+    assert_eq!(
+        event.payload,
+        RollupsInput {
+            parent_id: INITIAL_ID.to_owned(),
+            epoch_index: 0,
+            inputs_sent_count: 1,
+            data: RollupsData::AdvanceStateInput(RollupsAdvanceStateInput {
+                metadata: InputMetadata {
+                    msg_sender: ADDRESS,
+                    block_number: 10,
+                    timestamp: 1700000000,
+                    epoch_index: 0,
+                    input_index: 0,
+                },
+                payload: payload(),
+                tx_hash: HASH,
+            }),
+        }
+    );
+
+    let event: Event<RollupsInput> =
+        read_event("1.4", "rollups_input_finish_epoch").unwrap();
+    assert_eq!(event.payload.data, RollupsData::FinishEpoch { boundary: None });
+}
+
+#[test]
+fn test_it_reads_outputs_of_release_1_4() {
+    let expected = [
+        (
+            "rollups_output_advance_result",
+            RollupsOutput::AdvanceResult(RollupsAdvanceResult {
+                input_index: 0,
+                status: RollupsCompletionStatus::Accepted,
+            }),
+        ),
+        (
+            "rollups_output_voucher",
+            RollupsOutput::Voucher(RollupsVoucher {
+                index: 0,
+                input_index: 0,
+                destination: ADDRESS,
+                payload: payload(),
+            }),
+        ),
+        (
+            "rollups_output_notice",
+            RollupsOutput::Notice(RollupsNotice {
+                index: 0,
+                input_index: 0,
+                payload: payload(),
+            }),
+        ),
+        (
+            "rollups_output_report",
+            RollupsOutput::Report(RollupsReport {
+                index: 0,
+                input_index: 0,
+                payload: payload(),
+            }),
+        ),
+        (
+            "rollups_output_proof",
+            RollupsOutput::Proof(RollupsProof {
+                input_index: 0,
+                output_index: 0,
+                output_enum: RollupsOutputEnum::Notice,
+                validity: RollupsOutputValidityProof {
+                    input_index_within_epoch: 0,
+                    output_index_within_input: 0,
+                    output_hashes_root_hash: HASH,
+                    vouchers_epoch_root_hash: HASH,
+                    notices_epoch_root_hash: HASH,
+                    machine_state_hash: HASH,
+                    output_hash_in_output_hashes_siblings: vec![HASH],
+                    output_hashes_in_epoch_siblings: vec![HASH],
+                },
+                context: Payload::default(),
+            }),
+        ),
+    ];
+    for (name, output) in expected {
+        let event: Event<RollupsOutput> = read_event("1.4", name).unwrap();
+        assert_eq!(event.payload, output);
+    }
+}
+
+#[test]
+fn test_it_upgrades_claims_of_release_1_4() {
+    let event: Event<RollupsClaim> =
+        read_event("1.4", "rollups_claim").unwrap();
+    assert_eq!(
+        event.payload,
+        RollupsClaim {
+            dapp_address: ADDRESS,
+            epoch_index: 0,
+            epoch_hash: HASH,
+            first_index: 0,
+            last_index: 0,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_it_reads_current_claims() {
+    let claim = RollupsClaim {
+        dapp_address: ADDRESS,
+        epoch_hash: HASH,
+        machine_state_hash: HASH,
+        ..Default::default()
+    };
+    for encoding in [PayloadEncoding::Json, PayloadEncoding::Cbor] {
+        let payload = encoding.encode(&claim).unwrap();
+        assert_eq!(payload.version, RollupsClaim::VERSION);
+        let event: Event<RollupsClaim> =
+            stream_entry(&payload).try_into().unwrap();
+        assert_eq!(event.payload, claim);
+    }
+}
+
+#[test]
+fn test_it_fails_to_read_events_of_newer_versions() {
+    let mut payload = PayloadEncoding::Json
+        .encode(&RollupsClaim::default())
+        .unwrap();
+    payload.version = RollupsClaim::VERSION + 1;
+    let result: Result<Event<RollupsClaim>, _> =
+        stream_entry(&payload).try_into();
+    assert!(matches!(
+        result,
+        Err(BrokerError::UnsupportedVersion { version: 3 })
+    ));
+}
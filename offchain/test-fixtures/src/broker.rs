@@ -8,6 +8,7 @@ use rollups_events::{
     RollupsInputsStream, RollupsOutput, RollupsOutputsStream, Url,
     ADDRESS_SIZE, INITIAL_ID,
 };
+use std::sync::atomic::{AtomicU64, Ordering};
 use testcontainers::{
     clients::Cli, core::WaitFor, images::generic::GenericImage, Container,
 };
@@ -17,8 +18,13 @@ const CHAIN_ID: u64 = 0;
 const DAPP_ADDRESS: Address = Address::new([0xfa; ADDRESS_SIZE]);
 const CONSUME_TIMEOUT: usize = 10_000; // ms
 
+/// Chain of the next in-memory fixture
+/// The in-memory streams are shared by the process, so each fixture uses
+/// another chain to keep the tests apart.
+static NEXT_MEMORY_CHAIN_ID: AtomicU64 = AtomicU64::new(1);
+
 pub struct BrokerFixture<'d> {
-    _node: Container<'d, GenericImage>,
+    _node: Option<Container<'d, GenericImage>>,
     client: Mutex<Broker>,
     inputs_stream: RollupsInputsStream,
     claims_stream: RollupsClaimsStream,
@@ -44,7 +50,24 @@ impl BrokerFixture<'_> {
                 .map(RedactedUrl::new)
                 .expect("failed to parse Redis Url"),
         );
-        let chain_id = CHAIN_ID;
+        Self::connect(Some(node), redis_endpoint, CHAIN_ID).await
+    }
+
+    /// Set up the fixture with the in-memory broker instead of Redis
+    /// The services under test must run in the same process and connect to
+    /// the endpoint of the fixture.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn setup_memory() -> BrokerFixture<'static> {
+        tracing::info!("setting up in-memory broker fixture");
+        let chain_id = NEXT_MEMORY_CHAIN_ID.fetch_add(1, Ordering::Relaxed);
+        BrokerFixture::connect(None, BrokerEndpoint::Memory, chain_id).await
+    }
+
+    async fn connect(
+        node: Option<Container<'_, GenericImage>>,
+        redis_endpoint: BrokerEndpoint,
+        chain_id: u64,
+    ) -> BrokerFixture<'_> {
         let dapp_address = DAPP_ADDRESS;
         let backoff = ExponentialBackoff::default();
         let metadata = DAppMetadata {
@@ -57,6 +80,9 @@ impl BrokerFixture<'_> {
         let config = BrokerConfig {
             redis_endpoint: redis_endpoint.clone(),
             consume_timeout: CONSUME_TIMEOUT,
+            retention: Default::default(),
+            payload_encoding: Default::default(),
+            consumer_group: None,
             backoff,
         };
 
@@ -123,7 +149,7 @@ impl BrokerFixture<'_> {
                 RollupsData::AdvanceStateInput { .. } => {
                     event.payload.epoch_index
                 }
-                RollupsData::FinishEpoch {} => event.payload.epoch_index + 1,
+                RollupsData::FinishEpoch { .. } => event.payload.epoch_index + 1,
             },
             None => 0,
         };
@@ -135,7 +161,7 @@ impl BrokerFixture<'_> {
             RollupsData::AdvanceStateInput { .. } => {
                 previous_inputs_sent_count + 1
             }
-            RollupsData::FinishEpoch {} => previous_inputs_sent_count,
+            RollupsData::FinishEpoch { .. } => previous_inputs_sent_count,
         };
         let parent_id = match last_event {
             Some(event) => event.id,
@@ -245,4 +271,16 @@ impl BrokerFixture<'_> {
             .await
             .expect("failed to produce output");
     }
+
+    /// Produce the output events at once, like the advance runner does
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub async fn produce_outputs(&self, outputs: Vec<RollupsOutput>) {
+        tracing::trace!(?outputs, "producing rollups-outputs events");
+        self.client
+            .lock()
+            .await
+            .produce_many(&self.outputs_stream, outputs)
+            .await
+            .expect("failed to produce outputs");
+    }
 }